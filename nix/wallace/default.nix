@@ -5,7 +5,7 @@ self: super:
 
         # Pinned versions of third-party dependencies.
         pinned = {
-            inherit (self.rust_1_49.packages.stable)
+            inherit (self.rust_1_86.packages.stable)
                 cargo;
         };
 
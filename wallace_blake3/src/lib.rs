@@ -0,0 +1,480 @@
+//! Implementation of BLAKE3 in portable Rust.
+//!
+//! This follows the reference implementation in the BLAKE3 specification,
+//! restricted to the default hash mode with 32-byte output.
+//! It does not use SIMD, but even so it is considerably faster than SHA-256
+//! on machines without SHA extensions.
+//!
+//! BLAKE3 hashes its input as a tree of 1 KiB chunks,
+//! and subtrees can be hashed independently of each other.
+//! Large buffers passed to [`Blake3::update`] are split into subtrees
+//! that are hashed on as many threads as the machine has cores.
+//! Small buffers, such as those passed by [`std::io::copy`],
+//! are hashed on the calling thread.
+
+#![doc(html_favicon_url = "../../../marketing/logo.svg")]
+#![doc(html_logo_url = "../../../marketing/logo.svg")]
+
+use std::io::Result;
+use std::io::Write;
+use std::thread;
+
+const BLOCK_LEN: usize = 64;
+const CHUNK_LEN: usize = 1024;
+
+/// Subtrees smaller than this are not worth hashing on another thread.
+const PARALLEL_MIN_LEN: usize = 128 * CHUNK_LEN;
+
+const CHUNK_START: u32 = 1 << 0;
+const CHUNK_END:   u32 = 1 << 1;
+const PARENT:      u32 = 1 << 2;
+const ROOT:        u32 = 1 << 3;
+
+const IV: [u32; 8] = [
+    0x6A09E667, 0xBB67AE85, 0x3C6EF372, 0xA54FF53A,
+    0x510E527F, 0x9B05688C, 0x1F83D9AB, 0x5BE0CD19,
+];
+
+const MSG_PERMUTATION: [usize; 16] =
+    [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+/// BLAKE3 digest with a multi-part interface.
+///
+/// The [`Write`] impl calls [`Blake3::update`] on writes.
+/// This is especially convenient when using [`copy`][`std::io::copy`].
+/// It never returns an error.
+#[derive(Clone)]
+pub struct Blake3
+{
+    chunk_state: ChunkState,
+
+    /// Chaining values of completed subtrees,
+    /// enough for inputs of up to 2^64 bytes.
+    cv_stack: [[u32; 8]; 54],
+    cv_stack_len: usize,
+}
+
+#[derive(Clone)]
+struct ChunkState
+{
+    chaining_value: [u32; 8],
+    chunk_counter: u64,
+    block: [u8; BLOCK_LEN],
+    block_len: usize,
+    blocks_compressed: usize,
+}
+
+/// Input to a compression whose output is not yet needed,
+/// because it may turn out to be the root.
+struct Output
+{
+    input_chaining_value: [u32; 8],
+    block_words: [u32; 16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+}
+
+fn g(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize,
+     mx: u32, my: u32)
+{
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(mx);
+    state[d] = (state[d] ^ state[a]).rotate_right(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(12);
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(my);
+    state[d] = (state[d] ^ state[a]).rotate_right(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(7);
+}
+
+fn round(state: &mut [u32; 16], m: &[u32; 16])
+{
+    // Mix the columns.
+    g(state, 0, 4,  8, 12, m[ 0], m[ 1]);
+    g(state, 1, 5,  9, 13, m[ 2], m[ 3]);
+    g(state, 2, 6, 10, 14, m[ 4], m[ 5]);
+    g(state, 3, 7, 11, 15, m[ 6], m[ 7]);
+
+    // Mix the diagonals.
+    g(state, 0, 5, 10, 15, m[ 8], m[ 9]);
+    g(state, 1, 6, 11, 12, m[10], m[11]);
+    g(state, 2, 7,  8, 13, m[12], m[13]);
+    g(state, 3, 4,  9, 14, m[14], m[15]);
+}
+
+fn permute(m: &mut [u32; 16])
+{
+    let original = *m;
+    for (word, &index) in m.iter_mut().zip(&MSG_PERMUTATION) {
+        *word = original[index];
+    }
+}
+
+fn compress(
+    chaining_value: &[u32; 8],
+    block_words:    &[u32; 16],
+    counter:        u64,
+    block_len:      u32,
+    flags:          u32,
+) -> [u32; 16]
+{
+    let mut state = [
+        chaining_value[0], chaining_value[1],
+        chaining_value[2], chaining_value[3],
+        chaining_value[4], chaining_value[5],
+        chaining_value[6], chaining_value[7],
+        IV[0], IV[1], IV[2], IV[3],
+        counter as u32, (counter >> 32) as u32, block_len, flags,
+    ];
+
+    let mut block = *block_words;
+    for i in 0 .. 7 {
+        round(&mut state, &block);
+        if i != 6 {
+            permute(&mut block);
+        }
+    }
+
+    for i in 0 .. 8 {
+        state[i] ^= state[i + 8];
+        state[i + 8] ^= chaining_value[i];
+    }
+    state
+}
+
+fn first_8_words(words: [u32; 16]) -> [u32; 8]
+{
+    let mut first = [0; 8];
+    first.copy_from_slice(&words[.. 8]);
+    first
+}
+
+fn words_from_le_bytes(bytes: &[u8; BLOCK_LEN]) -> [u32; 16]
+{
+    let mut words = [0; 16];
+    for (word, bytes) in words.iter_mut().zip(bytes.chunks_exact(4)) {
+        *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    words
+}
+
+impl Output
+{
+    fn chaining_value(&self) -> [u32; 8]
+    {
+        first_8_words(compress(&self.input_chaining_value, &self.block_words,
+                               self.counter, self.block_len, self.flags))
+    }
+
+    fn root_hash(&self) -> [u8; 32]
+    {
+        let words = compress(&self.input_chaining_value, &self.block_words,
+                             0, self.block_len, self.flags | ROOT);
+        let mut hash = [0; 32];
+        for (bytes, word) in hash.chunks_exact_mut(4).zip(&words) {
+            bytes.copy_from_slice(&word.to_le_bytes());
+        }
+        hash
+    }
+}
+
+impl ChunkState
+{
+    fn new(chunk_counter: u64) -> Self
+    {
+        Self{
+            chaining_value: IV,
+            chunk_counter,
+            block: [0; BLOCK_LEN],
+            block_len: 0,
+            blocks_compressed: 0,
+        }
+    }
+
+    fn len(&self) -> usize
+    {
+        BLOCK_LEN * self.blocks_compressed + self.block_len
+    }
+
+    fn start_flag(&self) -> u32
+    {
+        if self.blocks_compressed == 0 { CHUNK_START } else { 0 }
+    }
+
+    fn update(&mut self, mut input: &[u8])
+    {
+        while !input.is_empty() {
+            // Only compress a full block once more input arrives,
+            // as the last block of a chunk is compressed differently.
+            if self.block_len == BLOCK_LEN {
+                let block_words = words_from_le_bytes(&self.block);
+                self.chaining_value = first_8_words(compress(
+                    &self.chaining_value, &block_words, self.chunk_counter,
+                    BLOCK_LEN as u32, self.start_flag(),
+                ));
+                self.blocks_compressed += 1;
+                self.block = [0; BLOCK_LEN];
+                self.block_len = 0;
+            }
+
+            let take = (BLOCK_LEN - self.block_len).min(input.len());
+            self.block[self.block_len .. self.block_len + take]
+                .copy_from_slice(&input[.. take]);
+            self.block_len += take;
+            input = &input[take ..];
+        }
+    }
+
+    fn output(&self) -> Output
+    {
+        Output{
+            input_chaining_value: self.chaining_value,
+            block_words: words_from_le_bytes(&self.block),
+            counter: self.chunk_counter,
+            block_len: self.block_len as u32,
+            flags: self.start_flag() | CHUNK_END,
+        }
+    }
+}
+
+fn parent_output(left: [u32; 8], right: [u32; 8]) -> Output
+{
+    let mut block_words = [0; 16];
+    block_words[.. 8].copy_from_slice(&left);
+    block_words[8 ..].copy_from_slice(&right);
+    Output{
+        input_chaining_value: IV,
+        block_words,
+        counter: 0,
+        block_len: BLOCK_LEN as u32,
+        flags: PARENT,
+    }
+}
+
+/// Compute the chaining value of a subtree that is not the root.
+///
+/// The input must consist of a power of two number of full chunks,
+/// the first of which has the given chunk counter.
+/// The halves of the subtree are hashed in parallel
+/// if more than one thread may be used.
+fn subtree_chaining_value(input: &[u8], chunk_counter: u64, threads: usize)
+    -> [u32; 8]
+{
+    if input.len() == CHUNK_LEN {
+        let mut chunk_state = ChunkState::new(chunk_counter);
+        chunk_state.update(input);
+        return chunk_state.output().chaining_value();
+    }
+
+    let (left, right) = input.split_at(input.len() / 2);
+    let right_counter = chunk_counter + (left.len() / CHUNK_LEN) as u64;
+    let parallel = threads > 1 && left.len() >= PARALLEL_MIN_LEN;
+    let (left_cv, right_cv) = if parallel {
+        let left_threads = threads / 2;
+        thread::scope(|scope| {
+            let left_cv = scope.spawn(|| {
+                subtree_chaining_value(left, chunk_counter, left_threads)
+            });
+            let right_cv = subtree_chaining_value(right, right_counter,
+                                                  threads - left_threads);
+            (left_cv.join().unwrap(), right_cv)
+        })
+    } else {
+        (subtree_chaining_value(left, chunk_counter, 1),
+         subtree_chaining_value(right, right_counter, 1))
+    };
+    parent_output(left_cv, right_cv).chaining_value()
+}
+
+impl Blake3
+{
+    /// Create a new, empty digest.
+    pub fn new() -> Self
+    {
+        Self{
+            chunk_state: ChunkState::new(0),
+            cv_stack: [[0; 8]; 54],
+            cv_stack_len: 0,
+        }
+    }
+
+    /// Update the digest using a buffer.
+    ///
+    /// Large buffers are hashed on multiple threads.
+    pub fn update(&mut self, mut buf: &[u8])
+    {
+        while !buf.is_empty() {
+            // Only finish a full chunk once more input arrives,
+            // as the last chunk may be the root.
+            if self.chunk_state.len() == CHUNK_LEN {
+                let chunk_cv = self.chunk_state.output().chaining_value();
+                let total_chunks = self.chunk_state.chunk_counter + 1;
+                self.add_chunk_chaining_value(chunk_cv, total_chunks);
+                self.chunk_state = ChunkState::new(total_chunks);
+            }
+
+            // Hash whole subtrees at once when the chunk state is empty,
+            // again keeping back some input as the root may follow.
+            if self.chunk_state.len() == 0 {
+                let chunk_counter = self.chunk_state.chunk_counter;
+                let subtree_chunks = subtree_chunks(buf.len(), chunk_counter);
+                if subtree_chunks > 1 {
+                    let (subtree, rest) =
+                        buf.split_at(subtree_chunks as usize * CHUNK_LEN);
+                    let threads = thread::available_parallelism()
+                        .map_or(1, |n| n.get());
+                    let cv = subtree_chaining_value(subtree, chunk_counter,
+                                                    threads);
+                    let total_chunks = chunk_counter + subtree_chunks;
+                    self.add_subtree_chaining_value(cv, total_chunks,
+                                                    subtree_chunks);
+                    self.chunk_state = ChunkState::new(total_chunks);
+                    buf = rest;
+                    continue;
+                }
+            }
+
+            let take = (CHUNK_LEN - self.chunk_state.len()).min(buf.len());
+            self.chunk_state.update(&buf[.. take]);
+            buf = &buf[take ..];
+        }
+    }
+
+    /// Finalize the digest, returning the hash.
+    pub fn finalize(self) -> [u8; 32]
+    {
+        let mut output = self.chunk_state.output();
+        for cv in self.cv_stack[.. self.cv_stack_len].iter().rev() {
+            output = parent_output(*cv, output.chaining_value());
+        }
+        output.root_hash()
+    }
+
+    /// Add the chaining value of a subtree of the given number of chunks,
+    /// which must be a power of two that divides the number of chunks
+    /// that precede the subtree.
+    fn add_subtree_chaining_value(&mut self, cv: [u32; 8],
+                                  total_chunks: u64, subtree_chunks: u64)
+    {
+        // The subtree takes the place of its leftmost chunk,
+        // in a tree whose leaves are subtrees of this size.
+        let total_subtrees = total_chunks >> subtree_chunks.trailing_zeros();
+        self.add_chunk_chaining_value(cv, total_subtrees);
+    }
+
+    /// Merge completed subtrees, of which there is one
+    /// for every bit that is set in the number of chunks.
+    fn add_chunk_chaining_value(&mut self, mut cv: [u32; 8],
+                                mut total_chunks: u64)
+    {
+        while total_chunks & 1 == 0 {
+            self.cv_stack_len -= 1;
+            let left = self.cv_stack[self.cv_stack_len];
+            cv = parent_output(left, cv).chaining_value();
+            total_chunks >>= 1;
+        }
+        self.cv_stack[self.cv_stack_len] = cv;
+        self.cv_stack_len += 1;
+    }
+}
+
+/// The number of chunks in the largest subtree that can be hashed
+/// at the start of the input, which must be a power of two
+/// that divides the chunk counter.
+/// The subtree leaves at least one byte of input,
+/// so that it is certain not to be the root.
+fn subtree_chunks(input_len: usize, chunk_counter: u64) -> u64
+{
+    if input_len <= CHUNK_LEN {
+        return 0;
+    }
+    let max_chunks = ((input_len - 1) / CHUNK_LEN) as u64;
+    let mut chunks = 1 << (63 - max_chunks.leading_zeros());
+    if chunk_counter != 0 {
+        chunks = chunks.min(1 << chunk_counter.trailing_zeros());
+    }
+    chunks
+}
+
+impl Default for Blake3
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl Write for Blake3
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize>
+    {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()>
+    {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_example_hashes()
+    {
+        // The inputs of the official test vectors
+        // repeat the bytes 0 through 250.
+        let input: Vec<u8> = (0 .. 31744).map(|i| (i % 251) as u8).collect();
+
+        let table: &[(usize, &str)] = &[
+            (0,     "af1349b9f5f9a1a6a0404dea36dcc949\
+                     9bcb25c9adc112b7cc9a93cae41f3262"),
+            (1,     "2d3adedff11b61f14c886e35afa03673\
+                     6dcd87a74d27b5c1510225d0f592e213"),
+            (1024,  "42214739f095a406f3fc83deb889744a\
+                     c00df831c10daa55189b5d121c855af7"),
+            (1025,  "d00278ae47eb27b34faecf67b4fe263f\
+                     82d5412916c1ffd97c8cb7fb814b8444"),
+            (31744, "62b6960e1a44bcc1eb1a611a8d6235b6\
+                     b4b78f32e7abc4fb4c6cdcce94895c47"),
+        ];
+        for &(len, expected) in table {
+            // Feed the input in uneven pieces, and all at once,
+            // which hashes subtrees of it in one go.
+            for &piece_len in &[100, 3000, len.max(1)] {
+                let mut blake3 = Blake3::new();
+                for piece in input[.. len].chunks(piece_len) {
+                    blake3.update(piece);
+                }
+                let actual: String = blake3.finalize().iter()
+                    .map(|b| format!("{:02x}", b)).collect();
+                assert_eq!(actual, expected, "input length {}", len);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parallel()
+    {
+        // Large enough to be hashed on multiple threads,
+        // and not a power of two number of chunks.
+        let input: Vec<u8> = (0 .. 3 << 20).map(|i| (i % 251) as u8)
+            .chain(Some(1)).collect();
+
+        // Hash the input all at once and in small pieces.
+        let mut blake3_all = Blake3::new();
+        blake3_all.update(&input);
+        let mut blake3_pieces = Blake3::new();
+        for piece in input.chunks(1000) {
+            blake3_pieces.update(piece);
+        }
+
+        // Check the results.
+        assert_eq!(blake3_all.finalize(), blake3_pieces.finalize());
+    }
+}
@@ -19,6 +19,13 @@ pub enum ParsedPath
 
     /// Path to an object in the objects directory.
     ObjectsObject(Hash),
+
+    /// Path to the provenance directory.
+    Provenance,
+
+    /// Path to the provenance of an object,
+    /// listing the producers that signed the object.
+    ProvenanceObject(Hash),
 }
 
 impl ParsedPath
@@ -29,29 +36,84 @@ impl ParsedPath
     /// This function ignores empty components.
     pub fn from_components<'a>(components: impl IntoIterator<Item=&'a str>)
         -> Option<Self>
+    {
+        Self::from_components_with(components, |_| None)
+    }
+
+    /// Like [`ParsedPath::from_components`],
+    /// but accept abbreviated object names.
+    ///
+    /// Object names that are not full hashes are passed to `resolve`,
+    /// which returns the hash they abbreviate, if any.
+    /// Typically `resolve` calls [`Volume::resolve_prefix`]
+    /// and accepts only [`PrefixMatch::Unique`] results.
+    ///
+    /// [`Volume::resolve_prefix`]: `wallace_volume::Volume::resolve_prefix`
+    /// [`PrefixMatch::Unique`]: `wallace_volume::PrefixMatch::Unique`
+    pub fn from_components_with<'a>(
+        components: impl IntoIterator<Item=&'a str>,
+        resolve: impl Fn(&str) -> Option<Hash>,
+    ) -> Option<Self>
     {
         // Keep only non-empty components.
         let components = components.into_iter();
-        let mut components = components.filter(|&c| c != "");
+        let mut components = components.filter(|c| !c.is_empty());
 
         match components.next() {
             None            => Some(Self::Root),
-            Some("objects") => Self::from_objects_components(components),
+            Some("objects") =>
+                Self::from_objects_components(components, &resolve),
+            Some("provenance") =>
+                Self::from_provenance_components(components, &resolve),
             _               => None,
         }
     }
 
-    fn from_objects_components<'a>(mut components: impl Iterator<Item=&'a str>)
-        -> Option<Self>
+    /// Like [`FromStr::from_str`],
+    /// but accept abbreviated object names.
+    ///
+    /// See [`ParsedPath::from_components_with`] for `resolve`.
+    pub fn parse_with(s: &str, resolve: impl Fn(&str) -> Option<Hash>)
+        -> Result<Self, InvalidPath>
+    {
+        Self::from_components_with(s.split('/'), resolve)
+            .ok_or(InvalidPath)
+    }
+
+    fn from_objects_components<'a>(
+        mut components: impl Iterator<Item=&'a str>,
+        resolve: &dyn Fn(&str) -> Option<Hash>,
+    ) -> Option<Self>
     {
         match (components.next(), components.next()) {
             (None,       _      ) => Some(Self::Objects),
-            (Some(hash), None   ) => hash.parse().ok().map(Self::ObjectsObject),
+            (Some(hash), None   ) => parse_hash(hash, resolve)
+                                         .map(Self::ObjectsObject),
+            (Some(_),    Some(_)) => None,
+        }
+    }
+
+    fn from_provenance_components<'a>(
+        mut components: impl Iterator<Item=&'a str>,
+        resolve: &dyn Fn(&str) -> Option<Hash>,
+    ) -> Option<Self>
+    {
+        match (components.next(), components.next()) {
+            (None,       _      ) => Some(Self::Provenance),
+            (Some(hash), None   ) => parse_hash(hash, resolve)
+                                         .map(Self::ProvenanceObject),
             (Some(_),    Some(_)) => None,
         }
     }
 }
 
+/// Parse a full hash, or resolve an abbreviated one.
+fn parse_hash(name: &str, resolve: &dyn Fn(&str) -> Option<Hash>)
+    -> Option<Hash>
+{
+    name.parse().ok().or_else(|| resolve(name))
+}
+
 /// Returned when a path could not be parsed.
 #[derive(Clone, Copy, Debug)]
 pub struct InvalidPath;
@@ -93,6 +155,12 @@ mod tests
                               "ffffffffffffffffffffffffffffffff/"),
              Some(ParsedPath::ObjectsObject(Hash{bytes: [0xFF; 32]}))),
 
+            ("provenance", Some(ParsedPath::Provenance)),
+            ("/provenance/", Some(ParsedPath::Provenance)),
+            (concat!("/provenance/ffffffffffffffffffffffffffffffff",
+                                 "ffffffffffffffffffffffffffffffff"),
+             Some(ParsedPath::ProvenanceObject(Hash{bytes: [0xFF; 32]}))),
+
             ("hello", None),
             ("/hello", None),
             ("objectsx", None),
@@ -104,6 +172,9 @@ mod tests
                               "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF"), None),
             (concat!("/objects/00000000000000000000000000000000",
                               "000000000000000000000000000000000"), None),
+            ("/provenance/x", None),
+            (concat!("/provenance/ffffffffffffffffffffffffffffffff",
+                                 "ffffffffffffffffffffffffffffffff/x"), None),
 
         ];
 
@@ -112,4 +183,28 @@ mod tests
             assert_eq!(&actual, expected);
         }
     }
+
+    #[test]
+    fn test_parse_with()
+    {
+        let hash = Hash{bytes: [0xAB; 32]};
+        let resolve = |name: &str| {
+            if name == "abab" { Some(hash) } else { None }
+        };
+
+        let examples = &[
+            ("/objects/abab", Some(ParsedPath::ObjectsObject(hash))),
+            ("/provenance/abab/", Some(ParsedPath::ProvenanceObject(hash))),
+            (concat!("/objects/ffffffffffffffffffffffffffffffff",
+                              "ffffffffffffffffffffffffffffffff"),
+             Some(ParsedPath::ObjectsObject(Hash{bytes: [0xFF; 32]}))),
+            ("/objects/ab", None),
+            ("/objects/abab/x", None),
+        ];
+
+        for (input, expected) in examples {
+            let actual = ParsedPath::parse_with(input, resolve).ok();
+            assert_eq!(&actual, expected);
+        }
+    }
 }
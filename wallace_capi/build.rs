@@ -0,0 +1,30 @@
+//! Generate the header file from the functions in this crate,
+//! so that the two cannot get out of sync.
+//!
+//! The header is written to `OUT_DIR`, so that building
+//! never touches the source tree; a test checks that it matches
+//! the committed copy in `include/wallace.h`.
+//! To update the committed copy, build with the environment variable
+//! `WALLACE_CAPI_UPDATE_HEADER` set to `1`.
+
+use std::env;
+use std::path::PathBuf;
+
+fn main()
+{
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml"))
+        .expect("cannot read cbindgen.toml");
+
+    let bindings = cbindgen::generate_with_config(&crate_dir, config)
+        .expect("cannot generate header file");
+    bindings.write_to_file(out_dir.join("wallace.h"));
+    if env::var_os("WALLACE_CAPI_UPDATE_HEADER").is_some_and(|v| v == "1") {
+        bindings.write_to_file(crate_dir.join("include/wallace.h"));
+    }
+
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-env-changed=WALLACE_CAPI_UPDATE_HEADER");
+}
@@ -0,0 +1,163 @@
+/*
+ * C interface to wallace volumes.
+ *
+ * Functions that return int return zero on success,
+ * and a positive errno value on failure.
+ * Negative values, such as WALLACE_STOPPED, are not failures.
+ * Hashes are passed as pointers to 32 bytes.
+ * File descriptors remain owned by the caller.
+ *
+ * See the documentation of the wallace_capi crate for more information.
+ */
+
+#ifndef WALLACE_H
+#define WALLACE_H
+
+/* Generated by build.rs with cbindgen; do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Returned by [`wallace_volume_all`] when the callback stopped it.
+ *
+ * This is negative, so that it cannot be mistaken for an `errno` value.
+ */
+#define WALLACE_STOPPED -1
+
+/**
+ * Opaque handle to an opened volume.
+ */
+typedef struct wallace_volume wallace_volume;
+
+/**
+ * Callback invoked by [`wallace_volume_all`] for each object.
+ *
+ * Returning a non-zero value stops the iteration,
+ * and causes [`wallace_volume_all`] to return [`WALLACE_STOPPED`].
+ */
+typedef int (*wallace_volume_all_callback)(const uint8_t *hash, void *userdata);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Open the volume at the given path.
+ *
+ * On success, `*out` is set to a handle
+ * that must be closed with [`wallace_volume_close`].
+ *
+ * # Safety
+ *
+ * `path` must point to a null-terminated string,
+ * and `out` must point to writable memory.
+ */
+int wallace_volume_open(const char *path, struct wallace_volume **out);
+
+/**
+ * Close a volume handle.
+ *
+ * Passing a null pointer is allowed and does nothing.
+ *
+ * # Safety
+ *
+ * `volume` must be null or a handle returned by [`wallace_volume_open`]
+ * that was not closed before.
+ */
+void wallace_volume_close(struct wallace_volume *volume);
+
+/**
+ * Insert an object from a file descriptor,
+ * as in [`Volume::insert_from_file`].
+ *
+ * The hash of the object is written to the 32 bytes at `hash`.
+ * The caller keeps ownership of the file descriptor.
+ *
+ * The file is linked into the volume rather than copied,
+ * so the caller's file becomes the object:
+ * its permissions are changed to `0400`,
+ * and it must not be modified afterwards.
+ * The file is read through the caller's file descriptor,
+ * which shares its offset with the caller;
+ * the offset is restored before this function returns.
+ * To keep the file as it is, use
+ * [`wallace_volume_insert_from_buffer`] instead.
+ *
+ * # Safety
+ *
+ * `volume` must be a valid handle,
+ * and `hash` must point to 32 writable bytes.
+ */
+int wallace_volume_insert_from_fd(const struct wallace_volume *volume,
+                                  int fd,
+                                  uint8_t *hash);
+
+/**
+ * Insert an object from a buffer,
+ * as in [`Volume::insert_from_reader`].
+ *
+ * The hash of the object is written to the 32 bytes at `hash`.
+ *
+ * # Safety
+ *
+ * `volume` must be a valid handle,
+ * `buf` must point to `len` readable bytes,
+ * and `hash` must point to 32 writable bytes.
+ */
+int wallace_volume_insert_from_buffer(const struct wallace_volume *volume,
+                                      const uint8_t *buf,
+                                      size_t len,
+                                      uint8_t *hash);
+
+/**
+ * Write the contents of an object to a file descriptor.
+ *
+ * If the object does not exist, this function returns `ENOENT`.
+ *
+ * # Safety
+ *
+ * `volume` must be a valid handle,
+ * and `hash` must point to 32 readable bytes.
+ */
+int wallace_volume_get_to_fd(const struct wallace_volume *volume,
+                             const uint8_t *hash,
+                             int fd);
+
+/**
+ * Call a function for each object in the volume,
+ * as in [`Volume::all`].
+ *
+ * The callback receives a pointer to the 32 bytes of the hash,
+ * which is only valid for the duration of the call.
+ * If the callback returns a non-zero value,
+ * this function stops and returns [`WALLACE_STOPPED`].
+ *
+ * # Safety
+ *
+ * `volume` must be a valid handle,
+ * and `callback` must be safe to call with `userdata`.
+ */
+int wallace_volume_all(const struct wallace_volume *volume,
+                       wallace_volume_all_callback callback,
+                       void *userdata);
+
+/**
+ * Format a hash as 64 lowercase hexadecimal digits,
+ * followed by a null terminator.
+ *
+ * # Safety
+ *
+ * `hash` must point to 32 readable bytes,
+ * and `out` must point to 65 writable bytes.
+ */
+void wallace_hash_format(const uint8_t *hash, char *out);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* WALLACE_H */
@@ -0,0 +1,414 @@
+//! C interface to the `wallace_volume` crate.
+//!
+//! This crate is built as a shared library,
+//! so that programs written in languages other than Rust
+//! can work with volumes without spawning subprocesses.
+//! The accompanying header file is `include/wallace.h`,
+//! which the build script generates from this crate with cbindgen.
+//! See the build script for how to update it.
+//!
+//! # Error handling
+//!
+//! Functions that can fail return an `int`.
+//! Zero means success; a positive value is an `errno` value
+//! describing the failure.
+//! If the failure had no corresponding `errno` value,
+//! `EIO` is returned instead.
+//! Negative values are reserved for outcomes that are not failures,
+//! such as [`WALLACE_STOPPED`].
+//!
+//! Panics do not unwind into the caller, which would be undefined behaviour.
+//! They are caught, and reported as `EIO`.
+//!
+//! # Ownership
+//!
+//! File descriptors passed to these functions remain owned by the caller.
+//! The functions never close them.
+
+#![doc(html_favicon_url = "../../../marketing/logo.svg")]
+#![doc(html_logo_url = "../../../marketing/logo.svg")]
+#![allow(non_camel_case_types)]
+
+use std::ffi::CStr;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::Error;
+use std::io::Result;
+use std::io::copy;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::mem::ManuallyDrop;
+use std::os::raw::c_char;
+use std::os::raw::c_int;
+use std::os::raw::c_void;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::FromRawFd;
+use std::panic::AssertUnwindSafe;
+use std::panic::catch_unwind;
+use std::slice;
+use ::wallace_volume::Hash;
+use ::wallace_volume::Volume;
+use ::wallace_volume::VolumeError;
+
+/// Opaque handle to an opened volume.
+pub struct wallace_volume
+{
+    inner: Volume,
+}
+
+/// Returned by [`wallace_volume_all`] when the callback stopped it.
+///
+/// This is negative, so that it cannot be mistaken for an `errno` value.
+pub const WALLACE_STOPPED: c_int = -1;
+
+/// Callback invoked by [`wallace_volume_all`] for each object.
+///
+/// Returning a non-zero value stops the iteration,
+/// and causes [`wallace_volume_all`] to return [`WALLACE_STOPPED`].
+pub type wallace_volume_all_callback =
+    unsafe extern "C" fn(hash: *const u8, userdata: *mut c_void) -> c_int;
+
+/// Run the body of a function that returns a status code,
+/// reporting a panic as `EIO` rather than unwinding into the caller.
+fn guard(body: impl FnOnce() -> c_int) -> c_int
+{
+    catch_unwind(AssertUnwindSafe(body)).unwrap_or(libc::EIO)
+}
+
+/// Run the body of a function that returns nothing,
+/// aborting on a panic rather than unwinding into the caller.
+fn guard_void(body: impl FnOnce())
+{
+    if catch_unwind(AssertUnwindSafe(body)).is_err() {
+        std::process::abort();
+    }
+}
+
+/// Convert a result into a status code.
+fn status(result: Result<()>) -> c_int
+{
+    match result {
+        Ok(()) => 0,
+        Err(err) =>
+            VolumeError::from(err).raw_os_error().unwrap_or(libc::EIO),
+    }
+}
+
+/// Read a hash from a pointer to 32 bytes.
+unsafe fn read_hash(hash: *const u8) -> Hash
+{
+    let mut bytes = [0; 32];
+    bytes.copy_from_slice(slice::from_raw_parts(hash, 32));
+    Hash{bytes}
+}
+
+/// Borrow a file descriptor as a file without taking ownership.
+unsafe fn borrow_fd(fd: c_int) -> ManuallyDrop<File>
+{
+    ManuallyDrop::new(File::from_raw_fd(fd))
+}
+
+/// Open the volume at the given path.
+///
+/// On success, `*out` is set to a handle
+/// that must be closed with [`wallace_volume_close`].
+///
+/// # Safety
+///
+/// `path` must point to a null-terminated string,
+/// and `out` must point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn wallace_volume_open(
+    path: *const c_char,
+    out: *mut *mut wallace_volume,
+) -> c_int
+{
+    guard(|| {
+        let path = OsStr::from_bytes(CStr::from_ptr(path).to_bytes());
+        status(
+            Volume::open(path).map(|inner| {
+                *out = Box::into_raw(Box::new(wallace_volume{inner}));
+            })
+        )
+    })
+}
+
+/// Close a volume handle.
+///
+/// Passing a null pointer is allowed and does nothing.
+///
+/// # Safety
+///
+/// `volume` must be null or a handle returned by [`wallace_volume_open`]
+/// that was not closed before.
+#[no_mangle]
+pub unsafe extern "C" fn wallace_volume_close(volume: *mut wallace_volume)
+{
+    guard_void(|| {
+        if !volume.is_null() {
+            drop(Box::from_raw(volume));
+        }
+    })
+}
+
+/// Insert an object from a file descriptor,
+/// as in [`Volume::insert_from_file`].
+///
+/// The hash of the object is written to the 32 bytes at `hash`.
+/// The caller keeps ownership of the file descriptor.
+///
+/// The file is linked into the volume rather than copied,
+/// so the caller's file becomes the object:
+/// its permissions are changed to `0400`,
+/// and it must not be modified afterwards.
+/// The file is read through the caller's file descriptor,
+/// which shares its offset with the caller;
+/// the offset is restored before this function returns.
+/// To keep the file as it is, use
+/// [`wallace_volume_insert_from_buffer`] instead.
+///
+/// # Safety
+///
+/// `volume` must be a valid handle,
+/// and `hash` must point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wallace_volume_insert_from_fd(
+    volume: *const wallace_volume,
+    fd: c_int,
+    hash: *mut u8,
+) -> c_int
+{
+    guard(|| {
+        // insert_from_file takes ownership of the file,
+        // so give it a duplicate of the caller's file descriptor.
+        let mut file = borrow_fd(fd);
+        status((|| {
+            let offset = file.stream_position()?;
+            let result = file.try_clone()
+                .and_then(|file| (*volume).inner.insert_from_file(file));
+            file.seek(SeekFrom::Start(offset))?;
+            let h = result?;
+            hash.copy_from_nonoverlapping(h.bytes.as_ptr(), 32);
+            Ok(())
+        })())
+    })
+}
+
+/// Insert an object from a buffer,
+/// as in [`Volume::insert_from_reader`].
+///
+/// The hash of the object is written to the 32 bytes at `hash`.
+///
+/// # Safety
+///
+/// `volume` must be a valid handle,
+/// `buf` must point to `len` readable bytes,
+/// and `hash` must point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wallace_volume_insert_from_buffer(
+    volume: *const wallace_volume,
+    buf: *const u8,
+    len: usize,
+    hash: *mut u8,
+) -> c_int
+{
+    guard(|| {
+        let mut buf = if len == 0 { &[][..] }
+                      else { slice::from_raw_parts(buf, len) };
+        status(
+            (*volume).inner.insert_from_reader(&mut buf)
+            .map(|h| hash.copy_from_nonoverlapping(h.bytes.as_ptr(), 32))
+        )
+    })
+}
+
+/// Write the contents of an object to a file descriptor.
+///
+/// If the object does not exist, this function returns `ENOENT`.
+///
+/// # Safety
+///
+/// `volume` must be a valid handle,
+/// and `hash` must point to 32 readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wallace_volume_get_to_fd(
+    volume: *const wallace_volume,
+    hash: *const u8,
+    fd: c_int,
+) -> c_int
+{
+    guard(|| {
+        let hash = read_hash(hash);
+        let mut file = borrow_fd(fd);
+        status(
+            (*volume).inner.get(hash).and_then(|object| {
+                let (mut reader, _) = object.ok_or_else(|| {
+                    Error::from_raw_os_error(libc::ENOENT)
+                })?;
+                copy(&mut reader, &mut *file)?;
+                Ok(())
+            })
+        )
+    })
+}
+
+/// Call a function for each object in the volume,
+/// as in [`Volume::all`].
+///
+/// The callback receives a pointer to the 32 bytes of the hash,
+/// which is only valid for the duration of the call.
+/// If the callback returns a non-zero value,
+/// this function stops and returns [`WALLACE_STOPPED`].
+///
+/// # Safety
+///
+/// `volume` must be a valid handle,
+/// and `callback` must be safe to call with `userdata`.
+#[no_mangle]
+pub unsafe extern "C" fn wallace_volume_all(
+    volume: *const wallace_volume,
+    callback: wallace_volume_all_callback,
+    userdata: *mut c_void,
+) -> c_int
+{
+    guard(|| {
+        let all = match (*volume).inner.all() {
+            Ok(all) => all,
+            Err(err) => return status(Err(err)),
+        };
+
+        for hash in all {
+            let hash = match hash {
+                Ok(hash) => hash,
+                Err(err) => return status(Err(err)),
+            };
+            if callback(hash.bytes.as_ptr(), userdata) != 0 {
+                return WALLACE_STOPPED;
+            }
+        }
+
+        0
+    })
+}
+
+/// Format a hash as 64 lowercase hexadecimal digits,
+/// followed by a null terminator.
+///
+/// # Safety
+///
+/// `hash` must point to 32 readable bytes,
+/// and `out` must point to 65 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wallace_hash_format(
+    hash: *const u8,
+    out: *mut c_char,
+)
+{
+    guard_void(|| {
+        let text = format!("{}\0", read_hash(hash));
+        out.copy_from_nonoverlapping(text.as_ptr() as *const c_char, 65);
+    })
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::ffi::CString;
+    use std::fs;
+    use std::os::unix::io::AsRawFd;
+    use std::ptr::null_mut;
+    use super::*;
+    use ::wallace_volume::TestData;
+
+    #[test]
+    fn test_capi()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_capi").unwrap();
+        let output_path = test_data.root_path.join("output");
+
+        unsafe extern "C" fn collect(hash: *const u8, userdata: *mut c_void)
+            -> c_int
+        {
+            let hashes = &mut *(userdata as *mut Vec<Hash>);
+            hashes.push(read_hash(hash));
+            0
+        }
+
+        unsafe extern "C" fn stop(_hash: *const u8, _userdata: *mut c_void)
+            -> c_int
+        {
+            1
+        }
+
+        unsafe {
+            // Open the volume.
+            let path = test_data.volume1_path.as_os_str().as_bytes();
+            let path = CString::new(path).unwrap();
+            let mut volume = null_mut();
+            assert_eq!(wallace_volume_open(path.as_ptr(), &mut volume), 0);
+
+            // Insert the object.
+            let mut hash = [0; 32];
+            let status = wallace_volume_insert_from_buffer(
+                volume, b"hello".as_ptr(), 5, hash.as_mut_ptr());
+            assert_eq!(status, 0);
+            assert_eq!(hash, Hash::compute_from_bytes(b"hello").bytes);
+
+            // Insert the object from a file descriptor.
+            let mut regular = File::open(&test_data.regular1_path).unwrap();
+            regular.seek(SeekFrom::Start(2)).unwrap();
+            let mut hash_fd = [0; 32];
+            let status = wallace_volume_insert_from_fd(
+                volume, regular.as_raw_fd(), hash_fd.as_mut_ptr());
+            assert_eq!(status, 0);
+            assert_eq!(hash_fd, test_data.regular1_hash.bytes);
+            assert_eq!(regular.stream_position().unwrap(), 2);
+
+            // Format the hash.
+            let mut text = [0; 65];
+            wallace_hash_format(hash.as_ptr(), text.as_mut_ptr());
+            let text = CStr::from_ptr(text.as_ptr()).to_str().unwrap();
+            assert_eq!(text, format!("{}", Hash{bytes: hash}));
+
+            // Get the object.
+            let output = File::create(&output_path).unwrap();
+            let status = wallace_volume_get_to_fd(
+                volume, hash.as_ptr(), output.as_raw_fd());
+            assert_eq!(status, 0);
+            assert_eq!(fs::read(&output_path).unwrap(), b"hello");
+
+            // Get a missing object.
+            let missing = [0; 32];
+            let status = wallace_volume_get_to_fd(
+                volume, missing.as_ptr(), output.as_raw_fd());
+            assert_eq!(status, libc::ENOENT);
+
+            // List the objects.
+            let mut hashes = Vec::<Hash>::new();
+            let status = wallace_volume_all(
+                volume, collect, &mut hashes as *mut _ as *mut c_void);
+            assert_eq!(status, 0);
+            assert_eq!(hashes, vec![Hash{bytes: hash}]);
+
+            // Stop listing the objects.
+            let status = wallace_volume_all(volume, stop, null_mut());
+            assert_eq!(status, WALLACE_STOPPED);
+
+            wallace_volume_close(volume);
+        }
+    }
+
+    #[test]
+    fn test_header()
+    {
+        // The header generated by the build script.
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/wallace.h"));
+        let committed = include_str!("../include/wallace.h");
+
+        // Check the results.
+        assert!(generated == committed,
+                "include/wallace.h is out of date; \
+                 build with WALLACE_CAPI_UPDATE_HEADER=1 to update it");
+    }
+}
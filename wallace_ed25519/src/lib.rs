@@ -0,0 +1,263 @@
+//! Implementation of Ed25519 signatures based on libsodium.
+
+#![doc(html_favicon_url = "../../../marketing/logo.svg")]
+#![doc(html_logo_url = "../../../marketing/logo.svg")]
+
+use std::fmt;
+use std::os::raw::c_int;
+use std::os::raw::c_uchar;
+use std::os::raw::c_ulonglong;
+use std::ptr::null_mut;
+use std::str::FromStr;
+
+#[link(name = "sodium")]
+extern "C"
+{
+    fn sodium_init() -> c_int;
+
+    fn crypto_sign_keypair(
+        pk: *mut c_uchar,
+        sk: *mut c_uchar,
+    ) -> c_int;
+
+    fn crypto_sign_seed_keypair(
+        pk:   *mut c_uchar,
+        sk:   *mut c_uchar,
+        seed: *const c_uchar,
+    ) -> c_int;
+
+    fn crypto_sign_detached(
+        sig:    *mut c_uchar,
+        siglen: *mut c_ulonglong,
+        m:      *const c_uchar,
+        mlen:   c_ulonglong,
+        sk:     *const c_uchar,
+    ) -> c_int;
+
+    fn crypto_sign_verify_detached(
+        sig:  *const c_uchar,
+        m:    *const c_uchar,
+        mlen: c_ulonglong,
+        pk:   *const c_uchar,
+    ) -> c_int;
+}
+
+/// Public key used to verify signatures.
+///
+/// The [`Display`][`fmt::Display`] impl formats the public key
+/// as a 64-digit lowercase hexadecimal number.
+/// The [`FromStr`] impl parses this same format.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PublicKey
+{
+    /// The bytes that make up the public key.
+    pub bytes: [u8; 32],
+}
+
+/// Secret key used to create signatures.
+///
+/// The bytes of the secret key are deliberately not exposed,
+/// and the [`Debug`][`fmt::Debug`] impl does not print them.
+#[derive(Clone)]
+pub struct SecretKey
+{
+    bytes: [u8; 64],
+}
+
+/// Detached signature over a message.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Signature
+{
+    /// The bytes that make up the signature.
+    pub bytes: [u8; 64],
+}
+
+/// Generate a new random key pair.
+pub fn keypair() -> (PublicKey, SecretKey)
+{
+    let mut pk = [0; 32];
+    let mut sk = [0; 64];
+
+    // SAFETY: The buffers have the sizes libsodium expects.
+    // sodium_init must be called before using the random number generator;
+    // it is safe to call multiple times and from multiple threads.
+    unsafe {
+        if sodium_init() == -1 {
+            panic!("sodium_init failed");
+        }
+        crypto_sign_keypair(pk.as_mut_ptr(), sk.as_mut_ptr());
+    }
+
+    (PublicKey{bytes: pk}, SecretKey{bytes: sk})
+}
+
+/// Deterministically derive a key pair from a 32-byte seed.
+pub fn keypair_from_seed(seed: &[u8; 32]) -> (PublicKey, SecretKey)
+{
+    let mut pk = [0; 32];
+    let mut sk = [0; 64];
+
+    // SAFETY: The buffers have the sizes libsodium expects.
+    unsafe {
+        crypto_sign_seed_keypair(pk.as_mut_ptr(), sk.as_mut_ptr(),
+                                 seed.as_ptr());
+    }
+
+    (PublicKey{bytes: pk}, SecretKey{bytes: sk})
+}
+
+impl SecretKey
+{
+    /// Sign a message, returning a detached signature.
+    pub fn sign(&self, message: &[u8]) -> Signature
+    {
+        let mut sig = [0; 64];
+
+        // SAFETY: The buffers have the sizes libsodium expects.
+        unsafe {
+            crypto_sign_detached(
+                sig.as_mut_ptr(),
+                null_mut(),
+                message.as_ptr(),
+                message.len() as u64,
+                self.bytes.as_ptr(),
+            );
+        }
+
+        Signature{bytes: sig}
+    }
+
+    /// The public key that verifies signatures made with this secret key.
+    pub fn public_key(&self) -> PublicKey
+    {
+        // libsodium stores the public key in the second half.
+        let mut bytes = [0; 32];
+        bytes.copy_from_slice(&self.bytes[32 ..]);
+        PublicKey{bytes}
+    }
+}
+
+impl fmt::Debug for SecretKey
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        f.debug_struct("SecretKey").finish_non_exhaustive()
+    }
+}
+
+impl PublicKey
+{
+    /// Check that the signature over the message
+    /// was made with the corresponding secret key.
+    pub fn verify(&self, message: &[u8], signature: &Signature) -> bool
+    {
+        // SAFETY: The buffers have the sizes libsodium expects.
+        let status = unsafe {
+            crypto_sign_verify_detached(
+                signature.bytes.as_ptr(),
+                message.as_ptr(),
+                message.len() as u64,
+                self.bytes.as_ptr(),
+            )
+        };
+
+        status == 0
+    }
+
+    /// Similar to the [`FromStr`] impl,
+    /// but takes `[u8]` instead of [`str`].
+    pub fn from_ascii(s: &[u8]) -> Result<Self, InvalidPublicKey>
+    {
+        if s.len() != 64 {
+            return Err(InvalidPublicKey);
+        }
+
+        fn hex(c: u8) -> Result<u8, InvalidPublicKey>
+        {
+            match c {
+                b'0' ..= b'9' => Ok(c - b'0'),
+                b'a' ..= b'f' => Ok(c - b'a' + 10),
+                _ => Err(InvalidPublicKey),
+            }
+        }
+
+        let mut bytes = [0; 32];
+        for (i, pair) in s.chunks(2).enumerate() {
+            bytes[i] = hex(pair[0])? << 4 | hex(pair[1])?;
+        }
+
+        Ok(Self{bytes})
+    }
+}
+
+impl fmt::Display for PublicKey
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        for byte in &self.bytes {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returned when a public key could not be parsed.
+#[derive(Clone, Copy, Debug)]
+pub struct InvalidPublicKey;
+
+impl FromStr for PublicKey
+{
+    type Err = InvalidPublicKey;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        PublicKey::from_ascii(s.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_rfc8032_vector()
+    {
+        // Test 1 from section 7.1 of RFC 8032.
+        let seed =
+            [0x9d, 0x61, 0xb1, 0x9d, 0xef, 0xfd, 0x5a, 0x60,
+             0xba, 0x84, 0x4a, 0xf4, 0x92, 0xec, 0x2c, 0xc4,
+             0x44, 0x49, 0xc5, 0x69, 0x7b, 0x32, 0x69, 0x19,
+             0x70, 0x3b, 0xac, 0x03, 0x1c, 0xae, 0x7f, 0x60];
+        let expected_pk = concat!("d75a980182b10ab7d54bfed3c964073a",
+                                  "0ee172f3daa62325af021a68f707511a");
+        let expected_sig =
+            [0xe5, 0x56, 0x43, 0x00, 0xc3, 0x60, 0xac, 0x72,
+             0x90, 0x86, 0xe2, 0xcc, 0x80, 0x6e, 0x82, 0x8a,
+             0x84, 0x87, 0x7f, 0x1e, 0xb8, 0xe5, 0xd9, 0x74,
+             0xd8, 0x73, 0xe0, 0x65, 0x22, 0x49, 0x01, 0x55,
+             0x5f, 0xb8, 0x82, 0x15, 0x90, 0xa3, 0x3b, 0xac,
+             0xc6, 0x1e, 0x39, 0x70, 0x1c, 0xf9, 0xb4, 0x6b,
+             0xd2, 0x5b, 0xf5, 0xf0, 0x59, 0x5b, 0xbe, 0x24,
+             0x65, 0x51, 0x41, 0x43, 0x8e, 0x7a, 0x10, 0x0b];
+
+        let (pk, sk) = keypair_from_seed(&seed);
+        let sig = sk.sign(b"");
+
+        assert_eq!(format!("{}", pk), expected_pk);
+        assert_eq!(sk.public_key(), pk);
+        assert_eq!(sig.bytes[..], expected_sig[..]);
+        assert!(pk.verify(b"", &sig));
+    }
+
+    #[test]
+    fn test_verify()
+    {
+        let (pk1, sk1) = keypair();
+        let (pk2, _)   = keypair();
+        let sig = sk1.sign(b"Hello, world!");
+        assert!( pk1.verify(b"Hello, world!", &sig));
+        assert!(!pk1.verify(b"Hello, world?", &sig));
+        assert!(!pk2.verify(b"Hello, world!", &sig));
+    }
+}
@@ -0,0 +1,43 @@
+use crate::FsError;
+use crate::Result;
+use libc::loff_t;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+use std::ptr::null_mut;
+
+/// Perform the `copy_file_range` system call.
+///
+/// If an offset is given, it is used and updated
+/// instead of the file offset of the corresponding file.
+/// Returns the number of bytes that were copied,
+/// which is zero at the end of the input file.
+pub fn copy_file_range(
+    fd_in: impl AsFd,
+    off_in: Option<&mut loff_t>,
+    fd_out: impl AsFd,
+    off_out: Option<&mut loff_t>,
+    len: usize,
+) -> Result<usize>
+{
+    let off_in_ptr = off_in.map_or(null_mut(), |o| o as *mut loff_t);
+    let off_out_ptr = off_out.map_or(null_mut(), |o| o as *mut loff_t);
+
+    // SAFETY: The offset pointers are either null
+    // or derived from mutable references.
+    let status = unsafe {
+        libc::copy_file_range(
+            fd_in.as_fd().as_raw_fd(),
+            off_in_ptr,
+            fd_out.as_fd().as_raw_fd(),
+            off_out_ptr,
+            len,
+            0,
+        )
+    };
+
+    if status == -1 {
+        Err(FsError::last_os_error("copy_file_range", None))
+    } else {
+        Ok(status as usize)
+    }
+}
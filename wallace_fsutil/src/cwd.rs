@@ -0,0 +1,11 @@
+use std::os::unix::io::BorrowedFd;
+
+/// The current working directory,
+/// for passing to the `*at` functions in place of a directory.
+///
+/// This is the `AT_FDCWD` constant, which is not a file descriptor,
+/// so it must only be passed where the system call expects a directory.
+// SAFETY: AT_FDCWD is not -1, and it is never closed.
+pub const CWD: BorrowedFd<'static> = unsafe {
+    BorrowedFd::borrow_raw(libc::AT_FDCWD)
+};
@@ -0,0 +1,88 @@
+use crate::FsError;
+use crate::Result;
+use std::os::raw::c_int;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::OwnedFd;
+
+/// Perform the `dup3` system call.
+///
+/// The file descriptor `newfd` is atomically replaced
+/// by a duplicate of `oldfd`, keeping its number.
+/// This is useful for replacing standard input or output.
+/// The only flag is `O_CLOEXEC`, which is not inherited from `oldfd`.
+/// Passing the same file descriptor twice fails with `EINVAL`.
+///
+/// macOS lacks `dup3`, so there it is emulated with `dup2`,
+/// and the flag is set afterwards.
+pub fn dup3(oldfd: impl AsFd, newfd: &mut OwnedFd, flags: c_int)
+    -> Result<()>
+{
+    let oldfd = oldfd.as_fd().as_raw_fd();
+
+    // SAFETY: This function just takes integers,
+    // and the new file descriptor remains owned by newfd.
+    #[cfg(not(target_os = "macos"))]
+    let status = unsafe {
+        libc::dup3(oldfd, newfd.as_raw_fd(), flags)
+    };
+
+    // SAFETY: As above.
+    #[cfg(target_os = "macos")]
+    let status = if oldfd == newfd.as_raw_fd() {
+        let err = std::io::Error::from_raw_os_error(libc::EINVAL);
+        return Err(FsError::new("dup3", None, err));
+    } else {
+        unsafe { libc::dup2(oldfd, newfd.as_raw_fd()) }
+    };
+
+    if status == -1 {
+        return Err(FsError::last_os_error("dup3", None));
+    }
+
+    #[cfg(target_os = "macos")]
+    crate::fcntl::emulate_open_flags(&*newfd, flags)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::fcntl_getfd;
+    use crate::pipe2;
+    use super::*;
+    use std::fs::File;
+    use std::io::Read;
+    use std::io::Write;
+    use std::os::unix::io::BorrowedFd;
+
+    #[test]
+    fn test_dup3()
+    {
+        // Prepare the test.
+        let (mut read1, write1) = pipe2(libc::O_CLOEXEC).unwrap();
+        let (_read2, write2) = pipe2(libc::O_CLOEXEC).unwrap();
+        let mut write2 = OwnedFd::from(write2);
+        let raw_write2 = write2.as_raw_fd();
+
+        // Redirect the second pipe into the first,
+        // and try to duplicate a file descriptor onto itself.
+        dup3(&write1, &mut write2, 0).unwrap();
+        drop(write1);
+        // SAFETY: The file descriptor remains owned by write2.
+        let same = unsafe { BorrowedFd::borrow_raw(raw_write2) };
+        let err = dup3(same, &mut write2, 0).unwrap_err();
+        let fd_flags = fcntl_getfd(&write2).unwrap();
+        File::from(write2).write_all(b"Hello").unwrap();
+
+        // Check the results.
+        let mut contents = Vec::new();
+        read1.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, b"Hello");
+        assert_eq!(fd_flags & libc::FD_CLOEXEC, 0);
+        assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+        assert_eq!(err.syscall(), "dup3");
+        assert_eq!(err.path(), None);
+    }
+}
@@ -0,0 +1,43 @@
+use std::os::raw::c_int;
+
+/// Set `errno` for the calling thread.
+///
+/// Every C library keeps `errno` in a thread-local variable,
+/// but each has its own name for the function that finds it.
+pub (crate) fn set_errno(value: c_int)
+{
+    // SAFETY: The pointer is valid for the calling thread.
+    unsafe {
+        *errno_location() = value;
+    }
+}
+
+#[cfg(target_os = "linux")]
+unsafe fn errno_location() -> *mut c_int
+{
+    libc::__errno_location()
+}
+
+#[cfg(any(target_os = "freebsd", target_os = "macos"))]
+unsafe fn errno_location() -> *mut c_int
+{
+    libc::__error()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::io::Error;
+
+    #[test]
+    fn test_set_errno()
+    {
+        // Set errno, and read it back.
+        set_errno(libc::EINTR);
+        let err = Error::last_os_error();
+
+        // Check the results.
+        assert_eq!(err.raw_os_error(), Some(libc::EINTR));
+    }
+}
@@ -0,0 +1,169 @@
+use std::error::Error;
+use std::ffi::CString;
+use std::fmt;
+use std::io;
+use std::io::ErrorKind;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Result of the functions in this crate.
+pub type Result<T> = std::result::Result<T, FsError>;
+
+/// Failure of a system call, with the system call and path that failed.
+///
+/// Every function in this crate returns this error,
+/// so that reports say what failed rather than just why.
+/// For system calls with two paths, such as `renameat`,
+/// the path is the new path.
+///
+/// The error converts into an [`io::Error`] of the same kind
+/// that keeps the system call and path,
+/// so `?` works in functions that return [`io::Result`].
+/// The `errno` value is then hidden inside the [`io::Error`];
+/// use [`FsError::raw_os_error_of`] to find it.
+///
+/// [`io::Result`]: `std::io::Result`
+#[derive(Debug)]
+pub struct FsError
+{
+    syscall: &'static str,
+    path: Option<PathBuf>,
+    source: io::Error,
+}
+
+impl FsError
+{
+    /// Attribute an error to a system call and, optionally, a path.
+    pub fn new(syscall: &'static str, path: Option<&Path>, source: io::Error)
+        -> Self
+    {
+        Self{syscall, path: path.map(Path::to_owned), source}
+    }
+
+    /// Attribute the current `errno` to a system call and path.
+    pub (crate) fn last_os_error(syscall: &'static str, path: Option<&Path>)
+        -> Self
+    {
+        Self::new(syscall, path, io::Error::last_os_error())
+    }
+
+    /// The name of the system call that failed.
+    pub fn syscall(&self) -> &'static str
+    {
+        self.syscall
+    }
+
+    /// The path passed to the system call, if any.
+    pub fn path(&self) -> Option<&Path>
+    {
+        self.path.as_deref()
+    }
+
+    /// The kind of the underlying error.
+    pub fn kind(&self) -> ErrorKind
+    {
+        self.source.kind()
+    }
+
+    /// The `errno` value of the underlying error, if any.
+    pub fn raw_os_error(&self) -> Option<i32>
+    {
+        self.source.raw_os_error()
+    }
+
+    /// The underlying error, without the system call and path.
+    pub fn into_source(self) -> io::Error
+    {
+        self.source
+    }
+
+    /// The `errno` value of an [`io::Error`],
+    /// also if it was converted from an [`FsError`].
+    pub fn raw_os_error_of(err: &io::Error) -> Option<i32>
+    {
+        err.raw_os_error().or_else(|| {
+            err.get_ref()?.downcast_ref::<Self>()?.raw_os_error()
+        })
+    }
+}
+
+impl fmt::Display for FsError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match &self.path {
+            Some(path) =>
+                write!(f, "{} {}: {}", self.syscall, path.display(),
+                       self.source),
+            None =>
+                write!(f, "{}: {}", self.syscall, self.source),
+        }
+    }
+}
+
+impl Error for FsError
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)>
+    {
+        Some(&self.source)
+    }
+}
+
+impl From<FsError> for io::Error
+{
+    fn from(err: FsError) -> Self
+    {
+        io::Error::new(err.kind(), err)
+    }
+}
+
+/// Convert a path to a C string for the given system call,
+/// which fails if the path contains a null byte.
+pub (crate) fn path_to_cstring(syscall: &'static str, path: &Path)
+    -> Result<CString>
+{
+    CString::new(path.as_os_str().as_bytes())
+        .map_err(|err| FsError::new(syscall, Some(path), err.into()))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_fs_error()
+    {
+        // Prepare the test.
+        let source = io::Error::from_raw_os_error(libc::ENOENT);
+
+        // Attribute the error to a system call and a path,
+        // and convert it into an io::Error.
+        let err = FsError::new("openat", Some(Path::new("missing")), source);
+        let message = err.to_string();
+        let io_err = io::Error::from(err);
+
+        // Check the results.
+        assert!(message.starts_with("openat missing: "));
+        assert_eq!(io_err.kind(), ErrorKind::NotFound);
+        assert_eq!(io_err.to_string(), message);
+        assert_eq!(FsError::raw_os_error_of(&io_err), Some(libc::ENOENT));
+    }
+
+    #[test]
+    fn test_path_to_cstring()
+    {
+        // Try to convert a path with a null byte,
+        // and convert the error into an io::Error.
+        let err = path_to_cstring("openat", Path::new("a\0b")).unwrap_err();
+
+        let message = err.to_string();
+        let io_err = io::Error::from(err);
+
+        // Check the results.
+        assert_eq!(io_err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(io_err.raw_os_error(), None);
+        assert_eq!(io_err.to_string(), message);
+    }
+}
@@ -0,0 +1,32 @@
+use crate::FsError;
+use crate::Result;
+use crate::path_to_cstring;
+use std::os::raw::c_int;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+/// Perform the `faccessat` system call.
+pub fn faccessat(
+    dir: impl AsFd,
+    pathname: impl AsRef<Path>,
+    mode: c_int,
+    flags: c_int,
+) -> Result<()>
+{
+    let pathname = pathname.as_ref();
+    let pathname_c = path_to_cstring("faccessat", pathname)?;
+
+    // SAFETY: The C string is of type CString
+    // and is therefore null-terminated.
+    let status = unsafe {
+        libc::faccessat(dir.as_fd().as_raw_fd(), pathname_c.as_ptr(),
+                        mode, flags)
+    };
+
+    if status == -1 {
+        Err(FsError::last_os_error("faccessat", Some(pathname)))
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use crate::FsError;
+use crate::Result;
+use std::io::Error;
+#[cfg(target_os = "linux")]
+use std::os::raw::c_int;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+
+/// Perform the `fallocate` system call.
+///
+/// The mode is a combination of the `FALLOC_FL_*` flags,
+/// or zero to allocate the range and extend the file if necessary.
+/// See [`punch_hole`] for deallocating a range.
+/// File systems that do not support the mode fail with `EOPNOTSUPP`.
+#[cfg(target_os = "linux")]
+pub fn fallocate(fd: impl AsFd, mode: c_int,
+                 offset: libc::off_t, len: libc::off_t) -> Result<()>
+{
+    // SAFETY: This usage is safe.
+    let status = unsafe {
+        libc::fallocate(fd.as_fd().as_raw_fd(), mode, offset, len)
+    };
+
+    if status == -1 {
+        Err(FsError::last_os_error("fallocate", None))
+    } else {
+        Ok(())
+    }
+}
+
+/// Call `posix_fallocate`.
+///
+/// Unlike [`fallocate`], this works on every file system:
+/// where allocation is not supported,
+/// the C library emulates it by writing to each block,
+/// which is slow for large ranges.
+pub fn posix_fallocate(fd: impl AsFd,
+                       offset: libc::off_t, len: libc::off_t) -> Result<()>
+{
+    // SAFETY: This usage is safe.
+    let status = unsafe {
+        libc::posix_fallocate(fd.as_fd().as_raw_fd(), offset, len)
+    };
+
+    // posix_fallocate returns the error rather than setting errno.
+    if status != 0 {
+        Err(FsError::new("posix_fallocate", None,
+                         Error::from_raw_os_error(status)))
+    } else {
+        Ok(())
+    }
+}
+
+/// Deallocate a range of a file with `fallocate`,
+/// so that it reads as zeros without taking up space.
+///
+/// The size of the file does not change.
+/// Blocks that are only partially in the range are zeroed instead.
+#[cfg(target_os = "linux")]
+pub fn punch_hole(fd: impl AsFd,
+                  offset: libc::off_t, len: libc::off_t) -> Result<()>
+{
+    let mode = libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE;
+    fallocate(fd, mode, offset, len)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::testdata::TestData;
+    use super::*;
+    use std::fs::File;
+    use std::fs::OpenOptions;
+    #[cfg(target_os = "linux")]
+    use std::io::Read;
+
+    #[test]
+    fn test_posix_fallocate()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_posix_fallocate").unwrap();
+        let file = OpenOptions::new().write(true)
+            .open(&test_data.regular_path).unwrap();
+        let read_only = File::open(&test_data.regular_path).unwrap();
+
+        // Allocate beyond the end of the file,
+        // and try to allocate in a file not open for writing.
+        posix_fallocate(&file, 0, 4096).unwrap();
+        let err = posix_fallocate(&read_only, 0, 8192).unwrap_err();
+
+        // Check the results.
+        assert_eq!(file.metadata().unwrap().len(), 4096);
+        assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+        assert_eq!(err.syscall(), "posix_fallocate");
+        assert_eq!(err.path(), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_punch_hole()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_punch_hole").unwrap();
+        let mut file = OpenOptions::new().read(true).write(true)
+            .open(&test_data.regular_path).unwrap();
+
+        // Punch a hole in the middle of the file,
+        // and try to punch one at a negative offset.
+        punch_hole(&file, 2, 3).unwrap();
+        let err = punch_hole(&file, -1, 3).unwrap_err();
+
+        // Check the results.
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).unwrap();
+        let mut expected = test_data.regular_contents.clone();
+        expected[2 .. 5].fill(0);
+        assert_eq!(contents, expected);
+        assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+        assert_eq!(err.syscall(), "fallocate");
+        assert_eq!(err.path(), None);
+    }
+}
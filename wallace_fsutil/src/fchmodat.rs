@@ -0,0 +1,37 @@
+use crate::FsError;
+use crate::Result;
+use crate::path_to_cstring;
+use libc::mode_t;
+use std::os::raw::c_int;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+/// Perform the `fchmodat` system call.
+pub fn fchmodat(
+    dir: impl AsFd,
+    pathname: impl AsRef<Path>,
+    mode: mode_t,
+    flags: c_int,
+) -> Result<()>
+{
+    let pathname = pathname.as_ref();
+    let pathname_c = path_to_cstring("fchmodat", pathname)?;
+
+    // SAFETY: The C string is of type CString
+    // and is therefore null-terminated.
+    let status = unsafe {
+        libc::fchmodat(
+            dir.as_fd().as_raw_fd(),
+            pathname_c.as_ptr(),
+            mode,
+            flags,
+        )
+    };
+
+    if status == -1 {
+        Err(FsError::last_os_error("fchmodat", Some(pathname)))
+    } else {
+        Ok(())
+    }
+}
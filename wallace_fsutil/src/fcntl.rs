@@ -1,34 +1,292 @@
-use std::io::Error;
-use std::io::Result;
+use crate::FsError;
+use crate::Result;
 use std::os::raw::c_int;
+#[cfg(target_os = "linux")]
+use std::os::raw::c_short;
+use std::os::unix::io::AsFd;
 use std::os::unix::io::AsRawFd;
+#[cfg(target_os = "linux")]
+use std::os::unix::io::BorrowedFd;
 
 /// Perform the `fcntl` system call with command `F_GETFD`.
-pub fn fcntl_getfd(fd: &impl AsRawFd) -> Result<c_int>
+pub fn fcntl_getfd(fd: impl AsFd) -> Result<c_int>
 {
     // SAFETY: This usage is safe.
     let status = unsafe {
-        libc::fcntl(fd.as_raw_fd(), libc::F_GETFD)
+        libc::fcntl(fd.as_fd().as_raw_fd(), libc::F_GETFD)
     };
 
     if status == -1 {
-        Err(Error::last_os_error())
+        Err(FsError::last_os_error("fcntl", None))
     } else {
         Ok(status)
     }
 }
 
 /// Perform the `fcntl` system call with command `F_SETFD`.
-pub fn fcntl_setfd(fd: &impl AsRawFd, arg: c_int) -> Result<()>
+pub fn fcntl_setfd(fd: impl AsFd, arg: c_int) -> Result<()>
 {
     // SAFETY: This usage is safe.
     let status = unsafe {
-        libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, arg)
+        libc::fcntl(fd.as_fd().as_raw_fd(), libc::F_SETFD, arg)
     };
 
     if status == -1 {
-        Err(Error::last_os_error())
+        Err(FsError::last_os_error("fcntl", None))
     } else {
         Ok(())
     }
 }
+
+/// Perform the `fcntl` system call with command `F_GETFL`.
+///
+/// Unlike [`fcntl_getfd`], which returns the file descriptor flags,
+/// this returns the file status flags and access mode,
+/// such as `O_NONBLOCK` and `O_APPEND`.
+pub fn fcntl_getfl(fd: impl AsFd) -> Result<c_int>
+{
+    // SAFETY: This usage is safe.
+    let status = unsafe {
+        libc::fcntl(fd.as_fd().as_raw_fd(), libc::F_GETFL)
+    };
+
+    if status == -1 {
+        Err(FsError::last_os_error("fcntl", None))
+    } else {
+        Ok(status)
+    }
+}
+
+/// Perform the `fcntl` system call with command `F_SETFL`.
+///
+/// Only some file status flags can be changed, such as `O_NONBLOCK`;
+/// the access mode and file creation flags are ignored.
+pub fn fcntl_setfl(fd: impl AsFd, arg: c_int) -> Result<()>
+{
+    // SAFETY: This usage is safe.
+    let status = unsafe {
+        libc::fcntl(fd.as_fd().as_raw_fd(), libc::F_SETFL, arg)
+    };
+
+    if status == -1 {
+        Err(FsError::last_os_error("fcntl", None))
+    } else {
+        Ok(())
+    }
+}
+
+/// Apply `O_CLOEXEC` and `O_NONBLOCK` from the given flags
+/// to a file descriptor that was created without them,
+/// on systems that lack the system calls taking flags.
+#[cfg(target_os = "macos")]
+pub (crate) fn emulate_open_flags(fd: impl AsFd, flags: c_int) -> Result<()>
+{
+    let fd = fd.as_fd();
+    if flags & libc::O_CLOEXEC != 0 {
+        let fd_flags = fcntl_getfd(fd)?;
+        fcntl_setfd(fd, fd_flags | libc::FD_CLOEXEC)?;
+    }
+    if flags & libc::O_NONBLOCK != 0 {
+        let status_flags = fcntl_getfl(fd)?;
+        fcntl_setfl(fd, status_flags | libc::O_NONBLOCK)?;
+    }
+    Ok(())
+}
+
+/// Perform the `fcntl` system call with command `F_ADD_SEALS`.
+///
+/// The seals are a combination of the `F_SEAL_*` flags.
+/// Only files created with [`memfd_create`] and `MFD_ALLOW_SEALING`
+/// can be sealed; other files fail with `EINVAL` or `EPERM`.
+/// `F_SEAL_WRITE` fails with `EBUSY`
+/// while the file has writable shared memory mappings.
+///
+/// [`memfd_create`]: `crate::memfd_create`
+#[cfg(target_os = "linux")]
+pub fn fcntl_add_seals(fd: impl AsFd, seals: c_int) -> Result<()>
+{
+    // SAFETY: This usage is safe.
+    let status = unsafe {
+        libc::fcntl(fd.as_fd().as_raw_fd(), libc::F_ADD_SEALS, seals)
+    };
+
+    if status == -1 {
+        Err(FsError::last_os_error("fcntl", None))
+    } else {
+        Ok(())
+    }
+}
+
+/// Perform the `fcntl` system call with command `F_GET_SEALS`.
+#[cfg(target_os = "linux")]
+pub fn fcntl_get_seals(fd: impl AsFd) -> Result<c_int>
+{
+    // SAFETY: This usage is safe.
+    let status = unsafe {
+        libc::fcntl(fd.as_fd().as_raw_fd(), libc::F_GET_SEALS)
+    };
+
+    if status == -1 {
+        Err(FsError::last_os_error("fcntl", None))
+    } else {
+        Ok(status)
+    }
+}
+
+/// Perform the `fcntl` system call with command `F_OFD_SETLK`,
+/// or `F_OFD_SETLKW` if `wait` is true.
+///
+/// The lock type is `F_RDLCK`, `F_WRLCK`, or `F_UNLCK`,
+/// and applies to `len` bytes from `start`,
+/// or to the end of the file and beyond if `len` is zero.
+/// Without waiting, a conflicting lock fails with `EAGAIN`.
+///
+/// Unlike the traditional `F_SETLK` locks, these locks belong
+/// to the open file description rather than to the process,
+/// so they are not released when another file descriptor
+/// for the same file is closed, and they exclude
+/// other open file descriptions within the same process.
+/// See [`OfdLockGuard`] for a lock that is released automatically.
+#[cfg(target_os = "linux")]
+pub fn fcntl_ofd_setlk(fd: impl AsFd, lock_type: c_short,
+                       start: libc::off_t, len: libc::off_t, wait: bool)
+    -> Result<()>
+{
+    // SAFETY: Zero is a valid value for every field of flock.
+    let mut flock: libc::flock = unsafe { std::mem::zeroed() };
+    flock.l_type = lock_type;
+    flock.l_whence = libc::SEEK_SET as c_short;
+    flock.l_start = start;
+    flock.l_len = len;
+
+    let command = if wait { libc::F_OFD_SETLKW } else { libc::F_OFD_SETLK };
+
+    // SAFETY: The flock struct is initialized.
+    let status = unsafe {
+        libc::fcntl(fd.as_fd().as_raw_fd(), command, &flock)
+    };
+
+    if status == -1 {
+        Err(FsError::last_os_error("fcntl", None))
+    } else {
+        Ok(())
+    }
+}
+
+/// Byte-range lock taken with [`fcntl_ofd_setlk`],
+/// which is released when dropped.
+#[cfg(target_os = "linux")]
+pub struct OfdLockGuard<'a>
+{
+    fd: BorrowedFd<'a>,
+    start: libc::off_t,
+    len: libc::off_t,
+}
+
+#[cfg(target_os = "linux")]
+impl<'a> OfdLockGuard<'a>
+{
+    /// Lock a range of the file as with [`fcntl_ofd_setlk`].
+    /// The lock type must not be `F_UNLCK`.
+    pub fn lock(fd: BorrowedFd<'a>, lock_type: c_short,
+                start: libc::off_t, len: libc::off_t, wait: bool)
+        -> Result<Self>
+    {
+        fcntl_ofd_setlk(fd, lock_type, start, len, wait)?;
+        Ok(Self{fd, start, len})
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl<'a> Drop for OfdLockGuard<'a>
+{
+    fn drop(&mut self)
+    {
+        // Unlocking only fails for invalid file descriptors.
+        let unlock = libc::F_UNLCK as c_short;
+        let _ = fcntl_ofd_setlk(self.fd, unlock, self.start, self.len, false);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    #[cfg(target_os = "linux")]
+    use crate::testdata::TestData;
+    use super::*;
+    #[cfg(target_os = "linux")]
+    use std::fs::File;
+    #[cfg(target_os = "linux")]
+    use std::fs::OpenOptions;
+    use std::io::ErrorKind::WouldBlock;
+    use std::io::Read;
+    use std::os::unix::net::UnixStream;
+
+    #[test]
+    fn test_fcntl_setfl()
+    {
+        // Prepare the test.
+        let (mut read, _write) = UnixStream::pair().unwrap();
+
+        // Make one end non-blocking, and read before anything is sent.
+        let flags = fcntl_getfl(&read).unwrap();
+        fcntl_setfl(&read, flags | libc::O_NONBLOCK).unwrap();
+        let result = read.read(&mut [0]);
+
+        // Check the results.
+        assert_eq!(flags & libc::O_NONBLOCK, 0);
+        assert_ne!(fcntl_getfl(&read).unwrap() & libc::O_NONBLOCK, 0);
+        assert_eq!(result.unwrap_err().kind(), WouldBlock);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_fcntl_add_seals()
+    {
+        // Prepare the test.
+        let memfd = crate::memfd_create("test", libc::MFD_ALLOW_SEALING)
+            .unwrap();
+        let unsealable = crate::memfd_create("test", 0).unwrap();
+
+        // Seal the file, and try to seal one created without sealing.
+        fcntl_add_seals(&memfd, libc::F_SEAL_WRITE).unwrap();
+        let err = fcntl_add_seals(&unsealable, libc::F_SEAL_WRITE)
+            .unwrap_err();
+
+        // Check the results.
+        let seals = fcntl_get_seals(&memfd).unwrap();
+        assert_eq!(seals & libc::F_SEAL_WRITE, libc::F_SEAL_WRITE);
+        assert_eq!(err.raw_os_error(), Some(libc::EPERM));
+        assert_eq!(err.syscall(), "fcntl");
+        assert_eq!(err.path(), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_ofd_lock_guard()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_ofd_lock_guard").unwrap();
+        let file1 = File::open(&test_data.regular_path).unwrap();
+        let file2 = OpenOptions::new().write(true)
+            .open(&test_data.regular_path).unwrap();
+        let (read, write) = (libc::F_RDLCK as c_short,
+                             libc::F_WRLCK as c_short);
+
+        // Lock a range through one open file description,
+        // and lock through another before and after unlocking.
+        let guard = OfdLockGuard::lock(file1.as_fd(), read, 0, 4, false)
+            .unwrap();
+        let disjoint = fcntl_ofd_setlk(&file2, write, 4, 4, false);
+        let err = fcntl_ofd_setlk(&file2, write, 0, 4, false).unwrap_err();
+        drop(guard);
+        let relocked = fcntl_ofd_setlk(&file2, write, 0, 4, false);
+
+        // Check the results.
+        assert!(disjoint.is_ok());
+        assert_eq!(err.raw_os_error(), Some(libc::EAGAIN));
+        assert_eq!(err.syscall(), "fcntl");
+        assert_eq!(err.path(), None);
+        assert!(relocked.is_ok());
+    }
+}
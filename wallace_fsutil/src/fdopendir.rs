@@ -1,34 +1,123 @@
-use std::io::Error;
-use std::io::Result;
+use crate::FsError;
+use crate::Result;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::BorrowedFd;
 use std::os::unix::io::IntoRawFd;
+use std::os::unix::io::OwnedFd;
 
 /// Owned wrapper around `DIR`.
+///
+/// The `DIR` owns the file descriptor it was opened from,
+/// which is closed together with it.
+/// This is why the directory holds no [`OwnedFd`] of its own:
+/// `closedir` always closes the file descriptor,
+/// and there is no portable way to free a `DIR` without doing so,
+/// so an [`OwnedFd`] next to it would close the file descriptor twice.
+/// [`fdopendir`] takes an [`OwnedFd`], and [`AsFd`] borrows it back,
+/// so the file descriptor is owned by exactly one value at any time.
+///
+/// A directory can be sent to and shared with other threads.
+/// Reading and seeking take `&mut self`, so they never race;
+/// the C library does not lock the `DIR` for us.
+/// To enumerate a directory from several threads at once,
+/// put the directory, or its [`IntoEntries`] iterator,
+/// in a [`Mutex`], and take [`DirentBuf`]s,
+/// which remain valid after the lock is released.
+///
+/// [`DirentBuf`]: `crate::DirentBuf`
+/// [`IntoEntries`]: `crate::IntoEntries`
+/// [`Mutex`]: `std::sync::Mutex`
 pub struct Dir
 {
     pub (crate) inner: *mut libc::DIR,
 }
 
+// SAFETY: A DIR is not tied to the thread that opened it.
+// Everything but dirfd requires `&mut self`, so it is never concurrent.
+unsafe impl Send for Dir { }
+unsafe impl Sync for Dir { }
+
 impl Drop for Dir
 {
     fn drop(&mut self)
     {
+        // SAFETY: The DIR is valid and is not used after this.
         unsafe {
             libc::closedir(self.inner);
         }
     }
 }
 
+impl AsFd for Dir
+{
+    fn as_fd(&self) -> BorrowedFd<'_>
+    {
+        // SAFETY: The file descriptor is open while the DIR is,
+        // and dirfd does not fail for a valid DIR.
+        unsafe {
+            BorrowedFd::borrow_raw(libc::dirfd(self.inner))
+        }
+    }
+}
+
 /// Perform the `fdopendir` system call.
-pub fn fdopendir(fd: impl IntoRawFd) -> Result<Dir>
+///
+/// On success, the file descriptor is owned by the returned [`Dir`].
+/// On failure, it is closed.
+pub fn fdopendir(fd: impl Into<OwnedFd>) -> Result<Dir>
 {
+    let fd = fd.into();
+
     // SAFETY: This function just takes an integer.
     let dir = unsafe {
-        libc::fdopendir(fd.into_raw_fd())
+        libc::fdopendir(fd.as_raw_fd())
     };
 
     if dir.is_null() {
-        Err(Error::last_os_error())
+        Err(FsError::last_os_error("fdopendir", None))
     } else {
+        // The DIR now closes the file descriptor.
+        let _ = fd.into_raw_fd();
         Ok(Dir{inner: dir})
     }
 }
+
+#[cfg(test)]
+mod tests
+{
+    use crate::testdata::TestData;
+    use super::*;
+    use std::fs::File;
+    use std::sync::Mutex;
+    use std::thread;
+
+    #[test]
+    fn test_fdopendir()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_fdopendir").unwrap();
+        let root = File::open(&test_data.root_path).unwrap();
+        let regular = File::open(&test_data.regular_path).unwrap();
+
+        // Open the directory and share it between threads,
+        // and try to open a regular file as a directory.
+        let entries = Mutex::new(fdopendir(root).unwrap().into_iter());
+        let count = thread::scope(|scope| {
+            let threads = (0 .. 4)
+                .map(|_| scope.spawn(|| {
+                    let next = || entries.lock().unwrap().next();
+                    std::iter::from_fn(next).count()
+                }))
+                .collect::<Vec<_>>();
+            threads.into_iter().map(|t| t.join().unwrap()).sum::<usize>()
+        });
+        let err = fdopendir(regular).err().unwrap();
+
+        // Check the results.
+        assert_eq!(count, 3);
+        assert_eq!(err.raw_os_error(), Some(libc::ENOTDIR));
+        assert_eq!(err.syscall(), "fdopendir");
+        assert_eq!(err.path(), None);
+    }
+}
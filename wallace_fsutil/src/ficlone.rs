@@ -0,0 +1,28 @@
+use crate::FsError;
+use crate::Result;
+use std::os::raw::c_ulong;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+
+/// The `FICLONE` ioctl request, which `libc` does not define.
+/// This is `_IOW(0x94, 9, int)` from `linux/fs.h`.
+const FICLONE: c_ulong = 0x40049409;
+
+/// Perform the `ioctl` system call with request `FICLONE`.
+///
+/// This makes `dest` share the extents of `src`,
+/// on file systems that support reflinks, such as Btrfs and XFS.
+/// On other file systems this fails with `EOPNOTSUPP` or similar.
+pub fn ficlone(dest: impl AsFd, src: impl AsFd) -> Result<()>
+{
+    // SAFETY: This usage is safe.
+    let status = unsafe {
+        libc::ioctl(dest.as_fd().as_raw_fd(), FICLONE, src.as_fd().as_raw_fd())
+    };
+
+    if status == -1 {
+        Err(FsError::last_os_error("ioctl", None))
+    } else {
+        Ok(())
+    }
+}
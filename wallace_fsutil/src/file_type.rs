@@ -0,0 +1,113 @@
+use libc::mode_t;
+use std::os::raw::c_uchar;
+
+/// Type of a file, as found in `d_type` and in the `st_mode` format bits.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum FileType
+{
+    /// Named pipe, `DT_FIFO` or `S_IFIFO`.
+    Fifo,
+
+    /// Character device, `DT_CHR` or `S_IFCHR`.
+    CharacterDevice,
+
+    /// Directory, `DT_DIR` or `S_IFDIR`.
+    Directory,
+
+    /// Block device, `DT_BLK` or `S_IFBLK`.
+    BlockDevice,
+
+    /// Regular file, `DT_REG` or `S_IFREG`.
+    Regular,
+
+    /// Symbolic link, `DT_LNK` or `S_IFLNK`.
+    Symlink,
+
+    /// Unix domain socket, `DT_SOCK` or `S_IFSOCK`.
+    Socket,
+
+    /// The type is not known.
+    ///
+    /// Some file systems do not fill in `d_type`,
+    /// in which case the file must be stat’ed to find its type.
+    Unknown,
+}
+
+impl FileType
+{
+    /// Convert the `d_type` field of a directory entry.
+    pub fn from_d_type(d_type: c_uchar) -> Self
+    {
+        match d_type {
+            libc::DT_FIFO => Self::Fifo,
+            libc::DT_CHR  => Self::CharacterDevice,
+            libc::DT_DIR  => Self::Directory,
+            libc::DT_BLK  => Self::BlockDevice,
+            libc::DT_REG  => Self::Regular,
+            libc::DT_LNK  => Self::Symlink,
+            libc::DT_SOCK => Self::Socket,
+            _             => Self::Unknown,
+        }
+    }
+
+    /// Convert the format bits of the `st_mode` field of a stat.
+    ///
+    /// The permission bits are ignored.
+    pub fn from_st_mode(st_mode: mode_t) -> Self
+    {
+        match st_mode & libc::S_IFMT {
+            libc::S_IFIFO  => Self::Fifo,
+            libc::S_IFCHR  => Self::CharacterDevice,
+            libc::S_IFDIR  => Self::Directory,
+            libc::S_IFBLK  => Self::BlockDevice,
+            libc::S_IFREG  => Self::Regular,
+            libc::S_IFLNK  => Self::Symlink,
+            libc::S_IFSOCK => Self::Socket,
+            _              => Self::Unknown,
+        }
+    }
+
+    /// The format bits for `st_mode`, or zero for [`FileType::Unknown`].
+    ///
+    /// Combine these with the permission bits to pass to [`mknodat`].
+    ///
+    /// [`mknodat`]: `crate::mknodat`
+    pub fn to_st_mode(self) -> mode_t
+    {
+        match self {
+            Self::Fifo            => libc::S_IFIFO,
+            Self::CharacterDevice => libc::S_IFCHR,
+            Self::Directory       => libc::S_IFDIR,
+            Self::BlockDevice     => libc::S_IFBLK,
+            Self::Regular         => libc::S_IFREG,
+            Self::Symlink         => libc::S_IFLNK,
+            Self::Socket          => libc::S_IFSOCK,
+            Self::Unknown         => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_file_type_st_mode()
+    {
+        // Prepare the test.
+        let file_types = [FileType::Fifo, FileType::CharacterDevice,
+                          FileType::Directory, FileType::BlockDevice,
+                          FileType::Regular, FileType::Symlink,
+                          FileType::Socket, FileType::Unknown];
+
+        // Check the results.
+        for &file_type in &file_types {
+            let st_mode = file_type.to_st_mode() | 0o644;
+            assert_eq!(FileType::from_st_mode(st_mode), file_type);
+        }
+        assert_eq!(FileType::from_d_type(libc::DT_LNK), FileType::Symlink);
+        assert_eq!(FileType::from_d_type(libc::DT_UNKNOWN),
+                   FileType::Unknown);
+    }
+}
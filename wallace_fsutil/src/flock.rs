@@ -0,0 +1,87 @@
+use crate::FsError;
+use crate::Result;
+use std::os::raw::c_int;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::BorrowedFd;
+
+/// Perform the `flock` system call.
+///
+/// The operation is `LOCK_SH`, `LOCK_EX`, or `LOCK_UN`,
+/// optionally combined with `LOCK_NB`,
+/// in which case a conflicting lock fails with `EWOULDBLOCK`.
+/// See [`FlockGuard`] for a lock that is released automatically.
+pub fn flock(fd: impl AsFd, operation: c_int) -> Result<()>
+{
+    // SAFETY: This usage is safe.
+    let status = unsafe {
+        libc::flock(fd.as_fd().as_raw_fd(), operation)
+    };
+
+    if status == -1 {
+        Err(FsError::last_os_error("flock", None))
+    } else {
+        Ok(())
+    }
+}
+
+/// Lock taken with [`flock`], which is released when dropped.
+///
+/// The lock belongs to the open file description,
+/// so it is shared with duplicates of the file descriptor,
+/// and released early if any of them unlocks it.
+pub struct FlockGuard<'a>
+{
+    fd: BorrowedFd<'a>,
+}
+
+impl<'a> FlockGuard<'a>
+{
+    /// Lock the file with the given [`flock`] operation,
+    /// which must not be `LOCK_UN`.
+    pub fn lock(fd: BorrowedFd<'a>, operation: c_int) -> Result<Self>
+    {
+        flock(fd, operation)?;
+        Ok(Self{fd})
+    }
+}
+
+impl<'a> Drop for FlockGuard<'a>
+{
+    fn drop(&mut self)
+    {
+        // Unlocking only fails for invalid file descriptors.
+        let _ = flock(self.fd, libc::LOCK_UN);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::testdata::TestData;
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_flock_guard()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_flock_guard").unwrap();
+        let file1 = File::open(&test_data.regular_path).unwrap();
+        let file2 = File::open(&test_data.regular_path).unwrap();
+        let exclusive = libc::LOCK_EX | libc::LOCK_NB;
+
+        // Lock the file through one open file description,
+        // and try to lock it through another before and after unlocking.
+        let guard = FlockGuard::lock(file1.as_fd(), exclusive).unwrap();
+        let err = flock(&file2, exclusive).unwrap_err();
+        drop(guard);
+        let relocked = flock(&file2, exclusive);
+
+        // Check the results.
+        assert_eq!(err.raw_os_error(), Some(libc::EWOULDBLOCK));
+        assert_eq!(err.syscall(), "flock");
+        assert_eq!(err.path(), None);
+        assert!(relocked.is_ok());
+    }
+}
@@ -0,0 +1,39 @@
+use crate::FsError;
+use crate::Result;
+use crate::path_to_cstring;
+use std::mem::MaybeUninit;
+use std::os::raw::c_int;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+/// Perform the `fstatat` system call.
+pub fn fstatat(
+    dir: impl AsFd,
+    pathname: impl AsRef<Path>,
+    flags: c_int,
+) -> Result<libc::stat>
+{
+    let pathname = pathname.as_ref();
+    let pathname_c = path_to_cstring("fstatat", pathname)?;
+
+    let mut statbuf = MaybeUninit::uninit();
+
+    // SAFETY: The C string is of type CString
+    // and is therefore null-terminated.
+    let status = unsafe {
+        libc::fstatat(
+            dir.as_fd().as_raw_fd(),
+            pathname_c.as_ptr(),
+            statbuf.as_mut_ptr(),
+            flags,
+        )
+    };
+
+    if status == -1 {
+        Err(FsError::last_os_error("fstatat", Some(pathname)))
+    } else {
+        // SAFETY: fstatat initialized the buffer.
+        Ok(unsafe { statbuf.assume_init() })
+    }
+}
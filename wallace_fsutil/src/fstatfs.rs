@@ -0,0 +1,138 @@
+use crate::FsError;
+use crate::Result;
+use std::mem::MaybeUninit;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+
+/// Statistics about a file system, returned by [`fstatfs`].
+///
+/// Block counts are in units of [`StatFs::block_size`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StatFs
+{
+    /// The size of a block, in bytes.
+    pub block_size: u64,
+
+    /// The total number of blocks.
+    pub blocks: u64,
+
+    /// The number of free blocks.
+    pub free_blocks: u64,
+
+    /// The number of free blocks available to unprivileged users,
+    /// which excludes blocks reserved for the superuser.
+    pub available_blocks: u64,
+
+    /// The total number of inodes.
+    pub files: u64,
+
+    /// The number of free inodes.
+    pub free_files: u64,
+}
+
+impl StatFs
+{
+    /// The number of bytes available to unprivileged users.
+    pub fn available_bytes(&self) -> u64
+    {
+        self.available_blocks.saturating_mul(self.block_size)
+    }
+}
+
+/// Perform the `fstatfs` system call.
+///
+/// The file descriptor may refer to any file on the file system,
+/// such as the directory of a volume.
+pub fn fstatfs(fd: impl AsFd) -> Result<StatFs>
+{
+    let mut statbuf = MaybeUninit::<libc::statfs>::uninit();
+
+    // SAFETY: The buffer is valid for writing.
+    let status = unsafe {
+        libc::fstatfs(fd.as_fd().as_raw_fd(), statbuf.as_mut_ptr())
+    };
+
+    if status == -1 {
+        return Err(FsError::last_os_error("fstatfs", None));
+    }
+
+    // SAFETY: fstatfs initialized the buffer.
+    let statbuf = unsafe { statbuf.assume_init() };
+    Ok(StatFs::from_statfs(&statbuf))
+}
+
+impl StatFs
+{
+    #[cfg(target_os = "linux")]
+    fn from_statfs(statbuf: &libc::statfs) -> Self
+    {
+        // Like statvfs in glibc, count blocks in units of the fragment size,
+        // which old kernels do not report.
+        let block_size = match statbuf.f_frsize {
+            0 => statbuf.f_bsize,
+            f_frsize => f_frsize,
+        };
+
+        Self{
+            block_size:       block_size as u64,
+            blocks:           statbuf.f_blocks,
+            free_blocks:      statbuf.f_bfree,
+            available_blocks: statbuf.f_bavail,
+            files:            statbuf.f_files,
+            free_files:       statbuf.f_ffree,
+        }
+    }
+
+    /// FreeBSD counts blocks in units of `f_bsize`,
+    /// and reports negative free counts when the reserve is in use.
+    #[cfg(target_os = "freebsd")]
+    fn from_statfs(statbuf: &libc::statfs) -> Self
+    {
+        Self{
+            block_size:       statbuf.f_bsize,
+            blocks:           statbuf.f_blocks,
+            free_blocks:      statbuf.f_bfree,
+            available_blocks: statbuf.f_bavail.max(0) as u64,
+            files:            statbuf.f_files,
+            free_files:       statbuf.f_ffree.max(0) as u64,
+        }
+    }
+
+    /// macOS counts blocks in units of `f_bsize`.
+    #[cfg(target_os = "macos")]
+    fn from_statfs(statbuf: &libc::statfs) -> Self
+    {
+        Self{
+            block_size:       u64::from(statbuf.f_bsize),
+            blocks:           statbuf.f_blocks,
+            free_blocks:      statbuf.f_bfree,
+            available_blocks: statbuf.f_bavail,
+            files:            statbuf.f_files,
+            free_files:       statbuf.f_ffree,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::testdata::TestData;
+    use super::*;
+
+    #[test]
+    fn test_fstatfs()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_fstatfs").unwrap();
+
+        // Find the statistics of the file system of the test data.
+        let statfs = fstatfs(&test_data.root).unwrap();
+
+        // Check the results.
+        assert!(statfs.block_size > 0);
+        assert!(statfs.free_blocks <= statfs.blocks);
+        assert!(statfs.available_blocks <= statfs.free_blocks);
+        assert_eq!(statfs.available_bytes(),
+                   statfs.available_blocks * statfs.block_size);
+    }
+}
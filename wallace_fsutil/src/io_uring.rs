@@ -0,0 +1,360 @@
+use crate::FsError;
+use crate::Mmap;
+use crate::Result;
+use crate::mmap;
+use libc::mode_t;
+use std::ffi::CStr;
+use std::io::Error;
+use std::io::ErrorKind::Interrupted;
+use std::os::raw::c_int;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::BorrowedFd;
+use std::os::unix::io::FromRawFd;
+use std::os::unix::io::IntoRawFd;
+use std::os::unix::io::OwnedFd;
+use std::os::unix::io::RawFd;
+use std::ptr::null;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering::Acquire;
+use std::sync::atomic::Ordering::Release;
+
+const IORING_OP_OPENAT: u8 = 18;
+const IORING_OP_CLOSE:  u8 = 19;
+const IORING_OP_READ:   u8 = 22;
+
+const IORING_OFF_SQ_RING: libc::off_t = 0x0000_0000;
+const IORING_OFF_CQ_RING: libc::off_t = 0x0800_0000;
+const IORING_OFF_SQES:    libc::off_t = 0x1000_0000;
+
+const IORING_ENTER_GETEVENTS: u32 = 1;
+
+#[derive(Default)]
+#[repr(C)]
+struct IoUringParams
+{
+    sq_entries: u32,
+    cq_entries: u32,
+    flags: u32,
+    sq_thread_cpu: u32,
+    sq_thread_idle: u32,
+    features: u32,
+    wq_fd: u32,
+    resv: [u32; 3],
+    sq_off: IoSqringOffsets,
+    cq_off: IoCqringOffsets,
+}
+
+#[derive(Default)]
+#[repr(C)]
+struct IoSqringOffsets
+{
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    flags: u32,
+    dropped: u32,
+    array: u32,
+    resv1: u32,
+    resv2: u64,
+}
+
+#[derive(Default)]
+#[repr(C)]
+struct IoCqringOffsets
+{
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    overflow: u32,
+    cqes: u32,
+    flags: u32,
+    resv1: u32,
+    resv2: u64,
+}
+
+/// Submission queue entry for [`IoUring`].
+///
+/// Entries refer to memory by raw pointer,
+/// hence [`IoUring::push`] is unsafe.
+#[derive(Clone, Copy, Default)]
+#[repr(C)]
+pub struct Sqe
+{
+    opcode: u8,
+    flags: u8,
+    ioprio: u16,
+    fd: i32,
+    off: u64,
+    addr: u64,
+    len: u32,
+    op_flags: u32,
+    user_data: u64,
+    buf_index: u16,
+    personality: u16,
+    splice_fd_in: i32,
+    pad: [u64; 2],
+}
+
+/// Completion queue entry for [`IoUring`].
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct Cqe
+{
+    /// The user data of the corresponding submission queue entry.
+    pub user_data: u64,
+
+    /// The result of the operation, or a negated `errno` value.
+    pub res: i32,
+
+    /// Flags, which are not used by any of the operations in this crate.
+    pub flags: u32,
+}
+
+/// Minimal owned io_uring instance.
+///
+/// Only the operations needed by this workspace are supported;
+/// see the constructors of [`Sqe`].
+/// Entries are submitted and reaped by the same thread,
+/// which is why the methods take `&mut self`.
+pub struct IoUring
+{
+    fd: OwnedFd,
+    sq_ring: Mmap,
+    cq_ring: Mmap,
+    sqes: Mmap,
+    sq_off: IoSqringOffsets,
+    cq_off: IoCqringOffsets,
+
+    /// The number of entries pushed but not yet submitted.
+    unsubmitted: u32,
+}
+
+// SAFETY: The rings are only accessed through `&mut self`.
+unsafe impl Send for IoUring { }
+
+impl Sqe
+{
+    /// Open the file at the given path relative to the given directory.
+    ///
+    /// The result is the new file descriptor,
+    /// which the caller must close.
+    pub fn openat(dir: impl AsFd, pathname: &CStr,
+                  flags: c_int, mode: mode_t, user_data: u64) -> Self
+    {
+        Self{
+            opcode: IORING_OP_OPENAT,
+            fd: dir.as_fd().as_raw_fd(),
+            addr: pathname.as_ptr() as u64,
+            len: mode,
+            op_flags: flags as u32,
+            user_data,
+            ..Self::default()
+        }
+    }
+
+    /// Read into the buffer at the given offset in the file.
+    ///
+    /// The result is the number of bytes read.
+    pub fn read(fd: BorrowedFd<'_>, buf: &mut [u8], offset: u64,
+                user_data: u64) -> Self
+    {
+        Self{
+            opcode: IORING_OP_READ,
+            fd: fd.as_raw_fd(),
+            off: offset,
+            addr: buf.as_mut_ptr() as u64,
+            len: buf.len() as u32,
+            user_data,
+            ..Self::default()
+        }
+    }
+
+    /// Close the file descriptor.
+    ///
+    /// The entry takes ownership of the file descriptor,
+    /// which is leaked if the entry is never pushed.
+    pub fn close(fd: OwnedFd, user_data: u64) -> Self
+    {
+        let fd = fd.into_raw_fd();
+        Self{opcode: IORING_OP_CLOSE, fd, user_data, ..Self::default()}
+    }
+}
+
+impl Cqe
+{
+    /// Convert the result of the operation to an [`io::Result`].
+    ///
+    /// [`io::Result`]: `std::io::Result`
+    pub fn result(&self) -> Result<u32>
+    {
+        if self.res < 0 {
+            Err(FsError::new("io_uring", None,
+                             Error::from_raw_os_error(-self.res)))
+        } else {
+            Ok(self.res as u32)
+        }
+    }
+}
+
+impl IoUring
+{
+    /// Perform the `io_uring_setup` system call,
+    /// and map the rings into memory.
+    ///
+    /// This fails on kernels without io_uring,
+    /// or where it is disabled, for instance by seccomp.
+    pub fn new(entries: u32) -> Result<Self>
+    {
+        let mut params = IoUringParams::default();
+
+        // SAFETY: The parameters are valid for writing.
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_io_uring_setup,
+                entries,
+                &mut params as *mut IoUringParams,
+            )
+        };
+
+        if fd == -1 {
+            return Err(FsError::last_os_error("io_uring_setup", None));
+        }
+
+        // SAFETY: The file descriptor was just created,
+        // so nothing else owns it.
+        let fd = unsafe { OwnedFd::from_raw_fd(fd as RawFd) };
+
+        let prot = libc::PROT_READ | libc::PROT_WRITE;
+        let flags = libc::MAP_SHARED | libc::MAP_POPULATE;
+        let sq_ring_len = params.sq_off.array as usize
+                        + params.sq_entries as usize * 4;
+        let cq_ring_len = params.cq_off.cqes as usize
+                        + params.cq_entries as usize * 16;
+        let sqes_len = params.sq_entries as usize * 64;
+        let sq_ring = mmap(sq_ring_len, prot, flags, &fd, IORING_OFF_SQ_RING)?;
+        let cq_ring = mmap(cq_ring_len, prot, flags, &fd, IORING_OFF_CQ_RING)?;
+        let sqes = mmap(sqes_len, prot, flags, &fd, IORING_OFF_SQES)?;
+
+        Ok(Self{fd, sq_ring, cq_ring, sqes, sq_off: params.sq_off,
+                cq_off: params.cq_off, unsubmitted: 0})
+    }
+
+    /// The number of entries in the submission queue.
+    ///
+    /// The completion queue is at least this large,
+    /// so waiting for the completion of every submitted entry
+    /// before pushing more never overflows it.
+    pub fn capacity(&self) -> u32
+    {
+        self.sq_u32(self.sq_off.ring_entries)
+    }
+
+    /// Push an entry onto the submission queue.
+    ///
+    /// Returns false if the queue is full.
+    ///
+    /// # Safety
+    ///
+    /// Memory and file descriptors that the entry refers to
+    /// must remain valid until the entry completes.
+    pub unsafe fn push(&mut self, sqe: &Sqe) -> bool
+    {
+        let head = self.sq_atomic(self.sq_off.head).load(Acquire);
+        let tail = self.sq_atomic(self.sq_off.tail).load(Acquire);
+        if tail.wrapping_sub(head) == self.capacity() {
+            return false;
+        }
+
+        let index = tail & self.sq_u32(self.sq_off.ring_mask);
+        let sqes = self.sqes.as_ptr() as *mut Sqe;
+        sqes.add(index as usize).write(*sqe);
+        let array = self.sq_ring.as_ptr().add(self.sq_off.array as usize);
+        (array as *mut u32).add(index as usize).write(index);
+
+        self.sq_atomic(self.sq_off.tail).store(tail.wrapping_add(1), Release);
+        self.unsubmitted += 1;
+        true
+    }
+
+    /// Perform the `io_uring_enter` system call,
+    /// submitting the pushed entries and waiting for completions.
+    ///
+    /// Returns once at least `min_complete` entries have completed.
+    pub fn submit_and_wait(&mut self, min_complete: u32) -> Result<()>
+    {
+        loop {
+            // SAFETY: No signal mask is passed.
+            let status = unsafe {
+                libc::syscall(
+                    libc::SYS_io_uring_enter,
+                    self.fd.as_raw_fd(),
+                    self.unsubmitted,
+                    min_complete,
+                    IORING_ENTER_GETEVENTS,
+                    null::<libc::sigset_t>(),
+                    0usize,
+                )
+            };
+
+            if status == -1 {
+                let err = FsError::last_os_error("io_uring_enter", None);
+                if err.kind() == Interrupted {
+                    continue;
+                }
+                return Err(err);
+            }
+
+            self.unsubmitted -= status as u32;
+            if self.unsubmitted == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Pop an entry from the completion queue, if any.
+    pub fn pop(&mut self) -> Option<Cqe>
+    {
+        let head = self.cq_atomic(self.cq_off.head).load(Acquire);
+        let tail = self.cq_atomic(self.cq_off.tail).load(Acquire);
+        if head == tail {
+            return None;
+        }
+
+        let mask = self.cq_u32(self.cq_off.ring_mask);
+        // SAFETY: The offsets were reported by the kernel,
+        // and the entry at the head is owned by us until we advance it.
+        let cqe = unsafe {
+            let cqes = self.cq_ring.as_ptr().add(self.cq_off.cqes as usize);
+            (cqes as *const Cqe).add((head & mask) as usize).read()
+        };
+
+        self.cq_atomic(self.cq_off.head).store(head.wrapping_add(1), Release);
+        Some(cqe)
+    }
+
+    fn sq_u32(&self, offset: u32) -> u32
+    {
+        self.sq_atomic(offset).load(Acquire)
+    }
+
+    fn cq_u32(&self, offset: u32) -> u32
+    {
+        self.cq_atomic(offset).load(Acquire)
+    }
+
+    fn sq_atomic(&self, offset: u32) -> &AtomicU32
+    {
+        // SAFETY: The offsets were reported by the kernel,
+        // and the fields are shared with it, hence atomic.
+        unsafe { &*(self.sq_ring.as_ptr().add(offset as usize)).cast() }
+    }
+
+    fn cq_atomic(&self, offset: u32) -> &AtomicU32
+    {
+        // SAFETY: See sq_atomic.
+        unsafe { &*(self.cq_ring.as_ptr().add(offset as usize)).cast() }
+    }
+}
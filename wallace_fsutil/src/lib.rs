@@ -1,18 +1,125 @@
 //! File system functions that are not provided by the `std` crate.
+//!
+//! # Platform support
+//!
+//! The functions are written for Linux,
+//! and most of them also work on FreeBSD and macOS.
+//! Where those systems lack a system call,
+//! the function is emulated if that can be done reasonably,
+//! for instance [`pipe2`] and [`dup3`] on macOS,
+//! which are not atomic there.
+//! Functions without a reasonable emulation,
+//! such as [`linkat_fd`] and the io_uring interface,
+//! are only available on Linux.
+//!
+//! # Errors
+//!
+//! The functions return [`FsError`], which records
+//! the system call and path that failed alongside the error.
+//! It converts into [`std::io::Error`], so `?` keeps working
+//! in functions that return [`std::io::Result`].
+//! The converted error keeps the system call and path;
+//! use [`FsError::raw_os_error_of`] to find its `errno` value.
 
 #![doc(html_favicon_url = "../../../marketing/logo.svg")]
 #![doc(html_logo_url = "../../../marketing/logo.svg")]
 
+#[cfg(target_os = "linux")]
+pub use self::copy_file_range::*;
+pub use self::cwd::*;
+pub use self::dup3::*;
+pub use self::error::*;
+pub use self::faccessat::*;
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+pub use self::fallocate::*;
+pub use self::fchmodat::*;
 pub use self::fcntl::*;
 pub use self::fdopendir::*;
+pub use self::file_type::*;
+pub use self::flock::*;
+#[cfg(target_os = "linux")]
+pub use self::ficlone::*;
+pub use self::fstatat::*;
+pub use self::fstatfs::*;
+#[cfg(target_os = "linux")]
+pub use self::io_uring::*;
 pub use self::linkat::*;
+#[cfg(target_os = "linux")]
+pub use self::linkat_fd::*;
+pub use self::lseek::*;
+#[cfg(target_os = "linux")]
+pub use self::memfd_create::*;
+pub use self::mkdirat::*;
 pub use self::mknod::*;
+#[cfg(not(target_os = "macos"))]
+pub use self::mknodat::*;
+pub use self::mmap::*;
+#[cfg(target_os = "linux")]
+pub use self::name_to_handle_at::*;
 pub use self::openat::*;
+pub use self::opendir_at::*;
+pub use self::pipe2::*;
 pub use self::readdir::*;
+pub use self::renameat::*;
+#[cfg(target_os = "linux")]
+pub use self::renameat2::*;
+#[cfg(target_os = "linux")]
+pub use self::sendfile::*;
+pub use self::unlinkat::*;
+pub use self::utimensat::*;
+#[cfg(target_os = "linux")]
+pub use self::verity::*;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub use self::xattr::*;
 
+#[cfg(target_os = "linux")]
+mod copy_file_range;
+mod cwd;
+mod dup3;
+mod errno;
+mod error;
+mod faccessat;
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+mod fallocate;
+mod fchmodat;
 mod fcntl;
 mod fdopendir;
+mod file_type;
+mod flock;
+#[cfg(target_os = "linux")]
+mod ficlone;
+mod fstatat;
+mod fstatfs;
+#[cfg(target_os = "linux")]
+mod io_uring;
 mod linkat;
+#[cfg(target_os = "linux")]
+mod linkat_fd;
+mod lseek;
+#[cfg(target_os = "linux")]
+mod memfd_create;
+mod mkdirat;
 mod mknod;
+#[cfg(not(target_os = "macos"))]
+mod mknodat;
+mod mmap;
+#[cfg(target_os = "linux")]
+mod name_to_handle_at;
 mod openat;
+mod opendir_at;
+mod pipe2;
 mod readdir;
+mod renameat;
+#[cfg(target_os = "linux")]
+mod renameat2;
+mod seekdir;
+#[cfg(target_os = "linux")]
+mod sendfile;
+#[cfg(test)]
+mod testdata;
+mod unlinkat;
+mod utimensat;
+#[cfg(target_os = "linux")]
+mod verity;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+mod xattr;
@@ -1,39 +1,39 @@
-use std::ffi::CString;
-use std::io::Error;
-use std::io::Result;
+use crate::FsError;
+use crate::Result;
+use crate::path_to_cstring;
 use std::os::raw::c_int;
-use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::AsFd;
 use std::os::unix::io::AsRawFd;
 use std::path::Path;
 
 /// Perform the `linkat` system call.
 pub fn linkat(
-    olddir: &impl AsRawFd,
+    olddir: impl AsFd,
     oldpath: impl AsRef<Path>,
-    newdir: &impl AsRawFd,
+    newdir: impl AsFd,
     newpath: impl AsRef<Path>,
     flags: c_int,
 ) -> Result<()>
 {
-    let cstr = |p: &Path| CString::new(p.as_os_str().as_bytes());
-
-    let oldpath_c: CString = cstr(oldpath.as_ref())?;
-    let newpath_c: CString = cstr(newpath.as_ref())?;
+    let oldpath = oldpath.as_ref();
+    let newpath = newpath.as_ref();
+    let oldpath_c = path_to_cstring("linkat", oldpath)?;
+    let newpath_c = path_to_cstring("linkat", newpath)?;
 
     // SAFETY: All C strings are of type CString
     // and are therefore null-terminated.
     let status = unsafe {
         libc::linkat(
-            olddir.as_raw_fd(),
+            olddir.as_fd().as_raw_fd(),
             oldpath_c.as_ptr(),
-            newdir.as_raw_fd(),
+            newdir.as_fd().as_raw_fd(),
             newpath_c.as_ptr(),
             flags,
         )
     };
 
     if status == -1 {
-        Err(Error::last_os_error())
+        Err(FsError::last_os_error("linkat", Some(newpath)))
     } else {
         Ok(())
     }
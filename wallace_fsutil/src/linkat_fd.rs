@@ -0,0 +1,79 @@
+use crate::CWD;
+use crate::FsError;
+use crate::Result;
+use crate::linkat;
+use std::io::Error;
+use std::io::ErrorKind::Unsupported;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+/// Create a hard link to an open file,
+/// which need not have a name, such as an `O_TMPFILE` file.
+///
+/// This first tries `linkat` with `AT_EMPTY_PATH`,
+/// which requires the `CAP_DAC_READ_SEARCH` capability.
+/// Without it, it falls back to linking `/proc/self/fd/<fd>`
+/// with `AT_SYMLINK_FOLLOW`, as documented in linkat(2).
+/// If neither works because `/proc` is not mounted,
+/// this fails with an error of kind [`Unsupported`].
+pub fn linkat_fd(
+    fd: impl AsFd,
+    newdir: impl AsFd,
+    newpath: impl AsRef<Path>,
+) -> Result<()>
+{
+    let fd = fd.as_fd();
+    let newdir = newdir.as_fd();
+    let newpath = newpath.as_ref();
+
+    // Without the capability, AT_EMPTY_PATH fails with ENOENT.
+    match linkat(fd, "", newdir, newpath, libc::AT_EMPTY_PATH) {
+        Err(err) if err.raw_os_error() == Some(libc::ENOENT) => (),
+        result => return result,
+    }
+
+    let proc_path = format!("/proc/self/fd/{}", fd.as_raw_fd());
+    match linkat(CWD, proc_path, newdir, newpath,
+                 libc::AT_SYMLINK_FOLLOW) {
+        Err(err) if err.raw_os_error() == Some(libc::ENOENT)
+                    && !Path::new("/proc/self/fd").exists() => {
+            let message = "cannot link file descriptor: \
+                           AT_EMPTY_PATH is not permitted \
+                           and /proc is not mounted";
+            Err(FsError::new("linkat", Some(newpath),
+                             Error::new(Unsupported, message)))
+        },
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::openat;
+    use crate::testdata::TestData;
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_linkat_fd()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_linkat_fd").unwrap();
+        let flags = libc::O_TMPFILE | libc::O_WRONLY | libc::O_CLOEXEC;
+        let mut file = openat(&test_data.root, ".", flags, 0o644).unwrap();
+        file.write_all(b"Hello").unwrap();
+
+        // Give the file a name, and try to give it a name that is taken.
+        linkat_fd(&file, &test_data.root, "linked").unwrap();
+        let err = linkat_fd(&file, &test_data.root, "regular").unwrap_err();
+
+        // Check the results.
+        let linked = std::fs::read(test_data.root_path.join("linked"));
+        assert_eq!(linked.unwrap(), b"Hello");
+        assert_eq!(err.raw_os_error(), Some(libc::EEXIST));
+        assert_eq!(err.syscall(), "linkat");
+        assert_eq!(err.path(), Some(Path::new("regular")));
+    }
+}
@@ -0,0 +1,23 @@
+use crate::FsError;
+use crate::Result;
+use std::os::raw::c_int;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+
+/// Perform the `lseek` system call.
+///
+/// Unlike [`std::io::Seek`], this supports `SEEK_DATA` and `SEEK_HOLE`.
+pub fn lseek(fd: impl AsFd, offset: libc::off_t, whence: c_int)
+    -> Result<libc::off_t>
+{
+    // SAFETY: This usage is safe.
+    let offset = unsafe {
+        libc::lseek(fd.as_fd().as_raw_fd(), offset, whence)
+    };
+
+    if offset == -1 {
+        Err(FsError::last_os_error("lseek", None))
+    } else {
+        Ok(offset)
+    }
+}
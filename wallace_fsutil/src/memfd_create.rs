@@ -0,0 +1,68 @@
+use crate::FsError;
+use crate::Result;
+use std::ffi::CString;
+use std::fs::File;
+use std::os::raw::c_int;
+use std::os::raw::c_uint;
+use std::os::unix::io::FromRawFd;
+use std::os::unix::io::OwnedFd;
+
+/// Perform the `memfd_create` system call.
+///
+/// The name is only used for debugging, and need not be unique.
+/// Pass `MFD_ALLOW_SEALING` in the flags
+/// to be able to seal the file with [`fcntl_add_seals`].
+///
+/// [`fcntl_add_seals`]: `crate::fcntl_add_seals`
+pub fn memfd_create(name: &str, flags: c_uint) -> Result<File>
+{
+    let name_c = CString::new(name)
+        .map_err(|err| FsError::new("memfd_create", None, err.into()))?;
+
+    // SAFETY: The C string is of type CString
+    // and is therefore null-terminated.
+    // The system call is used rather than the libc function,
+    // which is missing from older C libraries.
+    let fd = unsafe {
+        libc::syscall(libc::SYS_memfd_create, name_c.as_ptr(), flags)
+    };
+
+    if fd == -1 {
+        Err(FsError::last_os_error("memfd_create", None))
+    } else {
+        // SAFETY: The file descriptor was just created,
+        // so nothing else owns it.
+        Ok(File::from(unsafe { OwnedFd::from_raw_fd(fd as c_int) }))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::io::Read;
+    use std::io::Seek;
+    use std::io::SeekFrom;
+    use std::io::Write;
+
+    #[test]
+    fn test_memfd_create()
+    {
+        // Prepare the test.
+        let flags = libc::MFD_CLOEXEC;
+
+        // Create a file and write to it, and try to pass unknown flags.
+        let mut file = memfd_create("test_memfd_create", flags).unwrap();
+        file.write_all(b"Hello").unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        let err = memfd_create("test_memfd_create", !0).unwrap_err();
+
+        // Check the results.
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, b"Hello");
+        assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+        assert_eq!(err.syscall(), "memfd_create");
+        assert_eq!(err.path(), None);
+    }
+}
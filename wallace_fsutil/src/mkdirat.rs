@@ -0,0 +1,34 @@
+use crate::FsError;
+use crate::Result;
+use crate::path_to_cstring;
+use libc::mode_t;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+/// Perform the `mkdirat` system call.
+pub fn mkdirat(
+    dir: impl AsFd,
+    pathname: impl AsRef<Path>,
+    mode: mode_t,
+) -> Result<()>
+{
+    let pathname = pathname.as_ref();
+    let pathname_c = path_to_cstring("mkdirat", pathname)?;
+
+    // SAFETY: The C string is of type CString
+    // and is therefore null-terminated.
+    let status = unsafe {
+        libc::mkdirat(
+            dir.as_fd().as_raw_fd(),
+            pathname_c.as_ptr(),
+            mode,
+        )
+    };
+
+    if status == -1 {
+        Err(FsError::last_os_error("mkdirat", Some(pathname)))
+    } else {
+        Ok(())
+    }
+}
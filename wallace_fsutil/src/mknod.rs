@@ -1,17 +1,15 @@
+use crate::FsError;
+use crate::Result;
+use crate::path_to_cstring;
 use libc::dev_t;
 use libc::mode_t;
-use std::ffi::CString;
-use std::io::Error;
-use std::io::Result;
-use std::os::unix::ffi::OsStrExt;
 use std::path::Path;
 
 /// Perform the `mknod` system call.
 pub fn mknod(pathname: impl AsRef<Path>, mode: mode_t, dev: dev_t) -> Result<()>
 {
-    let cstr = |p: &Path| CString::new(p.as_os_str().as_bytes());
-
-    let pathname_c = cstr(pathname.as_ref())?;
+    let pathname = pathname.as_ref();
+    let pathname_c = path_to_cstring("mknod", pathname)?;
 
     // SAFETY: The C string is of type CString
     // and is therefore null-terminated.
@@ -24,7 +22,7 @@ pub fn mknod(pathname: impl AsRef<Path>, mode: mode_t, dev: dev_t) -> Result<()>
     };
 
     if fd == -1 {
-        Err(Error::last_os_error())
+        Err(FsError::last_os_error("mknod", Some(pathname)))
     } else {
         Ok(())
     }
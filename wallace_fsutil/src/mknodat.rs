@@ -0,0 +1,71 @@
+use crate::FsError;
+use crate::Result;
+use crate::path_to_cstring;
+use libc::dev_t;
+use libc::mode_t;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+/// Perform the `mknodat` system call.
+///
+/// The mode combines the type of the file,
+/// for instance from [`FileType::to_st_mode`],
+/// with its permission bits.
+///
+/// [`FileType::to_st_mode`]: `crate::FileType::to_st_mode`
+pub fn mknodat(
+    dir: impl AsFd,
+    pathname: impl AsRef<Path>,
+    mode: mode_t,
+    dev: dev_t,
+) -> Result<()>
+{
+    let pathname = pathname.as_ref();
+    let pathname_c = path_to_cstring("mknodat", pathname)?;
+
+    // SAFETY: The C string is of type CString
+    // and is therefore null-terminated.
+    let status = unsafe {
+        libc::mknodat(
+            dir.as_fd().as_raw_fd(),
+            pathname_c.as_ptr(),
+            mode,
+            dev,
+        )
+    };
+
+    if status == -1 {
+        Err(FsError::last_os_error("mknodat", Some(pathname)))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::FileType;
+    use crate::testdata::TestData;
+    use super::*;
+    use std::os::unix::fs::FileTypeExt;
+
+    #[test]
+    fn test_mknodat()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_mknodat").unwrap();
+        let mode = FileType::Fifo.to_st_mode() | 0o600;
+
+        // Create a named pipe, and try to create one where a file exists.
+        mknodat(&test_data.root, "fifo", mode, 0).unwrap();
+        let err = mknodat(&test_data.root, "regular", mode, 0).unwrap_err();
+
+        // Check the results.
+        let metadata = test_data.root_path.join("fifo").metadata().unwrap();
+        assert!(metadata.file_type().is_fifo());
+        assert_eq!(err.raw_os_error(), Some(libc::EEXIST));
+        assert_eq!(err.syscall(), "mknodat");
+        assert_eq!(err.path(), Some(Path::new("regular")));
+    }
+}
@@ -0,0 +1,82 @@
+use crate::FsError;
+use crate::Result;
+use std::os::raw::c_int;
+use std::os::raw::c_void;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+
+/// Owned wrapper around a memory mapping.
+///
+/// The mapping is removed with `munmap` when the wrapper is dropped.
+pub struct Mmap
+{
+    ptr: *mut c_void,
+    len: usize,
+}
+
+// SAFETY: A mapping is just memory; it is not tied to a thread.
+unsafe impl Send for Mmap { }
+unsafe impl Sync for Mmap { }
+
+impl Mmap
+{
+    /// The address at which the mapping starts.
+    pub fn as_ptr(&self) -> *const u8
+    {
+        self.ptr as *const u8
+    }
+
+    /// The length of the mapping in bytes.
+    pub fn len(&self) -> usize
+    {
+        self.len
+    }
+
+    /// Whether the mapping has a length of zero,
+    /// which `mmap` does not actually allow.
+    pub fn is_empty(&self) -> bool
+    {
+        self.len == 0
+    }
+}
+
+impl Drop for Mmap
+{
+    fn drop(&mut self)
+    {
+        unsafe {
+            libc::munmap(self.ptr, self.len);
+        }
+    }
+}
+
+/// Perform the `mmap` system call, mapping a file.
+///
+/// The address is chosen by the kernel.
+pub fn mmap(
+    length: usize,
+    prot: c_int,
+    flags: c_int,
+    fd: impl AsFd,
+    offset: libc::off_t,
+) -> Result<Mmap>
+{
+    // SAFETY: Passing a null address lets the kernel pick one,
+    // so no existing memory is replaced.
+    let ptr = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            length,
+            prot,
+            flags,
+            fd.as_fd().as_raw_fd(),
+            offset,
+        )
+    };
+
+    if ptr == libc::MAP_FAILED {
+        Err(FsError::last_os_error("mmap", None))
+    } else {
+        Ok(Mmap{ptr, len: length})
+    }
+}
@@ -0,0 +1,199 @@
+use crate::FsError;
+use crate::Result;
+use crate::path_to_cstring;
+use std::fs::File;
+use std::io::Error;
+use std::io::ErrorKind::InvalidInput;
+use std::os::raw::c_int;
+use std::os::raw::c_uint;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::FromRawFd;
+use std::os::unix::io::OwnedFd;
+use std::path::Path;
+
+/// The maximum size of a file handle, as defined by Linux.
+const MAX_HANDLE_SZ: usize = 128;
+
+/// Layout of `struct file_handle` with room for the largest handle.
+#[repr(C)]
+struct RawFileHandle
+{
+    handle_bytes: c_uint,
+    handle_type: c_int,
+    f_handle: [u8; MAX_HANDLE_SZ],
+}
+
+/// Opaque handle to a file, returned by [`name_to_handle_at`].
+///
+/// The handle remains valid across process restarts,
+/// as long as the file exists and the file system supports it,
+/// so it may be stored and later passed to [`open_by_handle_at`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct FileHandle
+{
+    handle_type: c_int,
+    bytes: Vec<u8>,
+}
+
+impl FileHandle
+{
+    /// Reconstruct a file handle from its stored parts.
+    ///
+    /// Returns [`None`] if the handle is longer than Linux allows.
+    pub fn new(handle_type: c_int, bytes: Vec<u8>) -> Option<Self>
+    {
+        if bytes.len() > MAX_HANDLE_SZ {
+            return None;
+        }
+        Some(Self{handle_type, bytes})
+    }
+
+    /// The type of the handle, which depends on the file system.
+    pub fn handle_type(&self) -> c_int
+    {
+        self.handle_type
+    }
+
+    /// The opaque bytes of the handle.
+    pub fn bytes(&self) -> &[u8]
+    {
+        &self.bytes
+    }
+}
+
+/// Perform the `name_to_handle_at` system call.
+///
+/// Returns the handle of the file and the ID of its mount.
+/// The mount ID identifies the file system that the handle is for;
+/// pass a file descriptor for a file on that mount
+/// to [`open_by_handle_at`].
+/// File systems that do not support handles fail with `EOPNOTSUPP`.
+pub fn name_to_handle_at(
+    dir: impl AsFd,
+    pathname: impl AsRef<Path>,
+    flags: c_int,
+) -> Result<(FileHandle, c_int)>
+{
+    let pathname = pathname.as_ref();
+    let pathname_c = path_to_cstring("name_to_handle_at", pathname)?;
+
+    let mut handle = RawFileHandle{
+        handle_bytes: MAX_HANDLE_SZ as c_uint,
+        handle_type: 0,
+        f_handle: [0; MAX_HANDLE_SZ],
+    };
+    let mut mount_id = 0;
+
+    // SAFETY: The C string is of type CString
+    // and is therefore null-terminated.
+    // The handle has room for as many bytes as it says.
+    // The system call is used rather than the libc function,
+    // which is missing from older C libraries.
+    let status = unsafe {
+        libc::syscall(
+            libc::SYS_name_to_handle_at,
+            dir.as_fd().as_raw_fd(),
+            pathname_c.as_ptr(),
+            &mut handle as *mut RawFileHandle,
+            &mut mount_id as *mut c_int,
+            flags,
+        )
+    };
+
+    if status == -1 {
+        Err(FsError::last_os_error("name_to_handle_at", Some(pathname)))
+    } else {
+        let bytes = handle.f_handle[.. handle.handle_bytes as usize].to_vec();
+        Ok((FileHandle{handle_type: handle.handle_type, bytes}, mount_id))
+    }
+}
+
+/// Perform the `open_by_handle_at` system call.
+///
+/// The mount file descriptor may refer to any file
+/// on the mount that the handle was obtained from.
+/// This requires the `CAP_DAC_READ_SEARCH` capability,
+/// and fails with `ESTALE` if the file no longer exists.
+pub fn open_by_handle_at(
+    mount_fd: impl AsFd,
+    handle: &FileHandle,
+    flags: c_int,
+) -> Result<File>
+{
+    let mut raw = RawFileHandle{
+        handle_bytes: handle.bytes.len() as c_uint,
+        handle_type: handle.handle_type,
+        f_handle: [0; MAX_HANDLE_SZ],
+    };
+    raw.f_handle.get_mut(.. handle.bytes.len())
+        .ok_or_else(|| {
+            let err = Error::new(InvalidInput, "file handle too long");
+            FsError::new("open_by_handle_at", None, err)
+        })?
+        .copy_from_slice(&handle.bytes);
+
+    // SAFETY: The handle has as many bytes as it says.
+    // The system call is used rather than the libc function,
+    // which is missing from older C libraries.
+    let fd = unsafe {
+        libc::syscall(
+            libc::SYS_open_by_handle_at,
+            mount_fd.as_fd().as_raw_fd(),
+            &raw as *const RawFileHandle,
+            flags,
+        )
+    };
+
+    if fd == -1 {
+        Err(FsError::last_os_error("open_by_handle_at", None))
+    } else {
+        // SAFETY: The file descriptor was just opened,
+        // so nothing else owns it.
+        Ok(File::from(unsafe { OwnedFd::from_raw_fd(fd as c_int) }))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::testdata::TestData;
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_name_to_handle_at()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_name_to_handle_at").unwrap();
+
+        // Find the handle of the file,
+        // and try to find that of a missing file.
+        let (handle, _) = name_to_handle_at(&test_data.root, "regular", 0)
+            .unwrap();
+        let err = name_to_handle_at(&test_data.root, "missing", 0)
+            .unwrap_err();
+
+        // Check the results.
+        let copy = FileHandle::new(handle.handle_type(),
+                                   handle.bytes().to_vec());
+        assert_eq!(copy.as_ref(), Some(&handle));
+        assert_eq!(FileHandle::new(0, vec![0; MAX_HANDLE_SZ + 1]), None);
+        assert_eq!(err.raw_os_error(), Some(libc::ENOENT));
+        assert_eq!(err.syscall(), "name_to_handle_at");
+        assert_eq!(err.path(), Some(Path::new("missing")));
+
+        // Opening the handle requires CAP_DAC_READ_SEARCH.
+        match open_by_handle_at(&test_data.root, &handle, libc::O_RDONLY) {
+            Ok(mut file) => {
+                let mut contents = Vec::new();
+                file.read_to_end(&mut contents).unwrap();
+                assert_eq!(contents, test_data.regular_contents);
+            },
+            Err(err) => {
+                assert_eq!(err.raw_os_error(), Some(libc::EPERM));
+                assert_eq!(err.syscall(), "open_by_handle_at");
+            },
+        }
+    }
+}
@@ -1,31 +1,37 @@
+use crate::FsError;
+use crate::Result;
+use crate::path_to_cstring;
 use libc::mode_t;
-use std::ffi::CString;
 use std::fs::File;
-use std::io::Error;
-use std::io::Result;
 use std::os::raw::c_int;
-use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::AsFd;
 use std::os::unix::io::AsRawFd;
 use std::os::unix::io::FromRawFd;
+use std::os::unix::io::OwnedFd;
 use std::path::Path;
 
 /// Perform the `openat` system call.
 pub fn openat(
-    dir: &impl AsRawFd,
+    dir: impl AsFd,
     pathname: impl AsRef<Path>,
     flags: c_int,
     mode: mode_t,
 ) -> Result<File>
 {
-    let cstr = |p: &Path| CString::new(p.as_os_str().as_bytes());
+    let pathname = pathname.as_ref();
+    let pathname_c = path_to_cstring("openat", pathname)?;
 
-    let pathname_c = cstr(pathname.as_ref())?;
+    // The mode is passed as a variadic argument,
+    // which Rust requires to be at least as wide as int,
+    // unlike mode_t on the BSDs.
+    #[cfg(not(target_os = "linux"))]
+    let mode = mode as libc::c_uint;
 
     // SAFETY: The C string is of type CString
     // and is therefore null-terminated.
     let fd = unsafe {
         libc::openat(
-            dir.as_raw_fd(),
+            dir.as_fd().as_raw_fd(),
             pathname_c.as_ptr(),
             flags,
             mode,
@@ -33,9 +39,10 @@ pub fn openat(
     };
 
     if fd == -1 {
-        Err(Error::last_os_error())
+        Err(FsError::last_os_error("openat", Some(pathname)))
     } else {
-        // SAFETY: FromRawFd::from_raw_fd being unsafe is silly.
-        Ok(unsafe { File::from_raw_fd(fd) })
+        // SAFETY: The file descriptor was just opened,
+        // so nothing else owns it.
+        Ok(File::from(unsafe { OwnedFd::from_raw_fd(fd) }))
     }
 }
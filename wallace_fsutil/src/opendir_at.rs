@@ -0,0 +1,49 @@
+use crate::Dir;
+use crate::Result;
+use crate::fdopendir;
+use crate::openat;
+use std::os::unix::io::AsFd;
+use std::path::Path;
+
+/// Open a directory relative to another for iteration,
+/// with [`openat`] followed by [`fdopendir`].
+///
+/// The directory is opened with `O_DIRECTORY`, `O_CLOEXEC`,
+/// and `O_NOFOLLOW`, so that opening anything but a directory fails,
+/// in particular a symbolic link to a directory.
+pub fn opendir_at(dir: impl AsFd, pathname: impl AsRef<Path>)
+    -> Result<Dir>
+{
+    let flags = { use libc::*; O_RDONLY | O_DIRECTORY |
+                               O_CLOEXEC | O_NOFOLLOW };
+    let file = openat(dir, pathname, flags, 0)?;
+    fdopendir(file)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::testdata::TestData;
+    use super::*;
+
+    #[test]
+    fn test_opendir_at()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_opendir_at").unwrap();
+        let root_path = &test_data.root_path;
+        std::fs::create_dir(root_path.join("directory")).unwrap();
+        std::os::unix::fs::symlink("directory", root_path.join("symlink"))
+            .unwrap();
+
+        // Open the directory, and try to open the symbolic link to it.
+        let dir = opendir_at(&test_data.root, "directory").unwrap();
+        let err = opendir_at(&test_data.root, "symlink").err().unwrap();
+
+        // Check the results.
+        assert_eq!(dir.into_iter().count(), 2);
+        assert_eq!(err.raw_os_error(), Some(libc::ENOTDIR));
+        assert_eq!(err.syscall(), "openat");
+        assert_eq!(err.path(), Some(Path::new("symlink")));
+    }
+}
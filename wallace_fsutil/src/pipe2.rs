@@ -0,0 +1,88 @@
+use crate::FsError;
+use crate::Result;
+use std::fs::File;
+use std::os::raw::c_int;
+use std::os::unix::io::FromRawFd;
+use std::os::unix::io::OwnedFd;
+
+/// Perform the `pipe2` system call.
+///
+/// Returns the read end and the write end of the pipe, in that order.
+/// The flags are typically `O_CLOEXEC`, optionally with `O_NONBLOCK`.
+///
+/// macOS lacks `pipe2`, so there the flags are set after creating the pipe,
+/// and a concurrent `fork` may inherit the file descriptors.
+pub fn pipe2(flags: c_int) -> Result<(File, File)>
+{
+    let mut fds = [-1; 2];
+
+    // SAFETY: The array has room for two file descriptors.
+    #[cfg(not(target_os = "macos"))]
+    let status = unsafe {
+        libc::pipe2(fds.as_mut_ptr(), flags)
+    };
+
+    // SAFETY: The array has room for two file descriptors.
+    #[cfg(target_os = "macos")]
+    let status = unsafe {
+        libc::pipe(fds.as_mut_ptr())
+    };
+
+    if status == -1 {
+        return Err(FsError::last_os_error("pipe2", None));
+    }
+
+    // SAFETY: The file descriptors were just created,
+    // so nothing else owns them.
+    let (read, write) = unsafe {
+        (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1]))
+    };
+
+    #[cfg(target_os = "macos")]
+    {
+        crate::fcntl::emulate_open_flags(&read, flags)?;
+        crate::fcntl::emulate_open_flags(&write, flags)?;
+    }
+
+    Ok((File::from(read), File::from(write)))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::fcntl_getfd;
+    use super::*;
+    use std::io::Read;
+    use std::io::Write;
+
+    #[test]
+    fn test_pipe2()
+    {
+        // Prepare the test.
+        let flags = libc::O_CLOEXEC;
+
+        // Create a pipe and send bytes through it.
+        let (mut read, mut write) = pipe2(flags).unwrap();
+        write.write_all(b"Hello").unwrap();
+        drop(write);
+        let mut contents = Vec::new();
+        read.read_to_end(&mut contents).unwrap();
+
+        // Check the results.
+        assert_eq!(contents, b"Hello");
+        assert_ne!(fcntl_getfd(&read).unwrap() & libc::FD_CLOEXEC, 0);
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn test_pipe2_invalid_flags()
+    {
+        // Try to create a pipe with a flag that pipes do not take.
+        let err = pipe2(libc::O_APPEND).unwrap_err();
+
+        // Check the results.
+        assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+        assert_eq!(err.syscall(), "pipe2");
+        assert_eq!(err.path(), None);
+    }
+}
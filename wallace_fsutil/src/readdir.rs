@@ -1,7 +1,10 @@
 use crate::Dir;
+use crate::FileType;
+use crate::FsError;
+use crate::Result;
+use crate::errno::set_errno;
 use std::ffi::CStr;
-use std::io::Error;
-use std::io::Result;
+use std::ffi::CString;
 use std::marker::PhantomPinned;
 use std::os::raw::c_char;
 use std::os::raw::c_uchar;
@@ -22,11 +25,21 @@ pub struct Dirent
 
 impl Dirent
 {
+    #[cfg(not(target_os = "freebsd"))]
     pub fn d_ino   (&self) -> libc::ino_t { self.inner.d_ino    }
+    #[cfg(target_os = "freebsd")]
+    pub fn d_ino   (&self) -> libc::ino_t { self.inner.d_fileno }
+    #[cfg(target_os = "linux")]
     pub fn d_off   (&self) -> libc::off_t { self.inner.d_off    }
     pub fn d_reclen(&self) -> c_ushort    { self.inner.d_reclen }
     pub fn d_type  (&self) -> c_uchar     { self.inner.d_type   }
 
+    /// The type of the file, converted from `d_type`.
+    pub fn file_type(&self) -> FileType
+    {
+        FileType::from_d_type(self.d_type())
+    }
+
     pub fn d_name(&self) -> &CStr
     {
         // SAFETY: d_name is guaranteed null-terminated.
@@ -39,22 +52,22 @@ impl Dirent
 /// Perform the `readdir` system call.
 pub fn readdir(dirp: &mut Dir) -> Result<Option<Pin<&Dirent>>>
 {
-    // SAFETY: Reading errno is safe.
-    unsafe {
-        // readdir returns NULL if the final entry has been reached,
-        // but it also returns NULL if an error occurs.
-        // To distinguish between these cases, set errno to zero first.
-        *libc::__errno_location() = 0;
+    // readdir returns NULL if the final entry has been reached,
+    // but it also returns NULL if an error occurs.
+    // To distinguish between these cases, set errno to zero first.
+    set_errno(0);
 
-        // SAFETY: Dir ensures the DIR is alive.
+    // SAFETY: Dir ensures the DIR is alive.
+    unsafe {
         let dirent = libc::readdir(dirp.inner);
 
         if dirent.is_null() {
 
-            if *libc::__errno_location() == 0 {
+            let err = FsError::last_os_error("readdir", None);
+            if err.raw_os_error() == Some(0) {
                 Ok(None)
             } else {
-                Err(Error::last_os_error())
+                Err(err)
             }
 
         } else {
@@ -71,3 +84,188 @@ pub fn readdir(dirp: &mut Dir) -> Result<Option<Pin<&Dirent>>>
         }
     }
 }
+
+/// Owned copy of a [`Dirent`],
+/// as returned by [`readdir_owned`] and [`Dir::entries`].
+///
+/// Unlike the entry returned by [`readdir`],
+/// this remains valid after the next call,
+/// so it can be collected or sent to another thread.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DirentBuf
+{
+    d_ino: libc::ino_t,
+    d_type: c_uchar,
+    d_name: CString,
+}
+
+impl DirentBuf
+{
+    pub fn d_ino (&self) -> libc::ino_t { self.d_ino  }
+    pub fn d_type(&self) -> c_uchar     { self.d_type }
+    pub fn d_name(&self) -> &CStr       { &self.d_name }
+
+    /// The type of the file, converted from `d_type`.
+    pub fn file_type(&self) -> FileType
+    {
+        FileType::from_d_type(self.d_type)
+    }
+
+    /// Take the name, without the other fields.
+    pub fn into_d_name(self) -> CString
+    {
+        self.d_name
+    }
+}
+
+impl From<Pin<&Dirent>> for DirentBuf
+{
+    fn from(dirent: Pin<&Dirent>) -> Self
+    {
+        Self{
+            d_ino: dirent.d_ino(),
+            d_type: dirent.d_type(),
+            d_name: dirent.d_name().to_owned(),
+        }
+    }
+}
+
+/// Like [`readdir`], but copy the entry.
+pub fn readdir_owned(dirp: &mut Dir) -> Result<Option<DirentBuf>>
+{
+    Ok(readdir(dirp)?.map(DirentBuf::from))
+}
+
+impl Dir
+{
+    /// Iterate over the remaining entries of the directory,
+    /// calling [`readdir_owned`] for each.
+    ///
+    /// This includes the entries `.` and `..`.
+    /// The iterator stops after the first error.
+    pub fn entries(&mut self) -> Entries<'_>
+    {
+        Entries{dir: Some(self)}
+    }
+}
+
+/// Iterator returned by [`Dir::entries`].
+pub struct Entries<'a>
+{
+    dir: Option<&'a mut Dir>,
+}
+
+impl<'a> Iterator for Entries<'a>
+{
+    type Item = Result<DirentBuf>;
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        let result = readdir_owned(self.dir.as_mut()?);
+        match result {
+            Ok(Some(dirent)) => Some(Ok(dirent)),
+            Ok(None) => { self.dir = None; None },
+            Err(err) => { self.dir = None; Some(Err(err)) },
+        }
+    }
+}
+
+impl IntoIterator for Dir
+{
+    type Item = Result<DirentBuf>;
+    type IntoIter = IntoEntries;
+
+    /// Like [`Dir::entries`], but take ownership of the directory.
+    fn into_iter(self) -> IntoEntries
+    {
+        IntoEntries{dir: Some(self)}
+    }
+}
+
+/// Iterator returned by [`Dir::into_iter`].
+pub struct IntoEntries
+{
+    dir: Option<Dir>,
+}
+
+impl Iterator for IntoEntries
+{
+    type Item = Result<DirentBuf>;
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        let result = readdir_owned(self.dir.as_mut()?);
+        match result {
+            Ok(Some(dirent)) => Some(Ok(dirent)),
+            Ok(None) => { self.dir = None; None },
+            Err(err) => { self.dir = None; Some(Err(err)) },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::dup3;
+    use crate::opendir_at;
+    use crate::testdata::TestData;
+    use super::*;
+    use std::fs::File;
+    use std::mem::ManuallyDrop;
+    use std::os::unix::io::AsFd;
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::io::FromRawFd;
+    use std::os::unix::io::OwnedFd;
+
+    #[test]
+    fn test_readdir_entries()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_readdir_entries").unwrap();
+        std::fs::create_dir(test_data.root_path.join("directory")).unwrap();
+
+        // List the directory twice, borrowing it and consuming it.
+        let mut dir = opendir_at(&test_data.root, ".").unwrap();
+        let mut borrowed = dir.entries().collect::<Result<Vec<_>>>()
+            .unwrap();
+        dir.rewind();
+        let mut owned = dir.into_iter().collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        // Check the results.
+        borrowed.sort_by(|a, b| a.d_name().cmp(b.d_name()));
+        owned.sort_by(|a, b| a.d_name().cmp(b.d_name()));
+        assert_eq!(borrowed, owned);
+        let entries = borrowed.iter()
+            .map(|entry| (entry.d_name().to_str().unwrap(),
+                          entry.file_type()))
+            .collect::<Vec<_>>();
+        assert_eq!(entries, [(".", FileType::Directory),
+                             ("..", FileType::Directory),
+                             ("directory", FileType::Directory),
+                             ("regular", FileType::Regular)]);
+    }
+
+    #[test]
+    fn test_readdir_not_directory()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_readdir_not_directory").unwrap();
+        let mut dir = opendir_at(&test_data.root, ".").unwrap();
+        let regular = File::open(&test_data.regular_path).unwrap();
+
+        // Replace the file descriptor of the directory
+        // with that of a regular file, and read the directory.
+        // SAFETY: The file descriptor remains owned by the directory.
+        let mut fd = ManuallyDrop::new(unsafe {
+            OwnedFd::from_raw_fd(dir.as_fd().as_raw_fd())
+        });
+        dup3(&regular, &mut fd, libc::O_CLOEXEC).unwrap();
+        let err = readdir_owned(&mut dir).unwrap_err();
+
+        // Check the results.
+        assert_eq!(err.raw_os_error(), Some(libc::ENOTDIR));
+        assert_eq!(err.syscall(), "readdir");
+        assert_eq!(err.path(), None);
+    }
+}
@@ -0,0 +1,37 @@
+use crate::FsError;
+use crate::Result;
+use crate::path_to_cstring;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+/// Perform the `renameat` system call.
+pub fn renameat(
+    olddir: impl AsFd,
+    oldpath: impl AsRef<Path>,
+    newdir: impl AsFd,
+    newpath: impl AsRef<Path>,
+) -> Result<()>
+{
+    let oldpath = oldpath.as_ref();
+    let newpath = newpath.as_ref();
+    let oldpath_c = path_to_cstring("renameat", oldpath)?;
+    let newpath_c = path_to_cstring("renameat", newpath)?;
+
+    // SAFETY: All C strings are of type CString
+    // and are therefore null-terminated.
+    let status = unsafe {
+        libc::renameat(
+            olddir.as_fd().as_raw_fd(),
+            oldpath_c.as_ptr(),
+            newdir.as_fd().as_raw_fd(),
+            newpath_c.as_ptr(),
+        )
+    };
+
+    if status == -1 {
+        Err(FsError::last_os_error("renameat", Some(newpath)))
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use crate::FsError;
+use crate::Result;
+use crate::linkat;
+use crate::path_to_cstring;
+use crate::renameat;
+use crate::unlinkat;
+use std::os::raw::c_uint;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+/// Perform the `renameat2` system call.
+///
+/// If the kernel or the file system does not support `renameat2`
+/// or the given flags, which is reported as `ENOSYS` or `EINVAL`,
+/// a fallback is used where one exists:
+/// without flags, `renameat` is called instead,
+/// and with just `RENAME_NOREPLACE`, the file is hard linked
+/// to its new path and then unlinked from its old path.
+/// The latter does not work for directories,
+/// and briefly leaves the file at both paths.
+/// Other flags, such as `RENAME_EXCHANGE`, have no fallback,
+/// and the original error is returned.
+pub fn renameat2(
+    olddir: impl AsFd,
+    oldpath: impl AsRef<Path>,
+    newdir: impl AsFd,
+    newpath: impl AsRef<Path>,
+    flags: c_uint,
+) -> Result<()>
+{
+    let olddir = olddir.as_fd();
+    let oldpath = oldpath.as_ref();
+    let newdir = newdir.as_fd();
+    let newpath = newpath.as_ref();
+
+    let oldpath_c = path_to_cstring("renameat2", oldpath)?;
+    let newpath_c = path_to_cstring("renameat2", newpath)?;
+
+    // SAFETY: All C strings are of type CString
+    // and are therefore null-terminated.
+    // The system call is used rather than the libc function,
+    // which is missing from older C libraries.
+    let status = unsafe {
+        libc::syscall(
+            libc::SYS_renameat2,
+            olddir.as_raw_fd(),
+            oldpath_c.as_ptr(),
+            newdir.as_raw_fd(),
+            newpath_c.as_ptr(),
+            flags,
+        )
+    };
+
+    if status != -1 {
+        return Ok(());
+    }
+
+    let err = FsError::last_os_error("renameat2", Some(newpath));
+    match err.raw_os_error() {
+        Some(libc::ENOSYS) | Some(libc::EINVAL) => (),
+        _ => return Err(err),
+    }
+
+    if flags == 0 {
+        renameat(olddir, oldpath, newdir, newpath)
+    } else if flags == libc::RENAME_NOREPLACE {
+        linkat(olddir, oldpath, newdir, newpath, 0)?;
+        unlinkat(olddir, oldpath, 0)
+    } else {
+        Err(err)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::testdata::TestData;
+    use super::*;
+
+    #[test]
+    fn test_renameat2()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_renameat2").unwrap();
+        let root = &test_data.root;
+        std::fs::write(test_data.root_path.join("other"), b"").unwrap();
+
+        // Rename the file, then try to rename it over another file.
+        renameat2(root, "regular", root, "renamed", libc::RENAME_NOREPLACE)
+            .unwrap();
+        let err = renameat2(root, "renamed", root, "other",
+                            libc::RENAME_NOREPLACE).unwrap_err();
+
+        // Check the results.
+        let renamed = std::fs::read(test_data.root_path.join("renamed"));
+        assert_eq!(renamed.unwrap(), test_data.regular_contents);
+        assert!(!test_data.regular_path.exists());
+        assert_eq!(err.raw_os_error(), Some(libc::EEXIST));
+        assert_eq!(err.syscall(), "renameat2");
+        assert_eq!(err.path(), Some(Path::new("other")));
+    }
+}
@@ -0,0 +1,82 @@
+use crate::Dir;
+use crate::FsError;
+use crate::Result;
+use std::os::raw::c_long;
+
+impl Dir
+{
+    /// Call `telldir`, returning the current position in the directory.
+    ///
+    /// The position can be passed to [`Dir::seek`]
+    /// to continue reading from the same entry later.
+    /// It is only meaningful for this `Dir`,
+    /// and not for other `Dir`s of the same directory,
+    /// although on Linux it is the stable `d_off` of the entry,
+    /// so a listing can in practice be resumed from a new `Dir`.
+    pub fn tell(&mut self) -> Result<c_long>
+    {
+        // SAFETY: Dir ensures the DIR is alive.
+        let position = unsafe {
+            libc::telldir(self.inner)
+        };
+
+        if position == -1 {
+            Err(FsError::last_os_error("telldir", None))
+        } else {
+            Ok(position)
+        }
+    }
+
+    /// Call `seekdir`, moving to a position returned by [`Dir::tell`].
+    pub fn seek(&mut self, position: c_long)
+    {
+        // SAFETY: Dir ensures the DIR is alive.
+        unsafe {
+            libc::seekdir(self.inner, position);
+        }
+    }
+
+    /// Call `rewinddir`, moving back to the first entry.
+    ///
+    /// This also picks up changes to the directory
+    /// made after the `Dir` was opened.
+    pub fn rewind(&mut self)
+    {
+        // SAFETY: Dir ensures the DIR is alive.
+        unsafe {
+            libc::rewinddir(self.inner);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::opendir_at;
+    use crate::readdir_owned;
+    use crate::testdata::TestData;
+
+    #[test]
+    fn test_seekdir()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_seekdir").unwrap();
+        let mut dir = opendir_at(&test_data.root, ".").unwrap();
+
+        // Remember the position after the first entry,
+        // read the remaining entries, and read them again.
+        let first = readdir_owned(&mut dir).unwrap().unwrap();
+        let position = dir.tell().unwrap();
+        let rest = dir.entries().collect::<Result<Vec<_>, _>>().unwrap();
+        dir.seek(position);
+        let rest_again = dir.entries().collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        dir.rewind();
+        let first_again = readdir_owned(&mut dir).unwrap().unwrap();
+
+        // Check the results.
+        assert_eq!(rest.len(), 2);
+        assert_eq!(rest, rest_again);
+        assert_eq!(first, first_again);
+    }
+}
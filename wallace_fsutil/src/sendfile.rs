@@ -0,0 +1,35 @@
+use crate::FsError;
+use crate::Result;
+use libc::off_t;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+use std::ptr::null_mut;
+
+/// Perform the `sendfile` system call.
+///
+/// If an offset is given, it is used and updated
+/// instead of the file offset of the input file.
+/// Returns the number of bytes that were copied,
+/// which is zero at the end of the input file.
+pub fn sendfile(
+    out_fd: impl AsFd,
+    in_fd: impl AsFd,
+    offset: Option<&mut off_t>,
+    count: usize,
+) -> Result<usize>
+{
+    let offset_ptr = offset.map_or(null_mut(), |o| o as *mut off_t);
+
+    // SAFETY: The offset pointer is either null
+    // or derived from a mutable reference.
+    let status = unsafe {
+        libc::sendfile(out_fd.as_fd().as_raw_fd(), in_fd.as_fd().as_raw_fd(),
+                       offset_ptr, count)
+    };
+
+    if status == -1 {
+        Err(FsError::last_os_error("sendfile", None))
+    } else {
+        Ok(status as usize)
+    }
+}
@@ -0,0 +1,36 @@
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::io::Result;
+use std::path::PathBuf;
+
+pub struct TestData
+{
+    pub root_path: PathBuf,
+    pub root: File,
+
+    pub regular_path: PathBuf,
+    #[cfg(target_os = "linux")]
+    pub regular_contents: Vec<u8>,
+}
+
+impl TestData
+{
+    pub fn new(name: &str) -> Result<Self>
+    {
+        // Create root.
+        let root_path = env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&root_path);
+        fs::create_dir(&root_path)?;
+        let root = File::open(&root_path)?;
+
+        // Create regular file.
+        let regular_path = root_path.join("regular");
+        let regular_contents = b"Hello, world!\n".to_vec();
+        fs::write(&regular_path, &regular_contents)?;
+
+        Ok(Self{root_path, root, regular_path,
+                #[cfg(target_os = "linux")]
+                regular_contents})
+    }
+}
@@ -0,0 +1,180 @@
+use crate::FsError;
+use crate::Result;
+use crate::path_to_cstring;
+use std::convert::TryFrom;
+use std::io::Error;
+use std::io::ErrorKind::InvalidInput;
+use std::os::raw::c_int;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+/// Timestamp to set with [`utimensat`] or [`futimens`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Timestamp
+{
+    /// Set the timestamp to the given time.
+    At(SystemTime),
+
+    /// Set the timestamp to the current time, as `UTIME_NOW`.
+    Now,
+
+    /// Leave the timestamp unchanged, as `UTIME_OMIT`.
+    Omit,
+}
+
+impl Timestamp
+{
+    fn to_timespec(self, syscall: &'static str) -> Result<libc::timespec>
+    {
+        let (tv_sec, tv_nsec) = match self {
+            Self::Now  => (0, libc::UTIME_NOW),
+            Self::Omit => (0, libc::UTIME_OMIT),
+            Self::At(time) => {
+                let out_of_range = || {
+                    let err = Error::new(InvalidInput,
+                                         "timestamp out of range");
+                    FsError::new(syscall, None, err)
+                };
+                match time.duration_since(UNIX_EPOCH) {
+                    Ok(after) => (
+                        libc::time_t::try_from(after.as_secs())
+                            .map_err(|_| out_of_range())?,
+                        after.subsec_nanos() as libc::c_long,
+                    ),
+                    // Times before the epoch have negative seconds,
+                    // but nanoseconds are always non-negative.
+                    Err(err) => {
+                        let before = err.duration();
+                        let secs = libc::time_t::try_from(before.as_secs())
+                            .map_err(|_| out_of_range())?;
+                        let nanos = before.subsec_nanos();
+                        if nanos == 0 {
+                            (-secs, 0)
+                        } else {
+                            (-secs - 1, (1_000_000_000 - nanos) as _)
+                        }
+                    },
+                }
+            },
+        };
+        Ok(libc::timespec{tv_sec, tv_nsec})
+    }
+}
+
+/// Perform the `utimensat` system call.
+///
+/// The flags are zero or `AT_SYMLINK_NOFOLLOW`.
+pub fn utimensat(
+    dir: impl AsFd,
+    pathname: impl AsRef<Path>,
+    atime: Timestamp,
+    mtime: Timestamp,
+    flags: c_int,
+) -> Result<()>
+{
+    let pathname = pathname.as_ref();
+    let pathname_c = path_to_cstring("utimensat", pathname)?;
+    let times = [atime.to_timespec("utimensat")?,
+                 mtime.to_timespec("utimensat")?];
+
+    // SAFETY: The C string is of type CString
+    // and is therefore null-terminated,
+    // and times has the two elements that utimensat reads.
+    let status = unsafe {
+        libc::utimensat(
+            dir.as_fd().as_raw_fd(),
+            pathname_c.as_ptr(),
+            times.as_ptr(),
+            flags,
+        )
+    };
+
+    if status == -1 {
+        Err(FsError::last_os_error("utimensat", Some(pathname)))
+    } else {
+        Ok(())
+    }
+}
+
+/// Perform the `futimens` system call.
+pub fn futimens(fd: impl AsFd, atime: Timestamp, mtime: Timestamp)
+    -> Result<()>
+{
+    let times = [atime.to_timespec("futimens")?,
+                 mtime.to_timespec("futimens")?];
+
+    // SAFETY: times has the two elements that futimens reads.
+    let status = unsafe {
+        libc::futimens(fd.as_fd().as_raw_fd(), times.as_ptr())
+    };
+
+    if status == -1 {
+        Err(FsError::last_os_error("futimens", None))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::testdata::TestData;
+    use super::*;
+    use std::fs::File;
+    use std::time::Duration;
+
+    #[test]
+    fn test_utimensat()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_utimensat").unwrap();
+        let file = File::open(&test_data.regular_path).unwrap();
+        let after = UNIX_EPOCH + Duration::new(1000, 500);
+        let before = UNIX_EPOCH - Duration::new(1000, 500);
+
+        // Set the timestamps, and try to set those of a missing file.
+        utimensat(&test_data.root, "regular",
+                  Timestamp::At(after), Timestamp::At(before), 0).unwrap();
+        let err = utimensat(&test_data.root, "missing",
+                            Timestamp::Now, Timestamp::Now, 0).unwrap_err();
+
+        // Check the results.
+        let metadata = file.metadata().unwrap();
+        assert_eq!(metadata.accessed().unwrap(), after);
+        assert_eq!(metadata.modified().unwrap(), before);
+        assert_eq!(err.raw_os_error(), Some(libc::ENOENT));
+        assert_eq!(err.syscall(), "utimensat");
+        assert_eq!(err.path(), Some(Path::new("missing")));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_futimens()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_futimens").unwrap();
+        let file = File::open(&test_data.regular_path).unwrap();
+        let path_only = crate::openat(&test_data.root, "regular",
+                                      libc::O_PATH | libc::O_CLOEXEC, 0)
+            .unwrap();
+        let modified = file.metadata().unwrap().modified().unwrap();
+        let time = UNIX_EPOCH + Duration::from_secs(1000);
+
+        // Set the access time only,
+        // and try to set it through an O_PATH file descriptor.
+        futimens(&file, Timestamp::At(time), Timestamp::Omit).unwrap();
+        let err = futimens(&path_only, Timestamp::Now, Timestamp::Omit)
+            .unwrap_err();
+
+        // Check the results.
+        let metadata = file.metadata().unwrap();
+        assert_eq!(metadata.accessed().unwrap(), time);
+        assert_eq!(metadata.modified().unwrap(), modified);
+        assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+        assert_eq!(err.syscall(), "futimens");
+        assert_eq!(err.path(), None);
+    }
+}
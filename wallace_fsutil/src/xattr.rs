@@ -0,0 +1,235 @@
+use crate::FsError;
+use crate::Result;
+use std::ffi::CString;
+use std::ffi::OsStr;
+use std::os::raw::c_int;
+use std::os::raw::c_void;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+
+/// Convert an attribute name to a C string for the given system call,
+/// which fails if the name contains a null byte.
+fn name_to_cstring(syscall: &'static str, name: &OsStr) -> Result<CString>
+{
+    CString::new(name.as_bytes())
+        .map_err(|err| FsError::new(syscall, None, err.into()))
+}
+
+/// Perform the `fgetxattr` system call, returning the value.
+///
+/// The buffer is sized by asking for the size of the value first.
+/// A missing attribute fails with `ENODATA`, or `ENOATTR` on macOS.
+pub fn fgetxattr(fd: impl AsFd, name: impl AsRef<OsStr>)
+    -> Result<Vec<u8>>
+{
+    let name_c = name_to_cstring("fgetxattr", name.as_ref())?;
+
+    // The value may grow between the calls, so retry on ERANGE.
+    loop {
+        // SAFETY: The C string is of type CString
+        // and is therefore null-terminated.
+        // A null buffer with size zero asks for the size of the value.
+        let size = unsafe {
+            sys::fgetxattr(fd.as_fd().as_raw_fd(), name_c.as_ptr(),
+                            std::ptr::null_mut(), 0)
+        };
+        if size == -1 {
+            return Err(FsError::last_os_error("fgetxattr", None));
+        }
+
+        let mut value = vec![0u8; size as usize];
+
+        // SAFETY: The buffer has the given size.
+        let size = unsafe {
+            sys::fgetxattr(fd.as_fd().as_raw_fd(), name_c.as_ptr(),
+                            value.as_mut_ptr() as *mut c_void, value.len())
+        };
+        if size == -1 {
+            let err = FsError::last_os_error("fgetxattr", None);
+            if err.raw_os_error() == Some(libc::ERANGE) {
+                continue;
+            }
+            return Err(err);
+        }
+
+        value.truncate(size as usize);
+        return Ok(value);
+    }
+}
+
+/// Perform the `fsetxattr` system call.
+///
+/// The flags are zero, `XATTR_CREATE`, or `XATTR_REPLACE`.
+/// Unprivileged processes can only set attributes
+/// in the `user.` namespace, and only on files they can write.
+pub fn fsetxattr(fd: impl AsFd, name: impl AsRef<OsStr>,
+                 value: &[u8], flags: c_int) -> Result<()>
+{
+    let name_c = name_to_cstring("fsetxattr", name.as_ref())?;
+
+    // SAFETY: The C string is of type CString
+    // and is therefore null-terminated,
+    // and the value has the given size.
+    let status = unsafe {
+        sys::fsetxattr(fd.as_fd().as_raw_fd(), name_c.as_ptr(),
+                        value.as_ptr() as *const c_void, value.len(), flags)
+    };
+
+    if status == -1 {
+        Err(FsError::last_os_error("fsetxattr", None))
+    } else {
+        Ok(())
+    }
+}
+
+/// Perform the `fremovexattr` system call.
+pub fn fremovexattr(fd: impl AsFd, name: impl AsRef<OsStr>)
+    -> Result<()>
+{
+    let name_c = name_to_cstring("fremovexattr", name.as_ref())?;
+
+    // SAFETY: The C string is of type CString
+    // and is therefore null-terminated.
+    let status = unsafe {
+        sys::fremovexattr(fd.as_fd().as_raw_fd(), name_c.as_ptr())
+    };
+
+    if status == -1 {
+        Err(FsError::last_os_error("fremovexattr", None))
+    } else {
+        Ok(())
+    }
+}
+
+/// Perform the `flistxattr` system call, returning the names.
+///
+/// Only the names the caller has access to are returned,
+/// which for unprivileged processes excludes `trusted.` names.
+pub fn flistxattr(fd: impl AsFd) -> Result<Vec<CString>>
+{
+    // The list may grow between the calls, so retry on ERANGE.
+    let list = loop {
+        // SAFETY: A null buffer with size zero asks for the size.
+        let size = unsafe {
+            sys::flistxattr(fd.as_fd().as_raw_fd(), std::ptr::null_mut(), 0)
+        };
+        if size == -1 {
+            return Err(FsError::last_os_error("flistxattr", None));
+        }
+
+        let mut list = vec![0u8; size as usize];
+
+        // SAFETY: The buffer has the given size.
+        let size = unsafe {
+            sys::flistxattr(fd.as_fd().as_raw_fd(),
+                             list.as_mut_ptr() as *mut libc::c_char,
+                             list.len())
+        };
+        if size == -1 {
+            let err = FsError::last_os_error("flistxattr", None);
+            if err.raw_os_error() == Some(libc::ERANGE) {
+                continue;
+            }
+            return Err(err);
+        }
+
+        list.truncate(size as usize);
+        break list;
+    };
+
+    // The names are each terminated by a null byte.
+    let names = list.split(|&b| b == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| CString::new(name).unwrap())
+        .collect();
+    Ok(names)
+}
+
+#[cfg(target_os = "linux")]
+mod sys
+{
+    pub (super) use libc::fgetxattr;
+    pub (super) use libc::flistxattr;
+    pub (super) use libc::fremovexattr;
+    pub (super) use libc::fsetxattr;
+}
+
+/// The functions on macOS take an additional position,
+/// which is only used for resource forks,
+/// and options, which take the place of the flags.
+#[cfg(target_os = "macos")]
+mod sys
+{
+    use libc::c_char;
+    use libc::c_int;
+    use libc::c_void;
+    use libc::size_t;
+    use libc::ssize_t;
+
+    pub (super) unsafe fn fgetxattr(fd: c_int, name: *const c_char,
+                                    value: *mut c_void, size: size_t)
+        -> ssize_t
+    {
+        libc::fgetxattr(fd, name, value, size, 0, 0)
+    }
+
+    pub (super) unsafe fn flistxattr(fd: c_int, list: *mut c_char,
+                                     size: size_t) -> ssize_t
+    {
+        libc::flistxattr(fd, list, size, 0)
+    }
+
+    pub (super) unsafe fn fremovexattr(fd: c_int, name: *const c_char)
+        -> c_int
+    {
+        libc::fremovexattr(fd, name, 0)
+    }
+
+    pub (super) unsafe fn fsetxattr(fd: c_int, name: *const c_char,
+                                    value: *const c_void, size: size_t,
+                                    flags: c_int) -> c_int
+    {
+        libc::fsetxattr(fd, name, value, size, 0, flags)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::testdata::TestData;
+    use super::*;
+    use std::fs::File;
+    use std::fs::OpenOptions;
+
+    #[cfg(target_os = "linux")]
+    const ENOATTR: c_int = libc::ENODATA;
+    #[cfg(target_os = "macos")]
+    const ENOATTR: c_int = libc::ENOATTR;
+
+    #[test]
+    fn test_xattr()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_xattr").unwrap();
+        let file = OpenOptions::new().write(true)
+            .open(&test_data.regular_path).unwrap();
+
+        // Set, get, list, and remove an attribute,
+        // and try to get it once it is gone.
+        fsetxattr(&file, "user.wallace", b"Hello", 0).unwrap();
+        let value = fgetxattr(&file, "user.wallace").unwrap();
+        let names = flistxattr(&file).unwrap();
+        fremovexattr(&file, "user.wallace").unwrap();
+        let err = fgetxattr(&file, "user.wallace").unwrap_err();
+
+        // Check the results.
+        assert_eq!(value, b"Hello");
+        assert!(names.iter().any(|name| name.as_bytes() == b"user.wallace"));
+        assert!(flistxattr(File::open(&test_data.regular_path).unwrap())
+                    .unwrap().is_empty());
+        assert_eq!(err.raw_os_error(), Some(ENOATTR));
+        assert_eq!(err.syscall(), "fgetxattr");
+        assert_eq!(err.path(), None);
+    }
+}
@@ -0,0 +1,265 @@
+//! Implementation of sealed boxes based on libsodium.
+//!
+//! A sealed box encrypts a message for a recipient,
+//! given only the public key of the recipient.
+//! The sender remains anonymous and cannot decrypt the box afterwards.
+//! Only the holder of the secret key can open the box,
+//! and opening fails if the box was tampered with.
+//!
+//! This makes sealed boxes suitable for sending object payloads
+//! through relays that should not be able to read or alter them.
+
+#![doc(html_favicon_url = "../../../marketing/logo.svg")]
+#![doc(html_logo_url = "../../../marketing/logo.svg")]
+
+use std::fmt;
+use std::os::raw::c_int;
+use std::os::raw::c_void;
+use std::os::raw::c_uchar;
+use std::os::raw::c_ulonglong;
+
+/// Number of bytes a sealed box is larger than the message it seals.
+pub const SEALBYTES: usize = 48;
+
+#[link(name = "sodium")]
+extern "C"
+{
+    fn sodium_init() -> c_int;
+
+    fn sodium_memzero(pnt: *mut c_void, len: usize);
+
+    fn crypto_scalarmult_base(
+        q: *mut c_uchar,
+        n: *const c_uchar,
+    ) -> c_int;
+
+    fn crypto_box_keypair(
+        pk: *mut c_uchar,
+        sk: *mut c_uchar,
+    ) -> c_int;
+
+    fn crypto_box_seal(
+        c:    *mut c_uchar,
+        m:    *const c_uchar,
+        mlen: c_ulonglong,
+        pk:   *const c_uchar,
+    ) -> c_int;
+
+    fn crypto_box_seal_open(
+        m:    *mut c_uchar,
+        c:    *const c_uchar,
+        clen: c_ulonglong,
+        pk:   *const c_uchar,
+        sk:   *const c_uchar,
+    ) -> c_int;
+}
+
+/// Public key used to seal boxes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PublicKey
+{
+    /// The bytes that make up the public key.
+    pub bytes: [u8; 32],
+}
+
+/// Secret key used to open boxes.
+///
+/// The secret key also remembers the corresponding public key,
+/// as libsodium needs both to open a sealed box.
+/// The [`Debug`][`fmt::Debug`] impl does not print the secret key,
+/// and the bytes are zeroed when the secret key is dropped.
+#[derive(Clone)]
+pub struct SecretKey
+{
+    public_key: PublicKey,
+    bytes: [u8; 32],
+}
+
+/// Returned when a sealed box could not be opened.
+///
+/// This happens when the box was sealed for a different recipient,
+/// or when the box was tampered with.
+#[derive(Clone, Copy, Debug)]
+pub struct OpenError;
+
+/// Generate a new random key pair.
+pub fn keypair() -> (PublicKey, SecretKey)
+{
+    let mut pk = [0; 32];
+    let mut sk = [0; 32];
+
+    // SAFETY: The buffers have the sizes libsodium expects.
+    // sodium_init must be called before using the random number generator;
+    // it is safe to call multiple times and from multiple threads.
+    unsafe {
+        if sodium_init() == -1 {
+            panic!("sodium_init failed");
+        }
+        crypto_box_keypair(pk.as_mut_ptr(), sk.as_mut_ptr());
+    }
+
+    let public_key = PublicKey{bytes: pk};
+    (public_key, SecretKey{public_key, bytes: sk})
+}
+
+/// Seal a message for the owner of the given public key.
+///
+/// The returned box is [`SEALBYTES`] bytes larger than the message.
+pub fn seal(recipient: &PublicKey, message: &[u8]) -> Vec<u8>
+{
+    let mut sealed = vec![0; message.len() + SEALBYTES];
+
+    // SAFETY: The buffers have the sizes libsodium expects.
+    // crypto_box_seal uses the random number generator,
+    // so sodium_init must be called first.
+    unsafe {
+        if sodium_init() == -1 {
+            panic!("sodium_init failed");
+        }
+        crypto_box_seal(
+            sealed.as_mut_ptr(),
+            message.as_ptr(),
+            message.len() as u64,
+            recipient.bytes.as_ptr(),
+        );
+    }
+
+    sealed
+}
+
+/// Open a box that was sealed for the owner of the given secret key.
+pub fn open(recipient: &SecretKey, sealed: &[u8])
+    -> Result<Vec<u8>, OpenError>
+{
+    if sealed.len() < SEALBYTES {
+        return Err(OpenError);
+    }
+
+    let mut message = vec![0; sealed.len() - SEALBYTES];
+
+    // SAFETY: The buffers have the sizes libsodium expects.
+    let status = unsafe {
+        crypto_box_seal_open(
+            message.as_mut_ptr(),
+            sealed.as_ptr(),
+            sealed.len() as u64,
+            recipient.public_key.bytes.as_ptr(),
+            recipient.bytes.as_ptr(),
+        )
+    };
+
+    if status == 0 {
+        Ok(message)
+    } else {
+        Err(OpenError)
+    }
+}
+
+impl PublicKey
+{
+    /// Create a public key from its bytes.
+    pub fn from_bytes(bytes: [u8; 32]) -> Self
+    {
+        Self{bytes}
+    }
+
+    /// The bytes that make up the public key.
+    pub fn as_bytes(&self) -> &[u8; 32]
+    {
+        &self.bytes
+    }
+}
+
+impl SecretKey
+{
+    /// Create a secret key from its bytes,
+    /// as previously returned by [`SecretKey::as_bytes`].
+    ///
+    /// The public key is derived from the secret key.
+    pub fn from_bytes(bytes: [u8; 32]) -> Self
+    {
+        let mut pk = [0; 32];
+
+        // SAFETY: The buffers have the sizes libsodium expects.
+        unsafe {
+            if sodium_init() == -1 {
+                panic!("sodium_init failed");
+            }
+            crypto_scalarmult_base(pk.as_mut_ptr(), bytes.as_ptr());
+        }
+
+        Self{public_key: PublicKey{bytes: pk}, bytes}
+    }
+
+    /// The bytes that make up the secret key,
+    /// for storing the secret key somewhere safe.
+    pub fn as_bytes(&self) -> &[u8; 32]
+    {
+        &self.bytes
+    }
+
+    /// The public key that seals boxes for this secret key.
+    pub fn public_key(&self) -> PublicKey
+    {
+        self.public_key
+    }
+}
+
+impl Drop for SecretKey
+{
+    fn drop(&mut self)
+    {
+        // SAFETY: The buffer has the given size.
+        // sodium_memzero is not optimized away, unlike a plain write.
+        unsafe {
+            sodium_memzero(self.bytes.as_mut_ptr() as *mut c_void,
+                           self.bytes.len());
+        }
+    }
+}
+
+impl fmt::Debug for SecretKey
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        f.debug_struct("SecretKey")
+            .field("public_key", &self.public_key)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_seal_open()
+    {
+        let (pk1, sk1) = keypair();
+        let (_,   sk2) = keypair();
+
+        let sealed = seal(&pk1, b"Hello, world!");
+        assert_eq!(sealed.len(), 13 + SEALBYTES);
+        assert_eq!(open(&sk1, &sealed).ok(), Some(b"Hello, world!".to_vec()));
+        assert!(open(&sk2, &sealed).is_err());
+
+        let mut tampered = sealed;
+        tampered[0] ^= 1;
+        assert!(open(&sk1, &tampered).is_err());
+        assert!(open(&sk1, &[]).is_err());
+    }
+
+    #[test]
+    fn test_from_bytes()
+    {
+        let (pk1, sk1) = keypair();
+        let pk2 = PublicKey::from_bytes(*pk1.as_bytes());
+        let sk2 = SecretKey::from_bytes(*sk1.as_bytes());
+
+        let sealed = seal(&pk2, b"Hello, world!");
+        assert_eq!(pk2, pk1);
+        assert_eq!(sk2.public_key(), pk1);
+        assert_eq!(open(&sk2, &sealed).ok(), Some(b"Hello, world!".to_vec()));
+    }
+}
@@ -0,0 +1,298 @@
+//! Implementation of encrypted streams based on libsodium.
+//!
+//! A stream is encrypted as a sequence of chunks with a secret key.
+//! Each chunk is authenticated, and the chunks cannot be
+//! reordered, duplicated, or dropped without detection.
+//! The last chunk is marked as final,
+//! so that truncation of the stream is detected as well.
+//!
+//! This makes encrypted streams suitable for storing
+//! object contents on disks that should not be able to read them.
+
+#![doc(html_favicon_url = "../../../marketing/logo.svg")]
+#![doc(html_logo_url = "../../../marketing/logo.svg")]
+
+use std::fmt;
+use std::os::raw::c_int;
+use std::os::raw::c_uchar;
+use std::os::raw::c_ulonglong;
+use std::ptr::null;
+use std::ptr::null_mut;
+
+/// Number of bytes of the header that starts a stream.
+pub const HEADERBYTES: usize = 24;
+
+/// Number of bytes an encrypted chunk is larger than its plaintext.
+pub const ABYTES: usize = 17;
+
+const TAG_MESSAGE: c_uchar = 0;
+const TAG_FINAL:   c_uchar = 3;
+
+/// Mirrors `crypto_secretstream_xchacha20poly1305_state`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct State
+{
+    k:     [c_uchar; 32],
+    nonce: [c_uchar; 12],
+    pad:   [c_uchar; 8],
+}
+
+#[link(name = "sodium")]
+extern "C"
+{
+    fn sodium_init() -> c_int;
+
+    fn crypto_secretstream_xchacha20poly1305_keygen(k: *mut c_uchar);
+
+    fn crypto_secretstream_xchacha20poly1305_init_push(
+        state:  *mut State,
+        header: *mut c_uchar,
+        k:      *const c_uchar,
+    ) -> c_int;
+
+    fn crypto_secretstream_xchacha20poly1305_push(
+        state:  *mut State,
+        c:      *mut c_uchar,
+        clen_p: *mut c_ulonglong,
+        m:      *const c_uchar,
+        mlen:   c_ulonglong,
+        ad:     *const c_uchar,
+        adlen:  c_ulonglong,
+        tag:    c_uchar,
+    ) -> c_int;
+
+    fn crypto_secretstream_xchacha20poly1305_init_pull(
+        state:  *mut State,
+        header: *const c_uchar,
+        k:      *const c_uchar,
+    ) -> c_int;
+
+    fn crypto_secretstream_xchacha20poly1305_pull(
+        state:  *mut State,
+        m:      *mut c_uchar,
+        mlen_p: *mut c_ulonglong,
+        tag_p:  *mut c_uchar,
+        c:      *const c_uchar,
+        clen:   c_ulonglong,
+        ad:     *const c_uchar,
+        adlen:  c_ulonglong,
+    ) -> c_int;
+}
+
+/// Secret key used to encrypt and decrypt streams.
+///
+/// The [`Debug`][`fmt::Debug`] impl does not print the key.
+#[derive(Clone)]
+pub struct Key
+{
+    /// The bytes that make up the key.
+    pub bytes: [u8; 32],
+}
+
+/// Encrypts the chunks of a stream.
+pub struct Encryptor
+{
+    state: State,
+    finished: bool,
+}
+
+/// Decrypts the chunks of a stream.
+pub struct Decryptor
+{
+    state: State,
+    finished: bool,
+}
+
+/// Returned when a chunk could not be decrypted.
+///
+/// This happens when the chunk was encrypted with a different key,
+/// with different associated data, or when the stream was tampered with.
+#[derive(Clone, Copy, Debug)]
+pub struct DecryptError;
+
+impl Key
+{
+    /// Generate a new random key.
+    pub fn generate() -> Self
+    {
+        let mut bytes = [0; 32];
+
+        // SAFETY: The buffer has the size libsodium expects.
+        // sodium_init must be called before using the random number generator;
+        // it is safe to call multiple times and from multiple threads.
+        unsafe {
+            if sodium_init() == -1 {
+                panic!("sodium_init failed");
+            }
+            crypto_secretstream_xchacha20poly1305_keygen(bytes.as_mut_ptr());
+        }
+
+        Self{bytes}
+    }
+}
+
+impl fmt::Debug for Key
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        f.debug_struct("Key").finish_non_exhaustive()
+    }
+}
+
+impl Encryptor
+{
+    /// Start encrypting a new stream.
+    ///
+    /// Returns the encryptor together with the header of the stream,
+    /// which must be stored before the encrypted chunks.
+    pub fn new(key: &Key) -> (Self, [u8; HEADERBYTES])
+    {
+        let mut state = State{k: [0; 32], nonce: [0; 12], pad: [0; 8]};
+        let mut header = [0; HEADERBYTES];
+
+        // SAFETY: The buffers have the sizes libsodium expects.
+        // init_push uses the random number generator,
+        // so sodium_init must be called first.
+        unsafe {
+            if sodium_init() == -1 {
+                panic!("sodium_init failed");
+            }
+            crypto_secretstream_xchacha20poly1305_init_push(
+                &mut state,
+                header.as_mut_ptr(),
+                key.bytes.as_ptr(),
+            );
+        }
+
+        (Self{state, finished: false}, header)
+    }
+
+    /// Encrypt the next chunk of the stream.
+    ///
+    /// The returned chunk is [`ABYTES`] bytes larger than the plaintext.
+    /// The associated data is authenticated but not stored;
+    /// the same associated data must be passed when decrypting.
+    /// The last chunk must be marked as final,
+    /// after which no more chunks may be encrypted.
+    pub fn push(&mut self, plaintext: &[u8], ad: &[u8], last: bool)
+        -> Vec<u8>
+    {
+        assert!(!self.finished, "Encryptor::push called after final chunk");
+        self.finished = last;
+
+        let mut chunk = vec![0; plaintext.len() + ABYTES];
+        let tag = if last { TAG_FINAL } else { TAG_MESSAGE };
+
+        // SAFETY: The buffers have the sizes libsodium expects.
+        unsafe {
+            crypto_secretstream_xchacha20poly1305_push(
+                &mut self.state,
+                chunk.as_mut_ptr(),
+                null_mut(),
+                plaintext.as_ptr(),
+                plaintext.len() as u64,
+                if ad.is_empty() { null() } else { ad.as_ptr() },
+                ad.len() as u64,
+                tag,
+            );
+        }
+
+        chunk
+    }
+}
+
+impl Decryptor
+{
+    /// Start decrypting a stream with the given header.
+    pub fn new(key: &Key, header: &[u8; HEADERBYTES])
+        -> Result<Self, DecryptError>
+    {
+        let mut state = State{k: [0; 32], nonce: [0; 12], pad: [0; 8]};
+
+        // SAFETY: The buffers have the sizes libsodium expects.
+        let status = unsafe {
+            crypto_secretstream_xchacha20poly1305_init_pull(
+                &mut state,
+                header.as_ptr(),
+                key.bytes.as_ptr(),
+            )
+        };
+
+        if status == 0 {
+            Ok(Self{state, finished: false})
+        } else {
+            Err(DecryptError)
+        }
+    }
+
+    /// Decrypt the next chunk of the stream.
+    ///
+    /// Returns the plaintext and whether the chunk was marked as final.
+    /// Chunks that follow the final chunk are rejected.
+    pub fn pull(&mut self, chunk: &[u8], ad: &[u8])
+        -> Result<(Vec<u8>, bool), DecryptError>
+    {
+        if self.finished || chunk.len() < ABYTES {
+            return Err(DecryptError);
+        }
+
+        let mut plaintext = vec![0; chunk.len() - ABYTES];
+        let mut tag = 0;
+
+        // SAFETY: The buffers have the sizes libsodium expects.
+        let status = unsafe {
+            crypto_secretstream_xchacha20poly1305_pull(
+                &mut self.state,
+                plaintext.as_mut_ptr(),
+                null_mut(),
+                &mut tag,
+                chunk.as_ptr(),
+                chunk.len() as u64,
+                if ad.is_empty() { null() } else { ad.as_ptr() },
+                ad.len() as u64,
+            )
+        };
+
+        if status != 0 {
+            return Err(DecryptError);
+        }
+
+        self.finished = tag == TAG_FINAL;
+        Ok((plaintext, self.finished))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_push_pull()
+    {
+        let key1 = Key::generate();
+        let key2 = Key::generate();
+
+        let (mut encryptor, header) = Encryptor::new(&key1);
+        let chunk1 = encryptor.push(b"Hello, ", b"", false);
+        let chunk2 = encryptor.push(b"world!", b"ad", true);
+        assert_eq!(chunk1.len(), 7 + ABYTES);
+
+        let mut decryptor = Decryptor::new(&key1, &header).unwrap();
+        let (plaintext1, last1) = decryptor.pull(&chunk1, b"").unwrap();
+        let (plaintext2, last2) = decryptor.pull(&chunk2, b"ad").unwrap();
+        assert_eq!((&plaintext1[..], last1), (&b"Hello, "[..], false));
+        assert_eq!((&plaintext2[..], last2), (&b"world!"[..], true));
+        assert!(decryptor.pull(&chunk2, b"ad").is_err());
+
+        // Wrong key, reordered chunks, and wrong associated data.
+        let mut decryptor = Decryptor::new(&key2, &header).unwrap();
+        assert!(decryptor.pull(&chunk1, b"").is_err());
+        let mut decryptor = Decryptor::new(&key1, &header).unwrap();
+        assert!(decryptor.pull(&chunk2, b"ad").is_err());
+        let mut decryptor = Decryptor::new(&key1, &header).unwrap();
+        decryptor.pull(&chunk1, b"").unwrap();
+        assert!(decryptor.pull(&chunk2, b"xx").is_err());
+    }
+}
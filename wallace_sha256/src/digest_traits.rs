@@ -0,0 +1,81 @@
+use crate::Sha256;
+use digest::FixedOutput;
+use digest::FixedOutputReset;
+use digest::HashMarker;
+use digest::Output;
+use digest::OutputSizeUser;
+use digest::Reset;
+use digest::Update;
+use digest::core_api::BlockSizeUser;
+use digest::typenum::U32;
+use digest::typenum::U64;
+
+// With these impls, Sha256 implements digest::Digest,
+// and can be used with crates such as hmac.
+
+impl HashMarker for Sha256
+{
+}
+
+impl OutputSizeUser for Sha256
+{
+    type OutputSize = U32;
+}
+
+impl BlockSizeUser for Sha256
+{
+    type BlockSize = U64;
+}
+
+impl Update for Sha256
+{
+    fn update(&mut self, data: &[u8])
+    {
+        Sha256::update(self, data);
+    }
+}
+
+impl FixedOutput for Sha256
+{
+    fn finalize_into(self, out: &mut Output<Self>)
+    {
+        out.copy_from_slice(&Sha256::finalize(self));
+    }
+}
+
+impl Reset for Sha256
+{
+    fn reset(&mut self)
+    {
+        Sha256::reset(self);
+    }
+}
+
+impl FixedOutputReset for Sha256
+{
+    fn finalize_into_reset(&mut self, out: &mut Output<Self>)
+    {
+        out.copy_from_slice(&Sha256::finalize_reset(self));
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_digest()
+    {
+        let mut digest = <Sha256 as digest::Digest>::new();
+        digest::Digest::update(&mut digest, b"Hello, ");
+        digest::Digest::update(&mut digest, b"world!");
+        let actual = digest::Digest::finalize_reset(&mut digest);
+
+        let mut sha256 = Sha256::new();
+        sha256.update(b"Hello, world!");
+        assert_eq!(actual[..], sha256.finalize());
+        assert_eq!(digest::Digest::finalize(digest)[..],
+                   Sha256::new().finalize());
+    }
+}
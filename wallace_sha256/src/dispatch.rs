@@ -0,0 +1,71 @@
+use crate::STATE_LEN;
+use crate::sodium::crypto_hash_sha256_state;
+
+#[cfg(target_arch = "x86_64")]
+use crate::portable::PortableState;
+
+/// SHA-256 state, computed with whichever backend is fastest.
+///
+/// On x86-64 CPUs with the SHA extensions,
+/// the portable implementation uses those instructions,
+/// and is faster than libsodium, which does not use them.
+/// Otherwise, libsodium is used.
+/// The CPU is inspected when the state is created.
+#[derive(Clone)]
+pub (crate) enum State
+{
+    Sodium(crypto_hash_sha256_state),
+
+    #[cfg(target_arch = "x86_64")]
+    Accelerated(PortableState),
+}
+
+impl State
+{
+    pub (crate) fn new() -> Self
+    {
+        #[cfg(target_arch = "x86_64")]
+        if crate::shani::available() {
+            return Self::Accelerated(PortableState::new());
+        }
+
+        Self::Sodium(crypto_hash_sha256_state::new())
+    }
+
+    pub (crate) fn update(&mut self, buf: &[u8])
+    {
+        match self {
+            Self::Sodium(state) => state.update(buf),
+            #[cfg(target_arch = "x86_64")]
+            Self::Accelerated(state) => state.update(buf),
+        }
+    }
+
+    pub (crate) fn export(&self) -> [u8; STATE_LEN]
+    {
+        match self {
+            Self::Sodium(state) => state.export(),
+            #[cfg(target_arch = "x86_64")]
+            Self::Accelerated(state) => state.export(),
+        }
+    }
+
+    pub (crate) fn import(bytes: &[u8; STATE_LEN]) -> Self
+    {
+        #[cfg(target_arch = "x86_64")]
+        if crate::shani::available() {
+            return Self::Accelerated(PortableState::import(bytes));
+        }
+
+        Self::Sodium(crypto_hash_sha256_state::import(bytes))
+    }
+
+    pub (crate) fn finalize(self) -> [u8; 32]
+    {
+        match self {
+            Self::Sodium(state) => state.finalize(),
+            #[cfg(target_arch = "x86_64")]
+            Self::Accelerated(state) => state.finalize(),
+        }
+    }
+}
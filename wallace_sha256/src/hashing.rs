@@ -0,0 +1,160 @@
+use crate::Sha256;
+use std::io::Read;
+use std::io::Result;
+use std::io::Write;
+
+/// Reader that hashes the bytes read through it.
+///
+/// Only the bytes actually returned by the inner reader are hashed,
+/// so the hash covers exactly what the caller has read.
+pub struct HashingReader<R>
+{
+    inner: R,
+    sha256: Sha256,
+}
+
+impl<R> HashingReader<R>
+{
+    /// Wrap a reader, starting with an empty digest.
+    pub fn new(inner: R) -> Self
+    {
+        Self{inner, sha256: Sha256::new()}
+    }
+
+    /// The inner reader.
+    ///
+    /// Bytes read from it directly are not hashed.
+    pub fn get_ref(&self) -> &R
+    {
+        &self.inner
+    }
+
+    /// The inner reader.
+    ///
+    /// Bytes read from it directly are not hashed.
+    pub fn get_mut(&mut self) -> &mut R
+    {
+        &mut self.inner
+    }
+
+    /// Return the inner reader and the hash of the bytes read so far.
+    pub fn into_inner_and_hash(self) -> (R, [u8; 32])
+    {
+        (self.inner, self.sha256.finalize())
+    }
+}
+
+impl<R> Read for HashingReader<R>
+    where R: Read
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>
+    {
+        let n = self.inner.read(buf)?;
+        self.sha256.update(&buf[.. n]);
+        Ok(n)
+    }
+}
+
+/// Writer that hashes the bytes written through it.
+///
+/// Only the bytes actually accepted by the inner writer are hashed,
+/// so the hash covers exactly what was written.
+pub struct HashingWriter<W>
+{
+    inner: W,
+    sha256: Sha256,
+}
+
+impl<W> HashingWriter<W>
+{
+    /// Wrap a writer, starting with an empty digest.
+    pub fn new(inner: W) -> Self
+    {
+        Self{inner, sha256: Sha256::new()}
+    }
+
+    /// The inner writer.
+    ///
+    /// Bytes written to it directly are not hashed.
+    pub fn get_ref(&self) -> &W
+    {
+        &self.inner
+    }
+
+    /// The inner writer.
+    ///
+    /// Bytes written to it directly are not hashed.
+    pub fn get_mut(&mut self) -> &mut W
+    {
+        &mut self.inner
+    }
+
+    /// Return the inner writer and the hash of the bytes written so far.
+    ///
+    /// The inner writer is not flushed.
+    pub fn into_inner_and_hash(self) -> (W, [u8; 32])
+    {
+        (self.inner, self.sha256.finalize())
+    }
+}
+
+impl<W> Write for HashingWriter<W>
+    where W: Write
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize>
+    {
+        let n = self.inner.write(buf)?;
+        self.sha256.update(&buf[.. n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<()>
+    {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::io::copy;
+    use super::*;
+
+    /// Writer that accepts at most three bytes at a time.
+    struct Slow(Vec<u8>);
+
+    impl Write for Slow
+    {
+        fn write(&mut self, buf: &[u8]) -> Result<usize>
+        {
+            let n = buf.len().min(3);
+            self.0.extend_from_slice(&buf[.. n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> Result<()>
+        {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_hashing_reader_writer()
+    {
+        let input = b"Hello, world!";
+        let mut expected = Sha256::new();
+        expected.update(input);
+        let expected = expected.finalize();
+
+        let mut reader = HashingReader::new(&input[..]);
+        let mut writer = HashingWriter::new(Slow(Vec::new()));
+        copy(&mut reader, &mut writer).unwrap();
+        let (rest, read_hash) = reader.into_inner_and_hash();
+        let (output, written_hash) = writer.into_inner_and_hash();
+
+        assert!(rest.is_empty());
+        assert_eq!(output.0, input);
+        assert_eq!(read_hash, expected);
+        assert_eq!(written_hash, expected);
+    }
+}
@@ -0,0 +1,141 @@
+use crate::Sha256;
+use core::hint::black_box;
+
+#[cfg(feature = "std")]
+use std::io::Result;
+
+#[cfg(feature = "std")]
+use std::io::Write;
+
+/// HMAC-SHA256 message authentication code with a multi-part interface,
+/// as specified in RFC 2104.
+///
+/// This is the same construction as libsodium's
+/// `crypto_auth_hmacsha256`, built on [`Sha256`],
+/// so it uses libsodium whenever [`Sha256`] does.
+/// Keys longer than the block size of 64 bytes are hashed first.
+///
+/// The [`Write`] impl calls [`HmacSha256::update`] on writes.
+/// It never returns an error.
+#[derive(Clone)]
+pub struct HmacSha256
+{
+    inner: Sha256,
+    outer: Sha256,
+}
+
+impl HmacSha256
+{
+    /// Create a new, empty MAC with the given key.
+    pub fn new(key: &[u8]) -> Self
+    {
+        let mut block = [0; 64];
+        if key.len() > block.len() {
+            let mut sha256 = Sha256::new();
+            sha256.update(key);
+            block[.. 32].copy_from_slice(&sha256.finalize());
+        } else {
+            block[.. key.len()].copy_from_slice(key);
+        }
+
+        let mut pad = [0; 64];
+        let mut inner = Sha256::new();
+        let mut outer = Sha256::new();
+        for (p, b) in pad.iter_mut().zip(&block) { *p = b ^ 0x36; }
+        inner.update(&pad);
+        for (p, b) in pad.iter_mut().zip(&block) { *p = b ^ 0x5c; }
+        outer.update(&pad);
+
+        Self{inner, outer}
+    }
+
+    /// Update the MAC using a buffer.
+    pub fn update(&mut self, buf: &[u8])
+    {
+        self.inner.update(buf);
+    }
+
+    /// Finalize the MAC, returning the tag.
+    pub fn finalize(self) -> [u8; 32]
+    {
+        let Self{inner, mut outer} = self;
+        outer.update(&inner.finalize());
+        outer.finalize()
+    }
+
+    /// Finalize the MAC, and check that it matches the given tag.
+    ///
+    /// The comparison takes the same time
+    /// regardless of where the tags differ,
+    /// so that it does not reveal the expected tag to an attacker.
+    pub fn verify(self, tag: &[u8; 32]) -> bool
+    {
+        ct_eq(&self.finalize(), tag)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Write for HmacSha256
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize>
+    {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()>
+    {
+        Ok(())
+    }
+}
+
+/// Compare two byte arrays in constant time.
+pub fn ct_eq(a: &[u8; 32], b: &[u8; 32]) -> bool
+{
+    let difference = a.iter().zip(b)
+        .fold(0, |acc, (x, y)| acc | black_box(x ^ y));
+    difference == 0
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// Decode a hexadecimal string.
+    fn hex(s: &str) -> Vec<u8>
+    {
+        (0 .. s.len()).step_by(2)
+            .map(|i| u8::from_str_radix(&s[i .. i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_rfc4231()
+    {
+        let table: &[(&[u8], &[u8], &str)] = &[
+            (&[0x0b; 20], b"Hi There",
+             "b0344c61d8db38535ca8afceaf0bf12b\
+              881dc200c9833da726e9376c2e32cff7"),
+            (b"Jefe", b"what do ya want for nothing?",
+             "5bdcc146bf60754e6a042426089575c7\
+              5a003f089d2739839dec58b964ec3843"),
+            (&[0xaa; 131],
+             b"Test Using Larger Than Block-Size Key - Hash Key First",
+             "60e431591ee0b67f0d8a26aacbf5b77f\
+              8e0bc6213728c5140546040f0ee37f54"),
+        ];
+        for &(key, data, expected) in table {
+            let mut hmac = HmacSha256::new(key);
+            hmac.update(data);
+            let mut tag = [0; 32];
+            tag.copy_from_slice(&hex(expected));
+            assert!(hmac.clone().verify(&tag));
+            assert_eq!(hmac.finalize(), tag);
+            tag[31] ^= 1;
+            let mut hmac = HmacSha256::new(key);
+            hmac.update(data);
+            assert!(!hmac.verify(&tag));
+        }
+    }
+}
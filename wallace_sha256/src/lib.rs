@@ -1,42 +1,75 @@
-//! Implementation of SHA-256 based on libsodium.
+//! Implementation of SHA-256.
+//!
+//! With the `libsodium` feature, which is enabled by default,
+//! the hash is computed by libsodium.
+//! Without it, a portable implementation written in Rust is used,
+//! which does not require linking with any C library.
+//! On x86-64 CPUs with the SHA extensions, detected at runtime,
+//! the portable implementation uses those instructions,
+//! and it is then used even with the `libsodium` feature,
+//! as it is considerably faster.
+//!
+//! The state of a digest can be exported and imported again,
+//! so that hashing a long stream can resume from a checkpoint.
+//! See [`Sha256::export_state`] for the format.
+//!
+//! [`HashingReader`] and [`HashingWriter`] hash the bytes
+//! that pass through them, for computing a hash while copying.
+//!
+//! [`HmacSha256`] computes message authentication codes
+//! on top of [`Sha256`], with either implementation.
+//!
+//! With the `digest` feature, [`Sha256`] implements
+//! the traits of the `digest` crate, including `Digest`,
+//! so that it can be used with crates built on them, such as `hmac`.
+//!
+//! Without the `std` feature, which is enabled by default,
+//! the crate is `no_std` and does not allocate,
+//! so that embedded agents compute the same hashes as volumes.
+//! The [`Write`][`std::io::Write`] impls and the hashing adapters
+//! are then unavailable, and on x86-64 the SHA extensions
+//! are used only if they are enabled at compile time.
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![doc(html_favicon_url = "../../../marketing/logo.svg")]
 #![doc(html_logo_url = "../../../marketing/logo.svg")]
 
+#[cfg(feature = "std")]
+pub use self::hashing::*;
+
+pub use self::hmac::*;
+
+#[cfg(feature = "std")]
 use std::io::Result;
+
+#[cfg(feature = "std")]
 use std::io::Write;
-use std::mem::MaybeUninit;
-use std::os::raw::c_int;
-use std::os::raw::c_uchar;
-use std::os::raw::c_ulonglong;
 
-#[repr(C)]
-#[derive(Clone)]
-struct crypto_hash_sha256_state
-{
-    state: [u32; 8],
-    count: u64,
-    buf:   [u8; 64],
-}
+#[cfg(feature = "libsodium")]
+use self::dispatch::State;
 
-#[link(name = "sodium")]
-extern "C"
-{
-    fn crypto_hash_sha256_init(
-        state: *mut crypto_hash_sha256_state,
-    ) -> c_int;
-
-    fn crypto_hash_sha256_update(
-        state: *mut crypto_hash_sha256_state,
-        r#in:  *const c_uchar,
-        inlen: c_ulonglong,
-    ) -> c_int;
-
-    fn crypto_hash_sha256_final(
-        state: *mut crypto_hash_sha256_state,
-        out:   *mut c_uchar,
-    ) -> c_int;
-}
+#[cfg(not(feature = "libsodium"))]
+use self::portable::PortableState as State;
+
+#[cfg(feature = "digest")]
+mod digest_traits;
+
+#[cfg(feature = "libsodium")]
+mod dispatch;
+
+#[cfg(feature = "std")]
+mod hashing;
+
+mod hmac;
+
+#[cfg(any(not(feature = "libsodium"), target_arch = "x86_64", test))]
+mod portable;
+
+#[cfg(target_arch = "x86_64")]
+mod shani;
+
+#[cfg(feature = "libsodium")]
+mod sodium;
 
 /// SHA-256 digest with a multi-part interface.
 ///
@@ -46,7 +79,7 @@ extern "C"
 #[derive(Clone)]
 pub struct Sha256
 {
-    inner: crypto_hash_sha256_state,
+    inner: State,
 }
 
 impl Sha256
@@ -54,39 +87,112 @@ impl Sha256
     /// Create a new, empty digest.
     pub fn new() -> Self
     {
-        unsafe {
-            let mut inner = MaybeUninit::uninit();
-            crypto_hash_sha256_init(inner.as_mut_ptr());
-            Self{inner: inner.assume_init()}
-        }
+        Self{inner: State::new()}
     }
 
     /// Update the digest using a buffer.
     pub fn update(&mut self, buf: &[u8])
     {
-        unsafe {
-            crypto_hash_sha256_update(
-                &mut self.inner,
-                buf.as_ptr(),
-                buf.len() as u64,
-            );
-        }
+        self.inner.update(buf);
     }
 
     /// Finalize the digest, returning the hash.
-    pub fn finalize(mut self) -> [u8; 32]
-    {
-        unsafe {
-            let mut buf = MaybeUninit::uninit();
-            crypto_hash_sha256_final(
-                &mut self.inner,
-                buf.as_mut_ptr() as *mut u8,
-            );
-            buf.assume_init()
-        }
+    pub fn finalize(self) -> [u8; 32]
+    {
+        self.inner.finalize()
+    }
+
+    /// Finalize the digest, writing the hash to the given buffer.
+    pub fn finalize_into(self, out: &mut [u8; 32])
+    {
+        *out = self.inner.finalize();
+    }
+
+    /// Finalize the digest, returning the hash,
+    /// and reset it so that it can be used for another message.
+    ///
+    /// This lets loops that hash many small messages
+    /// reuse one digest instead of creating one for each message.
+    pub fn finalize_reset(&mut self) -> [u8; 32]
+    {
+        core::mem::take(self).finalize()
+    }
+
+    /// Reset the digest to its empty state.
+    pub fn reset(&mut self)
+    {
+        *self = Self::new();
+    }
+
+    /// Export the state of the digest,
+    /// so that hashing can later resume with [`Sha256::import_state`].
+    ///
+    /// The format is stable, and the same for either implementation:
+    /// the eight words of the intermediate hash value,
+    /// followed by the number of bits hashed so far,
+    /// all big-endian, followed by the partial block of 64 bytes.
+    /// The bytes of the partial block past its length are zero.
+    ///
+    /// The state reveals the length of the input and its last partial block,
+    /// so it must be stored as carefully as the input itself.
+    pub fn export_state(&self) -> [u8; STATE_LEN]
+    {
+        self.inner.export()
+    }
+
+    /// Resume a digest from a state exported with [`Sha256::export_state`].
+    ///
+    /// Every sequence of bytes is a valid state,
+    /// so a corrupt state is not detected;
+    /// it merely results in a wrong hash.
+    /// Store a checksum alongside the state if this matters.
+    pub fn import_state(state: &[u8; STATE_LEN]) -> Self
+    {
+        Self{inner: State::import(state)}
+    }
+}
+
+/// Size of the state exported by [`Sha256::export_state`], in bytes.
+pub const STATE_LEN: usize = 104;
+
+/// Encode a state in the format of [`Sha256::export_state`].
+pub (crate) fn encode_state(state: &[u32; 8], count: u64, buf: &[u8; 64])
+    -> [u8; STATE_LEN]
+{
+    let mut bytes = [0; STATE_LEN];
+    for (chunk, word) in bytes[.. 32].chunks_exact_mut(4).zip(state) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    bytes[32 .. 40].copy_from_slice(&count.to_be_bytes());
+    let filled = (count / 8 % 64) as usize;
+    bytes[40 .. 40 + filled].copy_from_slice(&buf[.. filled]);
+    bytes
+}
+
+/// Decode a state in the format of [`Sha256::export_state`].
+pub (crate) fn decode_state(bytes: &[u8; STATE_LEN])
+    -> ([u32; 8], u64, [u8; 64])
+{
+    let mut state = [0; 8];
+    for (word, chunk) in state.iter_mut().zip(bytes[.. 32].chunks_exact(4)) {
+        *word = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    let mut count = [0; 8];
+    count.copy_from_slice(&bytes[32 .. 40]);
+    let mut buf = [0; 64];
+    buf.copy_from_slice(&bytes[40 ..]);
+    (state, u64::from_be_bytes(count), buf)
+}
+
+impl Default for Sha256
+{
+    fn default() -> Self
+    {
+        Self::new()
     }
 }
 
+#[cfg(feature = "std")]
 impl Write for Sha256
 {
     fn write(&mut self, buf: &[u8]) -> Result<usize>
@@ -128,4 +234,122 @@ mod tests
             assert_eq!(actual, expected);
         }
     }
+
+    #[test]
+    fn test_long_message()
+    {
+        // From FIPS 180-4 example B.3: one million times the letter a.
+        let mut sha256 = Sha256::new();
+        for _ in 0 .. 1000 {
+            sha256.update(&[b'a'; 1000]);
+        }
+        let expected =
+            [0xcd, 0xc7, 0x6e, 0x5c, 0x99, 0x14, 0xfb, 0x92,
+             0x81, 0xa1, 0xc7, 0xe2, 0x84, 0xd7, 0x3e, 0x67,
+             0xf1, 0x80, 0x9a, 0x48, 0xa4, 0x97, 0x20, 0x0e,
+             0x04, 0x6d, 0x39, 0xcc, 0xc7, 0x11, 0x2c, 0xd0];
+        assert_eq!(sha256.finalize(), expected);
+    }
+
+    #[test]
+    fn test_finalize_reset()
+    {
+        let mut sha256 = Sha256::new();
+        sha256.update(b"Hello, world!");
+        let hash1 = sha256.finalize_reset();
+        sha256.update(b"Hello, world!");
+        let hash2 = sha256.finalize_reset();
+        let mut hash3 = [0; 32];
+        sha256.finalize_into(&mut hash3);
+
+        let mut sha256 = Sha256::new();
+        sha256.update(b"discarded");
+        sha256.reset();
+        let hash4 = sha256.finalize();
+
+        assert_eq!(hash1[.. 4], [0x31, 0x5f, 0x5b, 0xdb]);
+        assert_eq!(hash2, hash1);
+        assert_eq!(hash3, Sha256::new().finalize());
+        assert_eq!(hash4, hash3);
+    }
+
+    #[test]
+    fn test_export_import_state()
+    {
+        let input: Vec<u8> = (0 .. 300u32).map(|i| (i * 7) as u8).collect();
+        let mut whole = Sha256::new();
+        whole.update(&input);
+        let expected = whole.finalize();
+
+        for split in &[0, 1, 63, 64, 65, 299, 300] {
+            let mut first = Sha256::new();
+            first.update(&input[.. *split]);
+            let state = first.export_state();
+            let mut second = Sha256::import_state(&state);
+            assert_eq!(second.export_state()[..], state[..]);
+            second.update(&input[*split ..]);
+            assert_eq!(second.finalize(), expected);
+        }
+    }
+
+    #[cfg(feature = "libsodium")]
+    #[test]
+    fn test_portable_state_matches_libsodium()
+    {
+        use crate::portable::PortableState;
+        use crate::sodium::crypto_hash_sha256_state;
+
+        let mut sodium = crypto_hash_sha256_state::new();
+        sodium.update(&[b'a'; 100]);
+        let portable = PortableState::import(&sodium.export());
+        assert_eq!(portable.export()[..], sodium.export()[..]);
+        assert_eq!(portable.finalize(), sodium.finalize());
+    }
+
+    #[cfg(feature = "libsodium")]
+    #[test]
+    fn test_portable_matches_libsodium()
+    {
+        use crate::portable::PortableState;
+        use crate::sodium::crypto_hash_sha256_state;
+
+        // Cover every padding case, and updates that straddle blocks.
+        let input: Vec<u8> = (0 .. 300u32).map(|i| (i * 7) as u8).collect();
+        for len in 0 .. input.len() {
+            for split in &[0, 1, 63, 64, 65] {
+                let split = (*split).min(len);
+                let mut sodium = crypto_hash_sha256_state::new();
+                let mut portable = PortableState::new();
+                for part in &[&input[.. split], &input[split .. len]] {
+                    sodium.update(part);
+                    portable.update(part);
+                }
+                assert_eq!(portable.finalize(), sodium.finalize());
+            }
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_shani_matches_soft()
+    {
+        use crate::portable::compress_soft;
+
+        if !crate::shani::available() {
+            return;
+        }
+
+        let mut block = [0; 64];
+        let mut soft = [0x6a09e667; 8];
+        let mut shani = soft;
+        for i in 0 .. 100u32 {
+            for (j, byte) in block.iter_mut().enumerate() {
+                *byte = (i * 31 + j as u32 * 7) as u8;
+            }
+            compress_soft(&mut soft, &block);
+            // SAFETY: The CPU supports the instructions.
+            unsafe { crate::shani::compress(&mut shani, &block) };
+            assert_eq!(shani, soft);
+        }
+    }
 }
@@ -0,0 +1,168 @@
+/// Round constants, from FIPS 180-4 section 4.2.2.
+pub (crate) const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5,
+    0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3,
+    0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc,
+    0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+    0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13,
+    0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3,
+    0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5,
+    0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208,
+    0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Initial hash value, from FIPS 180-4 section 5.3.3.
+const H0: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+    0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// SHA-256 state, laid out like that of libsodium.
+///
+/// On x86-64 CPUs with the SHA extensions,
+/// blocks are compressed with those instructions.
+#[derive(Clone)]
+pub (crate) struct PortableState
+{
+    state: [u32; 8],
+
+    /// The number of bits hashed so far.
+    count: u64,
+
+    /// The partial block, of `count / 8 % 64` bytes.
+    buf: [u8; 64],
+}
+
+impl PortableState
+{
+    pub (crate) fn new() -> Self
+    {
+        Self{state: H0, count: 0, buf: [0; 64]}
+    }
+
+    pub (crate) fn update(&mut self, mut buf: &[u8])
+    {
+        let mut filled = (self.count / 8 % 64) as usize;
+        self.count = self.count.wrapping_add((buf.len() as u64) << 3);
+
+        // Complete the partial block first.
+        if filled != 0 {
+            let n = buf.len().min(64 - filled);
+            self.buf[filled .. filled + n].copy_from_slice(&buf[.. n]);
+            buf = &buf[n ..];
+            filled += n;
+            if filled < 64 {
+                return;
+            }
+            let block = self.buf;
+            compress(&mut self.state, &block);
+        }
+
+        let mut blocks = buf.chunks_exact(64);
+        for block in &mut blocks {
+            let mut array = [0; 64];
+            array.copy_from_slice(block);
+            compress(&mut self.state, &array);
+        }
+
+        let rest = blocks.remainder();
+        self.buf[.. rest.len()].copy_from_slice(rest);
+    }
+
+    pub (crate) fn export(&self) -> [u8; crate::STATE_LEN]
+    {
+        crate::encode_state(&self.state, self.count, &self.buf)
+    }
+
+    pub (crate) fn import(bytes: &[u8; crate::STATE_LEN]) -> Self
+    {
+        let (state, count, buf) = crate::decode_state(bytes);
+        Self{state, count, buf}
+    }
+
+    pub (crate) fn finalize(mut self) -> [u8; 32]
+    {
+        let count = self.count;
+        let filled = (count / 8 % 64) as usize;
+        let padding = if filled < 56 { 56 - filled } else { 120 - filled };
+        let mut tail = [0; 72];
+        tail[0] = 0x80;
+        tail[padding .. padding + 8].copy_from_slice(&count.to_be_bytes());
+        self.update(&tail[.. padding + 8]);
+
+        let mut out = [0; 32];
+        for (chunk, word) in out.chunks_exact_mut(4).zip(&self.state) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        out
+    }
+}
+
+/// Apply the compression function to a block,
+/// using the SHA extensions of the CPU if it has them.
+fn compress(state: &mut [u32; 8], block: &[u8; 64])
+{
+    #[cfg(target_arch = "x86_64")]
+    if crate::shani::available() {
+        // SAFETY: The CPU supports the instructions.
+        unsafe { crate::shani::compress(state, block) };
+        return;
+    }
+
+    compress_soft(state, block);
+}
+
+/// Apply the compression function to a block,
+/// from FIPS 180-4 section 6.2.2.
+pub (crate) fn compress_soft(state: &mut [u32; 8], block: &[u8; 64])
+{
+    let mut w = [0u32; 64];
+    for (i, chunk) in block.chunks_exact(4).enumerate() {
+        w[i] = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    for i in 16 .. 64 {
+        let s0 = w[i - 15].rotate_right(7)
+               ^ w[i - 15].rotate_right(18)
+               ^ w[i - 15] >> 3;
+        let s1 = w[i - 2].rotate_right(17)
+               ^ w[i - 2].rotate_right(19)
+               ^ w[i - 2] >> 10;
+        w[i] = w[i - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for i in 0 .. 64 {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ (!e & g);
+        let t1 = h
+            .wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(K[i])
+            .wrapping_add(w[i]);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(maj);
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+    }
+
+    for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *s = s.wrapping_add(v);
+    }
+}
@@ -0,0 +1,92 @@
+//! Compression function using the x86 SHA extensions.
+//!
+//! This follows the reference code in Intel’s white paper
+//! “Intel SHA Extensions”, which processes four rounds at a time.
+//! The intermediate hash value is kept in two registers,
+//! one holding the words A, B, E, F, and one holding C, D, G, H.
+
+use crate::portable::K;
+use core::arch::x86_64::*;
+
+/// Whether the CPU supports the instructions used by [`compress`].
+///
+/// The result of the detection is cached by the standard library,
+/// so this is cheap to call for every block.
+/// Without the standard library, the CPU cannot be inspected,
+/// and the instructions are used only if the target enables them.
+#[cfg(feature = "std")]
+pub (crate) fn available() -> bool
+{
+    is_x86_feature_detected!("sha")
+        && is_x86_feature_detected!("sse2")
+        && is_x86_feature_detected!("ssse3")
+        && is_x86_feature_detected!("sse4.1")
+}
+
+#[cfg(not(feature = "std"))]
+pub (crate) fn available() -> bool
+{
+    cfg!(all(target_feature = "sha", target_feature = "sse2",
+             target_feature = "ssse3", target_feature = "sse4.1"))
+}
+
+/// Apply the compression function to a block.
+///
+/// # Safety
+///
+/// The CPU must support the instructions,
+/// which is the case if [`available`] returns true.
+#[target_feature(enable = "sha,sse2,ssse3,sse4.1")]
+pub (crate) unsafe fn compress(state: &mut [u32; 8], block: &[u8; 64])
+{
+    // Reverses the bytes in each word, as the words are big-endian.
+    let mask = _mm_set_epi64x(0x0C0D_0E0F_0809_0A0B, 0x0405_0607_0001_0203);
+
+    // SAFETY: The state is 32 bytes, and unaligned loads are used.
+    let dcba = _mm_loadu_si128(state.as_ptr() as *const __m128i);
+    let hgfe = _mm_loadu_si128(state.as_ptr().add(4) as *const __m128i);
+
+    let cdab = _mm_shuffle_epi32(dcba, 0xB1);
+    let efgh = _mm_shuffle_epi32(hgfe, 0x1B);
+    let mut abef = _mm_alignr_epi8(cdab, efgh, 8);
+    let mut cdgh = _mm_blend_epi16(efgh, cdab, 0xF0);
+    let (abef_save, cdgh_save) = (abef, cdgh);
+
+    // SAFETY: The block is 64 bytes, and unaligned loads are used.
+    let words = block.as_ptr() as *const __m128i;
+    let mut w = [
+        _mm_shuffle_epi8(_mm_loadu_si128(words), mask),
+        _mm_shuffle_epi8(_mm_loadu_si128(words.add(1)), mask),
+        _mm_shuffle_epi8(_mm_loadu_si128(words.add(2)), mask),
+        _mm_shuffle_epi8(_mm_loadu_si128(words.add(3)), mask),
+    ];
+
+    for i in 0 .. 16 {
+        // Extend the message schedule after the first sixteen words.
+        if i >= 4 {
+            let t1 = _mm_sha256msg1_epu32(w[i % 4], w[(i + 1) % 4]);
+            let t2 = _mm_alignr_epi8(w[(i + 3) % 4], w[(i + 2) % 4], 4);
+            let t3 = _mm_add_epi32(t1, t2);
+            w[i % 4] = _mm_sha256msg2_epu32(t3, w[(i + 3) % 4]);
+        }
+
+        // SAFETY: K has 64 words, and unaligned loads are used.
+        let k = _mm_loadu_si128(K.as_ptr().add(4 * i) as *const __m128i);
+        let t1 = _mm_add_epi32(w[i % 4], k);
+        cdgh = _mm_sha256rnds2_epu32(cdgh, abef, t1);
+        let t2 = _mm_shuffle_epi32(t1, 0x0E);
+        abef = _mm_sha256rnds2_epu32(abef, cdgh, t2);
+    }
+
+    abef = _mm_add_epi32(abef, abef_save);
+    cdgh = _mm_add_epi32(cdgh, cdgh_save);
+
+    let feba = _mm_shuffle_epi32(abef, 0x1B);
+    let dchg = _mm_shuffle_epi32(cdgh, 0xB1);
+    let dcba = _mm_blend_epi16(feba, dchg, 0xF0);
+    let hgef = _mm_alignr_epi8(dchg, feba, 8);
+
+    // SAFETY: The state is 32 bytes, and unaligned stores are used.
+    _mm_storeu_si128(state.as_mut_ptr() as *mut __m128i, dcba);
+    _mm_storeu_si128(state.as_mut_ptr().add(4) as *mut __m128i, hgef);
+}
@@ -0,0 +1,71 @@
+use core::ffi::c_int;
+use core::ffi::c_uchar;
+use core::ffi::c_ulonglong;
+use core::mem::MaybeUninit;
+
+#[repr(C)]
+#[derive(Clone)]
+pub (crate) struct crypto_hash_sha256_state
+{
+    state: [u32; 8],
+    count: u64,
+    buf:   [u8; 64],
+}
+
+#[link(name = "sodium")]
+extern "C"
+{
+    fn crypto_hash_sha256_init(
+        state: *mut crypto_hash_sha256_state,
+    ) -> c_int;
+
+    fn crypto_hash_sha256_update(
+        state: *mut crypto_hash_sha256_state,
+        r#in:  *const c_uchar,
+        inlen: c_ulonglong,
+    ) -> c_int;
+
+    fn crypto_hash_sha256_final(
+        state: *mut crypto_hash_sha256_state,
+        out:   *mut c_uchar,
+    ) -> c_int;
+}
+
+impl crypto_hash_sha256_state
+{
+    pub (crate) fn new() -> Self
+    {
+        unsafe {
+            let mut inner = MaybeUninit::uninit();
+            crypto_hash_sha256_init(inner.as_mut_ptr());
+            inner.assume_init()
+        }
+    }
+
+    pub (crate) fn update(&mut self, buf: &[u8])
+    {
+        unsafe {
+            crypto_hash_sha256_update(self, buf.as_ptr(), buf.len() as u64);
+        }
+    }
+
+    pub (crate) fn export(&self) -> [u8; crate::STATE_LEN]
+    {
+        crate::encode_state(&self.state, self.count, &self.buf)
+    }
+
+    pub (crate) fn import(bytes: &[u8; crate::STATE_LEN]) -> Self
+    {
+        let (state, count, buf) = crate::decode_state(bytes);
+        Self{state, count, buf}
+    }
+
+    pub (crate) fn finalize(mut self) -> [u8; 32]
+    {
+        unsafe {
+            let mut buf = MaybeUninit::uninit();
+            crypto_hash_sha256_final(&mut self, buf.as_mut_ptr() as *mut u8);
+            buf.assume_init()
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Ingest daemon that receives files over a Unix domain socket.
+//!
+//! Usage: `ingest <socket path> <volume path>`.
+//!
+//! Clients connect to the socket and send file descriptors,
+//! one per message, in `SCM_RIGHTS` control messages.
+//! For each file descriptor, the daemon inserts the file
+//! into the volume and replies with the hash of the object,
+//! followed by a newline, or with an error message.
+
+use std::env;
+use std::io::Error;
+use std::io::Result;
+use std::io::Write;
+use std::mem;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::FromRawFd;
+use std::os::unix::io::OwnedFd;
+use std::os::unix::net::UnixListener;
+use std::os::unix::net::UnixStream;
+use std::ptr;
+use wallace_volume::Volume;
+
+fn main() -> Result<()>
+{
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 {
+        eprintln!("Usage: {} <socket path> <volume path>", args[0]);
+        std::process::exit(1);
+    }
+
+    let volume = Volume::open(&args[2])?;
+    let listener = UnixListener::bind(&args[1])?;
+    for stream in listener.incoming() {
+        if let Err(err) = serve(&volume, stream?) {
+            eprintln!("{}", err);
+        }
+    }
+
+    Ok(())
+}
+
+/// Insert every file received over the connection.
+fn serve(volume: &Volume, mut stream: UnixStream) -> Result<()>
+{
+    while let Some(fd) = recv_fd(&stream)? {
+        match volume.insert_from_fd(fd) {
+            Ok(hash) => writeln!(stream, "{}", hash)?,
+            Err(err) => writeln!(stream, "error: {}", err)?,
+        }
+    }
+    Ok(())
+}
+
+/// Receive a file descriptor in an `SCM_RIGHTS` control message.
+///
+/// Returns [`None`] when the peer has closed the connection.
+fn recv_fd(stream: &UnixStream) -> Result<Option<OwnedFd>>
+{
+    // The message must carry at least one byte of ordinary data.
+    let mut byte = 0u8;
+    let mut iov = libc::iovec{
+        iov_base: &mut byte as *mut u8 as *mut libc::c_void,
+        iov_len: 1,
+    };
+
+    // Buffer for the control message, aligned for cmsghdr.
+    let mut control = [0u64; 8];
+
+    // SAFETY: msghdr is a plain C struct; all zeroes is valid.
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = mem::size_of_val(&control);
+
+    // SAFETY: The buffers referred to by msg outlive the call.
+    let n = unsafe {
+        libc::recvmsg(stream.as_raw_fd(), &mut msg, libc::MSG_CMSG_CLOEXEC)
+    };
+    if n == -1 {
+        return Err(Error::last_os_error());
+    }
+    if n == 0 {
+        return Ok(None);
+    }
+
+    // SAFETY: The kernel filled in a valid control message, if any.
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        if cmsg.is_null() ||
+           (*cmsg).cmsg_level != libc::SOL_SOCKET ||
+           (*cmsg).cmsg_type != libc::SCM_RIGHTS {
+            return Err(Error::from_raw_os_error(libc::EBADMSG));
+        }
+        let fd = ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const i32);
+        Ok(Some(OwnedFd::from_raw_fd(fd)))
+    }
+}
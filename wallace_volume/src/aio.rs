@@ -0,0 +1,222 @@
+//! Asynchronous interface to volumes.
+//!
+//! This module is only available with the `aio` feature.
+//! [`AsyncVolume`] runs the blocking volume operations
+//! on threads of their own, and returns futures
+//! that complete when the operations finish.
+//! The futures do not depend on any particular async runtime.
+
+use crate::Hash;
+use crate::ObjectReader;
+use crate::Volume;
+use std::future::Future;
+use std::io::Read;
+use std::io::Result;
+use std::panic::AssertUnwindSafe;
+use std::panic::catch_unwind;
+use std::panic::resume_unwind;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Waker;
+use std::thread;
+
+/// Handle to an opened volume with asynchronous methods.
+///
+/// Each method runs the corresponding method of [`Volume`]
+/// on a thread of its own, so that async servers
+/// do not block their executor while waiting for the file system.
+/// The handle is cheap to clone, and clones share the volume.
+#[derive(Clone)]
+pub struct AsyncVolume
+{
+    inner: Arc<Volume>,
+}
+
+/// Future returned by the methods of [`AsyncVolume`].
+///
+/// The operation runs to completion even if the future is dropped.
+/// If the operation panics, polling the future resumes the panic.
+pub struct Blocking<T>
+{
+    shared: Arc<Mutex<Shared<T>>>,
+}
+
+type Outcome<T> = std::thread::Result<Result<T>>;
+
+struct Shared<T>
+{
+    outcome: Option<Outcome<T>>,
+    waker: Option<Waker>,
+}
+
+impl AsyncVolume
+{
+    /// Wrap a volume so that it can be used asynchronously.
+    pub fn new(volume: Volume) -> Self
+    {
+        Self{inner: Arc::new(volume)}
+    }
+
+    /// The wrapped volume, for use with the blocking methods.
+    pub fn volume(&self) -> &Volume
+    {
+        &self.inner
+    }
+
+    /// See [`Volume::get`].
+    ///
+    /// Reading from the returned reader blocks,
+    /// which is fine for small objects.
+    /// For large objects, consider passing its file descriptor
+    /// to the async runtime, or reading it with [`Blocking`] tasks.
+    pub fn get(&self, hash: Hash) -> Blocking<Option<(ObjectReader, u64)>>
+    {
+        let inner = self.inner.clone();
+        spawn_blocking(move || inner.get(hash))
+    }
+
+    /// See [`Volume::insert_from_reader`].
+    ///
+    /// The reader is read on the thread that performs the insert.
+    pub fn insert_from_reader<R>(&self, mut reader: R) -> Blocking<Hash>
+        where R: 'static + Read + Send
+    {
+        let inner = self.inner.clone();
+        spawn_blocking(move || inner.insert_from_reader(&mut reader))
+    }
+
+    /// See [`Volume::insert_from_bytes`].
+    pub fn insert_from_bytes(&self, bytes: Vec<u8>) -> Blocking<Hash>
+    {
+        let inner = self.inner.clone();
+        spawn_blocking(move || inner.insert_from_bytes(&bytes))
+    }
+
+    /// See [`Volume::all`].
+    ///
+    /// The hashes are collected before the future completes.
+    pub fn all(&self) -> Blocking<Vec<Hash>>
+    {
+        let inner = self.inner.clone();
+        spawn_blocking(move || inner.all()?.collect())
+    }
+}
+
+impl From<Volume> for AsyncVolume
+{
+    fn from(volume: Volume) -> Self
+    {
+        Self::new(volume)
+    }
+}
+
+/// Run the given function on a new thread.
+fn spawn_blocking<T, F>(f: F) -> Blocking<T>
+    where T: 'static + Send
+        , F: 'static + Send + FnOnce() -> Result<T>
+{
+    let shared = Arc::new(Mutex::new(Shared{outcome: None, waker: None}));
+
+    let thread_shared = shared.clone();
+    let spawn_result = thread::Builder::new().spawn(move || {
+        let outcome = catch_unwind(AssertUnwindSafe(f));
+        let mut shared = thread_shared.lock().unwrap();
+        shared.outcome = Some(outcome);
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    });
+
+    // Report failure to spawn the thread through the future.
+    if let Err(err) = spawn_result {
+        shared.lock().unwrap().outcome = Some(Ok(Err(err)));
+    }
+
+    Blocking{shared}
+}
+
+impl<T> Future for Blocking<T>
+{
+    type Output = Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<T>>
+    {
+        let mut shared = self.shared.lock().unwrap();
+        match shared.outcome.take() {
+            Some(Ok(result)) => Poll::Ready(result),
+            Some(Err(panic)) => resume_unwind(panic),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use std::io::Cursor;
+    use std::task::Wake;
+    use std::thread::Thread;
+    use super::*;
+
+    /// Minimal executor that parks the thread while the future is pending.
+    fn block_on<F: Future>(future: F) -> F::Output
+    {
+        struct ThreadWaker(Thread);
+
+        impl Wake for ThreadWaker
+        {
+            fn wake(self: Arc<Self>)
+            {
+                self.0.unpark();
+            }
+        }
+
+        let waker = Arc::new(ThreadWaker(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_async_volume()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_async_volume").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+        let volume = AsyncVolume::new(volume);
+
+        // Insert the objects.
+        let data = Cursor::new(test_data.regular1_contents.clone());
+        let hash1 = block_on(volume.insert_from_reader(data));
+        let hash2 = block_on(volume.insert_from_bytes(b"Hello".to_vec()));
+
+        // Get an object.
+        let hash1 = hash1.unwrap();
+        let (mut reader, size) =
+            block_on(volume.get(hash1)).unwrap().unwrap();
+        let mut actual = Vec::new();
+        reader.read_to_end(&mut actual).unwrap();
+
+        // Check the results.
+        let mut all = block_on(volume.all()).unwrap();
+        all.sort();
+        let mut expected = vec![hash1, hash2.unwrap()];
+        expected.sort();
+        assert_eq!(hash1, test_data.regular1_hash);
+        assert_eq!(actual, test_data.regular1_contents);
+        assert_eq!(size, actual.len() as u64);
+        assert_eq!(all, expected);
+    }
+}
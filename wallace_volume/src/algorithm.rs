@@ -0,0 +1,226 @@
+use crate::Hash;
+use std::fs::File;
+use std::fs::rename;
+use std::fs::write;
+use std::io::BufWriter;
+use std::io::Error;
+use std::io::ErrorKind::InvalidData;
+use std::io::ErrorKind::NotFound;
+use std::io::Read;
+use std::io::Result;
+use std::io::Write;
+use std::io::copy;
+use std::path::Path;
+use wallace_blake3::Blake3;
+use wallace_fsutil as fsutil;
+use wallace_sha256::Sha256;
+
+/// Hash function used to compute the hashes of objects.
+///
+/// Each volume uses a single hash function for all of its objects,
+/// which is chosen when the volume is created
+/// with [`Volume::create_with_hash_algorithm`].
+/// It is recorded in the file `hash-algorithm` in the volume’s directory.
+/// Volumes that lack this file use SHA-256.
+///
+/// [`struct@Hash`] does not record which function computed it,
+/// so hashes from volumes with different functions must not be mixed.
+/// [Manifests][`crate::Manifest`] do record the hash function,
+/// so that they cannot be mixed up between such volumes.
+///
+/// [`Volume::create_with_hash_algorithm`]:
+///     `crate::Volume::create_with_hash_algorithm`
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, std::hash::Hash)]
+pub enum HashAlgorithm
+{
+    /// SHA-256, computed with libsodium.
+    /// This is the default.
+    #[default]
+    Sha256,
+
+    /// BLAKE3 with 32-byte output.
+    /// This is considerably faster than SHA-256
+    /// on machines without SHA extensions.
+    Blake3,
+}
+
+/// Hash function state for [`HashAlgorithm`].
+///
+/// The [`Write`] impl never returns an error.
+#[derive(Clone)]
+pub (crate) enum Hasher
+{
+    Sha256(Sha256),
+    Blake3(Box<Blake3>),
+}
+
+impl HashAlgorithm
+{
+    /// The name of the hash function,
+    /// as recorded in the volume’s directory.
+    pub fn name(self) -> &'static str
+    {
+        match self {
+            Self::Sha256 => "sha256",
+            Self::Blake3 => "blake3",
+        }
+    }
+
+    /// The hash function with the given name,
+    /// as returned by [`HashAlgorithm::name`].
+    pub fn from_name(name: &str) -> Option<Self>
+    {
+        match name {
+            "sha256" => Some(Self::Sha256),
+            "blake3" => Some(Self::Blake3),
+            _ => None,
+        }
+    }
+
+    /// Compute the hash of the given bytes.
+    pub fn compute_from_bytes(self, b: &[u8]) -> Hash
+    {
+        let mut hasher = self.hasher();
+        hasher.update(b);
+        hasher.finalize()
+    }
+
+    /// Read all bytes from the reader and compute their hash.
+    pub fn compute_from_reader(self, r: &mut impl Read) -> Result<Hash>
+    {
+        let hasher = match self {
+            Self::Sha256 => {
+                let mut hasher = self.hasher();
+                copy(r, &mut hasher)?;
+                hasher
+            },
+            Self::Blake3 => {
+                // BLAKE3 hashes large buffers on multiple threads.
+                let mut writer = BufWriter::with_capacity(1 << 20,
+                                                          self.hasher());
+                copy(r, &mut writer)?;
+                writer.into_inner().map_err(|err| err.into_error())?
+            },
+        };
+        Ok(hasher.finalize())
+    }
+
+    /// Start hashing with this hash function.
+    pub (crate) fn hasher(self) -> Hasher
+    {
+        match self {
+            Self::Sha256 => Hasher::Sha256(Sha256::new()),
+            Self::Blake3 => Hasher::Blake3(Box::new(Blake3::new())),
+        }
+    }
+}
+
+impl Hasher
+{
+    /// Update the hash using a buffer.
+    pub (crate) fn update(&mut self, buf: &[u8])
+    {
+        match self {
+            Self::Sha256(sha256) => sha256.update(buf),
+            Self::Blake3(blake3) => blake3.update(buf),
+        }
+    }
+
+    /// Finalize the hash.
+    pub (crate) fn finalize(self) -> Hash
+    {
+        let bytes = match self {
+            Self::Sha256(sha256) => sha256.finalize(),
+            Self::Blake3(blake3) => blake3.finalize(),
+        };
+        Hash{bytes}
+    }
+}
+
+impl Write for Hasher
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize>
+    {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()>
+    {
+        Ok(())
+    }
+}
+
+/// Read the hash algorithm of the volume in the given directory.
+pub (crate) fn read_hash_algorithm(directory: &File) -> Result<HashAlgorithm>
+{
+    let open_flags = libc::O_RDONLY | libc::O_CLOEXEC | libc::O_NOFOLLOW;
+    let file_result = fsutil::openat(directory, "hash-algorithm",
+                                     open_flags, 0);
+
+    let mut file = match file_result {
+        Ok(file) => file,
+        Err(err) if err.kind() == NotFound =>
+            return Ok(HashAlgorithm::default()),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    let name = contents.trim();
+    HashAlgorithm::from_name(name).ok_or_else(|| {
+        let message = format!("unknown volume hash algorithm {:?}", name);
+        Error::new(InvalidData, message)
+    })
+}
+
+/// Write the hash algorithm of the volume at the given path.
+pub (crate) fn write_hash_algorithm(path: &Path, algorithm: HashAlgorithm)
+    -> Result<()>
+{
+    let tmp_path = path.join("hash-algorithm.tmp");
+    write(&tmp_path, format!("{}\n", algorithm.name()))?;
+    rename(&tmp_path, path.join("hash-algorithm"))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use crate::Volume;
+    use super::*;
+
+    #[test]
+    fn test_blake3_volume()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_blake3_volume").unwrap();
+        let path = test_data.root_path.join("blake3");
+        Volume::create_with_hash_algorithm(&path, HashAlgorithm::Blake3)
+            .unwrap();
+        let mut volume = Volume::open(&path).unwrap();
+        volume.set_verify_on_read(true);
+
+        // Insert the objects.
+        let hash1 = volume.insert_from_bytes(b"Hello, world!").unwrap();
+        let mut writer = volume.start_insert().unwrap();
+        writer.write_all(b"Hello, world!").unwrap();
+        let hash2 = writer.finish().unwrap();
+        let hash3 = volume.insert_from_path(&test_data.regular1_path)
+            .unwrap();
+
+        // Read an object back.
+        let (mut reader, _) = volume.get(hash3).unwrap().unwrap();
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).unwrap();
+
+        // Check the results.
+        let blake3 = HashAlgorithm::Blake3;
+        assert_eq!(volume.hash_algorithm(), blake3);
+        assert_eq!(hash1, blake3.compute_from_bytes(b"Hello, world!"));
+        assert_eq!(hash1, hash2);
+        assert_ne!(hash3, test_data.regular1_hash);
+        assert_eq!(data, test_data.regular1_contents);
+        assert_eq!(volume.verify_all().unwrap().verified, 2);
+    }
+}
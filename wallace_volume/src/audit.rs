@@ -0,0 +1,167 @@
+use crate::Hash;
+use crate::Volume;
+use std::io::ErrorKind::NotFound;
+use std::io::Result;
+use wallace_fsutil as fsutil;
+use wallace_fsutil::FileType;
+
+/// Object whose backing file has additional hard links.
+///
+/// Returned by [`Volume::audit_links`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AliasedObject
+{
+    /// The hash of the object.
+    pub hash: Hash,
+
+    /// The inode number of the file backing the object,
+    /// which can be passed to `find -inum` to locate the other links.
+    pub inode: u64,
+
+    /// The number of hard links to the file backing the object.
+    pub nlink: u64,
+}
+
+/// Object whose backing file had its mode changed back to read-only.
+///
+/// Returned by [`Volume::enforce_readonly`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ModeFix
+{
+    /// The hash of the object.
+    pub hash: Hash,
+
+    /// The permission bits of the file before they were fixed.
+    pub old_mode: u32,
+}
+
+impl Volume
+{
+    /// Find objects whose backing files have more than one hard link.
+    ///
+    /// As explained in the crate documentation,
+    /// additional hard links can be used to modify an object,
+    /// which corrupts the volume.
+    /// They typically remain after inserting a file
+    /// with [`Volume::insert_from_file`] without removing the original.
+    /// Packed objects are not backed by files of their own,
+    /// and are never reported.
+    pub fn audit_links(&self) -> Result<Vec<AliasedObject>>
+    {
+        let mut aliased = Vec::new();
+        for hash in self.all_loose()? {
+            let hash = hash?;
+            let stat = match self.stat(hash)? {
+                Some(stat) => stat,
+                None => continue,
+            };
+            if stat.nlink > 1 {
+                aliased.push(AliasedObject{hash, inode: stat.inode,
+                                           nlink: stat.nlink});
+            }
+        }
+        Ok(aliased)
+    }
+
+    /// Make every object that is not read-only read-only again.
+    ///
+    /// Inserting an object makes its backing file read-only,
+    /// but backup tools may not preserve this when restoring the volume.
+    /// This method changes the mode of every backing file
+    /// whose permission bits are not `0o400` back to `0o400`,
+    /// and reports the objects whose mode was changed.
+    /// Packed objects are not backed by files of their own,
+    /// and are never reported.
+    pub fn enforce_readonly(&self) -> Result<Vec<ModeFix>>
+    {
+        let mut fixed = Vec::new();
+        for hash in self.all_loose()? {
+            let hash = hash?;
+
+            let path = format!("objects/{}", hash);
+            let stat_result = fsutil::fstatat(&self.directory, &path,
+                                              libc::AT_SYMLINK_NOFOLLOW);
+            let stat = match stat_result {
+                Ok(stat) => stat,
+                Err(err) if err.kind() == NotFound => continue,
+                Err(err) => return Err(err.into()),
+            };
+
+            // Do not follow symbolic links planted in the volume.
+            if FileType::from_st_mode(stat.st_mode) != FileType::Regular {
+                continue;
+            }
+
+            let old_mode = stat.st_mode & 0o7777;
+            if old_mode != 0o400 {
+                fsutil::fchmodat(&self.directory, &path, 0o400, 0)?;
+                fixed.push(ModeFix{hash, old_mode});
+            }
+        }
+        Ok(fixed)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use super::*;
+
+    #[test]
+    fn test_audit_links()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_audit_links").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+        let alias_path = test_data.root_path.join("alias");
+
+        // Insert the objects, keeping a link to one of them.
+        let hash1 = volume.insert_from_path(&test_data.regular1_path)
+            .unwrap();
+        volume.insert_from_path(&test_data.regular2_path).unwrap();
+        fs::remove_file(&test_data.regular2_path).unwrap();
+        let object_path = test_data.volume1_path.join("objects")
+            .join(hash1.to_string());
+        fs::hard_link(&object_path, &alias_path).unwrap();
+        fs::remove_file(&test_data.regular1_path).unwrap();
+
+        // Audit the links.
+        let aliased = volume.audit_links().unwrap();
+
+        // Check the results.
+        // The alias is reported even though the original link is gone.
+        let stat = volume.stat(hash1).unwrap().unwrap();
+        assert_eq!(aliased, [AliasedObject{hash: hash1, inode: stat.inode,
+                                           nlink: 2}]);
+    }
+
+    #[test]
+    fn test_enforce_readonly()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_enforce_readonly").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+
+        // Insert the objects, and loosen the mode of one of them.
+        let hash1 = volume.insert_from_path(&test_data.regular1_path)
+            .unwrap();
+        volume.insert_from_path(&test_data.regular2_path).unwrap();
+        let object_path = test_data.volume1_path.join("objects")
+            .join(hash1.to_string());
+        fs::set_permissions(&object_path, fs::Permissions::from_mode(0o644))
+            .unwrap();
+
+        // Enforce read-only twice.
+        let fixed1 = volume.enforce_readonly().unwrap();
+        let fixed2 = volume.enforce_readonly().unwrap();
+
+        // Check the results.
+        let mode = fs::metadata(&object_path).unwrap().permissions().mode();
+        assert_eq!(fixed1, [ModeFix{hash: hash1, old_mode: 0o644}]);
+        assert_eq!(fixed2, []);
+        assert_eq!(mode & 0o7777, 0o400);
+    }
+}
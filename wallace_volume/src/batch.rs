@@ -0,0 +1,346 @@
+use crate::Hash;
+use crate::Volume;
+use crate::VolumeError;
+use std::ffi::CString;
+use std::fs::File;
+use std::io::Error;
+use std::io::ErrorKind::NotFound;
+use std::io::Read;
+use std::io::Result;
+use std::mem::ManuallyDrop;
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::FromRawFd;
+use std::sync::atomic::Ordering::Relaxed;
+use wallace_fsutil::IoUring;
+use wallace_fsutil::Sqe;
+
+/// The number of objects read per io_uring batch.
+const BATCH_SIZE: u32 = 64;
+
+/// How [`Volume::read_objects`] performs its reads.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ReadBackend
+{
+    /// Read each object with ordinary system calls.
+    /// This is the default.
+    #[default]
+    Sync,
+
+    /// Open, read, and close the objects in batches using io_uring,
+    /// so that a batch costs a handful of system calls
+    /// rather than a few per object.
+    /// If io_uring is not available, fall back to [`ReadBackend::Sync`].
+    IoUring,
+}
+
+/// Outcome of reading a single object with [`Volume::read_objects`].
+pub type ReadResult = Result<Option<Vec<u8>>>;
+
+impl Volume
+{
+    /// The backend used by [`Volume::read_objects`].
+    pub fn read_backend(&self) -> ReadBackend
+    {
+        self.read_backend
+    }
+
+    /// Change the backend used by [`Volume::read_objects`].
+    pub fn set_read_backend(&mut self, read_backend: ReadBackend)
+    {
+        self.read_backend = read_backend;
+    }
+
+    /// Read the byte arrays of the given objects entirely into memory.
+    ///
+    /// The results are in the same order as the hashes,
+    /// and are [`None`] for objects that do not exist.
+    /// Reading many small objects at once is where
+    /// [`ReadBackend::IoUring`] pays off.
+    /// Objects are verified if [`Volume::verify_on_read`] is enabled.
+    pub fn read_objects(&self, hashes: &[Hash]) -> Vec<ReadResult>
+    {
+        let mut ring = match self.read_backend {
+            ReadBackend::Sync => None,
+            ReadBackend::IoUring => IoUring::new(BATCH_SIZE).ok(),
+        };
+
+        let mut results = Vec::with_capacity(hashes.len());
+        for batch in hashes.chunks(BATCH_SIZE as usize) {
+            // A ring that failed may hold entries that were not submitted,
+            // so it is discarded in favor of the synchronous path.
+            if let Some(r) = &mut ring {
+                match self.read_batch(r, batch) {
+                    Ok(batch_results) => {
+                        results.extend(batch_results);
+                        continue;
+                    },
+                    Err(_) => ring = None,
+                }
+            }
+            results.extend(batch.iter().map(|&h| self.read_object(h)));
+        }
+        results
+    }
+
+    /// Read an object with ordinary system calls.
+    fn read_object(&self, hash: Hash) -> ReadResult
+    {
+        let (mut reader, size) = match self.get(hash)? {
+            Some(object) => object,
+            None => return Ok(None),
+        };
+        let mut bytes = Vec::with_capacity(size as usize);
+        reader.read_to_end(&mut bytes)?;
+        Ok(Some(bytes))
+    }
+
+    /// Read a batch of at most [`BATCH_SIZE`] objects using io_uring.
+    ///
+    /// An error is returned only if the ring itself fails.
+    /// Entries may then still be in flight,
+    /// so the paths and buffers they refer to are leaked rather than freed.
+    fn read_batch(&self, ring: &mut IoUring, batch: &[Hash])
+        -> Result<Vec<ReadResult>>
+    {
+        let mut results: Vec<ReadResult> = Vec::with_capacity(batch.len());
+        let mut files: Vec<Option<File>> = Vec::with_capacity(batch.len());
+
+        // Open the files backing the objects.
+        let open_flags = libc::O_RDONLY | libc::O_CLOEXEC | libc::O_NOFOLLOW;
+        let paths: ManuallyDrop<Vec<CString>> = ManuallyDrop::new(
+            batch.iter()
+                .map(|hash| CString::new(format!("objects/{}", hash)).unwrap())
+                .collect()
+        );
+        let mut opens = Pending::new(ring, batch.len());
+        let submitted = paths.iter().enumerate().try_for_each(|(i, path)| {
+            let sqe = Sqe::openat(&self.directory, path, open_flags, 0,
+                                  i as u64);
+            // SAFETY: The paths are leaked unless the entries complete.
+            unsafe { opens.push(&sqe) }
+        }).and_then(|()| opens.wait());
+        // SAFETY: The file descriptors were just opened.
+        // They are closed when the files are dropped, even on error.
+        let opened: Vec<Option<Result<File>>> =
+            opens.results.into_iter()
+                .map(|r| r.map(|r| r.map(|fd| unsafe {
+                    File::from_raw_fd(fd as i32)
+                })))
+                .collect();
+        submitted?;
+        for file in opened {
+            results.push(Ok(None));
+            files.push(None);
+            match file.unwrap() {
+                Ok(file) => *files.last_mut().unwrap() = Some(file),
+                Err(err) if err.kind() == NotFound => (),
+                Err(err) => *results.last_mut().unwrap() = Err(err),
+            }
+        }
+        drop(ManuallyDrop::into_inner(paths));
+
+        // Read the files into buffers of the right size.
+        let mut buffers: Vec<Vec<u8>> = Vec::with_capacity(batch.len());
+        for (i, file) in files.iter_mut().enumerate() {
+            let mut buffer = Vec::new();
+            if let Some(f) = file {
+                match f.metadata() {
+                    Ok(m) if m.is_file() =>
+                        buffer.resize(m.len() as usize, 0),
+                    Ok(_) => {
+                        let err = VolumeError::NotRegularFile.into();
+                        results[i] = Err(err);
+                        *file = None;
+                    },
+                    Err(err) => {
+                        results[i] = Err(err);
+                        *file = None;
+                    },
+                }
+            }
+            buffers.push(buffer);
+        }
+        let mut buffers = ManuallyDrop::new(buffers);
+        let mut reads = Pending::new(ring, batch.len());
+        let submitted = files.iter().enumerate().try_for_each(|(i, file)| {
+            let f = match file {
+                Some(f) => f,
+                None => return Ok(()),
+            };
+            let sqe = Sqe::read(f.as_fd(), &mut buffers[i], 0, i as u64);
+            // SAFETY: The buffers are neither moved nor resized
+            // until the entries complete, and are leaked otherwise.
+            // In-flight reads hold their own references to the files.
+            unsafe { reads.push(&sqe) }
+        }).and_then(|()| reads.wait());
+        let mut read = reads.results;
+        submitted?;
+        let mut buffers = ManuallyDrop::into_inner(buffers);
+
+        // Close the files, and finish short reads synchronously.
+        let mut closes = Pending::new(ring, batch.len());
+        for (i, file) in files.into_iter().enumerate() {
+            let file = match file {
+                Some(file) => file,
+                None => continue,
+            };
+            results[i] = match read[i].take().unwrap() {
+                Ok(n) => finish_read(&file, &mut buffers[i], n as usize)
+                    .map(|()| Some(std::mem::take(&mut buffers[i]))),
+                Err(err) => Err(err),
+            };
+            let sqe = Sqe::close(file.into(), i as u64);
+            // SAFETY: Closing refers to no memory.
+            unsafe { closes.push(&sqe)?; }
+        }
+        closes.wait()?;
+
+        // Packed objects are not backed by files of their own.
+        for (result, &hash) in results.iter_mut().zip(batch) {
+            *result = match std::mem::replace(result, Ok(None)) {
+                Ok(None) => self.read_object(hash),
+                Ok(Some(bytes)) => {
+                    self.metrics.gets.fetch_add(1, Relaxed);
+                    self.metrics.bytes_read
+                        .fetch_add(bytes.len() as u64, Relaxed);
+                    if self.verify_on_read() {
+                        self.check_read(hash, &bytes).map(|()| Some(bytes))
+                    } else {
+                        Ok(Some(bytes))
+                    }
+                },
+                other => other,
+            };
+        }
+
+        Ok(results)
+    }
+
+    /// Check that the bytes of an object match its hash.
+    fn check_read(&self, hash: Hash, bytes: &[u8]) -> Result<()>
+    {
+        let actual = self.hash_algorithm.compute_from_bytes(bytes);
+        if !actual.ct_eq(&hash) {
+            return Err(VolumeError::CorruptObject{hash, actual}.into());
+        }
+        Ok(())
+    }
+}
+
+/// Entries pushed onto a ring, and the results of those that completed.
+struct Pending<'a>
+{
+    ring: &'a mut IoUring,
+
+    /// The number of entries that have not yet completed.
+    count: usize,
+
+    /// The results, indexed by the user data of the entries.
+    results: Vec<Option<Result<u32>>>,
+}
+
+impl<'a> Pending<'a>
+{
+    fn new(ring: &'a mut IoUring, len: usize) -> Self
+    {
+        let results = (0 .. len).map(|_| None).collect();
+        Self{ring, count: 0, results}
+    }
+
+    /// Push an entry onto the ring.
+    ///
+    /// If the submission queue is full,
+    /// the pending entries are first waited for.
+    ///
+    /// # Safety
+    ///
+    /// As for [`IoUring::push`].
+    unsafe fn push(&mut self, sqe: &Sqe) -> Result<()>
+    {
+        if !self.ring.push(sqe) {
+            self.wait()?;
+            if !self.ring.push(sqe) {
+                return Err(Error::other("io_uring submission queue full"));
+            }
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Submit the pending entries and wait for them to complete.
+    fn wait(&mut self) -> Result<()>
+    {
+        self.ring.submit_and_wait(self.count as u32)?;
+        while self.count > 0 {
+            let cqe = self.ring.pop()
+                .ok_or_else(|| Error::other("missing io_uring completion"))?;
+            self.results[cqe.user_data as usize] =
+                Some(cqe.result().map_err(Error::from));
+            self.count -= 1;
+        }
+        Ok(())
+    }
+}
+
+/// Read the remainder of a file after a short read.
+fn finish_read(file: &File, buffer: &mut [u8], n: usize) -> Result<()>
+{
+    if n < buffer.len() {
+        file.read_exact_at(&mut buffer[n ..], n as u64)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use super::*;
+
+    #[test]
+    fn test_read_objects()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_read_objects").unwrap();
+        let mut volume = Volume::open(&test_data.volume1_path).unwrap();
+        volume.set_pack_threshold(8);
+        volume.set_verify_on_read(true);
+
+        // Insert the objects.
+        let hash1 = volume.insert_from_path(&test_data.regular1_path)
+            .unwrap();
+        let hash2 = volume.insert_from_bytes(b"small").unwrap();
+        let hash3 = volume.insert_from_bytes(b"").unwrap();
+        let missing = Hash::compute_from_bytes(b"missing");
+        let hashes = [hash1, missing, hash2, hash3];
+
+        // Read the objects with both backends.
+        let mut results = Vec::new();
+        let mut metrics = Vec::new();
+        for &backend in &[ReadBackend::Sync, ReadBackend::IoUring] {
+            volume.set_read_backend(backend);
+            let before = volume.metrics();
+            let objects = volume.read_objects(&hashes).into_iter()
+                .collect::<Result<Vec<_>>>().unwrap();
+            let after = volume.metrics();
+            results.push(objects);
+            metrics.push((after.gets - before.gets,
+                          after.misses - before.misses,
+                          after.bytes_read - before.bytes_read));
+        }
+
+        // Check the results.
+        let expected = vec![
+            Some(test_data.regular1_contents.clone()),
+            None,
+            Some(b"small".to_vec()),
+            Some(Vec::new()),
+        ];
+        assert_eq!(results[0], expected);
+        assert_eq!(results[1], expected);
+        let bytes = test_data.regular1_contents.len() as u64 + 5;
+        assert_eq!(metrics[0], (3, 1, bytes));
+        assert_eq!(metrics[1], (3, 1, bytes));
+    }
+}
@@ -0,0 +1,219 @@
+use crate::Hash;
+use crate::Volume;
+use std::io::Error;
+use std::io::ErrorKind::InvalidData;
+use std::io::ErrorKind::NotFound;
+use std::io::Read;
+use std::io::Result;
+use std::io::Write;
+use std::sync::RwLock;
+use wallace_fsutil as fsutil;
+
+/// Magic bytes at the start of a persisted Bloom filter.
+const MAGIC: &[u8] = b"wallace-bloom 1\n";
+
+/// Number of bits set per object.
+const PROBES: u32 = 7;
+
+/// Number of bits per object, which with [`PROBES`]
+/// gives a false positive rate of about one percent.
+const BITS_PER_OBJECT: u64 = 10;
+
+/// Set of hashes with false positives but no false negatives.
+///
+/// Hashes are uniformly distributed already,
+/// so the bit positions are derived from the hash itself,
+/// with double hashing, rather than by hashing it again.
+pub (crate) struct BloomFilter
+{
+    words: Vec<u64>,
+}
+
+impl BloomFilter
+{
+    /// Create an empty filter sized for the given number of objects.
+    fn with_capacity(objects: u64) -> Self
+    {
+        let bits = (objects * BITS_PER_OBJECT).max(1024);
+        let words = vec![0; bits.div_ceil(64) as usize];
+        Self{words}
+    }
+
+    /// The bit positions for a hash.
+    fn positions(&self, hash: Hash) -> impl Iterator<Item=usize>
+    {
+        let mut h1 = [0; 8];
+        let mut h2 = [0; 8];
+        h1.copy_from_slice(&hash.bytes[0 .. 8]);
+        h2.copy_from_slice(&hash.bytes[8 .. 16]);
+        let h1 = u64::from_le_bytes(h1);
+        let h2 = u64::from_le_bytes(h2) | 1;
+        let bits = self.words.len() as u64 * 64;
+        (0 .. PROBES as u64).map(move |i| {
+            (h1.wrapping_add(i.wrapping_mul(h2)) % bits) as usize
+        })
+    }
+
+    fn insert(&mut self, hash: Hash)
+    {
+        for bit in self.positions(hash) {
+            self.words[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    fn might_contain(&self, hash: Hash) -> bool
+    {
+        self.positions(hash)
+            .all(|bit| self.words[bit / 64] & 1 << (bit % 64) != 0)
+    }
+
+    fn encode(&self) -> Vec<u8>
+    {
+        let mut buf = Vec::with_capacity(MAGIC.len() + self.words.len() * 8);
+        buf.extend_from_slice(MAGIC);
+        for word in &self.words {
+            buf.extend_from_slice(&word.to_le_bytes());
+        }
+        buf
+    }
+
+    fn decode(buf: &[u8]) -> Result<Self>
+    {
+        let invalid = || Error::new(InvalidData, "invalid Bloom filter");
+        let buf = buf.strip_prefix(MAGIC).ok_or_else(invalid)?;
+        if buf.is_empty() || buf.len() % 8 != 0 {
+            return Err(invalid());
+        }
+        let words = buf.chunks_exact(8).map(|chunk| {
+            let mut word = [0; 8];
+            word.copy_from_slice(chunk);
+            u64::from_le_bytes(word)
+        }).collect();
+        Ok(Self{words})
+    }
+}
+
+impl Volume
+{
+    /// Rebuild the Bloom filter of the volume from its objects.
+    ///
+    /// The filter is stored at the path `bloom-filter`
+    /// in the volume’s directory, and is used only
+    /// by volumes that load it with [`Volume::load_bloom_filter`].
+    /// It is not updated when objects are inserted
+    /// through other [`Volume`] values or by other processes,
+    /// so this method must be called after such inserts,
+    /// typically as part of regular maintenance.
+    /// If this volume has loaded the filter,
+    /// it uses the rebuilt filter afterwards.
+    pub fn rebuild_bloom_filter(&self) -> Result<()>
+    {
+        // Hold the lock while listing, so that concurrent inserts
+        // through this volume are not missing from the new filter.
+        let mut loaded = self.bloom.as_ref().map(|b| b.write().unwrap());
+
+        let hashes = self.all()?.collect::<Result<Vec<_>>>()?;
+        let mut filter = BloomFilter::with_capacity(hashes.len() as u64);
+        for hash in hashes {
+            filter.insert(hash);
+        }
+
+        // Write the filter to a temporary file and rename it,
+        // so that the filter is never observed half-written.
+        let open_flags = { use libc::*; O_WRONLY | O_CREAT | O_TRUNC |
+                                        O_CLOEXEC | O_NOFOLLOW };
+        let mut file = fsutil::openat(&self.directory, "bloom-filter.tmp",
+                                      open_flags, 0o644)?;
+        file.write_all(&filter.encode())?;
+        fsutil::renameat(&self.directory, "bloom-filter.tmp",
+                         &self.directory, "bloom-filter")?;
+
+        if let Some(loaded) = &mut loaded {
+            **loaded = filter;
+        }
+        Ok(())
+    }
+
+    /// Load the Bloom filter of the volume,
+    /// so that [`Volume::contains`] can answer
+    /// that an object does not exist without a system call.
+    ///
+    /// This is meant for checking many volumes for an object,
+    /// as in union stores and replication.
+    /// Objects inserted through this volume are added to the filter,
+    /// but objects inserted otherwise since the filter was last
+    /// rebuilt with [`Volume::rebuild_bloom_filter`] are not,
+    /// and [`Volume::contains`] reports them as missing.
+    ///
+    /// Returns whether the volume has a Bloom filter.
+    pub fn load_bloom_filter(&mut self) -> Result<bool>
+    {
+        let open_flags = libc::O_RDONLY | libc::O_CLOEXEC | libc::O_NOFOLLOW;
+        let mut file = match fsutil::openat(&self.directory, "bloom-filter",
+                                            open_flags, 0) {
+            Ok(file) => file,
+            Err(err) if err.kind() == NotFound => return Ok(false),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)?;
+        self.bloom = Some(RwLock::new(BloomFilter::decode(&buf)?));
+        Ok(true)
+    }
+
+    /// Whether the object may exist according to the Bloom filter.
+    ///
+    /// If this returns false, the object does not exist,
+    /// unless it was inserted behind the filter’s back;
+    /// see [`Volume::load_bloom_filter`].
+    /// If no filter was loaded, this always returns true.
+    pub fn might_contain(&self, hash: Hash) -> bool
+    {
+        match &self.bloom {
+            Some(bloom) => bloom.read().unwrap().might_contain(hash),
+            None => true,
+        }
+    }
+
+    /// Add an object to the loaded Bloom filter, if any.
+    pub (crate) fn record_in_bloom_filter(&self, hash: Hash)
+    {
+        if let Some(bloom) = &self.bloom {
+            bloom.write().unwrap().insert(hash);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use super::*;
+
+    #[test]
+    fn test_bloom_filter()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_bloom_filter").unwrap();
+        let volume1 = Volume::open(&test_data.volume1_path).unwrap();
+        let mut volume2 = Volume::open(&test_data.volume1_path).unwrap();
+        assert!(!volume2.load_bloom_filter().unwrap());
+
+        // Insert the objects, around a rebuild.
+        let hash1 = volume1.insert_from_bytes(b"Hello").unwrap();
+        volume1.rebuild_bloom_filter().unwrap();
+        assert!(volume2.load_bloom_filter().unwrap());
+        let hash2 = volume1.insert_from_bytes(b"world").unwrap();
+        let hash3 = volume2.insert_from_bytes(b"again").unwrap();
+
+        // Check the results.
+        assert!(volume2.contains(hash1).unwrap());
+        assert!(!volume2.contains(hash2).unwrap());
+        assert!(volume2.contains(hash3).unwrap());
+        assert!(!volume2.might_contain(test_data.regular1_hash));
+        assert!(volume1.might_contain(test_data.regular1_hash));
+        volume2.rebuild_bloom_filter().unwrap();
+        assert!(volume2.contains(hash2).unwrap());
+    }
+}
@@ -0,0 +1,259 @@
+use crate::Durability;
+use crate::HashAlgorithm;
+use crate::LAYOUT_VERSION;
+use crate::ReadBackend;
+use crate::Volume;
+use crate::VolumeId;
+use crate::algorithm;
+use crate::identity;
+use crate::layout;
+use std::fs::DirBuilder;
+use std::io::Result;
+use std::os::unix::fs::DirBuilderExt;
+use std::path::Path;
+
+/// Options for creating a volume.
+///
+/// Settings that are stored in the volume, such as the hash function,
+/// are fixed at creation time and apply to every handle.
+/// Settings that belong to a handle, such as the durability policy,
+/// apply to the handle returned by [`VolumeBuilder::create`]
+/// or [`VolumeBuilder::open`].
+///
+/// ```no_run
+/// # use wallace_volume::{Durability, HashAlgorithm, VolumeBuilder};
+/// let volume = VolumeBuilder::new()
+///     .mode(0o750)
+///     .hash_algorithm(HashAlgorithm::Blake3)
+///     .durability(Durability::SyncFile)
+///     .verify_on_read(true)
+///     .create("/srv/volume")?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct VolumeBuilder
+{
+    mode: u32,
+    hash_algorithm: HashAlgorithm,
+    durability: Durability,
+    pack_threshold: u64,
+    quota: Option<u64>,
+    trash: bool,
+    read_backend: ReadBackend,
+    verify_on_read: bool,
+    fs_verity: bool,
+}
+
+impl Default for VolumeBuilder
+{
+    fn default() -> Self
+    {
+        Self{
+            mode: 0o777,
+            hash_algorithm: HashAlgorithm::default(),
+            durability: Durability::default(),
+            pack_threshold: 0,
+            quota: None,
+            trash: false,
+            read_backend: ReadBackend::default(),
+            verify_on_read: false,
+            fs_verity: false,
+        }
+    }
+}
+
+impl VolumeBuilder
+{
+    /// Create a builder with the default options,
+    /// which are the same as those used by [`Volume::create`].
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    /// Set the permission bits of the directories of the volume.
+    ///
+    /// As with `mkdir`, the umask of the process is applied.
+    /// The default is `0o777`.
+    pub fn mode(&mut self, mode: u32) -> &mut Self
+    {
+        self.mode = mode;
+        self
+    }
+
+    /// Set the hash function with which objects are hashed.
+    ///
+    /// See [`Volume::create_with_hash_algorithm`].
+    pub fn hash_algorithm(&mut self, hash_algorithm: HashAlgorithm)
+        -> &mut Self
+    {
+        self.hash_algorithm = hash_algorithm;
+        self
+    }
+
+    /// Set the durability policy of the returned handle.
+    ///
+    /// See [`Volume::set_durability`].
+    pub fn durability(&mut self, durability: Durability) -> &mut Self
+    {
+        self.durability = durability;
+        self
+    }
+
+    /// Set the size up to which objects are packed by the returned handle.
+    ///
+    /// See [`Volume::set_pack_threshold`].
+    pub fn pack_threshold(&mut self, pack_threshold: u64) -> &mut Self
+    {
+        self.pack_threshold = pack_threshold;
+        self
+    }
+
+    /// Set the quota enforced by the returned handle.
+    ///
+    /// See [`Volume::set_quota`].
+    pub fn quota(&mut self, quota: Option<u64>) -> &mut Self
+    {
+        self.quota = quota;
+        self
+    }
+
+    /// Set whether the returned handle moves removed objects to the trash.
+    ///
+    /// See [`Volume::set_trash`].
+    pub fn trash(&mut self, trash: bool) -> &mut Self
+    {
+        self.trash = trash;
+        self
+    }
+
+    /// Set the backend used by the returned handle for batch reads.
+    ///
+    /// See [`Volume::set_read_backend`].
+    pub fn read_backend(&mut self, read_backend: ReadBackend) -> &mut Self
+    {
+        self.read_backend = read_backend;
+        self
+    }
+
+    /// Set whether readers returned by the handle verify objects.
+    ///
+    /// See [`Volume::set_verify_on_read`].
+    pub fn verify_on_read(&mut self, verify_on_read: bool) -> &mut Self
+    {
+        self.verify_on_read = verify_on_read;
+        self
+    }
+
+    /// Set whether the returned handle protects objects with fs-verity.
+    ///
+    /// See [`Volume::set_fs_verity`].
+    pub fn fs_verity(&mut self, fs_verity: bool) -> &mut Self
+    {
+        self.fs_verity = fs_verity;
+        self
+    }
+
+    /// Create a new volume at the given path,
+    /// which must not yet exist, and open it.
+    pub fn create(&self, path: impl AsRef<Path>) -> Result<Volume>
+    {
+        let path = path.as_ref();
+        self.create_layout(path)?;
+        self.open(path)
+    }
+
+    /// Open the existing volume at the given path
+    /// with the handle settings of this builder.
+    ///
+    /// Settings that are stored in the volume are ignored;
+    /// those of the volume apply instead.
+    /// See [`Volume::open`].
+    pub fn open(&self, path: impl AsRef<Path>) -> Result<Volume>
+    {
+        let mut volume = Volume::open(path)?;
+        volume.set_durability(self.durability);
+        volume.set_pack_threshold(self.pack_threshold);
+        volume.set_quota(self.quota);
+        volume.set_trash(self.trash);
+        volume.set_read_backend(self.read_backend);
+        volume.set_verify_on_read(self.verify_on_read);
+        volume.set_fs_verity(self.fs_verity);
+        Ok(volume)
+    }
+
+    /// Create the directories and files of a new volume.
+    pub (crate) fn create_layout(&self, path: &Path) -> Result<()>
+    {
+        let mut dir_builder = DirBuilder::new();
+        dir_builder.mode(self.mode);
+
+        dir_builder.create(path)?;
+        dir_builder.create(path.join("objects"))?;
+        algorithm::write_hash_algorithm(path, self.hash_algorithm)?;
+        identity::write_volume_id(path, VolumeId::generate()?)?;
+        layout::write_layout_version(path, LAYOUT_VERSION)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use super::*;
+
+    #[test]
+    fn test_builder()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_builder").unwrap();
+        let volume_path = test_data.root_path.join("volume3");
+
+        // Create the volume.
+        let volume = VolumeBuilder::new()
+            .mode(0o700)
+            .hash_algorithm(HashAlgorithm::Blake3)
+            .durability(Durability::SyncFile)
+            .create(&volume_path)
+            .unwrap();
+        let hash = volume.insert_from_bytes(b"Hello").unwrap();
+
+        // Check the results.
+        let mode = fs::metadata(&volume_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        assert_eq!(volume.hash_algorithm(), HashAlgorithm::Blake3);
+        assert_eq!(volume.durability(), Durability::SyncFile);
+        assert_eq!(hash, HashAlgorithm::Blake3.compute_from_bytes(b"Hello"));
+        assert!(VolumeBuilder::new().create(&volume_path).is_err());
+    }
+
+    #[test]
+    fn test_builder_open()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_builder_open").unwrap();
+
+        // Open the volume.
+        let volume = VolumeBuilder::new()
+            .hash_algorithm(HashAlgorithm::Blake3)
+            .pack_threshold(8)
+            .quota(Some(1000))
+            .trash(true)
+            .verify_on_read(true)
+            .fs_verity(true)
+            .open(&test_data.volume1_path)
+            .unwrap();
+
+        // Check the results.
+        assert_eq!(volume.hash_algorithm(), HashAlgorithm::Sha256);
+        assert_eq!(volume.pack_threshold(), 8);
+        assert_eq!(volume.quota(), Some(1000));
+        assert!(volume.trash());
+        assert!(volume.verify_on_read());
+        assert!(volume.fs_verity());
+    }
+}
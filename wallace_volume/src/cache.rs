@@ -0,0 +1,310 @@
+use crate::Hash;
+use crate::Hashes;
+use crate::ObjectReader;
+use crate::ObjectStore;
+use crate::Volume;
+use crate::VolumeError;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::io::ErrorKind::Unsupported;
+use std::io::Read;
+use std::io::Result;
+use std::sync::Mutex;
+
+/// Local volume in front of a slow object store.
+///
+/// Objects that are retrieved from the cache
+/// are served from the local volume if it has them.
+/// Otherwise they are retrieved from the backend,
+/// such as an [`HttpVolume`] or a union of stores,
+/// and inserted into the local volume before they are served.
+///
+/// When the total size of the objects in the local volume
+/// exceeds the budget of the cache, the least recently used objects
+/// are removed from the local volume until it no longer does.
+/// Access times are tracked in memory; when the cache is created,
+/// the objects already in the local volume are ordered by mtime.
+/// Packed objects cannot be removed, and neither are pinned objects,
+/// so they stop being tracked once they are up for eviction;
+/// the local volume should not pack objects.
+/// It must also use the same [hash algorithm][`crate::HashAlgorithm`]
+/// as the backend.
+///
+/// The backend is authoritative: [`ObjectStore::all`] lists its objects,
+/// and [`ObjectStore::insert_from_reader`] inserts into it.
+///
+/// [`HttpVolume`]: ../wallace_volume_http/struct.HttpVolume.html
+pub struct CacheVolume<B>
+{
+    local: Volume,
+    backend: B,
+    budget: u64,
+    lru: Mutex<Lru>,
+}
+
+/// Access order of the objects in the local volume.
+#[derive(Default)]
+struct Lru
+{
+    /// The last access and size of each object.
+    entries: HashMap<Hash, (u64, u64)>,
+
+    /// The objects by last access.
+    order: BTreeMap<u64, Hash>,
+
+    /// The clock used for access times.
+    clock: u64,
+
+    /// The total size of the objects.
+    size: u64,
+}
+
+impl Lru
+{
+    /// Record an access to an object.
+    fn touch(&mut self, hash: Hash, size: u64)
+    {
+        self.clock += 1;
+        if let Some((access, old_size)) =
+            self.entries.insert(hash, (self.clock, size))
+        {
+            self.order.remove(&access);
+            self.size -= old_size;
+        }
+        self.order.insert(self.clock, hash);
+        self.size += size;
+    }
+
+    /// Forget the least recently used object.
+    fn pop(&mut self) -> Option<Hash>
+    {
+        let (_, hash) = self.order.pop_first()?;
+        let (_, size) = self.entries.remove(&hash).unwrap();
+        self.size -= size;
+        Some(hash)
+    }
+}
+
+impl<B> CacheVolume<B>
+    where B: ObjectStore
+{
+    /// Put the local volume in front of the backend,
+    /// keeping at most `budget` bytes in the local volume.
+    ///
+    /// This lists and stats the objects in the local volume,
+    /// and evicts objects if they already exceed the budget.
+    pub fn new(local: Volume, backend: B, budget: u64) -> Result<Self>
+    {
+        let mut objects = Vec::new();
+        for hash in local.all_loose()? {
+            let hash = hash?;
+            if let Some(stat) = local.stat(hash)? {
+                objects.push((stat.mtime, hash, stat.size));
+            }
+        }
+        objects.sort_by_key(|&(mtime, _, _)| mtime);
+
+        let mut lru = Lru::default();
+        for (_, hash, size) in objects {
+            lru.touch(hash, size);
+        }
+
+        let cache = Self{local, backend, budget, lru: Mutex::new(lru)};
+        cache.evict()?;
+        Ok(cache)
+    }
+
+    /// The local volume.
+    pub fn local(&self) -> &Volume
+    {
+        &self.local
+    }
+
+    /// The backend.
+    pub fn backend(&self) -> &B
+    {
+        &self.backend
+    }
+
+    /// The maximum total size of the objects in the local volume.
+    pub fn budget(&self) -> u64
+    {
+        self.budget
+    }
+
+    /// The total size of the objects in the local volume.
+    pub fn cached_bytes(&self) -> u64
+    {
+        self.lru.lock().unwrap().size
+    }
+
+    /// Retrieve a reader for an object’s byte array,
+    /// as well as the size of the object in bytes,
+    /// populating the local volume if it does not have the object.
+    ///
+    /// If neither the local volume nor the backend has the object,
+    /// this method returns [`None`].
+    /// If the backend returns an object with other contents,
+    /// this method fails with
+    /// [`CorruptObject`][`VolumeError::CorruptObject`],
+    /// and the contents are not kept in the local volume.
+    pub fn get(&self, hash: Hash) -> Result<Option<(ObjectReader, u64)>>
+    {
+        if let Some((reader, size)) = self.local.get(hash)? {
+            self.lru.lock().unwrap().touch(hash, size);
+            return Ok(Some((reader, size)));
+        }
+
+        let (mut reader, _) = match self.backend.get(hash)? {
+            Some(object) => object,
+            None => return Ok(None),
+        };
+
+        let actual = self.local.insert_from_reader(&mut reader)?;
+        if actual != hash {
+            // Keep the object if it happened to be cached already.
+            let lru = self.lru.lock().unwrap();
+            let cached = lru.entries.contains_key(&actual);
+            drop(lru);
+            if !cached && !self.local.is_pinned(actual)? {
+                self.local.remove(actual)?;
+            }
+            return Err(VolumeError::CorruptObject{hash, actual}.into());
+        }
+
+        // Open the object before evicting,
+        // so that it cannot be evicted in between.
+        let object = self.local.get(hash)?;
+        if let Some((_, size)) = &object {
+            self.lru.lock().unwrap().touch(hash, *size);
+            self.evict()?;
+        }
+        Ok(object)
+    }
+
+    /// Remove least recently used objects from the local volume
+    /// until their total size is within the budget.
+    fn evict(&self) -> Result<()>
+    {
+        loop {
+            let hash = {
+                let mut lru = self.lru.lock().unwrap();
+                if lru.size <= self.budget {
+                    return Ok(());
+                }
+                lru.pop().unwrap()
+            };
+            if self.local.is_pinned(hash)? {
+                continue;
+            }
+            match self.local.remove(hash) {
+                Ok(_) => (),
+                Err(err) if err.kind() == Unsupported => (),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+impl<B> ObjectStore for CacheVolume<B>
+    where B: ObjectStore
+{
+    type Reader = ObjectReader;
+
+    fn get(&self, hash: Hash) -> Result<Option<(ObjectReader, u64)>>
+    {
+        CacheVolume::get(self, hash)
+    }
+
+    fn contains(&self, hash: Hash) -> Result<bool>
+    {
+        Ok(self.local.contains(hash)? || self.backend.contains(hash)?)
+    }
+
+    fn all(&self) -> Result<Hashes<'_>>
+    {
+        self.backend.all()
+    }
+
+    fn insert_from_reader(&self, reader: &mut dyn Read) -> Result<Hash>
+    {
+        self.backend.insert_from_reader(reader)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use super::*;
+
+    #[test]
+    fn test_cache_volume()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_cache_volume").unwrap();
+        let local = Volume::open(&test_data.volume1_path).unwrap();
+        let backend = Volume::open(&test_data.volume2_path).unwrap();
+
+        // Insert the objects.
+        let hash1 = backend.insert_from_bytes(b"Hello").unwrap();
+        let hash2 = backend.insert_from_bytes(b"world").unwrap();
+        let hash3 = backend.insert_from_bytes(b"again").unwrap();
+        let cache = CacheVolume::new(local, backend, 10).unwrap();
+
+        // Get the objects.
+        let read = |hash| {
+            let (mut reader, _) = cache.get(hash).unwrap().unwrap();
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data).unwrap();
+            data
+        };
+        assert_eq!(read(hash1), b"Hello");
+        assert_eq!(read(hash2), b"world");
+        assert_eq!(read(hash1), b"Hello");
+        assert_eq!(read(hash3), b"again");
+
+        // Check the results.
+        let local = cache.local();
+        assert!(local.contains(hash1).unwrap());
+        assert!(!local.contains(hash2).unwrap());
+        assert!(local.contains(hash3).unwrap());
+        assert_eq!(cache.cached_bytes(), 10);
+        assert_eq!(cache.all().unwrap().count(), 3);
+        let missing = Hash::compute_from_bytes(b"missing");
+        assert!(cache.get(missing).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_cache_volume_corrupt_backend()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_cache_volume_corrupt_backend")
+            .unwrap();
+        let local = Volume::open(&test_data.volume1_path).unwrap();
+        let backend = Volume::open(&test_data.volume2_path).unwrap();
+
+        // Insert and corrupt the object.
+        let hash = backend.insert_from_bytes(b"Corrupt me!").unwrap();
+        let path = test_data.volume2_path.join("objects")
+            .join(hash.to_string());
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))
+            .unwrap();
+        fs::write(&path, b"Corrupted!!").unwrap();
+        let cache = CacheVolume::new(local, backend, 100).unwrap();
+
+        // Get the object.
+        let result = cache.get(hash);
+
+        // Check the results.
+        let actual = Hash::compute_from_bytes(b"Corrupted!!");
+        assert!(matches!(result.map_err(VolumeError::from),
+                         Err(VolumeError::CorruptObject{hash: h, actual: a})
+                             if h == hash && a == actual));
+        assert!(!cache.local().contains(hash).unwrap());
+        assert!(!cache.local().contains(actual).unwrap());
+        assert_eq!(cache.cached_bytes(), 0);
+    }
+}
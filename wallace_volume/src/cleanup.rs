@@ -0,0 +1,135 @@
+use crate::Hash;
+use crate::Volume;
+use std::ffi::OsStr;
+use std::io::ErrorKind::NotFound;
+use std::io::Result;
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use wallace_fsutil as fsutil;
+use wallace_fsutil::FileType;
+
+impl Volume
+{
+    /// Remove leftovers of interrupted operations
+    /// that were last modified at least the given duration ago.
+    ///
+    /// These are files in the `tmp` directory,
+    /// left behind by inserts on file systems without `O_TMPFILE`,
+    /// entries in the objects directory not named after a hash,
+    /// and files ending in `.tmp` in the volume’s directory,
+    /// left behind by interrupted metadata updates.
+    /// Subdirectories are never removed.
+    /// The duration should be long enough
+    /// that no operation could still be using the files.
+    ///
+    /// Returns the paths of the removed files,
+    /// relative to the volume’s directory.
+    pub fn cleanup(&self, older_than: Duration) -> Result<Vec<PathBuf>>
+    {
+        let cutoff = SystemTime::now() - older_than;
+        let cutoff = cutoff.duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64);
+
+        let mut removed = Vec::new();
+        self.cleanup_directory("tmp", cutoff, &mut removed, |_| true)?;
+        self.cleanup_directory("objects", cutoff, &mut removed, |name| {
+            Hash::from_ascii(name).is_err()
+        })?;
+        self.cleanup_directory(".", cutoff, &mut removed, |name| {
+            name.ends_with(b".tmp")
+        })?;
+        Ok(removed)
+    }
+
+    /// Remove the stale files in a directory of the volume
+    /// whose names satisfy the predicate.
+    fn cleanup_directory<F>(&self, directory: &str, cutoff: i64,
+                            removed: &mut Vec<PathBuf>, predicate: F)
+        -> Result<()>
+        where F: Fn(&[u8]) -> bool
+    {
+        let open_flags = libc::O_RDONLY | libc::O_DIRECTORY;
+        let fd = match fsutil::openat(&self.directory, directory,
+                                      open_flags, 0) {
+            Ok(fd) => fd,
+            Err(err) if err.kind() == NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut names = Vec::new();
+        let mut dir = fsutil::fdopendir(fd)?;
+        while let Some(dirent) = fsutil::readdir(&mut dir)? {
+            let name = dirent.d_name().to_bytes();
+            if name != b"." && name != b".." && predicate(name) {
+                names.push(PathBuf::from(OsStr::from_bytes(name)));
+            }
+        }
+
+        for name in names {
+            let path = match directory {
+                "." => name,
+                _ => PathBuf::from(directory).join(name),
+            };
+            let stat = match fsutil::fstatat(&self.directory, &path,
+                                             libc::AT_SYMLINK_NOFOLLOW) {
+                Ok(stat) => stat,
+                Err(err) if err.kind() == NotFound => continue,
+                Err(err) => return Err(err.into()),
+            };
+            if FileType::from_st_mode(stat.st_mode) == FileType::Directory
+               || stat.st_mtime > cutoff {
+                continue;
+            }
+            match fsutil::unlinkat(&self.directory, &path, 0) {
+                Ok(()) => removed.push(path),
+                Err(err) if err.kind() == NotFound => (),
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use std::fs;
+    use super::*;
+
+    #[test]
+    fn test_cleanup()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_cleanup").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+        let path = &test_data.volume1_path;
+
+        // Insert an object, and leave behind some leftovers.
+        let hash = volume.insert_from_bytes(b"Hello").unwrap();
+        fs::create_dir(path.join("tmp")).unwrap();
+        fs::write(path.join("tmp/1234-5-6"), b"").unwrap();
+        fs::write(path.join("objects/stray"), b"").unwrap();
+        fs::create_dir(path.join("objects/subdir")).unwrap();
+        fs::write(path.join("layout-version.tmp"), b"").unwrap();
+
+        // Clean up, first with a long threshold.
+        let removed1 = volume.cleanup(Duration::from_secs(3600)).unwrap();
+        let removed2 = volume.cleanup(Duration::ZERO).unwrap();
+
+        // Check the results.
+        let mut removed2: Vec<_> = removed2.iter()
+            .map(|p| p.to_str().unwrap().to_owned()).collect();
+        removed2.sort();
+        assert_eq!(removed1, Vec::<PathBuf>::new());
+        assert_eq!(removed2, ["layout-version.tmp", "objects/stray",
+                              "tmp/1234-5-6"]);
+        assert!(volume.contains(hash).unwrap());
+        assert!(path.join("objects/subdir").exists());
+        assert!(path.join("layout-version").exists());
+    }
+}
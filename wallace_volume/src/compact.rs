@@ -0,0 +1,157 @@
+use crate::Hash;
+use crate::Volume;
+use std::io::ErrorKind::NotFound;
+use std::io::Read;
+use std::io::Result;
+use wallace_fsutil as fsutil;
+
+/// Number of objects moved into the pack at a time.
+/// Each batch synchronizes the pack once.
+const BATCH_SIZE: usize = 1024;
+
+impl Volume
+{
+    /// Move loose objects of at most the given size into the pack,
+    /// removing the files that backed them.
+    ///
+    /// This migrates volumes with many small objects,
+    /// for instance ones created before packing was enabled
+    /// with [`Volume::set_pack_threshold`], to the pack.
+    /// Objects are retrieved in the same way before and after,
+    /// and readers of the removed files remain usable.
+    ///
+    /// The objects are moved in batches.
+    /// For each batch, the pack is written and synchronized
+    /// before any loose file is removed, whatever the durability policy,
+    /// so an interrupted compaction never loses objects;
+    /// at worst they are both loose and packed,
+    /// which the next compaction resolves.
+    /// Objects whose contents do not match their hash are left alone,
+    /// so that [`Volume::verify_all`] still reports them.
+    ///
+    /// Returns the number of objects that were moved.
+    pub fn compact(&self, max_size: u64) -> Result<u64>
+    {
+        let mut compacted = 0;
+        let mut batch = Vec::new();
+        for hash in self.all_loose()? {
+            if let Some(object) = self.read_compactable(hash?, max_size)? {
+                batch.push(object);
+            }
+            if batch.len() == BATCH_SIZE {
+                compacted += self.compact_batch(&batch)?;
+                batch.clear();
+            }
+        }
+        compacted += self.compact_batch(&batch)?;
+        Ok(compacted)
+    }
+
+    /// Read a loose object if it is small enough and intact.
+    fn read_compactable(&self, hash: Hash, max_size: u64)
+        -> Result<Option<(Hash, Vec<u8>)>>
+    {
+        let open_flags = { use libc::*; O_RDONLY | O_CLOEXEC | O_NOCTTY |
+                                        O_NOFOLLOW | O_NONBLOCK };
+        let path = format!("objects/{}", hash);
+        let mut file = match fsutil::openat(&self.directory, path,
+                                            open_flags, 0) {
+            Ok(file) => file,
+            Err(err) if err.kind() == NotFound => return Ok(None),
+            Err(err) if err.raw_os_error() == Some(libc::ELOOP) =>
+                return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let metadata = file.metadata()?;
+        if !metadata.is_file() || metadata.len() > max_size {
+            return Ok(None);
+        }
+
+        let mut bytes = Vec::with_capacity(metadata.len() as usize);
+        file.read_to_end(&mut bytes)?;
+        if self.hash_algorithm.compute_from_bytes(&bytes) != hash {
+            return Ok(None);
+        }
+        Ok(Some((hash, bytes)))
+    }
+
+    /// Pack a batch of objects, then remove their loose files.
+    fn compact_batch(&self, batch: &[(Hash, Vec<u8>)]) -> Result<u64>
+    {
+        if batch.is_empty() {
+            return Ok(0);
+        }
+
+        let existing = self.append_packed_batch(batch)?;
+
+        let mut compacted = 0;
+        for (hash, _) in batch {
+            // A packed copy that predates this batch may have been
+            // written without synchronization, so check it first.
+            if existing.contains(hash) && !self.packed_intact(*hash)? {
+                continue;
+            }
+
+            let path = format!("objects/{}", hash);
+            match fsutil::unlinkat(&self.directory, path, 0) {
+                Ok(()) => compacted += 1,
+                Err(err) if err.kind() == NotFound => (),
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(compacted)
+    }
+
+    /// Whether the packed copy of an object matches its hash.
+    fn packed_intact(&self, hash: Hash) -> Result<bool>
+    {
+        match self.get_packed(hash)? {
+            Some(mut reader) => {
+                let actual = self.hash_algorithm
+                    .compute_from_reader(&mut reader)?;
+                Ok(actual == hash)
+            },
+            None => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use std::io::Read;
+    use super::*;
+
+    #[test]
+    fn test_compact()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_compact").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+        let objects_path = test_data.volume1_path.join("objects");
+
+        // Insert the objects as loose files.
+        let hash1 = volume.insert_from_bytes(b"small").unwrap();
+        let hash2 = volume.insert_from_bytes(b"not so small").unwrap();
+
+        // Compact the volume, twice.
+        let compacted1 = volume.compact(8).unwrap();
+        let compacted2 = volume.compact(8).unwrap();
+
+        // Check the results.
+        let other = Volume::open(&test_data.volume1_path).unwrap();
+        let (mut reader, size) = other.get(hash1).unwrap().unwrap();
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).unwrap();
+        assert_eq!(compacted1, 1);
+        assert_eq!(compacted2, 0);
+        assert_eq!(data, b"small");
+        assert_eq!(size, 5);
+        assert!(!objects_path.join(hash1.to_string()).exists());
+        assert!(objects_path.join(hash2.to_string()).exists());
+        assert_eq!(other.all().unwrap().count(), 2);
+        assert_eq!(other.verify_all().unwrap().verified, 2);
+    }
+}
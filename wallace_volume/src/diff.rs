@@ -0,0 +1,59 @@
+use crate::Hash;
+use crate::ObjectStore;
+use crate::Volume;
+use std::io::Result;
+
+impl Volume
+{
+    /// Return an iterator over the objects in this volume
+    /// that are absent from the given store.
+    ///
+    /// The objects in this volume are listed as in [`Volume::all`],
+    /// and each is looked up in the other store.
+    /// This is useful for incremental backups,
+    /// where only the missing objects need to be copied.
+    pub fn diff<'a, S>(&'a self, other: &'a S)
+        -> Result<impl 'a + Iterator<Item=Result<Hash>>>
+        where S: ObjectStore + ?Sized
+    {
+        let all = self.all()?;
+        Ok(all.filter_map(move |hash| {
+            let missing = hash.and_then(|hash| {
+                Ok(if other.contains(hash)? { None } else { Some(hash) })
+            });
+            missing.transpose()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use super::*;
+
+    #[test]
+    fn test_diff()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_diff").unwrap();
+        let volume1 = Volume::open(&test_data.volume1_path).unwrap();
+        let volume2 = Volume::open(&test_data.volume2_path).unwrap();
+
+        // Insert the objects.
+        let hash1 = volume1.insert_from_path(&test_data.regular1_path).unwrap();
+        let hash2 = volume1.insert_from_path(&test_data.regular2_path).unwrap();
+        volume2.insert_from_bytes(&test_data.regular2_contents).unwrap();
+
+        // Compute the differences.
+        let diff1 = volume1.diff(&volume2).unwrap()
+            .collect::<Result<Vec<_>>>().unwrap();
+        let diff2 = volume2.diff(&volume1).unwrap()
+            .collect::<Result<Vec<_>>>().unwrap();
+
+        // Check the results.
+        assert_eq!(hash2, test_data.regular2_hash);
+        assert_eq!(diff1, [hash1]);
+        assert_eq!(diff2, []);
+    }
+}
@@ -0,0 +1,252 @@
+use crate::Hash;
+use crate::Volume;
+use crate::VolumeError;
+use crate::metrics::Metrics;
+use std::alloc::Layout;
+use std::alloc::alloc_zeroed;
+use std::alloc::dealloc;
+use std::alloc::handle_alloc_error;
+use std::fs::File;
+use std::io::Error;
+use std::io::ErrorKind::NotFound;
+use std::io::ErrorKind::UnexpectedEof;
+use std::io::Read;
+use std::io::Result;
+use std::os::raw::c_int;
+use std::os::unix::fs::FileExt;
+use std::slice;
+use std::sync::Arc;
+use std::sync::atomic::Ordering::Relaxed;
+use wallace_fsutil as fsutil;
+
+/// Alignment of reads from files opened with `O_DIRECT`.
+/// This is a multiple of the logical block size of common devices.
+const ALIGNMENT: usize = 4096;
+
+/// Size of the buffer of a [`DirectReader`].
+const BUFFER_SIZE: usize = 1 << 20;
+
+/// Reader for an object that bypasses the page cache.
+///
+/// Returned by [`Volume::get_direct`].
+/// The file is opened with `O_DIRECT`,
+/// so reads go straight to the storage device,
+/// and do not evict pages that other processes need.
+/// Such reads must be aligned, so the reader reads
+/// whole aligned blocks into a buffer of its own,
+/// and copies the requested bytes out of it.
+///
+/// Unlike [`ObjectReader`][`crate::ObjectReader`],
+/// this reader never verifies the object.
+pub struct DirectReader
+{
+    file: File,
+    offset: u64,
+    size: u64,
+    position: u64,
+    buffer: AlignedBuffer,
+
+    /// The offset in the file of the first byte in the buffer.
+    buffer_start: u64,
+
+    /// The number of valid bytes in the buffer.
+    buffer_len: usize,
+
+    metrics: Arc<Metrics>,
+}
+
+/// Heap buffer aligned for use with `O_DIRECT`.
+struct AlignedBuffer
+{
+    ptr: *mut u8,
+}
+
+// SAFETY: The buffer is uniquely owned, like a Box<[u8]>.
+unsafe impl Send for AlignedBuffer { }
+unsafe impl Sync for AlignedBuffer { }
+
+impl AlignedBuffer
+{
+    fn layout() -> Layout
+    {
+        Layout::from_size_align(BUFFER_SIZE, ALIGNMENT).unwrap()
+    }
+
+    fn new() -> Self
+    {
+        // The buffer is zeroed so that it is initialized
+        // before it is ever viewed as a slice.
+        // SAFETY: The layout has a non-zero size.
+        let ptr = unsafe { alloc_zeroed(Self::layout()) };
+        if ptr.is_null() {
+            handle_alloc_error(Self::layout());
+        }
+        Self{ptr}
+    }
+
+    fn as_slice(&self) -> &[u8]
+    {
+        // SAFETY: The allocation is BUFFER_SIZE initialized bytes.
+        unsafe { slice::from_raw_parts(self.ptr, BUFFER_SIZE) }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8]
+    {
+        // SAFETY: As in as_slice, and the buffer is borrowed uniquely.
+        unsafe { slice::from_raw_parts_mut(self.ptr, BUFFER_SIZE) }
+    }
+}
+
+impl Drop for AlignedBuffer
+{
+    fn drop(&mut self)
+    {
+        // SAFETY: The pointer was allocated with this layout.
+        unsafe { dealloc(self.ptr, Self::layout()); }
+    }
+}
+
+impl Volume
+{
+    /// Retrieve a reader for an object that bypasses the page cache,
+    /// as well as the size of the object in bytes.
+    ///
+    /// This is meant for jobs that read many objects once,
+    /// such as backups and scrubs, on servers whose page cache
+    /// is better spent on the objects that clients request.
+    /// See [`DirectReader`] for more information.
+    /// On file systems that do not support `O_DIRECT`,
+    /// the object is read through the page cache after all.
+    ///
+    /// If the object does not exist, this method returns [`None`].
+    pub fn get_direct(&self, hash: Hash)
+        -> Result<Option<(DirectReader, u64)>>
+    {
+        let path = format!("objects/{}", hash);
+        let (file, offset, size) = match self.open_direct(&path) {
+            Ok(file) => {
+                let metadata = file.metadata()?;
+                if !metadata.is_file() {
+                    return Err(VolumeError::NotRegularFile.into());
+                }
+                (file, 0, metadata.len())
+            },
+            Err(err) if err.kind() == NotFound => {
+                let entry = match self.find_packed(hash)? {
+                    Some(entry) => entry,
+                    None => {
+                        self.metrics.misses.fetch_add(1, Relaxed);
+                        return Ok(None);
+                    },
+                };
+                (self.open_direct("packs/data")?, entry.offset, entry.size)
+            },
+            Err(err) => return Err(err),
+        };
+
+        self.metrics.gets.fetch_add(1, Relaxed);
+        let reader = DirectReader{
+            file, offset, size,
+            position: 0,
+            buffer: AlignedBuffer::new(),
+            buffer_start: 0,
+            buffer_len: 0,
+            metrics: self.metrics.clone(),
+        };
+        Ok(Some((reader, size)))
+    }
+
+    /// Open a file in the volume with `O_DIRECT` if possible.
+    fn open_direct(&self, path: &str) -> Result<File>
+    {
+        let open_flags: c_int = { use libc::*; O_RDONLY | O_CLOEXEC |
+                                               O_NOCTTY | O_NOFOLLOW };
+        let result = fsutil::openat(&self.directory, path,
+                                    open_flags | libc::O_DIRECT, 0);
+        match result {
+            Err(err) if err.raw_os_error() == Some(libc::EINVAL) =>
+                Ok(fsutil::openat(&self.directory, path, open_flags, 0)?),
+            result => Ok(result?),
+        }
+    }
+}
+
+impl DirectReader
+{
+    /// The size of the object in bytes.
+    pub fn size(&self) -> u64
+    {
+        self.size
+    }
+}
+
+impl Read for DirectReader
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>
+    {
+        if self.position >= self.size || buf.is_empty() {
+            return Ok(0);
+        }
+
+        // Refill the buffer if it does not hold the next byte.
+        let file_position = self.offset + self.position;
+        let buffer_end = self.buffer_start + self.buffer_len as u64;
+        if file_position < self.buffer_start || file_position >= buffer_end {
+            let aligned = file_position & !(ALIGNMENT as u64 - 1);
+            let n = self.file.read_at(self.buffer.as_mut_slice(), aligned)?;
+            self.buffer_start = aligned;
+            self.buffer_len = n;
+            if aligned + n as u64 <= file_position {
+                return Err(Error::new(UnexpectedEof,
+                                      "object file is truncated"));
+            }
+        }
+
+        let skip = (file_position - self.buffer_start) as usize;
+        let available = &self.buffer.as_slice()[skip .. self.buffer_len];
+        let remaining = (self.size - self.position).min(buf.len() as u64);
+        let n = available.len().min(remaining as usize);
+        buf[.. n].copy_from_slice(&available[.. n]);
+
+        self.position += n as u64;
+        self.metrics.bytes_read.fetch_add(n as u64, Relaxed);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use super::*;
+
+    #[test]
+    fn test_get_direct()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_get_direct").unwrap();
+        let mut volume = Volume::open(&test_data.volume1_path).unwrap();
+        volume.set_pack_threshold(16);
+        let large: Vec<u8> = (0 .. 3 * BUFFER_SIZE + 123)
+            .map(|i| (i % 251) as u8).collect();
+
+        // Insert the objects, packing some of them.
+        volume.insert_from_bytes(b"padding").unwrap();
+        let hash1 = volume.insert_from_bytes(b"small").unwrap();
+        let hash2 = volume.insert_from_bytes(&large).unwrap();
+
+        // Read the objects.
+        let read = |hash| {
+            let (mut reader, size) = volume.get_direct(hash).unwrap()?;
+            let mut buf = Vec::new();
+            reader.read_to_end(&mut buf).unwrap();
+            assert_eq!(buf.len() as u64, size);
+            Some(buf)
+        };
+
+        // Check the results.
+        assert_eq!(read(hash1).unwrap(), b"small");
+        assert_eq!(read(hash2).unwrap(), large);
+        assert_eq!(read(test_data.regular1_hash), None);
+    }
+}
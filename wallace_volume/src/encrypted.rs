@@ -0,0 +1,288 @@
+use crate::Hash;
+use crate::Volume;
+use std::io::Error;
+use std::io::ErrorKind::InvalidData;
+use std::io::Read;
+use std::io::Result;
+use std::io::Write;
+use std::path::Path;
+use wallace_secretstream::ABYTES;
+use wallace_secretstream::Decryptor;
+use wallace_secretstream::Encryptor;
+use wallace_secretstream::HEADERBYTES;
+use wallace_secretstream::Key;
+
+/// Number of plaintext bytes per encrypted chunk.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Handle to an opened volume whose objects are encrypted at rest.
+///
+/// Object contents are encrypted with [`wallace_secretstream`]
+/// using the key that was passed to [`Volume::open_encrypted`].
+/// Objects are still addressed by the hash of their plaintext,
+/// so an encrypted volume reveals which objects it contains
+/// to anyone who knows their hashes, but not what they contain.
+///
+/// Each object file consists of the stream header,
+/// followed by the plaintext encrypted in chunks of 64 KiB.
+/// The final chunk is authenticated together with the hash of the object,
+/// so that object files cannot be swapped without detection.
+///
+/// An encrypted volume must only be used through this type.
+/// In particular, [`Volume::verify_all`] would report
+/// every object in an encrypted volume as corrupt.
+pub struct EncryptedVolume
+{
+    inner: Volume,
+    key: Key,
+}
+
+impl Volume
+{
+    /// Open the encrypted volume at the given path.
+    ///
+    /// See [`Volume::open`] for more information.
+    /// The key is not checked when opening the volume;
+    /// using the wrong key causes reads to fail.
+    pub fn open_encrypted(path: impl AsRef<Path>, key: Key)
+        -> Result<EncryptedVolume>
+    {
+        let inner = Volume::open(path)?;
+        Ok(EncryptedVolume{inner, key})
+    }
+}
+
+impl EncryptedVolume
+{
+    /// Encrypt the given bytes into the volume, and return their hash.
+    pub fn insert_from_bytes(&self, mut bytes: &[u8]) -> Result<Hash>
+    {
+        self.insert_from_reader(&mut bytes)
+    }
+
+    /// Encrypt the bytes of the given reader into the volume,
+    /// and return their hash.
+    ///
+    /// The bytes are hashed and encrypted while they are copied.
+    /// If the object already exists in the volume,
+    /// the existing file is retained, and the new file is discarded.
+    pub fn insert_from_reader(&self, reader: &mut impl Read) -> Result<Hash>
+    {
+        let mut tmpfile = self.inner.create_tmpfile()?;
+        let mut hasher = self.inner.hash_algorithm.hasher();
+
+        let (mut encryptor, header) = Encryptor::new(&self.key);
+        tmpfile.write_all(&header)?;
+
+        // Read one chunk ahead, so that we know
+        // which chunk is the final chunk.
+        let mut pending = read_chunk(reader)?;
+        loop {
+            hasher.update(&pending);
+            let next = read_chunk(reader)?;
+            if next.is_empty() {
+                break;
+            }
+            tmpfile.write_all(&encryptor.push(&pending, b"", false))?;
+            pending = next;
+        }
+
+        let hash = hasher.finalize();
+        tmpfile.write_all(&encryptor.push(&pending, &hash.bytes, true))?;
+
+        self.inner.link_object(&tmpfile, hash)?;
+        Ok(hash)
+    }
+
+    /// Retrieve a decrypting reader for an object’s byte array,
+    /// as well as the size of the object in bytes.
+    ///
+    /// If the object does not exist, this method returns [`None`].
+    /// If the object file was tampered with, or the key is wrong,
+    /// reading fails with [`InvalidData`].
+    pub fn get(&self, hash: Hash) -> Result<Option<(impl Read, u64)>>
+    {
+        let (mut file, file_size) = match self.inner.get(hash)? {
+            Some(object) => object,
+            None => return Ok(None),
+        };
+
+        let size = plaintext_size(file_size)?;
+
+        let mut header = [0; HEADERBYTES];
+        file.read_exact(&mut header)?;
+        let decryptor = Decryptor::new(&self.key, &header)
+            .map_err(|_| decrypt_error())?;
+
+        let reader = DecryptingReader{
+            file, decryptor, hash,
+            remaining: file_size - HEADERBYTES as u64,
+            chunk: Vec::new(), offset: 0, finished: false,
+        };
+        Ok(Some((reader, size)))
+    }
+
+    /// See [`Volume::contains`].
+    pub fn contains(&self, hash: Hash) -> Result<bool>
+    {
+        self.inner.contains(hash)
+    }
+
+    /// See [`Volume::all`].
+    pub fn all(&self) -> Result<impl Iterator<Item=Result<Hash>>>
+    {
+        self.inner.all()
+    }
+
+    /// See [`Volume::remove`].
+    pub fn remove(&self, hash: Hash) -> Result<bool>
+    {
+        self.inner.remove(hash)
+    }
+}
+
+/// Reader that decrypts an object file chunk by chunk.
+struct DecryptingReader<R>
+{
+    file: R,
+    decryptor: Decryptor,
+    hash: Hash,
+
+    /// The number of encrypted bytes that have not yet been read.
+    remaining: u64,
+
+    /// The current decrypted chunk and the offset into it.
+    chunk: Vec<u8>,
+    offset: usize,
+
+    /// Whether the final chunk was decrypted.
+    finished: bool,
+}
+
+impl<R> Read for DecryptingReader<R>
+    where R: Read
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>
+    {
+        while self.offset == self.chunk.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            self.next_chunk()?;
+        }
+
+        let n = buf.len().min(self.chunk.len() - self.offset);
+        buf[.. n].copy_from_slice(&self.chunk[self.offset .. self.offset + n]);
+        self.offset += n;
+        Ok(n)
+    }
+}
+
+impl<R> DecryptingReader<R>
+    where R: Read
+{
+    fn next_chunk(&mut self) -> Result<()>
+    {
+        let chunk_size = self.remaining.min((CHUNK_SIZE + ABYTES) as u64);
+        let mut encrypted = vec![0; chunk_size as usize];
+        self.file.read_exact(&mut encrypted)?;
+        self.remaining -= chunk_size;
+
+        // Which chunk is final follows from the size of the file.
+        // Only the final chunk is authenticated with the hash.
+        let last = self.remaining == 0;
+        let ad: &[u8] = if last { &self.hash.bytes } else { b"" };
+
+        let (chunk, finished) = self.decryptor.pull(&encrypted, ad)
+            .map_err(|_| decrypt_error())?;
+        if finished != last {
+            return Err(decrypt_error());
+        }
+
+        self.chunk    = chunk;
+        self.offset   = 0;
+        self.finished = finished;
+        Ok(())
+    }
+}
+
+/// Compute the size of the plaintext of an object file.
+fn plaintext_size(file_size: u64) -> Result<u64>
+{
+    let chunk_size = (CHUNK_SIZE + ABYTES) as u64;
+    let body_size = file_size.checked_sub(HEADERBYTES as u64)
+        .filter(|&size| size >= ABYTES as u64)
+        .ok_or_else(decrypt_error)?;
+    let chunks = body_size.div_ceil(chunk_size);
+    Ok(body_size - chunks * ABYTES as u64)
+}
+
+/// Read up to a chunk of bytes from a reader.
+fn read_chunk(reader: &mut impl Read) -> Result<Vec<u8>>
+{
+    let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+    reader.take(CHUNK_SIZE as u64).read_to_end(&mut chunk)?;
+    Ok(chunk)
+}
+
+fn decrypt_error() -> Error
+{
+    Error::new(InvalidData, "encrypted object could not be decrypted")
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use std::fs;
+    use super::*;
+
+    #[test]
+    fn test_encrypted()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_encrypted").unwrap();
+        let key = Key::generate();
+        let volume = Volume::open_encrypted(&test_data.volume1_path,
+                                            key.clone()).unwrap();
+        let large: Vec<u8> = (0 .. 3 * CHUNK_SIZE as u32)
+            .map(|i| i as u8).collect();
+
+        // Insert the objects.
+        let hash1 = volume.insert_from_bytes(&test_data.regular1_contents)
+            .unwrap();
+        let hash2 = volume.insert_from_bytes(&large).unwrap();
+        let hash3 = volume.insert_from_bytes(b"").unwrap();
+
+        // Check the results.
+        let read = |volume: &EncryptedVolume, hash| {
+            let (mut reader, size) = volume.get(hash)?.unwrap();
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data)?;
+            assert_eq!(size, data.len() as u64);
+            Ok::<_, Error>(data)
+        };
+        assert_eq!(hash1, test_data.regular1_hash);
+        assert_eq!(hash2, Hash::compute_from_bytes(&large));
+        assert_eq!(read(&volume, hash1).unwrap(), test_data.regular1_contents);
+        assert_eq!(read(&volume, hash2).unwrap(), large);
+        assert_eq!(read(&volume, hash3).unwrap(), b"");
+
+        // The plaintext must not be stored.
+        let path1 = test_data.volume1_path.join(format!("objects/{}", hash1));
+        assert_ne!(fs::read(&path1).unwrap(), test_data.regular1_contents);
+
+        // Reading with the wrong key must fail.
+        let wrong = Volume::open_encrypted(&test_data.volume1_path,
+                                           Key::generate()).unwrap();
+        let error = read(&wrong, hash1).unwrap_err();
+        assert_eq!(error.kind(), InvalidData);
+
+        // Swapping object files must be detected.
+        let path3 = test_data.volume1_path.join(format!("objects/{}", hash3));
+        fs::remove_file(&path3).unwrap();
+        fs::hard_link(&path1, &path3).unwrap();
+        let error = read(&volume, hash3).unwrap_err();
+        assert_eq!(error.kind(), InvalidData);
+    }
+}
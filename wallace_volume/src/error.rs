@@ -0,0 +1,209 @@
+use crate::Hash;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::io::ErrorKind;
+use wallace_fsutil::FsError;
+
+/// Failure of a volume operation, by cause.
+///
+/// Volume methods return [`io::Error`]s, so that they compose
+/// with the standard library and with each other.
+/// Failures specific to volumes are wrapped in them,
+/// and can be recovered with the [`From<io::Error>`] impl,
+/// which turns any other error into [`VolumeError::Io`]:
+///
+/// ```no_run
+/// # use wallace_volume::{Volume, VolumeError};
+/// match Volume::open("/srv/volume").map_err(VolumeError::from) {
+///     Ok(volume) => { /* ... */ },
+///     Err(VolumeError::LayoutVersionMismatch{..}) => { /* too new */ },
+///     Err(err) => return Err(err),
+/// }
+/// # Ok::<(), VolumeError>(())
+/// ```
+///
+/// Exceeding the quota is reported with [`QuotaExceeded`] instead.
+///
+/// [`QuotaExceeded`]: `crate::QuotaExceeded`
+#[derive(Debug)]
+pub enum VolumeError
+{
+    /// A file that backs an object is not a regular file,
+    /// which means that the volume is corrupt.
+    ///
+    /// As it was before this type existed, this is reported
+    /// as an [`io::Error`] with the `errno` value `EISDIR`,
+    /// so that [`io::Error::raw_os_error`] keeps working.
+    /// Conversely, any [`io::Error`] with that value
+    /// converts back into this variant.
+    NotRegularFile,
+
+    /// The directory is not a volume.
+    NotAVolume,
+
+    /// The contents of an object do not match its hash.
+    CorruptObject
+    {
+        /// The hash of the object.
+        hash: Hash,
+
+        /// The hash of the contents of the object.
+        actual: Hash,
+    },
+
+    /// The volume has a layout version newer than the current one,
+    /// so it was created by a newer version of this crate.
+    LayoutVersionMismatch
+    {
+        /// The layout version of the volume.
+        found: u32,
+
+        /// The layout version of this version of the crate.
+        supported: u32,
+    },
+
+    /// Any other error.
+    Io(io::Error),
+}
+
+impl VolumeError
+{
+    /// The kind of the [`io::Error`] that wraps this error.
+    pub fn kind(&self) -> ErrorKind
+    {
+        match self {
+            Self::NotRegularFile            => ErrorKind::IsADirectory,
+            Self::NotAVolume                => ErrorKind::InvalidData,
+            Self::CorruptObject{..}         => ErrorKind::InvalidData,
+            Self::LayoutVersionMismatch{..} => ErrorKind::InvalidData,
+            Self::Io(err)                   => err.kind(),
+        }
+    }
+
+    /// The closest `errno` value for this error, if any.
+    ///
+    /// Non-regular files are reported as `EISDIR`,
+    /// as they were before this type existed.
+    pub fn raw_os_error(&self) -> Option<i32>
+    {
+        match self {
+            Self::NotRegularFile => Some(libc::EISDIR),
+            Self::Io(err) => FsError::raw_os_error_of(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for VolumeError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self {
+            Self::NotRegularFile =>
+                write!(f, "file backing object is not a regular file"),
+            Self::NotAVolume =>
+                write!(f, "directory is not a volume"),
+            Self::CorruptObject{hash, actual} =>
+                write!(f, "object {} is corrupt; its contents hash to {}",
+                       hash, actual),
+            Self::LayoutVersionMismatch{found, supported} =>
+                write!(f, "volume layout version {} is newer than \
+                           supported layout version {}", found, supported),
+            Self::Io(err) =>
+                err.fmt(f),
+        }
+    }
+}
+
+impl Error for VolumeError
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)>
+    {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for VolumeError
+{
+    fn from(err: io::Error) -> Self
+    {
+        if err.raw_os_error() == Some(libc::EISDIR) {
+            Self::NotRegularFile
+        } else if err.get_ref().is_some_and(|e| e.is::<VolumeError>()) {
+            let inner = err.into_inner().unwrap();
+            *inner.downcast::<VolumeError>().unwrap()
+        } else {
+            Self::Io(err)
+        }
+    }
+}
+
+impl From<VolumeError> for io::Error
+{
+    fn from(err: VolumeError) -> Self
+    {
+        match err {
+            VolumeError::NotRegularFile =>
+                io::Error::from_raw_os_error(libc::EISDIR),
+            VolumeError::Io(err) => err,
+            err => io::Error::new(err.kind(), err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::LAYOUT_VERSION;
+    use crate::TestData;
+    use crate::Volume;
+    use std::fs;
+    use std::io::Read;
+    use std::os::unix::fs::PermissionsExt;
+    use super::*;
+
+    #[test]
+    fn test_volume_error()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_volume_error").unwrap();
+        let mut volume = Volume::open(&test_data.volume1_path).unwrap();
+        volume.set_verify_on_read(true);
+        let hash = volume.insert_from_bytes(b"Corrupt me!").unwrap();
+
+        // Corrupt an object and the layout of a volume.
+        let object_path = test_data.volume1_path.join("objects")
+            .join(hash.to_string());
+        fs::set_permissions(&object_path, fs::Permissions::from_mode(0o600))
+            .unwrap();
+        fs::write(&object_path, b"Corrupted!!").unwrap();
+        fs::write(test_data.volume2_path.join("layout-version"), "1000\n")
+            .unwrap();
+
+        // Cause the errors.
+        let (mut reader, _) = volume.get(hash).unwrap().unwrap();
+        let err1 = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        let err2 = Volume::open(&test_data.volume2_path).err().unwrap();
+        let err3 = Volume::open(&test_data.root_path).err().unwrap();
+        let err4 = Volume::open(test_data.root_path.join("nope"))
+            .err().unwrap();
+
+        // Check the results.
+        let actual = Hash::compute_from_bytes(b"Corrupted!!");
+        assert_eq!(err1.kind(), ErrorKind::InvalidData);
+        assert!(matches!(VolumeError::from(err1),
+                         VolumeError::CorruptObject{hash: h, actual: a}
+                             if h == hash && a == actual));
+        assert!(matches!(VolumeError::from(err2),
+                         VolumeError::LayoutVersionMismatch{
+                             found: 1000,
+                             supported: LAYOUT_VERSION,
+                         }));
+        assert!(matches!(VolumeError::from(err3), VolumeError::NotAVolume));
+        assert!(matches!(VolumeError::from(err4), VolumeError::Io(_)));
+    }
+}
@@ -0,0 +1,101 @@
+use crate::Hash;
+use crate::Volume;
+
+/// Change to a volume, passed to observers.
+///
+/// See [`Volume::add_observer`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum VolumeEvent
+{
+    /// An object was inserted that was not in the volume before.
+    /// Inserts of objects that already exist do not produce events.
+    Inserted
+    {
+        /// The hash of the object.
+        hash: Hash,
+
+        /// The size of the object in bytes.
+        size: u64,
+    },
+
+    /// An object was removed from the volume.
+    Removed
+    {
+        /// The hash of the object.
+        hash: Hash,
+
+        /// The size of the object in bytes.
+        size: u64,
+    },
+}
+
+pub (crate) type Observer = Box<dyn Fn(&VolumeEvent) + Send + Sync>;
+
+impl Volume
+{
+    /// Register a callback that is called after each change
+    /// made to the volume through this handle.
+    ///
+    /// This saves indexers and metrics exporters
+    /// from having to poll [`Volume::all`].
+    /// The callback is called on the thread that made the change,
+    /// after the change is complete; it should return quickly.
+    /// Changes made through other handles are not observed.
+    pub fn add_observer<F>(&mut self, observer: F)
+        where F: 'static + Fn(&VolumeEvent) + Send + Sync
+    {
+        self.observers.push(Box::new(observer));
+    }
+
+    /// Whether any observers are registered.
+    pub (crate) fn is_observed(&self) -> bool
+    {
+        !self.observers.is_empty()
+    }
+
+    /// Call the observers with the given event.
+    pub (crate) fn notify(&self, event: VolumeEvent)
+    {
+        for observer in &self.observers {
+            observer(&event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use std::io::Write;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use super::*;
+
+    #[test]
+    fn test_observer()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_observer").unwrap();
+        let mut volume = Volume::open(&test_data.volume1_path).unwrap();
+        volume.set_pack_threshold(4);
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let events_clone = events.clone();
+        volume.add_observer(move |e| events_clone.lock().unwrap().push(*e));
+
+        // Insert and remove the objects.
+        let hash1 = volume.insert_from_bytes(b"Hello").unwrap();
+        volume.insert_from_bytes(b"Hello").unwrap();
+        let mut writer = volume.start_insert().unwrap();
+        writer.write_all(b"tiny").unwrap();
+        let hash2 = writer.finish().unwrap();
+        volume.remove(hash1).unwrap();
+        volume.remove(hash1).unwrap();
+
+        // Check the results.
+        assert_eq!(*events.lock().unwrap(), [
+            VolumeEvent::Inserted{hash: hash1, size: 5},
+            VolumeEvent::Inserted{hash: hash2, size: 4},
+            VolumeEvent::Removed{hash: hash1, size: 5},
+        ]);
+    }
+}
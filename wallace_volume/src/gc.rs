@@ -0,0 +1,224 @@
+use crate::Hash;
+use crate::ManifestEntryKind;
+use crate::Volume;
+use std::collections::HashSet;
+use std::io::Result;
+
+/// Set of objects that garbage collection must retain.
+///
+/// Roots can be registered individually with [`GcRoots::add`],
+/// or in bulk by a callback registered with [`GcRoots::add_callback`].
+/// The callbacks are invoked once per garbage collection,
+/// which is useful when the set of reachable objects
+/// is stored elsewhere, for instance in a database.
+///
+/// Roots that are [manifests][`crate::Manifest`] retain
+/// the entire trees they describe, so registering the hash
+/// returned by [`Volume::import_manifest`] is enough to retain a tree.
+#[derive(Default)]
+pub struct GcRoots<'a>
+{
+    hashes: Vec<Hash>,
+    callbacks: Vec<RootsCallback<'a>>,
+}
+
+type RootsCallback<'a> =
+    Box<dyn 'a + FnMut(&mut dyn FnMut(Hash)) -> Result<()>>;
+
+impl<'a> GcRoots<'a>
+{
+    /// Create an empty set of roots.
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    /// Register an object as a root.
+    pub fn add(&mut self, hash: Hash)
+    {
+        self.hashes.push(hash);
+    }
+
+    /// Register a callback that yields roots.
+    ///
+    /// The callback is given a function that it must call
+    /// for each object that is to be retained.
+    /// If the callback returns an error,
+    /// garbage collection is aborted before anything is removed.
+    pub fn add_callback<F>(&mut self, callback: F)
+        where F: 'a + FnMut(&mut dyn FnMut(Hash)) -> Result<()>
+    {
+        self.callbacks.push(Box::new(callback));
+    }
+}
+
+/// Returned by [`Volume::collect_garbage`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct GcReport
+{
+    /// The objects that were removed,
+    /// or would have been removed in a dry run.
+    pub garbage: Vec<Hash>,
+
+    /// The total size in bytes of the garbage objects.
+    pub garbage_bytes: u64,
+}
+
+impl Volume
+{
+    /// Remove every object that is not a root.
+    ///
+    /// If `dry_run` is set, no objects are removed,
+    /// but the report still lists the objects that would be removed.
+    ///
+    /// Objects inserted while garbage collection is running
+    /// may be removed if they are not roots.
+    /// Make sure that anything that inserts objects
+    /// also registers them as roots,
+    /// or does not run concurrently with garbage collection.
+    ///
+    /// Packed objects are never removed;
+    /// see [`Volume::set_pack_threshold`].
+    /// Neither are pinned objects; see [`Volume::pin`].
+    pub fn collect_garbage(&self, roots: GcRoots, dry_run: bool)
+        -> Result<GcReport>
+    {
+        let GcRoots{hashes, mut callbacks} = roots;
+
+        // Gather all roots before touching anything,
+        // so that a failing callback does not cause a partial collection.
+        let mut retain = HashSet::new();
+        retain.extend(hashes.iter().map(|h| h.bytes));
+        retain.extend(self.pinned()?.iter().map(|h| h.bytes));
+        for callback in &mut callbacks {
+            callback(&mut |h| { retain.insert(h.bytes); })?;
+        }
+        self.retain_manifest_trees(&mut retain)?;
+
+        // Collect the garbage before removing anything,
+        // as removing entries while reading the directory
+        // may cause entries to be skipped.
+        let is_garbage = |h: &Hash| !retain.contains(&h.bytes);
+        let garbage =
+            self.all_loose()?
+            .filter(|r| r.as_ref().map_or(true, is_garbage))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut report = GcReport::default();
+        for hash in garbage {
+            let size = match self.stat(hash)? {
+                Some(stat) => stat.size,
+                None => continue,
+            };
+            if dry_run || self.remove(hash)? {
+                report.garbage.push(hash);
+                report.garbage_bytes += size;
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Retain the objects in the trees of the manifests that are retained.
+    ///
+    /// Every retained object is checked for being a manifest,
+    /// but only subdirectories are checked among the entries,
+    /// as files are never manifests as far as the tree is concerned.
+    fn retain_manifest_trees(&self, retain: &mut HashSet<[u8; 32]>)
+        -> Result<()>
+    {
+        let mut pending: Vec<Hash> =
+            retain.iter().map(|&bytes| Hash{bytes}).collect();
+        while let Some(hash) = pending.pop() {
+            let manifest = match self.sniff_manifest(hash)? {
+                Some(manifest) => manifest,
+                None => continue,
+            };
+            for entry in manifest.entries {
+                let new = retain.insert(entry.hash.bytes);
+                if new && entry.kind == ManifestEntryKind::Directory {
+                    pending.push(entry.hash);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::ImportOptions;
+    use crate::TestData;
+    use std::fs;
+    use super::*;
+
+    #[test]
+    fn test_collect_garbage()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_collect_garbage").unwrap();
+        let volume = Volume::open(test_data.volume1_path).unwrap();
+
+        // Insert the objects.
+        let hash1 = volume.insert_from_path(&test_data.regular1_path).unwrap();
+        let hash2 = volume.insert_from_path(&test_data.regular2_path).unwrap();
+        let hash3 = volume.insert_from_reader(&mut &b"garbage"[..]).unwrap();
+
+        // Collect the garbage, first in a dry run.
+        let roots = || {
+            let mut roots = GcRoots::new();
+            roots.add(hash1);
+            roots.add_callback(move |reach| { reach(hash2); Ok(()) });
+            roots
+        };
+        let report1 = volume.collect_garbage(roots(), true).unwrap();
+        let exists1 = volume.get(hash3).unwrap().is_some();
+        let report2 = volume.collect_garbage(roots(), false).unwrap();
+        let exists2 = volume.get(hash3).unwrap().is_some();
+        let report3 = volume.collect_garbage(roots(), false).unwrap();
+
+        // Check the results.
+        let expected = GcReport{garbage: vec![hash3], garbage_bytes: 7};
+        assert_eq!(report1, expected);
+        assert_eq!(report2, expected);
+        assert_eq!(report3, GcReport::default());
+        assert!(exists1);
+        assert!(!exists2);
+        assert!(volume.get(hash1).unwrap().is_some());
+        assert!(volume.get(hash2).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_collect_garbage_manifest()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_collect_garbage_manifest")
+            .unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+        let tree_path = test_data.root_path.join("tree");
+        fs::create_dir_all(tree_path.join("a/b")).unwrap();
+        fs::write(tree_path.join("x"), b"x").unwrap();
+        fs::write(tree_path.join("a/b/y"), b"y").unwrap();
+
+        // Store the tree, and root only its manifest.
+        let options = ImportOptions::default();
+        let root = volume.import_manifest(&tree_path, &options).unwrap();
+        let garbage = volume.insert_from_bytes(b"garbage").unwrap();
+        let mut roots = GcRoots::new();
+        roots.add(root);
+        let report = volume.collect_garbage(roots, false).unwrap();
+
+        // Walk the tree back.
+        let mut walked = Vec::new();
+        volume.walk_manifest(root, |path, entry| {
+            assert!(volume.contains(entry.hash)?);
+            walked.push(path.to_path_buf());
+            Ok(())
+        }).unwrap();
+
+        // Check the results.
+        assert_eq!(report.garbage, [garbage]);
+        assert_eq!(walked.len(), 4);
+    }
+}
@@ -15,19 +15,45 @@ use wallace_sha256::Sha256;
 /// The [`FromStr`] impl parses this same format.
 /// This hexadecimal format is used consistently
 /// when hashes need to be communicated as text.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+/// Front ends that need shorter names can use the base32 and base64url
+/// formats instead; see [`Hash::to_base32`] and [`Hash::to_base64url`].
+/// To record which hash function computed a hash,
+/// use [`TaggedHash`][`crate::TaggedHash`].
+///
+/// Hashes are ordered by their bytes,
+/// which is also the order of their hexadecimal formats.
+/// With the `serde` feature, hashes also implement
+/// `Serialize` and `Deserialize`, using this format
+/// in human-readable formats, and raw bytes in binary formats.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Hash
 {
     /// The bytes that make up the hash.
     /// These are _not_ the bytes that the hash was computed from;
     /// those bytes cannot be recovered from the hash alone.
-    /// You typically do not need to access this field.
+    /// You typically do not need to access this field;
+    /// prefer [`Hash::as_bytes`] and [`Hash::from_bytes`].
     pub bytes: [u8; 32],
 }
 
 impl Hash
 {
-    /// Compute the hash of the given bytes.
+    /// Create a hash from the bytes that make it up.
+    pub const fn from_bytes(bytes: [u8; 32]) -> Self
+    {
+        Self{bytes}
+    }
+
+    /// The bytes that make up the hash.
+    pub fn as_bytes(&self) -> &[u8; 32]
+    {
+        &self.bytes
+    }
+
+    /// Compute the SHA-256 hash of the given bytes.
+    ///
+    /// Volumes may use another hash function;
+    /// see [`HashAlgorithm`][`crate::HashAlgorithm`].
     pub fn compute_from_bytes(b: &[u8]) -> Self
     {
         let mut sha256 = Sha256::new();
@@ -36,7 +62,7 @@ impl Hash
         Self{bytes}
     }
 
-    /// Read all bytes from the reader and compute their hash.
+    /// Read all bytes from the reader and compute their SHA-256 hash.
     pub fn compute_from_reader(r: &mut impl io::Read) -> io::Result<Self>
     {
         let mut sha256 = Sha256::new();
@@ -45,30 +71,135 @@ impl Hash
         Ok(Self{bytes})
     }
 
+    /// Compare two hashes in constant time.
+    ///
+    /// The [`PartialEq`] impl stops at the first byte that differs,
+    /// so the time it takes reveals how many leading bytes match.
+    /// When one of the hashes comes from untrusted input
+    /// and the other must not be revealed, use this method instead.
+    pub fn ct_eq(&self, other: &Hash) -> bool
+    {
+        wallace_sha256::ct_eq(&self.bytes, &other.bytes)
+    }
+
     /// Similar to the [`FromStr`] impl,
     /// but takes `[u8]` instead of [`str`].
     pub fn from_ascii(s: &[u8]) -> Result<Self, InvalidHash>
     {
         if s.len() != 64 {
-            return Err(InvalidHash);
+            return Err(InvalidHash::InvalidLength{found: s.len(),
+                                                  expected: 64});
         }
 
-        fn hex(c: u8) -> Result<u8, InvalidHash>
-        {
-            match c {
-                b'0' ..= b'9' => Ok(c - b'0'),
-                b'a' ..= b'f' => Ok(c - b'a' + 10),
-                _ => Err(InvalidHash),
+        let hex = |position: usize| -> Result<u8, InvalidHash> {
+            match s[position] {
+                c @ b'0' ..= b'9' => Ok(c - b'0'),
+                c @ b'a' ..= b'f' => Ok(c - b'a' + 10),
+                byte => Err(InvalidHash::InvalidCharacter{position, byte}),
             }
-        }
+        };
 
         let mut bytes = [0; 32];
-        for (i, pair) in s.chunks(2).enumerate() {
-            bytes[i] = hex(pair[0])? << 4 | hex(pair[1])?;
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = hex(2 * i)? << 4 | hex(2 * i + 1)?;
         }
 
         Ok(Self{bytes})
     }
+
+    /// Format the hash as 52 characters of base32,
+    /// using the lowercase alphabet from RFC 4648, without padding.
+    pub fn to_base32(&self) -> String
+    {
+        encode_bits(&self.bytes, 5, BASE32)
+    }
+
+    /// Parse the format produced by [`Hash::to_base32`].
+    ///
+    /// Uppercase letters, padding, and unused bits
+    /// that are not zero are rejected,
+    /// so that every hash has exactly one base32 format.
+    pub fn from_base32(s: &str) -> Result<Self, InvalidHash>
+    {
+        decode_bits(s.as_bytes(), 5, BASE32)
+    }
+
+    /// Format the hash as 43 characters of base64url,
+    /// using the alphabet from RFC 4648, without padding.
+    pub fn to_base64url(&self) -> String
+    {
+        encode_bits(&self.bytes, 6, BASE64URL)
+    }
+
+    /// Parse the format produced by [`Hash::to_base64url`].
+    ///
+    /// Padding and unused bits that are not zero are rejected,
+    /// so that every hash has exactly one base64url format.
+    pub fn from_base64url(s: &str) -> Result<Self, InvalidHash>
+    {
+        decode_bits(s.as_bytes(), 6, BASE64URL)
+    }
+}
+
+/// Alphabet of the base32 format, from RFC 4648, in lowercase.
+const BASE32: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// Alphabet of the base64url format, from RFC 4648.
+const BASE64URL: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Encode bytes with an alphabet of `1 << width` characters.
+fn encode_bits(bytes: &[u8], width: u32, alphabet: &[u8]) -> String
+{
+    let mask = (1 << width) - 1;
+    let mut out = String::new();
+    let (mut buf, mut bits) = (0u32, 0);
+    for &byte in bytes {
+        buf = buf << 8 | byte as u32;
+        bits += 8;
+        while bits >= width {
+            bits -= width;
+            out.push(alphabet[(buf >> bits & mask) as usize] as char);
+        }
+        buf &= (1 << bits) - 1;
+    }
+    if bits != 0 {
+        out.push(alphabet[(buf << (width - bits) & mask) as usize] as char);
+    }
+    out
+}
+
+/// Decode a hash encoded by [`encode_bits`].
+fn decode_bits(s: &[u8], width: u32, alphabet: &[u8])
+    -> Result<Hash, InvalidHash>
+{
+    let expected = 256_usize.div_ceil(width as usize);
+    if s.len() != expected {
+        return Err(InvalidHash::InvalidLength{found: s.len(), expected});
+    }
+
+    let mut bytes = [0; 32];
+    let mut i = 0;
+    let (mut buf, mut bits) = (0u32, 0);
+    for (position, &byte) in s.iter().enumerate() {
+        let invalid = InvalidHash::InvalidCharacter{position, byte};
+        let value = alphabet.iter().position(|&a| a == byte)
+            .ok_or(invalid)?;
+        buf = buf << width | value as u32;
+        bits += width;
+        if bits >= 8 {
+            bits -= 8;
+            bytes[i] = (buf >> bits) as u8;
+            buf &= (1 << bits) - 1;
+            i += 1;
+        }
+        // The unused bits of the last character must be zero.
+        if position == expected - 1 && buf != 0 {
+            return Err(invalid);
+        }
+    }
+
+    Ok(Hash{bytes})
 }
 
 impl fmt::Display for Hash
@@ -83,8 +214,48 @@ impl fmt::Display for Hash
 }
 
 /// Returned when a hash could not be parsed.
-#[derive(Clone, Copy, Debug)]
-pub struct InvalidHash;
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InvalidHash
+{
+    /// The text does not have the length of a hash in its format.
+    InvalidLength
+    {
+        /// The length of the text, in bytes.
+        found: usize,
+
+        /// The length of a hash in the format, in bytes.
+        expected: usize,
+    },
+
+    /// The text contains a byte that is not valid in its format.
+    InvalidCharacter
+    {
+        /// The offset of the byte in the text.
+        position: usize,
+
+        /// The byte itself.
+        byte: u8,
+    },
+}
+
+impl fmt::Display for InvalidHash
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self {
+            Self::InvalidLength{found, expected} =>
+                write!(f, "hash has length {} instead of {}",
+                       found, expected),
+            Self::InvalidCharacter{position, byte} =>
+                write!(f, "hash has invalid character '{}' at position {}",
+                       std::ascii::escape_default(*byte), position),
+        }
+    }
+}
+
+impl std::error::Error for InvalidHash
+{
+}
 
 impl FromStr for Hash
 {
@@ -96,12 +267,163 @@ impl FromStr for Hash
     }
 }
 
+/// Hashes are serialized in the hexadecimal format
+/// in human-readable formats such as JSON,
+/// and as 32 raw bytes in binary formats.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Hash
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: serde::Serializer
+    {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            serializer.serialize_bytes(&self.bytes)
+        }
+    }
+}
+
+/// See the [`Serialize`][`serde::Serialize`] impl for the formats.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Hash
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: serde::Deserializer<'de>
+    {
+        use serde::de::Error;
+
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor
+        {
+            type Value = Hash;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result
+            {
+                write!(f, "a hash")
+            }
+
+            fn visit_str<E: Error>(self, v: &str) -> Result<Hash, E>
+            {
+                v.parse().map_err(|_| E::invalid_value(
+                    serde::de::Unexpected::Str(v), &self))
+            }
+
+            fn visit_bytes<E: Error>(self, v: &[u8]) -> Result<Hash, E>
+            {
+                if v.len() != 32 {
+                    return Err(E::invalid_length(v.len(), &self));
+                }
+                let mut bytes = [0; 32];
+                bytes.copy_from_slice(v);
+                Ok(Hash{bytes})
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Hash, A::Error>
+                where A: serde::de::SeqAccess<'de>
+            {
+                let mut bytes = [0; 32];
+                for (i, byte) in bytes.iter_mut().enumerate() {
+                    *byte = seq.next_element()?
+                        .ok_or_else(|| A::Error::invalid_length(i, &self))?;
+                }
+                if seq.next_element::<u8>()?.is_some() {
+                    return Err(A::Error::invalid_length(33, &self));
+                }
+                Ok(Hash{bytes})
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(Visitor)
+        } else {
+            deserializer.deserialize_bytes(Visitor)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests
 {
     use super::*;
     use std::io::Cursor;
 
+    #[test]
+    fn test_ord()
+    {
+        let hash1 = Hash::from_bytes([0x01; 32]);
+        let hash2 = Hash::from_bytes([0x10; 32]);
+        let mut hashes = vec![hash2, hash1];
+        hashes.sort();
+        assert_eq!(hashes, [hash1, hash2]);
+        assert!(hash1.to_string() < hash2.to_string());
+        assert_eq!(hash2.as_bytes(), &[0x10; 32]);
+    }
+
+    #[test]
+    fn test_invalid_hash()
+    {
+        let mut text = "0".repeat(64);
+        assert_eq!(text[1 ..].parse::<Hash>(),
+                   Err(InvalidHash::InvalidLength{found: 63, expected: 64}));
+        text.replace_range(9 .. 10, "G");
+        let err = text.parse::<Hash>().unwrap_err();
+        assert_eq!(err, InvalidHash::InvalidCharacter{position: 9,
+                                                      byte: b'G'});
+        assert_eq!(err.to_string(),
+                   "hash has invalid character 'G' at position 9");
+        assert_eq!(Hash::from_ascii(&[0xFF; 64]).unwrap_err().to_string(),
+                   "hash has invalid character '\\xff' at position 0");
+
+        let boxed: Box<dyn std::error::Error> = err.into();
+        assert!(boxed.is::<InvalidHash>());
+    }
+
+    #[test]
+    fn test_base32_base64url()
+    {
+        let hash = Hash::compute_from_bytes(b"Hello, world!");
+        let base32 = "gfpvxw3w2b4mio4kyade4sqbmrqswh6oo7egsnc37skmoweu5xjq";
+        let base64url = "MV9b23bQeMQ7isAGTkoBZGErH853yGk0W_yUx1iU7dM";
+
+        assert_eq!(hash.to_base32(), base32);
+        assert_eq!(hash.to_base64url(), base64url);
+        assert_eq!(Hash::from_base32(base32).unwrap(), hash);
+        assert_eq!(Hash::from_base64url(base64url).unwrap(), hash);
+
+        let zero = Hash::from_bytes([0; 32]);
+        let ones = Hash::from_bytes([0xFF; 32]);
+        for hash in &[zero, ones] {
+            let base32 = hash.to_base32();
+            let base64url = hash.to_base64url();
+            assert_eq!(Hash::from_base32(&base32).unwrap(), *hash);
+            assert_eq!(Hash::from_base64url(&base64url).unwrap(), *hash);
+        }
+
+        // Unused bits must be zero.
+        assert!(Hash::from_base32(&format!("{}b", &base32[.. 51])).is_err());
+        assert!(Hash::from_base64url(&format!("{}N", &base64url[.. 42]))
+            .is_err());
+
+        assert!(Hash::from_base32(&base32.to_uppercase()).is_err());
+        assert!(Hash::from_base32(&format!("{}====", base32)).is_err());
+        assert!(Hash::from_base64url(&format!("{}=", base64url)).is_err());
+        assert!(Hash::from_base64url(&base64url.replace('_', "/")).is_err());
+    }
+
+    #[test]
+    fn test_ct_eq()
+    {
+        let hash1 = Hash::compute_from_bytes(b"Hello");
+        let hash2 = Hash::compute_from_bytes(b"world");
+        let mut hash3 = hash1;
+        hash3.bytes[31] ^= 1;
+        assert!(hash1.ct_eq(&hash1));
+        assert!(!hash1.ct_eq(&hash2));
+        assert!(!hash1.ct_eq(&hash3));
+    }
+
     #[test]
     fn test_compute_from_reader()
     {
@@ -135,7 +457,7 @@ mod tests
                            "27ae41e4649b934ca495991b7852b855")),
 
             // Unparseable examples.
-            (false, concat!("")),
+            (false, ""),
             (false, concat!("E3B0C44298FC1C149AFBF4C8996FB924",
                             "27AE41E4649B934CA495991B7852B855")),
             (false, concat!("XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX",
@@ -156,4 +478,23 @@ mod tests
             }
         }
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde()
+    {
+        use serde_test::Configure;
+        use serde_test::Token;
+        use serde_test::assert_tokens;
+
+        let hash = Hash::compute_from_bytes(b"Hello, world!");
+        let hex = "315f5bdb76d078c43b8ac0064e4a0164\
+                   612b1fce77c869345bfc94c75894edd3";
+        let bytes = &[0x31, 0x5f, 0x5b, 0xdb, 0x76, 0xd0, 0x78, 0xc4,
+                      0x3b, 0x8a, 0xc0, 0x06, 0x4e, 0x4a, 0x01, 0x64,
+                      0x61, 0x2b, 0x1f, 0xce, 0x77, 0xc8, 0x69, 0x34,
+                      0x5b, 0xfc, 0x94, 0xc7, 0x58, 0x94, 0xed, 0xd3];
+        assert_tokens(&hash.readable(), &[Token::Str(hex)]);
+        assert_tokens(&hash.compact(), &[Token::Bytes(bytes)]);
+    }
 }
@@ -0,0 +1,178 @@
+use crate::Hash;
+use crate::Volume;
+use crate::layout;
+use std::ffi::OsStr;
+use std::ffi::OsString;
+use std::io::ErrorKind::NotFound;
+use std::io::ErrorKind::PermissionDenied;
+use std::io::Result;
+use std::os::unix::ffi::OsStrExt;
+use wallace_fsutil as fsutil;
+use wallace_fsutil::FileType;
+
+/// Findings of [`Volume::check`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HealthReport
+{
+    /// The layout version of the volume.
+    pub layout_version: u32,
+
+    /// Whether this process may insert objects into the volume.
+    pub writable: bool,
+
+    /// Entries in the objects directory not named after a hash.
+    /// These are ignored by the volume,
+    /// and can be removed with [`Volume::cleanup`].
+    pub stray_entries: Vec<OsString>,
+
+    /// Objects whose backing file is not a regular file.
+    /// Reading these fails with
+    /// [`NotRegularFile`][`crate::VolumeError::NotRegularFile`].
+    pub not_regular: Vec<Hash>,
+
+    /// Objects whose backing file is not read-only;
+    /// see [`Volume::enforce_readonly`].
+    pub not_readonly: Vec<Hash>,
+
+    /// Objects whose backing file has additional hard links;
+    /// see [`Volume::audit_links`].
+    pub aliased: Vec<Hash>,
+
+    /// The number of temporary files left in the `tmp` directory,
+    /// which may be in use by concurrent inserts.
+    pub temporaries: u64,
+}
+
+impl HealthReport
+{
+    /// Whether the check found nothing wrong with the objects.
+    ///
+    /// Whether the volume is writable,
+    /// and whether there are temporary files,
+    /// do not affect the outcome.
+    pub fn is_healthy(&self) -> bool
+    {
+        self.stray_entries.is_empty() &&
+        self.not_regular.is_empty() &&
+        self.not_readonly.is_empty() &&
+        self.aliased.is_empty()
+    }
+}
+
+impl Volume
+{
+    /// Check the volume for problems, without changing anything.
+    ///
+    /// This lists the objects directory once,
+    /// and stats every entry in it, without opening any files.
+    /// The contents of objects are not checked;
+    /// see [`Volume::verify_all`] for that.
+    /// Packed objects are not checked either.
+    pub fn check(&self) -> Result<HealthReport>
+    {
+        let mut report = HealthReport{
+            layout_version: layout::read_layout_version(&self.directory)?,
+            ..HealthReport::default()
+        };
+
+        report.writable = match fsutil::faccessat(&self.directory, "objects",
+                                                  libc::W_OK, 0) {
+            Ok(()) => true,
+            Err(err) if err.kind() == PermissionDenied => false,
+            Err(err) if err.raw_os_error() == Some(libc::EROFS) => false,
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut dir = fsutil::opendir_at(&self.directory, "objects")?;
+        while let Some(dirent) = fsutil::readdir(&mut dir)? {
+            let filename = dirent.d_name().to_bytes();
+            if filename == b"." || filename == b".." {
+                continue;
+            }
+            let hash = match Hash::from_ascii(filename) {
+                Ok(hash) => hash,
+                Err(_) => {
+                    let name = OsStr::from_bytes(filename).to_owned();
+                    report.stray_entries.push(name);
+                    continue;
+                },
+            };
+
+            let path = format!("objects/{}", hash);
+            let stat = match fsutil::fstatat(&self.directory, path,
+                                             libc::AT_SYMLINK_NOFOLLOW) {
+                Ok(stat) => stat,
+                Err(err) if err.kind() == NotFound => continue,
+                Err(err) => return Err(err.into()),
+            };
+            if FileType::from_st_mode(stat.st_mode) != FileType::Regular {
+                report.not_regular.push(hash);
+                continue;
+            }
+            if stat.st_mode & 0o7777 != 0o400 {
+                report.not_readonly.push(hash);
+            }
+            if stat.st_nlink > 1 {
+                report.aliased.push(hash);
+            }
+        }
+
+        let tmp = match fsutil::opendir_at(&self.directory, "tmp") {
+            Ok(tmp) => Some(tmp),
+            Err(err) if err.kind() == NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+        if let Some(mut dir) = tmp {
+            while let Some(dirent) = fsutil::readdir(&mut dir)? {
+                let filename = dirent.d_name().to_bytes();
+                if filename != b"." && filename != b".." {
+                    report.temporaries += 1;
+                }
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::LAYOUT_VERSION;
+    use crate::TestData;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use super::*;
+
+    #[test]
+    fn test_check()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_check").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+        let objects_path = test_data.volume1_path.join("objects");
+
+        // Insert the objects.
+        let hash1 = volume.insert_from_bytes(b"Hello").unwrap();
+        let hash2 = volume.insert_from_path(&test_data.regular1_path)
+            .unwrap();
+        let report1 = volume.check().unwrap();
+
+        // Damage the volume.
+        fs::set_permissions(objects_path.join(hash1.to_string()),
+                            fs::Permissions::from_mode(0o644)).unwrap();
+        fs::write(objects_path.join("stray"), b"").unwrap();
+        fs::create_dir(objects_path.join(test_data.regular2_hash.to_string()))
+            .unwrap();
+        let report2 = volume.check().unwrap();
+
+        // Check the results.
+        assert_eq!(report1.layout_version, LAYOUT_VERSION);
+        assert!(report1.writable);
+        assert_eq!(report1.aliased, [hash2]);
+        assert!(!report1.is_healthy());
+        assert_eq!(report2.stray_entries, [OsString::from("stray")]);
+        assert_eq!(report2.not_regular, [test_data.regular2_hash]);
+        assert_eq!(report2.not_readonly, [hash1]);
+    }
+}
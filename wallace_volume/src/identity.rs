@@ -0,0 +1,195 @@
+use crate::Volume;
+use std::fmt;
+use std::fs::File;
+use std::fs::rename;
+use std::fs::write;
+use std::io::Error;
+use std::io::ErrorKind::InvalidData;
+use std::io::ErrorKind::NotFound;
+use std::io::Read;
+use std::io::Result;
+use std::path::Path;
+use std::str::FromStr;
+use wallace_fsutil as fsutil;
+
+/// Identifier that tells volumes apart.
+///
+/// Each volume is given a random version 4 UUID when it is created,
+/// which is recorded in the file `volume-id` in the volume’s directory.
+/// Volumes created before identifiers were introduced have none,
+/// until they are given one by [`Volume::migrate`].
+/// The identifier survives renames of the directory,
+/// but copies of the directory share the identifier of the original.
+///
+/// The [`Display`][`fmt::Display`] impl formats the identifier
+/// in the usual hyphenated lowercase UUID format.
+/// The [`FromStr`] impl parses this same format.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct VolumeId
+{
+    /// The bytes that make up the UUID.
+    pub bytes: [u8; 16],
+}
+
+/// Returned when a volume identifier could not be parsed.
+#[derive(Clone, Copy, Debug)]
+pub struct InvalidVolumeId;
+
+impl VolumeId
+{
+    /// Generate a new random identifier.
+    pub fn generate() -> Result<Self>
+    {
+        let mut bytes = [0; 16];
+        File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+        bytes[6] = bytes[6] & 0x0F | 0x40;  // Version 4.
+        bytes[8] = bytes[8] & 0x3F | 0x80;  // RFC 4122 variant.
+        Ok(Self{bytes})
+    }
+}
+
+impl fmt::Display for VolumeId
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        for (i, byte) in self.bytes.iter().enumerate() {
+            if let 4 | 6 | 8 | 10 = i {
+                write!(f, "-")?;
+            }
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for VolumeId
+{
+    type Err = InvalidVolumeId;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err>
+    {
+        fn hex(c: u8) -> std::result::Result<u8, InvalidVolumeId>
+        {
+            match c {
+                b'0' ..= b'9' => Ok(c - b'0'),
+                b'a' ..= b'f' => Ok(c - b'a' + 10),
+                _ => Err(InvalidVolumeId),
+            }
+        }
+
+        // Hyphens must be exactly where Display puts them,
+        // and every other character must be a hex digit.
+        let s = s.as_bytes();
+        if s.len() != 36 {
+            return Err(InvalidVolumeId);
+        }
+        let mut digits = Vec::with_capacity(32);
+        for (i, &c) in s.iter().enumerate() {
+            match (i, c) {
+                (8 | 13 | 18 | 23, b'-') => (),
+                (8 | 13 | 18 | 23, _) => return Err(InvalidVolumeId),
+                _ => digits.push(hex(c)?),
+            }
+        }
+
+        let mut bytes = [0; 16];
+        for (byte, pair) in bytes.iter_mut().zip(digits.chunks_exact(2)) {
+            *byte = pair[0] << 4 | pair[1];
+        }
+
+        Ok(Self{bytes})
+    }
+}
+
+impl Volume
+{
+    /// The identifier of the volume,
+    /// or [`None`] if the volume predates identifiers.
+    pub fn id(&self) -> Option<VolumeId>
+    {
+        self.id
+    }
+}
+
+/// Read the identifier of the volume in the given directory,
+/// if the volume has one.
+pub (crate) fn read_volume_id(directory: &File) -> Result<Option<VolumeId>>
+{
+    let open_flags = libc::O_RDONLY | libc::O_CLOEXEC | libc::O_NOFOLLOW;
+    let mut file = match fsutil::openat(directory, "volume-id",
+                                        open_flags, 0) {
+        Ok(file) => file,
+        Err(err) if err.kind() == NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    contents.trim().parse().map(Some).map_err(|_| {
+        Error::new(InvalidData, "unparseable volume identifier")
+    })
+}
+
+/// Write the identifier of the volume at the given path.
+pub (crate) fn write_volume_id(path: &Path, id: VolumeId) -> Result<()>
+{
+    let tmp_path = path.join("volume-id.tmp");
+    write(&tmp_path, format!("{}\n", id))?;
+    rename(&tmp_path, path.join("volume-id"))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use std::fs;
+    use super::*;
+
+    #[test]
+    fn test_volume_id()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_volume_id").unwrap();
+        let renamed_path = test_data.root_path.join("renamed");
+
+        // Open the volumes, renaming one of them.
+        let id1 = Volume::open(&test_data.volume1_path).unwrap().id();
+        let id2 = Volume::open(&test_data.volume2_path).unwrap().id();
+        fs::rename(&test_data.volume1_path, &renamed_path).unwrap();
+        let id3 = Volume::open(&renamed_path).unwrap().id();
+
+        // Check the results.
+        let (id1, id2, id3) = (id1.unwrap(), id2.unwrap(), id3.unwrap());
+        let text = id1.to_string();
+        assert_ne!(id1, id2);
+        assert_eq!(id1, id3);
+        assert_eq!(text.len(), 36);
+        assert_eq!(&text[14 .. 15], "4");
+        assert_eq!(text.parse::<VolumeId>().ok(), Some(id1));
+        assert!("not-a-uuid".parse::<VolumeId>().is_err());
+    }
+
+    #[test]
+    fn test_volume_id_parse_invalid()
+    {
+        let valid = "0123abcd-4567-4890-abcd-ef0123456789";
+        let invalid = [
+            "",
+            "0123abcd-4567-4890-abcd-ef01234567890",
+            "0123abcd-4567-4890-abcd-ef012345678",
+            "0123abcd-4567-4890-abcd-ef012345678-",
+            "0123abcd-4567-4890-abcd-ef01-3456789",
+            "0123abcd-4567-4890-abcd-ef01--456789",
+            "0123abcd04567048900abcd0ef0123456789",
+            "0123abcd-4567-4890-abcd-EF0123456789",
+            "0123abcd-4567-4890-abcd-ef012345678g",
+            "0123abcd+4567-4890-abcd-ef0123456789",
+            "0123abc-d4567-4890-abcd-ef0123456789",
+        ];
+
+        assert_eq!(valid.parse::<VolumeId>().unwrap().to_string(), valid);
+        for s in &invalid {
+            assert!(s.parse::<VolumeId>().is_err(), "{:?}", s);
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use crate::Hash;
+use crate::Volume;
+use std::collections::BTreeMap;
+use std::fs::read_dir;
+use std::io::Result;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use std::thread;
+
+/// Options for [`Volume::import_tree`].
+#[derive(Clone, Debug)]
+pub struct ImportOptions
+{
+    /// The number of worker threads that insert files.
+    /// Defaults to the available parallelism of the machine.
+    pub threads: usize,
+
+    /// Flag that stops the import early once it is set.
+    /// Services can keep a clone of it and set it to shut down promptly.
+    /// Files that are being inserted at that point are still inserted.
+    pub cancel: Arc<AtomicBool>,
+}
+
+impl Default for ImportOptions
+{
+    fn default() -> Self
+    {
+        let threads = thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        Self{threads, cancel: Arc::default()}
+    }
+}
+
+impl Volume
+{
+    /// Insert every regular file in a directory tree into the volume.
+    ///
+    /// The directory is walked recursively,
+    /// and each regular file is inserted as in [`Volume::insert_from_path`].
+    /// Symbolic links and other non-regular files are skipped.
+    /// Note that this hard links the files into the volume
+    /// and makes them read-only, so they must not be modified afterwards.
+    /// The files are inserted by a pool of worker threads.
+    ///
+    /// Returns a mapping from the path of each file,
+    /// relative to the given directory, to the hash of the file.
+    /// If inserting any file fails, this method returns an error,
+    /// but files inserted up to that point remain in the volume.
+    /// If the import is cancelled through [`ImportOptions::cancel`],
+    /// the mapping covers only the files that were inserted.
+    pub fn import_tree(&self, path: impl AsRef<Path>, options: &ImportOptions)
+        -> Result<BTreeMap<PathBuf, Hash>>
+    {
+        let root = path.as_ref();
+
+        let mut files = Vec::new();
+        walk(root, Path::new(""), &options.cancel, &mut files)?;
+
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(BTreeMap::new());
+        let failure = Mutex::new(None);
+
+        thread::scope(|scope| {
+            for _ in 0 .. options.threads.max(1) {
+                scope.spawn(|| {
+                    loop {
+                        // Stop early if another worker failed.
+                        if failure.lock().unwrap().is_some() ||
+                           options.cancel.load(Relaxed) {
+                            break;
+                        }
+
+                        let index = next.fetch_add(1, Relaxed);
+                        let relative: &PathBuf = match files.get(index) {
+                            Some(relative) => relative,
+                            None => break,
+                        };
+
+                        match self.insert_from_path(root.join(relative)) {
+                            Ok(hash) => {
+                                let mut results = results.lock().unwrap();
+                                results.insert(relative.clone(), hash);
+                            },
+                            Err(err) => {
+                                failure.lock().unwrap().get_or_insert(err);
+                                break;
+                            },
+                        }
+                    }
+                });
+            }
+        });
+
+        match failure.into_inner().unwrap() {
+            Some(err) => Err(err),
+            None => Ok(results.into_inner().unwrap()),
+        }
+    }
+}
+
+/// Find the regular files in a directory tree,
+/// stopping early if the import is cancelled.
+fn walk(root: &Path, relative: &Path, cancel: &AtomicBool,
+        files: &mut Vec<PathBuf>) -> Result<()>
+{
+    for entry in read_dir(root.join(relative))? {
+        if cancel.load(Relaxed) {
+            break;
+        }
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let entry_relative = relative.join(entry.file_name());
+        if file_type.is_dir() {
+            walk(root, &entry_relative, cancel, files)?;
+        } else if file_type.is_file() {
+            files.push(entry_relative);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use std::fs;
+    use super::*;
+
+    #[test]
+    fn test_import_tree()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_import_tree").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+        let tree_path = test_data.root_path.join("tree");
+        fs::create_dir_all(tree_path.join("a/b")).unwrap();
+        fs::write(tree_path.join("x"), &test_data.regular1_contents).unwrap();
+        fs::write(tree_path.join("a/b/y"), &test_data.regular2_contents)
+            .unwrap();
+        std::os::unix::fs::symlink("x", tree_path.join("a/z")).unwrap();
+
+        // Import the tree.
+        let options = ImportOptions{threads: 3, ..ImportOptions::default()};
+        let actual = volume.import_tree(&tree_path, &options).unwrap();
+
+        // Check the results.
+        let mut expected = BTreeMap::new();
+        expected.insert(PathBuf::from("x"), test_data.regular1_hash);
+        expected.insert(PathBuf::from("a/b/y"), test_data.regular2_hash);
+        assert_eq!(actual, expected);
+        assert!(volume.contains(test_data.regular1_hash).unwrap());
+        assert!(volume.contains(test_data.regular2_hash).unwrap());
+    }
+
+    #[test]
+    fn test_import_tree_cancelled()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_import_tree_cancelled").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+        let tree_path = test_data.root_path.join("tree");
+        fs::create_dir(&tree_path).unwrap();
+        fs::write(tree_path.join("x"), &test_data.regular1_contents).unwrap();
+
+        // Import the tree, having cancelled the import up front.
+        let options = ImportOptions::default();
+        options.cancel.store(true, Relaxed);
+        let actual = volume.import_tree(&tree_path, &options).unwrap();
+
+        // Check the results.
+        assert!(actual.is_empty());
+        assert!(!volume.contains(test_data.regular1_hash).unwrap());
+    }
+}
@@ -0,0 +1,206 @@
+use crate::Volume;
+use crate::VolumeError;
+use crate::VolumeId;
+use crate::identity::read_volume_id;
+use crate::identity::write_volume_id;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::fs::rename;
+use std::fs::write;
+use std::io::Error;
+use std::io::ErrorKind::InvalidData;
+use std::io::ErrorKind::NotFound;
+use std::io::Read;
+use std::io::Result;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use wallace_fsutil as fsutil;
+use wallace_fsutil::FileType;
+
+/// The version of the on-disk layout of volumes
+/// created by this version of the crate.
+///
+/// The layout version is stored in the file `layout-version`
+/// in the volume’s directory, as a decimal number.
+/// Volumes created before layout versions were introduced
+/// lack this file, and are considered to be of layout version 0.
+///
+/// | Version | Changes                                         |
+/// | ------- | ----------------------------------------------- |
+/// | 0       | Initial layout.                                 |
+/// | 1       | Added the `layout-version` file.                |
+///
+/// Some files are optional, and a volume that lacks them
+/// behaves as if they had their default contents.
+/// They do not require a new layout version:
+///
+/// | File             | Default                                  |
+/// | ---------------- | ---------------------------------------- |
+/// | `hash-algorithm` | SHA-256.                                 |
+/// | `volume-id`      | The volume has no identifier.            |
+///
+/// [`Volume::open`] opens volumes of any layout version
+/// up to and including this one, without modifying them.
+/// Volumes of a newer layout version are rejected,
+/// as they may have changed in ways this crate does not understand.
+pub const LAYOUT_VERSION: u32 = 1;
+
+impl Volume
+{
+    /// Upgrade the volume at the given path to the current layout version.
+    ///
+    /// Older volumes can be opened without being migrated,
+    /// but migration records the layout version,
+    /// and gives the volume an identifier if it has none.
+    /// Migrating a volume that is already up to date does nothing.
+    /// No other process may use the volume during migration.
+    ///
+    /// Each migration step is performed in such a way
+    /// that an interrupted migration can be resumed
+    /// by calling this method again.
+    pub fn migrate(path: impl AsRef<Path>) -> Result<()>
+    {
+        let path = path.as_ref();
+        let directory = open_directory(path)?;
+        check_is_volume(&directory)?;
+
+        let version = read_layout_version(&directory)?;
+        check_layout_version(version)?;
+
+        // Keep the identifier of an interrupted migration,
+        // as it may already have been observed.
+        if read_volume_id(&directory)?.is_none() {
+            write_volume_id(path, VolumeId::generate()?)?;
+        }
+
+        if version < LAYOUT_VERSION {
+            write_layout_version(path, LAYOUT_VERSION)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Open the directory of a volume.
+pub (crate) fn open_directory(path: &Path) -> Result<File>
+{
+    OpenOptions::new()
+        .custom_flags(libc::O_DIRECTORY)
+        .read(true)
+        .open(path)
+}
+
+/// Return an error if the directory is not a volume.
+///
+/// Every layout version has an `objects` directory.
+pub (crate) fn check_is_volume(directory: &File) -> Result<()>
+{
+    match fsutil::fstatat(directory, "objects", 0) {
+        Ok(stat) if FileType::from_st_mode(stat.st_mode)
+                    == FileType::Directory => Ok(()),
+        Ok(_) => Err(VolumeError::NotAVolume.into()),
+        Err(err) if err.kind() == NotFound =>
+            Err(VolumeError::NotAVolume.into()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Read the layout version of the volume in the given directory.
+pub (crate) fn read_layout_version(directory: &File) -> Result<u32>
+{
+    let open_flags = libc::O_RDONLY | libc::O_CLOEXEC | libc::O_NOFOLLOW;
+    let file_result = fsutil::openat(directory, "layout-version",
+                                     open_flags, 0);
+
+    let mut file = match file_result {
+        Ok(file) => file,
+        Err(err) if err.kind() == NotFound => return Ok(0),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    contents.trim().parse().map_err(|_| {
+        Error::new(InvalidData, "unparseable volume layout version")
+    })
+}
+
+/// Write the layout version of the volume at the given path.
+pub (crate) fn write_layout_version(path: &Path, version: u32) -> Result<()>
+{
+    // Write to a temporary file and rename it,
+    // so that the layout version is never observed half-written.
+    let tmp_path = path.join("layout-version.tmp");
+    write(&tmp_path, format!("{}\n", version))?;
+    rename(&tmp_path, path.join("layout-version"))
+}
+
+/// Return an error if the layout version is newer than the current one.
+pub (crate) fn check_layout_version(version: u32) -> Result<()>
+{
+    if version > LAYOUT_VERSION {
+        let supported = LAYOUT_VERSION;
+        let error = VolumeError::LayoutVersionMismatch{found: version,
+                                                       supported};
+        return Err(error.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::HashAlgorithm;
+    use crate::TestData;
+    use std::fs;
+    use super::*;
+
+    #[test]
+    fn test_migrate()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_migrate").unwrap();
+        let layout_path = test_data.volume1_path.join("layout-version");
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+        let hash = volume.insert_from_bytes(b"Hello").unwrap();
+
+        // Pretend the volume predates layout versions
+        // and the optional files.
+        for name in &["layout-version", "hash-algorithm", "volume-id"] {
+            fs::remove_file(test_data.volume1_path.join(name)).unwrap();
+        }
+        let volume1 = Volume::open(&test_data.volume1_path).unwrap();
+        let contains1 = volume1.contains(hash).unwrap();
+        let exists1 = layout_path.exists();
+        Volume::migrate(&test_data.volume1_path).unwrap();
+        let volume2 = Volume::open(&test_data.volume1_path).unwrap();
+        Volume::migrate(&test_data.volume1_path).unwrap();
+        let volume3 = Volume::open(&test_data.volume1_path).unwrap();
+
+        // Check the results.
+        assert!(contains1);
+        assert!(!exists1);
+        assert_eq!(volume1.id(), None);
+        assert!(volume2.id().is_some());
+        assert_eq!(volume2.id(), volume3.id());
+        assert_eq!(volume3.hash_algorithm(), HashAlgorithm::Sha256);
+        assert_eq!(fs::read_to_string(&layout_path).unwrap(), "1\n");
+    }
+
+    #[test]
+    fn test_open_newer()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_open_newer").unwrap();
+        let layout_path = test_data.volume1_path.join("layout-version");
+
+        // Pretend the volume was created by a newer version.
+        fs::write(&layout_path, "1000\n").unwrap();
+        let result1 = Volume::open(&test_data.volume1_path);
+        let result2 = Volume::migrate(&test_data.volume1_path);
+
+        // Check the results.
+        assert_eq!(result1.err().map(|e| e.kind()), Some(InvalidData));
+        assert_eq!(result2.err().map(|e| e.kind()), Some(InvalidData));
+    }
+}
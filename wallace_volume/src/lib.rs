@@ -12,7 +12,8 @@
 //! Each object is identified by its hash.
 //! The hash of an object is computed by feeding
 //! the bytes that make up the object
-//! through the SHA-256 hash function.
+//! through the hash function of the volume,
+//! which is SHA-256 unless chosen otherwise with [`HashAlgorithm`].
 //! This happens automatically when inserting a new object
 //! using the methods on the [`Volume`][`crate::Volume`] type.
 //! Two objects made up of the same byte array
@@ -36,9 +37,52 @@
 //! after they are inserted into a volume.
 //! It is therefore recommended to delete any hard links.
 //!
+//! The volume’s directory also records the version of its layout.
+//! See [`LAYOUT_VERSION`] for more information.
+//!
 //! For more information on how files are inserted into a volume,
 //! see the documentation on the insert methods on the [`Volume`] type.
 //!
+//! # Provenance
+//!
+//! Objects can be accompanied by signatures from their producers.
+//! Consumers can use these to require that objects
+//! were signed by producers they trust.
+//! See [`Provenance`] for more information.
+//!
+//! # Encryption at rest
+//!
+//! Volumes can store the contents of objects encrypted with a secret key,
+//! while still addressing them by the hash of their plaintext.
+//! See [`EncryptedVolume`] for more information.
+//!
+//! # Encryption in transit
+//!
+//! Objects can be moved between volumes through untrusted relays
+//! in packs sealed for the public key of the receiving side.
+//! See [`Volume::export_sealed_pack`] for more information.
+//!
+//! # Directory trees
+//!
+//! Whole directory trees can be stored by inserting
+//! a manifest object for each directory, listing its entries.
+//! See [`Manifest`] for more information.
+//!
+//! # Platform support
+//!
+//! This crate requires Linux, and building it elsewhere fails.
+//! A Windows backend is out of scope for now,
+//! as are the other Unix systems that `wallace_fsutil` supports.
+//! Inserts rely on `O_TMPFILE` and on linking open files,
+//! with `AT_EMPTY_PATH` or through `/proc/self/fd`,
+//! objects are protected with Unix permission bits,
+//! and every file is accessed relative to the volume’s directory
+//! through the `*at` system calls in `wallace_fsutil`.
+//! Supporting other systems, such as Windows,
+//! requires an alternative for each of these,
+//! for instance named temporary files linked with `CreateHardLink`,
+//! and the read-only attribute instead of permission bits.
+//!
 //! # How to use this crate
 //!
 //! Volumes can be manipulated through the methods on the [`Volume`] type,
@@ -48,13 +92,99 @@
 #![doc(html_favicon_url = "../../../marketing/logo.svg")]
 #![doc(html_logo_url = "../../../marketing/logo.svg")]
 
+#[cfg(not(target_os = "linux"))]
+compile_error!("wallace_volume supports only Linux; \
+                see the crate documentation on platform support");
+
+pub use self::algorithm::*;
+pub use self::audit::*;
+pub use self::batch::*;
+pub use self::builder::*;
+pub use self::cache::*;
+pub use self::direct::*;
+pub use self::encrypted::*;
+pub use self::error::*;
+pub use self::events::*;
+pub use self::gc::*;
 pub use self::hash::*;
+pub use self::identity::*;
+pub use self::health::*;
+pub use self::import::*;
+pub use self::layout::LAYOUT_VERSION;
+pub use self::listing::*;
+pub use self::manifest::*;
+pub use self::memory::*;
+pub use self::metrics::*;
+pub use self::prefix::*;
+pub use self::mmap::*;
+pub use self::provenance::*;
+pub use self::reader::*;
+pub use self::quota::*;
+pub use self::read_only::*;
+pub use self::scrub::*;
+pub use self::sharded::*;
+pub use self::stats::*;
+pub use self::store::*;
+pub use self::tagged_hash::*;
+pub use self::tree_hash::*;
 pub use self::union::*;
+pub use self::verify::*;
 pub use self::volume::*;
+pub use self::writer::*;
+
+#[cfg(feature = "aio")] pub mod aio;
 
+mod algorithm;
+mod audit;
+mod batch;
+mod bloom;
+mod builder;
+mod cache;
+mod cleanup;
+mod compact;
+mod diff;
+mod direct;
+mod encrypted;
+mod error;
+mod events;
+mod gc;
 mod hash;
+mod health;
+mod identity;
+mod import;
+mod layout;
+mod listing;
+mod manifest;
+mod memory;
+mod metrics;
+mod mmap;
+mod pack;
+mod packfile;
+mod parallel;
+mod pin;
+mod prefix;
+mod provenance;
+mod quota;
+mod read_only;
+mod reader;
+mod scrub;
+mod sealed_pack;
+mod sendfile;
+mod sharded;
+mod snapshot;
+mod stats;
+mod store;
+mod tagged_hash;
+mod tmpfile;
+mod transfer;
+mod trash;
+mod tree_hash;
 mod union;
+mod verify;
 mod volume;
+mod writer;
 
-#[cfg(test)] use self::testdata::*;
-#[cfg(test)] mod testdata;
+#[cfg(any(test, feature = "testdata"))]
+#[doc(hidden)]
+pub use self::testdata::*;
+#[cfg(any(test, feature = "testdata"))] mod testdata;
@@ -0,0 +1,208 @@
+use crate::Hash;
+use crate::Volume;
+use crate::VolumeError;
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::io::ErrorKind::NotFound;
+use std::io::Result;
+use std::str::FromStr;
+use wallace_fsutil as fsutil;
+use wallace_fsutil::FileType;
+
+/// Position in the sorted listing of a volume.
+///
+/// Returned by [`Volume::list_page`] to continue where the page ended.
+/// The cursor is opaque, but it can be passed between processes
+/// through its [`Display`][`fmt::Display`] and [`FromStr`] impls.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PageCursor
+{
+    after: Hash,
+}
+
+/// Returned when a page cursor could not be parsed.
+#[derive(Clone, Copy, Debug)]
+pub struct InvalidPageCursor;
+
+/// Page of the sorted listing of a volume.
+///
+/// Returned by [`Volume::list_page`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Page
+{
+    /// The hashes on the page, in ascending order of their bytes.
+    pub hashes: Vec<Hash>,
+
+    /// The cursor for the next page,
+    /// or [`None`] if this is the last page.
+    pub next: Option<PageCursor>,
+}
+
+impl Volume
+{
+    /// List the objects in the volume in ascending order of hash,
+    /// one page at a time.
+    ///
+    /// The page contains at most `limit` objects,
+    /// following the given cursor, or from the start if there is none.
+    /// Objects inserted or removed between calls
+    /// appear or disappear only if they fall on later pages,
+    /// so no object that exists throughout is skipped or repeated.
+    ///
+    /// Every call lists the entire volume,
+    /// but only keeps `limit` hashes in memory.
+    pub fn list_page(&self, cursor: Option<PageCursor>, limit: usize)
+        -> Result<Page>
+    {
+        // Keep the smallest hashes after the cursor in a max-heap,
+        // so the largest is evicted when a smaller one is found.
+        let mut heap = BinaryHeap::with_capacity(limit + 1);
+        for hash in self.all()? {
+            let hash = hash?;
+            if cursor.is_some_and(|c| hash <= c.after) {
+                continue;
+            }
+            heap.push(hash);
+            if heap.len() > limit {
+                heap.pop();
+            }
+        }
+
+        // A full page may be followed by more objects;
+        // an empty next page is harmless, but a missing one is not.
+        let full = heap.len() == limit && limit > 0;
+        let hashes = heap.into_sorted_vec();
+        let next = if full { hashes.last().map(|&after| PageCursor{after}) }
+                   else { None };
+        Ok(Page{hashes, next})
+    }
+
+    /// Return an iterator over the hashes and sizes of the objects.
+    ///
+    /// This is like [`Volume::all`], but the size of each object
+    /// is found with a single `fstatat` call, without opening the file,
+    /// which is cheaper than calling [`Volume::stat`] for each object.
+    /// Objects removed during iteration are skipped.
+    pub fn all_with_sizes(&self)
+        -> Result<impl '_ + Iterator<Item=Result<(Hash, u64)>>>
+    {
+        // As in all, list objects that are both packed and loose once.
+        let mut packed = Vec::new();
+        for hash in self.all_packed()? {
+            let path = format!("objects/{}", hash);
+            match fsutil::fstatat(&self.directory, path,
+                                  libc::AT_SYMLINK_NOFOLLOW) {
+                Ok(_) => continue,
+                Err(err) if err.kind() == NotFound => (),
+                Err(err) => return Err(err.into()),
+            }
+            if let Some(entry) = self.find_packed(hash)? {
+                packed.push(Ok((hash, entry.size)));
+            }
+        }
+
+        let loose = self.all_loose()?.filter_map(move |hash| {
+            let hash = match hash {
+                Ok(hash) => hash,
+                Err(err) => return Some(Err(err)),
+            };
+            let path = format!("objects/{}", hash);
+            match fsutil::fstatat(&self.directory, path,
+                                  libc::AT_SYMLINK_NOFOLLOW) {
+                Ok(stat) if FileType::from_st_mode(stat.st_mode)
+                             != FileType::Regular =>
+                    Some(Err(VolumeError::NotRegularFile.into())),
+                Ok(stat) => Some(Ok((hash, stat.st_size as u64))),
+                Err(err) if err.kind() == NotFound => None,
+                Err(err) => Some(Err(err.into())),
+            }
+        });
+
+        Ok(loose.chain(packed))
+    }
+}
+
+impl fmt::Display for PageCursor
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{}", self.after)
+    }
+}
+
+impl FromStr for PageCursor
+{
+    type Err = InvalidPageCursor;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err>
+    {
+        let after = s.parse().map_err(|_| InvalidPageCursor)?;
+        Ok(Self{after})
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use super::*;
+
+    #[test]
+    fn test_list_page()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_list_page").unwrap();
+        let mut volume = Volume::open(&test_data.volume1_path).unwrap();
+        volume.set_pack_threshold(1);
+
+        // Insert the objects, packing some of them.
+        let mut expected = Vec::new();
+        for i in 0 .. 10u8 {
+            let bytes = if i % 3 == 0 { vec![i] } else { vec![i; 8] };
+            expected.push(volume.insert_from_bytes(&bytes).unwrap());
+        }
+        expected.sort();
+
+        // List the objects, passing the cursor around as text.
+        let mut actual = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = volume.list_page(cursor, 3).unwrap();
+            assert!(page.hashes.len() <= 3);
+            actual.extend(page.hashes);
+            match page.next {
+                Some(next) => cursor = Some(next.to_string().parse().unwrap()),
+                None => break,
+            }
+        }
+
+        // Check the results.
+        assert_eq!(actual, expected);
+        assert!("nope".parse::<PageCursor>().is_err());
+    }
+
+    #[test]
+    fn test_all_with_sizes()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_all_with_sizes").unwrap();
+        let mut volume = Volume::open(&test_data.volume1_path).unwrap();
+        volume.set_pack_threshold(8);
+
+        // Insert the objects, packing one of them.
+        let hash1 = volume.insert_from_path(&test_data.regular1_path)
+            .unwrap();
+        let hash2 = volume.insert_from_bytes(b"small").unwrap();
+
+        // List the objects.
+        let mut actual = volume.all_with_sizes().unwrap()
+            .collect::<Result<Vec<_>>>().unwrap();
+        actual.sort();
+
+        // Check the results.
+        let regular1_size = test_data.regular1_contents.len() as u64;
+        let mut expected = vec![(hash1, regular1_size), (hash2, 5)];
+        expected.sort();
+        assert_eq!(actual, expected);
+    }
+}
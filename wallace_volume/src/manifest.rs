@@ -0,0 +1,504 @@
+use crate::Hash;
+use crate::HashAlgorithm;
+use crate::ImportOptions;
+use crate::Volume;
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::ffi::OsString;
+use std::fs::DirBuilder;
+use std::fs::OpenOptions;
+use std::fs::Permissions;
+use std::fs::read_dir;
+use std::fs::set_permissions;
+use std::fs::symlink_metadata;
+use std::io::Error;
+use std::io::ErrorKind::InvalidData;
+use std::io::Read;
+use std::io::Result;
+use std::io::copy;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::DirBuilderExt;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// The first bytes of every encoded manifest.
+const MAGIC: &[u8] = b"wallace-manifest 1";
+
+/// Listing of a directory, stored as an object.
+///
+/// A manifest records the name, kind, permission bits, and hash
+/// of each regular file and subdirectory in a directory.
+/// The hash of a subdirectory is the hash of its own manifest,
+/// so the hash of the manifest of the root of a tree
+/// identifies the entire tree, including the contents of its files.
+/// Symbolic links and other kinds of files are not recorded.
+///
+/// Manifests are built with [`Volume::import_manifest`]
+/// and walked with [`Volume::walk_manifest`].
+///
+/// # Encoding
+///
+/// The encoding starts with the line `wallace-manifest 1`,
+/// followed by a space and the [name][`HashAlgorithm::name`]
+/// of the hash function unless that is SHA-256,
+/// so that hashes from different hash functions are not mixed up.
+/// It is followed by the entries, sorted by name,
+/// each of which consists of a kind byte (`f` or `d`),
+/// the permission bits as a big-endian 32-bit integer,
+/// the 32 bytes of the hash,
+/// the length of the name as a big-endian 32-bit integer,
+/// and the bytes of the name.
+/// As entries are sorted, equal trees have equal encodings.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Manifest
+{
+    /// The hash function that computed the hashes in the entries.
+    pub hash_algorithm: HashAlgorithm,
+
+    /// The entries of the manifest, sorted by name.
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// Entry in a [`Manifest`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ManifestEntry
+{
+    /// The name of the file in the directory.
+    /// This is a single path component.
+    pub name: OsString,
+
+    /// Whether the entry is a file or a directory.
+    pub kind: ManifestEntryKind,
+
+    /// The permission bits of the file.
+    pub mode: u32,
+
+    /// The hash of the contents of the file,
+    /// or of the manifest of the directory.
+    pub hash: Hash,
+}
+
+/// Kind of a [`ManifestEntry`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ManifestEntryKind
+{
+    /// A regular file.
+    File,
+
+    /// A directory.
+    Directory,
+}
+
+impl Manifest
+{
+    /// Encode the manifest.
+    ///
+    /// The entries are sorted by name first.
+    pub fn encode(&self) -> Vec<u8>
+    {
+        let mut entries: Vec<&ManifestEntry> = self.entries.iter().collect();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut bytes = MAGIC.to_vec();
+        if self.hash_algorithm != HashAlgorithm::Sha256 {
+            bytes.push(b' ');
+            bytes.extend_from_slice(self.hash_algorithm.name().as_bytes());
+        }
+        bytes.push(b'\n');
+        for entry in entries {
+            let name = entry.name.as_bytes();
+            bytes.push(match entry.kind {
+                ManifestEntryKind::File      => b'f',
+                ManifestEntryKind::Directory => b'd',
+            });
+            bytes.extend_from_slice(&entry.mode.to_be_bytes());
+            bytes.extend_from_slice(&entry.hash.bytes);
+            bytes.extend_from_slice(&(name.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(name);
+        }
+        bytes
+    }
+
+    /// Decode a manifest.
+    ///
+    /// Returns an error if the bytes are not a manifest,
+    /// if the hash function is unknown,
+    /// if the entries are not sorted or have duplicate names,
+    /// or if any name is not a single path component.
+    pub fn decode(mut bytes: &[u8]) -> Result<Self>
+    {
+        let invalid = || Error::new(InvalidData, "invalid manifest");
+
+        let header_len = bytes.iter().position(|&b| b == b'\n')
+            .ok_or_else(invalid)?;
+        let header = &bytes[.. header_len];
+        bytes = &bytes[header_len + 1 ..];
+        let hash_algorithm = match header.strip_prefix(MAGIC) {
+            Some(b"") => HashAlgorithm::Sha256,
+            Some(name) => name.strip_prefix(b" ")
+                .and_then(|name| std::str::from_utf8(name).ok())
+                .and_then(HashAlgorithm::from_name)
+                .filter(|&a| a != HashAlgorithm::Sha256)
+                .ok_or_else(invalid)?,
+            None => return Err(invalid()),
+        };
+
+        let mut entries: Vec<ManifestEntry> = Vec::new();
+        while !bytes.is_empty() {
+            if bytes.len() < 41 {
+                return Err(invalid());
+            }
+            let kind = match bytes[0] {
+                b'f' => ManifestEntryKind::File,
+                b'd' => ManifestEntryKind::Directory,
+                _    => return Err(invalid()),
+            };
+            let mut mode = [0; 4];
+            let mut hash = Hash{bytes: [0; 32]};
+            let mut len = [0; 4];
+            mode.copy_from_slice(&bytes[1 .. 5]);
+            hash.bytes.copy_from_slice(&bytes[5 .. 37]);
+            len.copy_from_slice(&bytes[37 .. 41]);
+            let mode = u32::from_be_bytes(mode);
+            let len = u32::from_be_bytes(len);
+            bytes = &bytes[41 ..];
+
+            let name = bytes.get(.. len as usize).ok_or_else(invalid)?;
+            bytes = &bytes[len as usize ..];
+            if !is_valid_name(name) {
+                return Err(invalid());
+            }
+            if entries.last().is_some_and(|e| e.name.as_bytes() >= name) {
+                return Err(invalid());
+            }
+
+            let name = OsStr::from_bytes(name).to_owned();
+            entries.push(ManifestEntry{name, kind, mode, hash});
+        }
+
+        Ok(Self{hash_algorithm, entries})
+    }
+}
+
+/// Whether a name in a manifest is a single path component.
+fn is_valid_name(name: &[u8]) -> bool
+{
+    !name.is_empty() && name != b"." && name != b".." &&
+        !name.contains(&b'/') && !name.contains(&0)
+}
+
+impl Volume
+{
+    /// Insert a directory tree into the volume,
+    /// along with manifests describing it.
+    ///
+    /// The regular files are inserted as in [`Volume::import_tree`],
+    /// which makes them read-only; their original permission bits
+    /// are recorded in the manifests.
+    /// A manifest is then inserted for each directory in the tree.
+    /// Returns the hash of the manifest of the given directory.
+    pub fn import_manifest(&self, path: impl AsRef<Path>,
+                           options: &ImportOptions) -> Result<Hash>
+    {
+        let root = path.as_ref();
+
+        // Inserting files changes their modes, so record them first.
+        let mut modes = BTreeMap::new();
+        record_modes(root, Path::new(""), &mut modes)?;
+
+        let hashes = self.import_tree(root, options)?;
+        self.insert_manifests(root, Path::new(""), &modes, &hashes)
+    }
+
+    /// Insert the manifests for a directory and its subdirectories.
+    fn insert_manifests(&self, root: &Path, relative: &Path,
+                        modes: &BTreeMap<PathBuf, u32>,
+                        hashes: &BTreeMap<PathBuf, Hash>) -> Result<Hash>
+    {
+        let mut manifest = Manifest{hash_algorithm: self.hash_algorithm,
+                                    entries: Vec::new()};
+        for entry in read_dir(root.join(relative))? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            let entry_relative = relative.join(entry.file_name());
+
+            // Files that appeared during the import are left out.
+            let mode = match modes.get(&entry_relative) {
+                Some(&mode) => mode,
+                None => continue,
+            };
+
+            let (kind, hash) = if file_type.is_dir() {
+                let hash = self.insert_manifests(root, &entry_relative,
+                                                 modes, hashes)?;
+                (ManifestEntryKind::Directory, hash)
+            } else {
+                match hashes.get(&entry_relative) {
+                    Some(&hash) => (ManifestEntryKind::File, hash),
+                    None => continue,
+                }
+            };
+
+            let name = entry.file_name();
+            manifest.entries.push(ManifestEntry{name, kind, mode, hash});
+        }
+        self.insert_from_bytes(&manifest.encode())
+    }
+
+    /// Retrieve and decode a manifest.
+    ///
+    /// If the object does not exist, this method returns [`None`].
+    /// If the manifest was made with a different hash function
+    /// than that of the volume, this method fails with [`InvalidData`],
+    /// as the hashes in it would not refer to objects in the volume.
+    pub fn get_manifest(&self, hash: Hash) -> Result<Option<Manifest>>
+    {
+        let (mut reader, size) = match self.get(hash)? {
+            Some(object) => object,
+            None => return Ok(None),
+        };
+        let mut bytes = Vec::with_capacity(size as usize);
+        reader.read_to_end(&mut bytes)?;
+        let manifest = Manifest::decode(&bytes)?;
+        if manifest.hash_algorithm != self.hash_algorithm {
+            let message = format!("manifest uses hash function {}",
+                                  manifest.hash_algorithm.name());
+            return Err(Error::new(InvalidData, message));
+        }
+        Ok(Some(manifest))
+    }
+
+    /// Retrieve and decode an object if it is a manifest.
+    ///
+    /// Returns [`None`] if the object does not exist,
+    /// or if it is not a manifest of this volume.
+    /// Only the first few bytes of other objects are read.
+    pub (crate) fn sniff_manifest(&self, hash: Hash)
+        -> Result<Option<Manifest>>
+    {
+        let (mut reader, size) = match self.get(hash)? {
+            Some(object) => object,
+            None => return Ok(None),
+        };
+        let mut magic = Vec::with_capacity(MAGIC.len());
+        (&mut reader).take(MAGIC.len() as u64).read_to_end(&mut magic)?;
+        if magic != MAGIC {
+            return Ok(None);
+        }
+        let mut bytes = Vec::with_capacity(size as usize);
+        bytes.extend_from_slice(&magic);
+        reader.read_to_end(&mut bytes)?;
+        Ok(Manifest::decode(&bytes).ok()
+           .filter(|m| m.hash_algorithm == self.hash_algorithm))
+    }
+
+    /// Call a function for each entry in a tree of manifests.
+    ///
+    /// The function is given the path of the entry
+    /// relative to the root of the tree, and the entry itself.
+    /// Directories are visited before their contents.
+    /// Returns an error with [`NotFound`][`std::io::ErrorKind::NotFound`]
+    /// if any manifest in the tree is missing from the volume.
+    pub fn walk_manifest<F>(&self, hash: Hash, mut f: F) -> Result<()>
+        where F: FnMut(&Path, &ManifestEntry) -> Result<()>
+    {
+        self.walk_manifest_at(hash, Path::new(""), &mut f)
+    }
+
+    fn walk_manifest_at(&self, hash: Hash, relative: &Path,
+                        f: &mut dyn FnMut(&Path, &ManifestEntry)
+                                          -> Result<()>)
+        -> Result<()>
+    {
+        let manifest = self.get_manifest(hash)?
+            .ok_or_else(|| Error::from(std::io::ErrorKind::NotFound))?;
+        for entry in &manifest.entries {
+            let entry_relative = relative.join(&entry.name);
+            f(&entry_relative, entry)?;
+            if entry.kind == ManifestEntryKind::Directory {
+                self.walk_manifest_at(entry.hash, &entry_relative, f)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Recreate the tree described by a manifest at the given path,
+    /// which must not yet exist.
+    ///
+    /// Files are copied out of the volume,
+    /// so they can be modified without affecting the objects.
+    /// The permission bits of directories are applied
+    /// after their contents are written.
+    pub fn checkout_manifest(&self, hash: Hash, path: impl AsRef<Path>)
+        -> Result<()>
+    {
+        let root = path.as_ref();
+        DirBuilder::new().mode(0o700).create(root)?;
+
+        let mut directories = vec![(root.to_path_buf(), None)];
+        self.walk_manifest(hash, |relative, entry| {
+            let entry_path = root.join(relative);
+            match entry.kind {
+                ManifestEntryKind::Directory => {
+                    DirBuilder::new().mode(0o700).create(&entry_path)?;
+                    directories.push((entry_path, Some(entry.mode)));
+                },
+                ManifestEntryKind::File => {
+                    let (mut reader, _) = self.get(entry.hash)?
+                        .ok_or_else(|| {
+                            Error::from(std::io::ErrorKind::NotFound)
+                        })?;
+                    let mut file = OpenOptions::new()
+                        .write(true)
+                        .create_new(true)
+                        .mode(0o600)
+                        .open(&entry_path)?;
+                    copy(&mut reader, &mut file)?;
+                    file.set_permissions(Permissions::from_mode(entry.mode))?;
+                },
+            }
+            Ok(())
+        })?;
+
+        // Deepest directories first, in case parents become read-only.
+        for (path, mode) in directories.into_iter().rev() {
+            if let Some(mode) = mode {
+                set_permissions(path, Permissions::from_mode(mode))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Record the permission bits of the files and directories in a tree.
+fn record_modes(root: &Path, relative: &Path,
+                modes: &mut BTreeMap<PathBuf, u32>) -> Result<()>
+{
+    for entry in read_dir(root.join(relative))? {
+        let entry = entry?;
+        let entry_relative = relative.join(entry.file_name());
+        let metadata = symlink_metadata(root.join(&entry_relative))?;
+        if metadata.is_dir() {
+            record_modes(root, &entry_relative, modes)?;
+        } else if !metadata.is_file() {
+            continue;
+        }
+        modes.insert(entry_relative, metadata.permissions().mode() & 0o7777);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use std::fs;
+    use super::*;
+
+    #[test]
+    fn test_manifest_roundtrip()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_manifest_roundtrip").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+        let tree_path = test_data.root_path.join("tree");
+        let checkout_path = test_data.root_path.join("checkout");
+        fs::create_dir_all(tree_path.join("a/b")).unwrap();
+        fs::create_dir(tree_path.join("empty")).unwrap();
+        fs::write(tree_path.join("x"), &test_data.regular1_contents).unwrap();
+        fs::write(tree_path.join("a/b/y"), &test_data.regular2_contents)
+            .unwrap();
+        fs::set_permissions(tree_path.join("x"),
+                            fs::Permissions::from_mode(0o755)).unwrap();
+        std::os::unix::fs::symlink("x", tree_path.join("a/z")).unwrap();
+
+        // Import, walk, and check out the tree.
+        let options = ImportOptions{threads: 2, ..ImportOptions::default()};
+        let hash = volume.import_manifest(&tree_path, &options).unwrap();
+        let mut walked = Vec::new();
+        volume.walk_manifest(hash, |path, entry| {
+            walked.push((path.to_path_buf(), entry.kind));
+            Ok(())
+        }).unwrap();
+        volume.checkout_manifest(hash, &checkout_path).unwrap();
+
+        // Check the results.
+        use ManifestEntryKind::*;
+        assert_eq!(walked, [
+            (PathBuf::from("a"), Directory),
+            (PathBuf::from("a/b"), Directory),
+            (PathBuf::from("a/b/y"), File),
+            (PathBuf::from("empty"), Directory),
+            (PathBuf::from("x"), File),
+        ]);
+        let x_path = checkout_path.join("x");
+        let x_mode = fs::metadata(&x_path).unwrap().permissions().mode();
+        assert_eq!(fs::read(&x_path).unwrap(), test_data.regular1_contents);
+        assert_eq!(fs::read(checkout_path.join("a/b/y")).unwrap(),
+                   test_data.regular2_contents);
+        assert_eq!(x_mode & 0o7777, 0o755);
+        assert!(!checkout_path.join("a/z").exists());
+    }
+
+    #[test]
+    fn test_manifest_decode_invalid()
+    {
+        let entry = |name: &str| ManifestEntry{
+            name: name.into(),
+            kind: ManifestEntryKind::File,
+            mode: 0o644,
+            hash: Hash{bytes: [0; 32]},
+        };
+
+        // Names that are not single path components.
+        for name in &["", ".", "..", "a/b"] {
+            let manifest = Manifest{entries: vec![entry(name)],
+                                    ..Manifest::default()};
+            assert!(Manifest::decode(&manifest.encode()).is_err());
+        }
+
+        // Duplicate names.
+        let manifest = Manifest{entries: vec![entry("a"), entry("a")],
+                                ..Manifest::default()};
+        assert!(Manifest::decode(&manifest.encode()).is_err());
+
+        // Unknown hash functions.
+        for header in &[&b"wallace-manifest 1 md5\n"[..],
+                        b"wallace-manifest 1 sha256\n",
+                        b"wallace-manifest 1blake3\n"] {
+            assert!(Manifest::decode(header).is_err());
+        }
+
+        // Truncated and valid encodings.
+        let manifest = Manifest{entries: vec![entry("b"), entry("a")],
+                                hash_algorithm: HashAlgorithm::Blake3};
+        let bytes = manifest.encode();
+        let decoded = Manifest::decode(&bytes).unwrap();
+        assert!(Manifest::decode(&bytes[.. bytes.len() - 1]).is_err());
+        assert!(bytes.starts_with(b"wallace-manifest 1 blake3\n"));
+        assert_eq!(decoded.entries, [entry("a"), entry("b")]);
+        assert_eq!(decoded.hash_algorithm, HashAlgorithm::Blake3);
+        assert_eq!(Manifest::default().encode(), b"wallace-manifest 1\n");
+    }
+
+    #[test]
+    fn test_get_manifest_other_hash_algorithm()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_get_manifest_other_hash_algorithm")
+            .unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+        let manifest = Manifest{hash_algorithm: HashAlgorithm::Blake3,
+                                entries: Vec::new()};
+
+        // Insert and retrieve the manifest.
+        let hash = volume.insert_from_bytes(&manifest.encode()).unwrap();
+        let result = volume.get_manifest(hash);
+
+        // Check the results.
+        assert_eq!(result.err().map(|e| e.kind()), Some(InvalidData));
+    }
+}
@@ -0,0 +1,148 @@
+use crate::Hash;
+use crate::Hashes;
+use crate::ObjectStore;
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::io::Read;
+use std::io::Result;
+use std::sync::Arc;
+use std::sync::RwLock;
+
+/// Volume that keeps its objects in memory.
+///
+/// This is useful for testing code that works with volumes
+/// without touching the file system.
+/// It offers the same insert and retrieval methods as [`Volume`],
+/// and implements [`ObjectStore`].
+/// The objects are lost when the volume is dropped.
+///
+/// [`Volume`]: `crate::Volume`
+#[derive(Debug, Default)]
+pub struct MemoryVolume
+{
+    objects: RwLock<HashMap<Hash, Arc<[u8]>>>,
+}
+
+impl MemoryVolume
+{
+    /// Create a new, empty volume.
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    /// Insert the given bytes into the volume, and return their hash.
+    pub fn insert_from_bytes(&self, bytes: &[u8]) -> Hash
+    {
+        let hash = Hash::compute_from_bytes(bytes);
+        self.objects.write().unwrap()
+            .entry(hash)
+            .or_insert_with(|| bytes.into());
+        hash
+    }
+
+    /// Drain the given reader into the volume, and return its hash.
+    pub fn insert_from_reader(&self, reader: &mut impl Read) -> Result<Hash>
+    {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Ok(self.insert_from_bytes(&bytes))
+    }
+
+    /// Retrieve a reader for an object’s byte array,
+    /// as well as the size of the object in bytes.
+    ///
+    /// If the object does not exist, this method returns [`None`].
+    /// The reader shares the bytes with the volume,
+    /// so retrieving an object does not copy it.
+    pub fn get(&self, hash: Hash) -> Option<(Cursor<Arc<[u8]>>, u64)>
+    {
+        let objects = self.objects.read().unwrap();
+        let bytes = objects.get(&hash)?.clone();
+        let size = bytes.len() as u64;
+        Some((Cursor::new(bytes), size))
+    }
+
+    /// Check whether an object exists in the volume.
+    pub fn contains(&self, hash: Hash) -> bool
+    {
+        self.objects.read().unwrap().contains_key(&hash)
+    }
+
+    /// Remove an object from the volume.
+    ///
+    /// Returns whether the object existed prior to the call.
+    pub fn remove(&self, hash: Hash) -> bool
+    {
+        self.objects.write().unwrap().remove(&hash).is_some()
+    }
+
+    /// Return the hashes of the objects in the volume,
+    /// in no particular order.
+    pub fn all(&self) -> Vec<Hash>
+    {
+        self.objects.read().unwrap().keys().copied().collect()
+    }
+}
+
+impl ObjectStore for MemoryVolume
+{
+    type Reader = Cursor<Arc<[u8]>>;
+
+    fn get(&self, hash: Hash) -> Result<Option<(Self::Reader, u64)>>
+    {
+        Ok(MemoryVolume::get(self, hash))
+    }
+
+    fn contains(&self, hash: Hash) -> Result<bool>
+    {
+        Ok(MemoryVolume::contains(self, hash))
+    }
+
+    fn all(&self) -> Result<Hashes<'_>>
+    {
+        Ok(Box::new(MemoryVolume::all(self).into_iter().map(Ok)))
+    }
+
+    fn insert_from_reader(&self, reader: &mut dyn Read) -> Result<Hash>
+    {
+        MemoryVolume::insert_from_reader(self, &mut &mut *reader)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::union_get;
+    use super::*;
+
+    #[test]
+    fn test_memory_volume()
+    {
+        // Prepare the test.
+        let volume1 = MemoryVolume::new();
+        let volume2 = MemoryVolume::new();
+
+        // Insert the objects.
+        let hash1 = volume1.insert_from_bytes(b"Hello");
+        let hash2 = volume2.insert_from_reader(&mut &b"world"[..]).unwrap();
+        volume2.insert_from_bytes(b"Hello");
+
+        // Get the objects.
+        let (mut read, size) = union_get([&volume1, &volume2], hash2)
+            .unwrap().unwrap();
+        let mut data = Vec::new();
+        read.read_to_end(&mut data).unwrap();
+
+        // Check the results.
+        assert_eq!(hash1, Hash::compute_from_bytes(b"Hello"));
+        assert_eq!(data, b"world");
+        assert_eq!(size, 5);
+        assert!(volume1.contains(hash1));
+        assert!(!volume1.contains(hash2));
+        assert_eq!(volume2.all().len(), 2);
+        assert!(volume2.remove(hash1));
+        assert!(!volume2.remove(hash1));
+        assert!(volume2.get(hash1).is_none());
+    }
+}
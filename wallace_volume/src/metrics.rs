@@ -0,0 +1,173 @@
+use crate::Volume;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering::Relaxed;
+use std::time::Duration;
+
+/// The number of buckets in a [`Histogram`].
+const BUCKETS: usize = 25;
+
+/// Snapshot of the counters of a volume handle.
+///
+/// Returned by [`Volume::metrics`].
+/// The counters are cumulative since the handle was opened,
+/// and only cover operations made through that handle.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VolumeMetrics
+{
+    /// The number of successful inserts,
+    /// including those of objects that already existed.
+    pub inserts: u64,
+
+    /// The number of objects that were newly inserted.
+    pub objects_written: u64,
+
+    /// The total size in bytes of the objects that were newly inserted.
+    pub bytes_written: u64,
+
+    /// The number of calls to [`Volume::get`] that found the object.
+    pub gets: u64,
+
+    /// The number of calls to [`Volume::get`] that did not.
+    pub misses: u64,
+
+    /// The number of bytes read through readers returned by the volume.
+    pub bytes_read: u64,
+
+    /// The time taken by successful inserts,
+    /// from the start of hashing to the object being linked.
+    pub insert_latency: Histogram,
+}
+
+/// Distribution of durations over exponentially sized buckets.
+///
+/// Bucket `i` counts durations of at most 2<sup>`i`</sup> microseconds,
+/// that did not fit the previous bucket.
+/// The last bucket counts all longer durations.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Histogram
+{
+    counts: [u64; BUCKETS],
+    sum: Duration,
+}
+
+/// Live counters, shared with the readers returned by the volume.
+#[derive(Debug, Default)]
+pub (crate) struct Metrics
+{
+    pub inserts: AtomicU64,
+    pub objects_written: AtomicU64,
+    pub bytes_written: AtomicU64,
+    pub gets: AtomicU64,
+    pub misses: AtomicU64,
+    pub bytes_read: AtomicU64,
+    pub insert_latency: Mutex<Histogram>,
+}
+
+impl Histogram
+{
+    /// The number of recorded durations.
+    pub fn count(&self) -> u64
+    {
+        self.counts.iter().sum()
+    }
+
+    /// The sum of the recorded durations.
+    pub fn sum(&self) -> Duration
+    {
+        self.sum
+    }
+
+    /// The upper bound and count of each bucket.
+    ///
+    /// The upper bound of the last bucket is [`Duration::MAX`].
+    pub fn buckets(&self) -> impl '_ + Iterator<Item=(Duration, u64)>
+    {
+        self.counts.iter().enumerate().map(|(i, &count)| {
+            let bound = if i == BUCKETS - 1 { Duration::MAX }
+                        else { Duration::from_micros(1 << i) };
+            (bound, count)
+        })
+    }
+
+    /// Record a duration.
+    pub fn record(&mut self, duration: Duration)
+    {
+        let micros = duration.as_micros();
+        let bucket = (0 .. BUCKETS - 1)
+            .find(|&i| micros <= 1 << i)
+            .unwrap_or(BUCKETS - 1);
+        self.counts[bucket] += 1;
+        self.sum += duration;
+    }
+}
+
+impl Volume
+{
+    /// Take a snapshot of the counters of this handle.
+    pub fn metrics(&self) -> VolumeMetrics
+    {
+        let m = &self.metrics;
+        VolumeMetrics{
+            inserts:         m.inserts.load(Relaxed),
+            objects_written: m.objects_written.load(Relaxed),
+            bytes_written:   m.bytes_written.load(Relaxed),
+            gets:            m.gets.load(Relaxed),
+            misses:          m.misses.load(Relaxed),
+            bytes_read:      m.bytes_read.load(Relaxed),
+            insert_latency:  m.insert_latency.lock().unwrap().clone(),
+        }
+    }
+
+    /// Record a successful insert that took the given time.
+    pub (crate) fn record_insert(&self, latency: Duration)
+    {
+        self.metrics.inserts.fetch_add(1, Relaxed);
+        self.metrics.insert_latency.lock().unwrap().record(latency);
+    }
+
+    /// Record a newly inserted object of the given size.
+    pub (crate) fn record_written(&self, size: u64)
+    {
+        self.metrics.objects_written.fetch_add(1, Relaxed);
+        self.metrics.bytes_written.fetch_add(size, Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use std::io::Read;
+    use super::*;
+
+    #[test]
+    fn test_metrics()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_metrics").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+
+        // Insert the objects.
+        let hash = volume.insert_from_bytes(b"Hello").unwrap();
+        volume.insert_from_reader(&mut &b"Hello"[..]).unwrap();
+        volume.insert_from_path(&test_data.regular1_path).unwrap();
+
+        // Get the objects.
+        let (mut reader, _) = volume.get(hash).unwrap().unwrap();
+        reader.read_to_end(&mut Vec::new()).unwrap();
+        volume.get(test_data.regular2_hash).unwrap();
+
+        // Check the results.
+        let metrics = volume.metrics();
+        let regular1_size = test_data.regular1_contents.len() as u64;
+        assert_eq!(metrics.inserts, 3);
+        assert_eq!(metrics.objects_written, 2);
+        assert_eq!(metrics.bytes_written, 5 + regular1_size);
+        assert_eq!(metrics.gets, 1);
+        assert_eq!(metrics.misses, 1);
+        assert_eq!(metrics.bytes_read, 5);
+        assert_eq!(metrics.insert_latency.count(), 3);
+        assert_eq!(metrics.insert_latency.buckets().count(), BUCKETS);
+    }
+}
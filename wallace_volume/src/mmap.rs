@@ -0,0 +1,210 @@
+use crate::Hash;
+use crate::ObjectReader;
+use crate::Volume;
+use crate::VolumeError;
+use std::io::ErrorKind::NotFound;
+use std::io::Result;
+use std::ops::Deref;
+use std::slice;
+use std::sync::atomic::Ordering::Relaxed;
+use wallace_fsutil as fsutil;
+
+/// Read-only memory map of an object’s byte array.
+///
+/// Returned by [`Volume::get_mmap`].
+/// The map dereferences to the bytes of the object.
+///
+/// Objects are immutable, so the bytes do not change underneath the map.
+/// However, the mapping is shared with the file backing the object,
+/// so this relies on nobody breaking the rules of the volume.
+/// If the file is written to anyway, for instance through a hard link,
+/// the bytes change while they are borrowed as `&[u8]`,
+/// which is undefined behaviour.
+/// And if the file is truncated,
+/// accessing the map may crash the process with `SIGBUS`.
+/// Do not map objects of volumes whose files may be modified
+/// by untrusted parties; use [`Volume::get`] for those instead.
+pub struct ObjectMap
+{
+    /// [`None`] if the object is empty,
+    /// as empty mappings are not allowed.
+    mmap: Option<fsutil::Mmap>,
+
+    /// The position of the object in the mapping,
+    /// which starts at a page boundary.
+    start: usize,
+    len: usize,
+}
+
+impl Volume
+{
+    /// Retrieve a read-only memory map of an object’s byte array.
+    ///
+    /// If the object does not exist, this method returns [`None`].
+    /// For large objects, this is usually faster than
+    /// reading the object through [`Volume::get`].
+    /// See [`ObjectMap`] for the caveats of memory maps.
+    ///
+    /// If [verify-on-read][`Volume::set_verify_on_read`] is enabled,
+    /// the object is verified before the map is returned,
+    /// and this method fails with
+    /// [`CorruptObject`][`VolumeError::CorruptObject`] if it is corrupt.
+    /// Unlike with [`Volume::get`], this reads the entire object up front.
+    pub fn get_mmap(&self, hash: Hash) -> Result<Option<ObjectMap>>
+    {
+        let (reader, verity) = match self.open_for_mmap(hash)? {
+            Some(object) => object,
+            None => {
+                self.metrics.misses.fetch_add(1, Relaxed);
+                return Ok(None);
+            },
+        };
+        self.metrics.gets.fetch_add(1, Relaxed);
+
+        let map = self.map_object(&reader)?;
+
+        if self.verify_on_read && !verity {
+            let actual = self.hash_algorithm.compute_from_bytes(&map);
+            if !actual.ct_eq(&hash) {
+                return Err(VolumeError::CorruptObject{hash, actual}.into());
+            }
+        }
+
+        Ok(Some(map))
+    }
+
+    /// Open the file backing an object, without the options of the volume
+    /// that [`Volume::get`] applies to the reader.
+    ///
+    /// Also returns whether the kernel verifies the file with fs-verity.
+    fn open_for_mmap(&self, hash: Hash) -> Result<Option<(ObjectReader, bool)>>
+    {
+        let open_flags = { use libc::*; O_RDONLY | O_CLOEXEC |
+                                        O_NOCTTY | O_NOFOLLOW };
+        let path = format!("objects/{}", hash);
+        let file = match fsutil::openat(&self.directory, path,
+                                        open_flags, 0) {
+            Ok(file) => file,
+            Err(err) if err.kind() == NotFound => {
+                let reader = self.get_packed(hash)?;
+                return Ok(reader.map(|r| (r, false)));
+            },
+            Err(err) => return Err(err.into()),
+        };
+
+        let metadata = file.metadata()?;
+        if !metadata.is_file() {
+            return Err(VolumeError::NotRegularFile.into());
+        }
+
+        let verity = self.fs_verity && fsutil::is_verity(&file)
+            .unwrap_or(false);
+        Ok(Some((ObjectReader::new(file, 0, metadata.len()), verity)))
+    }
+
+    /// Map the bytes of the object that the reader reads.
+    fn map_object(&self, reader: &ObjectReader) -> Result<ObjectMap>
+    {
+        let size = reader.size();
+        if size == 0 {
+            return Ok(ObjectMap{mmap: None, start: 0, len: 0});
+        }
+
+        // The offset of a mapping must be a multiple of the page size,
+        // which is not the case for all packed objects.
+        // SAFETY: sysconf has no preconditions.
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u64;
+        let offset = reader.file_offset();
+        let map_offset = offset - offset % page_size;
+        let start = (offset - map_offset) as usize;
+
+        let mmap = fsutil::mmap(start + size as usize, libc::PROT_READ,
+                                libc::MAP_SHARED, reader,
+                                map_offset as libc::off_t)?;
+
+        Ok(ObjectMap{mmap: Some(mmap), start, len: size as usize})
+    }
+}
+
+impl Deref for ObjectMap
+{
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8]
+    {
+        match &self.mmap {
+            None => &[],
+            // SAFETY: The mapping is readable and covers the object.
+            Some(mmap) => unsafe {
+                slice::from_raw_parts(mmap.as_ptr().add(self.start), self.len)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use super::*;
+
+    #[test]
+    fn test_get_mmap()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_get_mmap").unwrap();
+        let mut volume = Volume::open(&test_data.volume1_path).unwrap();
+
+        // Insert the objects, one of which is packed at an odd offset.
+        let hash1 = volume.insert_from_path(&test_data.regular1_path).unwrap();
+        volume.set_pack_threshold(8);
+        volume.insert_from_bytes(b"abc").unwrap();
+        let hash2 = volume.insert_from_bytes(b"packed").unwrap();
+        let hash3 = volume.insert_from_bytes(b"").unwrap();
+
+        // Map the objects.
+        let map1 = volume.get_mmap(hash1).unwrap().unwrap();
+        let map2 = volume.get_mmap(hash2).unwrap().unwrap();
+        let map3 = volume.get_mmap(hash3).unwrap().unwrap();
+        let missing = Hash::compute_from_bytes(b"missing");
+
+        // Check the results.
+        assert_eq!(&*map1, &test_data.regular1_contents[..]);
+        assert_eq!(&*map2, b"packed");
+        assert_eq!(&*map3, b"");
+        assert!(volume.get_mmap(missing).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_get_mmap_verify_on_read()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_get_mmap_verify_on_read")
+            .unwrap();
+        let mut volume = Volume::open(&test_data.volume1_path).unwrap();
+        volume.set_verify_on_read(true);
+
+        // Insert the objects.
+        let hash1 = volume.insert_from_bytes(b"Hello, world!").unwrap();
+        let hash2 = volume.insert_from_bytes(b"Corrupt me!").unwrap();
+
+        // Corrupt an object.
+        let path2 = test_data.volume1_path.join("objects")
+            .join(hash2.to_string());
+        fs::set_permissions(&path2, fs::Permissions::from_mode(0o600))
+            .unwrap();
+        fs::write(&path2, b"Corrupted!!").unwrap();
+
+        // Map the objects.
+        let map1 = volume.get_mmap(hash1).unwrap().unwrap();
+        let result2 = volume.get_mmap(hash2);
+
+        // Check the results.
+        assert_eq!(&*map1, b"Hello, world!");
+        assert!(matches!(result2.map_err(VolumeError::from),
+                         Err(VolumeError::CorruptObject{hash, ..})
+                             if hash == hash2));
+    }
+}
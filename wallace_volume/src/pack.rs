@@ -0,0 +1,259 @@
+use crate::Hash;
+use crate::HashAlgorithm;
+use crate::Volume;
+use std::io::Error;
+use std::io::ErrorKind::InvalidData;
+use std::io::ErrorKind::NotFound;
+use std::io::Read;
+use std::io::Result;
+use std::io::Write;
+use std::io::copy;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::Relaxed;
+use wallace_sha256::HashingReader;
+use wallace_sha256::HashingWriter;
+
+/// Magic bytes at the start of every pack.
+const PACK_MAGIC: &[u8; 8] = b"WLCPACK2";
+
+/// Magic bytes at the start of packs that predate [`PACK_MAGIC`],
+/// whose objects are always hashed with SHA-256.
+const PACK_MAGIC_V1: &[u8; 8] = b"WLCPACK1";
+
+const TAG_END:    u8 = 0;
+const TAG_OBJECT: u8 = 1;
+
+impl Volume
+{
+    /// Write the given objects to a pack.
+    ///
+    /// A pack is a single stream that contains any number of objects,
+    /// which is easier to move around than many small files.
+    /// It can be read back with [`Volume::import_pack`].
+    ///
+    /// The pack starts with the magic bytes `WLCPACK2`,
+    /// followed by the name of the hash function of the volume,
+    /// as returned by [`HashAlgorithm::name`],
+    /// preceded by its length as a byte.
+    /// Each object is written as the byte 1, its hash,
+    /// its size as a 64-bit big-endian integer, and its bytes.
+    /// The pack ends with the byte 0, the number of objects
+    /// as a 64-bit big-endian integer, and a trailer,
+    /// which is the SHA-256 hash of all preceding bytes of the pack.
+    ///
+    /// If any of the objects does not exist,
+    /// this method fails with [`NotFound`].
+    pub fn export_pack<I>(&self, writer: &mut impl Write, hashes: I)
+        -> Result<()>
+        where I: IntoIterator<Item=Hash>
+    {
+        let cancel = AtomicBool::new(false);
+        self.export_pack_cancellable(writer, hashes, &cancel)?;
+        Ok(())
+    }
+
+    /// Like [`Volume::export_pack`], but stop early once the flag is set.
+    ///
+    /// The flag is checked before each object is written.
+    /// When it is set, the pack is ended as usual,
+    /// so that it is a valid pack of the objects written so far.
+    /// Returns the number of objects written.
+    pub fn export_pack_cancellable<I>(&self, writer: &mut impl Write,
+                                      hashes: I, cancel: &AtomicBool)
+        -> Result<u64>
+        where I: IntoIterator<Item=Hash>
+    {
+        let mut writer = HashingWriter::new(writer);
+        let algorithm = self.hash_algorithm.name().as_bytes();
+        writer.write_all(PACK_MAGIC)?;
+        writer.write_all(&[algorithm.len() as u8])?;
+        writer.write_all(algorithm)?;
+
+        let mut count = 0u64;
+        for hash in hashes {
+            if cancel.load(Relaxed) {
+                break;
+            }
+
+            let (mut object, size) = self.get(hash)?.ok_or_else(|| {
+                Error::new(NotFound, format!("object {} does not exist", hash))
+            })?;
+
+            writer.write_all(&[TAG_OBJECT])?;
+            writer.write_all(&hash.bytes)?;
+            writer.write_all(&size.to_be_bytes())?;
+
+            // Guard against the file changing size underneath us,
+            // which would make the pack unreadable.
+            let copied = copy(&mut (&mut object).take(size), &mut writer)?;
+            if copied != size {
+                let message = "object shrunk during export";
+                return Err(Error::new(InvalidData, message));
+            }
+
+            count += 1;
+        }
+
+        writer.write_all(&[TAG_END])?;
+        writer.write_all(&count.to_be_bytes())?;
+        let (writer, trailer) = writer.into_inner_and_hash();
+        writer.write_all(&trailer)?;
+        Ok(count)
+    }
+
+    /// Insert the objects in a pack into the volume.
+    ///
+    /// See [`Volume::export_pack`] for the format of packs.
+    /// Returns the hashes of the objects in the pack, in order.
+    ///
+    /// The hash of each object and the trailer are verified,
+    /// and a mismatch fails with [`InvalidData`].
+    /// So does a pack whose objects were hashed with
+    /// a different hash function than that of the volume,
+    /// as their hashes would not match.
+    /// Packs that start with the magic bytes `WLCPACK1` are also accepted;
+    /// they predate the hash function in the header, and use SHA-256.
+    /// Objects that were read before the failure remain in the volume;
+    /// this is harmless because they are stored under their actual hash.
+    pub fn import_pack(&self, reader: &mut impl Read) -> Result<Vec<Hash>>
+    {
+        let mut reader = HashingReader::new(reader);
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        let algorithm = match &magic {
+            PACK_MAGIC => {
+                let mut len = [0];
+                reader.read_exact(&mut len)?;
+                let mut name = vec![0; len[0] as usize];
+                reader.read_exact(&mut name)?;
+                std::str::from_utf8(&name).ok()
+                    .and_then(HashAlgorithm::from_name)
+                    .ok_or_else(|| invalid_pack("unknown hash function"))?
+            },
+            PACK_MAGIC_V1 => HashAlgorithm::Sha256,
+            _ => return Err(invalid_pack("bad magic bytes")),
+        };
+        if algorithm != self.hash_algorithm {
+            return Err(invalid_pack("hash function mismatch"));
+        }
+
+        let mut hashes = Vec::new();
+        loop {
+            let mut tag = [0];
+            reader.read_exact(&mut tag)?;
+            match tag[0] {
+                TAG_OBJECT => (),
+                TAG_END    => break,
+                _          => return Err(invalid_pack("bad record tag")),
+            }
+
+            let mut expected = Hash{bytes: [0; 32]};
+            let mut size = [0; 8];
+            reader.read_exact(&mut expected.bytes)?;
+            reader.read_exact(&mut size)?;
+            let size = u64::from_be_bytes(size);
+
+            let mut writer = self.start_insert()?;
+            let copied = copy(&mut (&mut reader).take(size), &mut writer)?;
+            if copied != size {
+                return Err(invalid_pack("truncated object"));
+            }
+            let actual = writer.finish()?;
+            if !actual.ct_eq(&expected) {
+                return Err(invalid_pack("object hash mismatch"));
+            }
+
+            hashes.push(actual);
+        }
+
+        let mut count = [0; 8];
+        reader.read_exact(&mut count)?;
+        if u64::from_be_bytes(count) != hashes.len() as u64 {
+            return Err(invalid_pack("object count mismatch"));
+        }
+
+        let (reader, expected) = reader.into_inner_and_hash();
+        let mut trailer = [0; 32];
+        reader.read_exact(&mut trailer)?;
+        if !wallace_sha256::ct_eq(&trailer, &expected) {
+            return Err(invalid_pack("trailer mismatch"));
+        }
+
+        Ok(hashes)
+    }
+}
+
+fn invalid_pack(message: &str) -> Error
+{
+    Error::new(InvalidData, format!("invalid pack: {}", message))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use super::*;
+
+    #[test]
+    fn test_export_import_pack()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_export_import_pack").unwrap();
+        let volume1 = Volume::open(&test_data.volume1_path).unwrap();
+        let volume2 = Volume::open(&test_data.volume2_path).unwrap();
+
+        // Insert the objects.
+        let hash1 = volume1.insert_from_path(&test_data.regular1_path).unwrap();
+        let hash2 = volume1.insert_from_path(&test_data.regular2_path).unwrap();
+
+        // Move the objects through a pack.
+        let mut pack = Vec::new();
+        volume1.export_pack(&mut pack, vec![hash2, hash1]).unwrap();
+        let hashes = volume2.import_pack(&mut &pack[..]).unwrap();
+
+        // Corrupt a pack.
+        let mut corrupt = pack.clone();
+        *corrupt.last_mut().unwrap() ^= 1;
+        let result = volume2.import_pack(&mut &corrupt[..]);
+
+        // Export a missing object.
+        let missing = Hash::compute_from_bytes(b"missing");
+        let result2 = volume1.export_pack(&mut Vec::new(), vec![missing]);
+
+        // Check the results.
+        assert_eq!(hashes, [hash2, hash1]);
+        assert!(volume2.contains(hash1).unwrap());
+        assert!(volume2.contains(hash2).unwrap());
+        assert_eq!(result.err().map(|e| e.kind()), Some(InvalidData));
+        assert_eq!(result2.err().map(|e| e.kind()), Some(NotFound));
+    }
+
+    #[test]
+    fn test_import_pack_hash_algorithm()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_import_pack_hash_algorithm")
+            .unwrap();
+        let volume1 = Volume::open(&test_data.volume1_path).unwrap();
+        let blake3_path = test_data.root_path.join("blake3");
+        Volume::create_with_hash_algorithm(&blake3_path, HashAlgorithm::Blake3)
+            .unwrap();
+        let volume2 = Volume::open(&blake3_path).unwrap();
+
+        // Export an object from a volume with another hash function.
+        let hash = volume1.insert_from_bytes(b"Hello").unwrap();
+        let mut pack = Vec::new();
+        volume1.export_pack(&mut pack, vec![hash]).unwrap();
+
+        // Import the pack into both volumes.
+        let result1 = volume1.import_pack(&mut &pack[..]);
+        let result2 = volume2.import_pack(&mut &pack[..]);
+
+        // Check the results.
+        assert_eq!(&pack[.. 15], b"WLCPACK2\x06sha256");
+        assert_eq!(result1.unwrap(), [hash]);
+        assert_eq!(result2.err().map(|e| e.kind()), Some(InvalidData));
+        assert!(!volume2.contains(hash).unwrap());
+    }
+}
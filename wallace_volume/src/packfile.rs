@@ -0,0 +1,374 @@
+use crate::Durability;
+use crate::Hash;
+use crate::ObjectReader;
+use crate::Volume;
+use crate::VolumeEvent;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::ErrorKind::AlreadyExists;
+use std::io::ErrorKind::NotFound;
+use std::io::Result;
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::AsFd;
+use wallace_fsutil as fsutil;
+
+/// Size in bytes of a record in the pack index.
+const RECORD_SIZE: u64 = 48;
+
+/// In-memory copy of the pack index.
+///
+/// Small objects are not stored as individual files,
+/// but appended to the file `packs/data` in the volume’s directory.
+/// For each such object, the file `packs/index` holds a record
+/// consisting of its hash, and its offset and size in the data file,
+/// both as 64-bit big-endian integers.
+///
+/// Records are only ever appended to the index,
+/// so the in-memory copy is refreshed by reading
+/// the records that were appended since it was last refreshed.
+#[derive(Debug, Default)]
+pub (crate) struct PackIndex
+{
+    entries: HashMap<Hash, PackEntry>,
+
+    /// The number of bytes of the index file read so far.
+    length: u64,
+}
+
+/// Location of an object in the pack data file.
+#[derive(Clone, Copy, Debug)]
+pub (crate) struct PackEntry
+{
+    pub offset: u64,
+    pub size: u64,
+}
+
+impl PackIndex
+{
+    /// Read the records appended to the index file since the last refresh.
+    fn refresh(&mut self, index: &File) -> Result<()>
+    {
+        // Ignore a trailing partial record left behind by a crash.
+        let length = index.metadata()?.len();
+        let length = length - length % RECORD_SIZE;
+        if length <= self.length {
+            return Ok(());
+        }
+
+        let mut records = vec![0; (length - self.length) as usize];
+        index.read_exact_at(&mut records, self.length)?;
+
+        for record in records.chunks_exact(RECORD_SIZE as usize) {
+            let mut hash = Hash{bytes: [0; 32]};
+            let mut offset = [0; 8];
+            let mut size = [0; 8];
+            hash.bytes.copy_from_slice(&record[0 .. 32]);
+            offset.copy_from_slice(&record[32 .. 40]);
+            size.copy_from_slice(&record[40 .. 48]);
+            let offset = u64::from_be_bytes(offset);
+            let size = u64::from_be_bytes(size);
+            self.entries.entry(hash).or_insert(PackEntry{offset, size});
+        }
+
+        self.length = length;
+        Ok(())
+    }
+}
+
+impl Volume
+{
+    /// The size in bytes up to which inserted objects are packed.
+    pub fn pack_threshold(&self) -> u64
+    {
+        self.pack_threshold
+    }
+
+    /// Change the size in bytes up to which inserted objects are packed.
+    ///
+    /// Objects of at most this size that are inserted
+    /// with [`Volume::insert_from_bytes`] or [`Volume::start_insert`]
+    /// are appended to a pack file, rather than stored in a file of their own.
+    /// This saves an inode and a file system block per object,
+    /// which adds up for volumes with millions of tiny objects.
+    /// Packed objects are retrieved in the same way as other objects.
+    /// Existing loose objects can be moved to the pack
+    /// with [`Volume::compact`].
+    ///
+    /// Packed objects cannot be removed individually,
+    /// so [`Volume::remove`] fails for them, and garbage collection skips them.
+    /// The default threshold is zero, which disables packing.
+    pub fn set_pack_threshold(&mut self, pack_threshold: u64)
+    {
+        self.pack_threshold = pack_threshold;
+    }
+
+    /// Whether an object of the given size should be packed.
+    ///
+    /// A threshold of zero disables packing,
+    /// even for empty objects.
+    pub (crate) fn should_pack(&self, size: u64) -> bool
+    {
+        self.pack_threshold != 0 && size <= self.pack_threshold
+    }
+
+    /// Append an object whose hash is already known to the pack.
+    ///
+    /// The caller is responsible for the hash being correct!
+    /// Returns whether the object was newly inserted.
+    pub (crate) fn insert_packed(&self, hash: Hash, bytes: &[u8])
+        -> Result<bool>
+    {
+        match fsutil::mkdirat(&self.directory, "packs", 0o755) {
+            Ok(()) => (),
+            Err(err) if err.kind() == AlreadyExists => (),
+            Err(err) => return Err(err.into()),
+        }
+
+        let open_flags = { use libc::*; O_RDWR | O_CREAT | O_CLOEXEC |
+                                        O_NOFOLLOW };
+        let index = fsutil::openat(&self.directory, "packs/index",
+                                   open_flags, 0o644)?;
+
+        // Other processes may append to the pack concurrently.
+        let _lock = fsutil::FlockGuard::lock(index.as_fd(), libc::LOCK_EX)?;
+
+        let mut packs = self.packs.lock().unwrap();
+        packs.refresh(&index)?;
+        if packs.entries.contains_key(&hash) {
+            return Ok(false);
+        }
+
+        let size = bytes.len() as u64;
+        self.reserve_quota(size)?;
+        let result = self.append_packed(&index, &mut packs, hash, bytes);
+
+        // Observers may use the volume, which needs the lock.
+        drop(packs);
+        match result {
+            Ok(()) => {
+                self.record_in_bloom_filter(hash);
+                self.record_written(size);
+                self.notify(VolumeEvent::Inserted{hash, size});
+                Ok(true)
+            },
+            Err(err) => {
+                self.release_quota(size);
+                Err(err)
+            },
+        }
+    }
+
+    /// Append an object to the pack while the index is locked.
+    fn append_packed(&self, index: &File, packs: &mut PackIndex,
+                     hash: Hash, bytes: &[u8]) -> Result<()>
+    {
+        // Data beyond the last record is garbage left behind by a crash,
+        // which is harmless to leave in place.
+        let open_flags = { use libc::*; O_RDWR | O_CREAT | O_CLOEXEC |
+                                        O_NOFOLLOW };
+        let data = fsutil::openat(&self.directory, "packs/data",
+                                  open_flags, 0o644)?;
+        let offset = data.metadata()?.len();
+        data.write_all_at(bytes, offset)?;
+        if self.durability != Durability::None {
+            data.sync_data()?;
+        }
+
+        // The record is written only after the data is,
+        // so that readers never observe a record without data.
+        let size = bytes.len() as u64;
+        let mut record = [0; RECORD_SIZE as usize];
+        record[0 .. 32].copy_from_slice(&hash.bytes);
+        record[32 .. 40].copy_from_slice(&offset.to_be_bytes());
+        record[40 .. 48].copy_from_slice(&size.to_be_bytes());
+        index.write_all_at(&record, packs.length)?;
+        if self.durability != Durability::None {
+            index.sync_data()?;
+        }
+
+        packs.entries.insert(hash, PackEntry{offset, size});
+        packs.length += RECORD_SIZE;
+        Ok(())
+    }
+
+    /// Append many objects whose hashes are already known to the pack,
+    /// synchronizing the pack regardless of the durability policy.
+    ///
+    /// All data is written and synchronized before any record is,
+    /// so that a record on disk always refers to data on disk.
+    /// The caller is responsible for the hashes being correct!
+    /// Returns the hashes of the objects that were already packed.
+    pub (crate) fn append_packed_batch(&self, objects: &[(Hash, Vec<u8>)])
+        -> Result<Vec<Hash>>
+    {
+        match fsutil::mkdirat(&self.directory, "packs", 0o755) {
+            Ok(()) => (),
+            Err(err) if err.kind() == AlreadyExists => (),
+            Err(err) => return Err(err.into()),
+        }
+
+        let open_flags = { use libc::*; O_RDWR | O_CREAT | O_CLOEXEC |
+                                        O_NOFOLLOW };
+        let index = fsutil::openat(&self.directory, "packs/index",
+                                   open_flags, 0o644)?;
+        let _lock = fsutil::FlockGuard::lock(index.as_fd(), libc::LOCK_EX)?;
+
+        let mut packs = self.packs.lock().unwrap();
+        packs.refresh(&index)?;
+
+        let data = fsutil::openat(&self.directory, "packs/data",
+                                  open_flags, 0o644)?;
+        let mut offset = data.metadata()?.len();
+        let mut existing = Vec::new();
+        let mut records = Vec::new();
+        let mut entries = Vec::new();
+        for (hash, bytes) in objects {
+            if packs.entries.contains_key(hash) {
+                existing.push(*hash);
+                continue;
+            }
+            data.write_all_at(bytes, offset)?;
+            let size = bytes.len() as u64;
+            records.extend_from_slice(&hash.bytes);
+            records.extend_from_slice(&offset.to_be_bytes());
+            records.extend_from_slice(&size.to_be_bytes());
+            entries.push((*hash, PackEntry{offset, size}));
+            offset += size;
+        }
+        data.sync_data()?;
+
+        index.write_all_at(&records, packs.length)?;
+        index.sync_data()?;
+
+        packs.entries.extend(entries);
+        packs.length += records.len() as u64;
+        Ok(existing)
+    }
+
+    /// Find the location of an object in the pack.
+    pub (crate) fn find_packed(&self, hash: Hash) -> Result<Option<PackEntry>>
+    {
+        let mut packs = self.packs.lock().unwrap();
+        if let Some(&entry) = packs.entries.get(&hash) {
+            return Ok(Some(entry));
+        }
+
+        // The object may have been packed by another handle.
+        match self.open_pack_file("packs/index")? {
+            Some(index) => packs.refresh(&index)?,
+            None => return Ok(None),
+        }
+        Ok(packs.entries.get(&hash).copied())
+    }
+
+    /// Retrieve a reader for a packed object.
+    pub (crate) fn get_packed(&self, hash: Hash)
+        -> Result<Option<ObjectReader>>
+    {
+        let entry = match self.find_packed(hash)? {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let data = self.open_pack_file("packs/data")?
+            .ok_or_else(|| std::io::Error::from(NotFound))?;
+        Ok(Some(ObjectReader::new(data, entry.offset, entry.size)))
+    }
+
+    /// Return the hashes of all packed objects.
+    pub (crate) fn all_packed(&self) -> Result<Vec<Hash>>
+    {
+        let mut packs = self.packs.lock().unwrap();
+        if let Some(index) = self.open_pack_file("packs/index")? {
+            packs.refresh(&index)?;
+        }
+        Ok(packs.entries.keys().copied().collect())
+    }
+
+    /// The total size in bytes of the packed objects.
+    pub (crate) fn packed_bytes(&self) -> Result<u64>
+    {
+        let mut packs = self.packs.lock().unwrap();
+        if let Some(index) = self.open_pack_file("packs/index")? {
+            packs.refresh(&index)?;
+        }
+        Ok(packs.entries.values().map(|e| e.size).sum())
+    }
+
+    fn open_pack_file(&self, path: &str) -> Result<Option<File>>
+    {
+        let open_flags = libc::O_RDONLY | libc::O_CLOEXEC | libc::O_NOFOLLOW;
+        match fsutil::openat(&self.directory, path, open_flags, 0) {
+            Ok(file) => Ok(Some(file)),
+            Err(err) if err.kind() == NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use std::io::ErrorKind::Unsupported;
+    use std::io::Read;
+    use std::io::Write;
+    use super::*;
+
+    #[test]
+    fn test_pack_small_objects()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_pack_small_objects").unwrap();
+        let mut volume = Volume::open(&test_data.volume1_path).unwrap();
+        volume.set_pack_threshold(8);
+
+        // Insert the objects.
+        let hash1 = volume.insert_from_bytes(b"small").unwrap();
+        let mut writer = volume.start_insert().unwrap();
+        writer.write_all(b"tiny").unwrap();
+        let hash2 = writer.finish().unwrap();
+        let hash3 = volume.insert_from_bytes(b"not so small").unwrap();
+        volume.insert_from_bytes(b"small").unwrap();
+
+        // Get the objects through another handle.
+        let other = Volume::open(&test_data.volume1_path).unwrap();
+        let read = |hash| {
+            let (mut reader, size) = other.get(hash).unwrap().unwrap();
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data).unwrap();
+            assert_eq!(size, data.len() as u64);
+            data
+        };
+
+        // Check the results.
+        let objects_path = test_data.volume1_path.join("objects");
+        assert_eq!(read(hash1), b"small");
+        assert_eq!(read(hash2), b"tiny");
+        assert_eq!(read(hash3), b"not so small");
+        assert!(!objects_path.join(hash1.to_string()).exists());
+        assert!(!objects_path.join(hash2.to_string()).exists());
+        assert!(objects_path.join(hash3.to_string()).exists());
+        assert_eq!(other.stat(hash1).unwrap().map(|s| s.size), Some(5));
+        assert_eq!(other.all().unwrap().count(), 3);
+        assert_eq!(other.verify_all().unwrap().verified, 3);
+        let error = other.remove(hash1).unwrap_err();
+        assert_eq!(error.kind(), Unsupported);
+    }
+
+    #[test]
+    fn test_pack_threshold_zero()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_pack_threshold_zero").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+
+        // Insert and remove the empty object.
+        let hash = volume.insert_from_bytes(b"").unwrap();
+        let removed = volume.remove(hash).unwrap();
+
+        // Check the results.
+        assert!(removed);
+        assert!(!volume.contains(hash).unwrap());
+        assert!(!test_data.volume1_path.join("packs").exists());
+    }
+}
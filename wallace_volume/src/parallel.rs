@@ -0,0 +1,101 @@
+use crate::Hash;
+use crate::Volume;
+use std::io::Result;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use std::thread;
+
+impl Volume
+{
+    /// Call a function for each object in the volume,
+    /// from the given number of worker threads.
+    ///
+    /// The objects are listed as in [`Volume::all`] up front,
+    /// and the workers then take them one at a time,
+    /// so that a slow object does not hold up the others.
+    /// This lets jobs that read every object,
+    /// such as verification or export, keep fast storage busy.
+    ///
+    /// If the function returns an error, the workers stop
+    /// taking new objects, and the first error is returned.
+    pub fn for_each_parallel<F>(&self, f: F, threads: usize) -> Result<()>
+        where F: Fn(Hash) -> Result<()> + Sync
+    {
+        let hashes = self.all()?.collect::<Result<Vec<_>>>()?;
+
+        let next = AtomicUsize::new(0);
+        let failure = Mutex::new(None);
+
+        thread::scope(|scope| {
+            for _ in 0 .. threads.max(1) {
+                scope.spawn(|| {
+                    loop {
+                        // Stop early if another worker failed.
+                        if failure.lock().unwrap().is_some() {
+                            break;
+                        }
+
+                        let index = next.fetch_add(1, Relaxed);
+                        let hash = match hashes.get(index) {
+                            Some(&hash) => hash,
+                            None => break,
+                        };
+
+                        if let Err(err) = f(hash) {
+                            failure.lock().unwrap().get_or_insert(err);
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+
+        match failure.into_inner().unwrap() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use std::io::Error;
+    use std::io::ErrorKind::Other;
+    use super::*;
+
+    #[test]
+    fn test_for_each_parallel()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_for_each_parallel").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+
+        // Insert the objects.
+        let mut expected = Vec::new();
+        for i in 0 .. 20u8 {
+            expected.push(volume.insert_from_bytes(&[i]).unwrap());
+        }
+        expected.sort();
+
+        // Visit the objects, and fail on one of them.
+        let visited = Mutex::new(Vec::new());
+        let result1 = volume.for_each_parallel(|hash| {
+            visited.lock().unwrap().push(hash);
+            Ok(())
+        }, 4);
+        let result2 = volume.for_each_parallel(|hash| {
+            if hash == expected[7] { Err(Error::new(Other, "boom")) }
+            else { Ok(()) }
+        }, 4);
+
+        // Check the results.
+        let mut visited = visited.into_inner().unwrap();
+        visited.sort();
+        assert!(result1.is_ok());
+        assert_eq!(visited, expected);
+        assert_eq!(result2.err().map(|e| e.kind()), Some(Other));
+    }
+}
@@ -0,0 +1,118 @@
+use crate::Hash;
+use crate::Volume;
+use std::io::ErrorKind::AlreadyExists;
+use std::io::ErrorKind::NotFound;
+use std::io::Result;
+use wallace_fsutil as fsutil;
+
+impl Volume
+{
+    /// Pin an object, so that it is never removed
+    /// by garbage collection or cache eviction.
+    ///
+    /// Pins are stored as empty files in the `pins` directory
+    /// in the volume’s directory, so they persist across handles.
+    /// The object need not exist yet;
+    /// pinning before inserting protects it from the start.
+    /// Explicitly removing a pinned object still works.
+    /// Returns whether the object was newly pinned.
+    pub fn pin(&self, hash: Hash) -> Result<bool>
+    {
+        match fsutil::mkdirat(&self.directory, "pins", 0o755) {
+            Ok(()) => (),
+            Err(err) if err.kind() == AlreadyExists => (),
+            Err(err) => return Err(err.into()),
+        }
+
+        let path = format!("pins/{}", hash);
+        let open_flags = { use libc::*; O_WRONLY | O_CREAT | O_EXCL |
+                                        O_CLOEXEC | O_NOFOLLOW };
+        match fsutil::openat(&self.directory, path, open_flags, 0o644) {
+            Ok(_) => Ok(true),
+            Err(err) if err.kind() == AlreadyExists => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Unpin an object that was pinned with [`Volume::pin`].
+    ///
+    /// Returns whether the object was pinned.
+    pub fn unpin(&self, hash: Hash) -> Result<bool>
+    {
+        let path = format!("pins/{}", hash);
+        match fsutil::unlinkat(&self.directory, path, 0) {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == NotFound => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Check whether an object is pinned.
+    pub fn is_pinned(&self, hash: Hash) -> Result<bool>
+    {
+        let path = format!("pins/{}", hash);
+        match fsutil::fstatat(&self.directory, path,
+                              libc::AT_SYMLINK_NOFOLLOW) {
+            Ok(_) => Ok(true),
+            Err(err) if err.kind() == NotFound => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Return the hashes of all pinned objects.
+    pub fn pinned(&self) -> Result<Vec<Hash>>
+    {
+        let open_flags = libc::O_RDONLY | libc::O_DIRECTORY;
+        let directory = match fsutil::openat(&self.directory, "pins",
+                                             open_flags, 0) {
+            Ok(directory) => directory,
+            Err(err) if err.kind() == NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut hashes = Vec::new();
+        let mut dir = fsutil::fdopendir(directory)?;
+        while let Some(dirent) = fsutil::readdir(&mut dir)? {
+            if let Ok(hash) = Hash::from_ascii(dirent.d_name().to_bytes()) {
+                hashes.push(hash);
+            }
+        }
+        Ok(hashes)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::GcRoots;
+    use crate::TestData;
+    use super::*;
+
+    #[test]
+    fn test_pin()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_pin").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+
+        // Insert and pin the objects.
+        let hash1 = volume.insert_from_bytes(b"Hello").unwrap();
+        let hash2 = volume.insert_from_bytes(b"world").unwrap();
+        let pinned1 = volume.pin(hash1).unwrap();
+        let pinned2 = volume.pin(hash1).unwrap();
+        let pinned3 = volume.pin(hash2).unwrap();
+        let unpinned = volume.unpin(hash2).unwrap();
+
+        // Collect the garbage.
+        let report = volume.collect_garbage(GcRoots::new(), false).unwrap();
+
+        // Check the results.
+        assert_eq!((pinned1, pinned2, pinned3), (true, false, true));
+        assert!(unpinned);
+        assert!(volume.is_pinned(hash1).unwrap());
+        assert!(!volume.is_pinned(hash2).unwrap());
+        assert_eq!(volume.pinned().unwrap(), [hash1]);
+        assert_eq!(report.garbage, [hash2]);
+        assert!(volume.contains(hash1).unwrap());
+    }
+}
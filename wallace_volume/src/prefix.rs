@@ -0,0 +1,116 @@
+use crate::Hash;
+use crate::Volume;
+use std::io::Error;
+use std::io::ErrorKind::InvalidInput;
+use std::io::Result;
+
+/// Returned by [`Volume::resolve_prefix`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PrefixMatch
+{
+    /// Exactly one object has a hash with the prefix.
+    Unique(Hash),
+
+    /// More than one object has a hash with the prefix.
+    Ambiguous,
+
+    /// No object has a hash with the prefix.
+    NotFound,
+}
+
+impl Volume
+{
+    /// Find the object whose hash starts with the given prefix,
+    /// in the hexadecimal format of hashes.
+    ///
+    /// This lets users refer to objects by abbreviated hashes,
+    /// as long as the abbreviation is unambiguous.
+    /// Every call lists the volume, so this is meant for interactive use.
+    /// A prefix that contains anything other than
+    /// lowercase hexadecimal digits, or that is longer than a hash,
+    /// fails with [`InvalidInput`].
+    pub fn resolve_prefix(&self, prefix: &str) -> Result<PrefixMatch>
+    {
+        let prefix = parse_prefix(prefix).ok_or_else(|| {
+            Error::new(InvalidInput, "invalid hash prefix")
+        })?;
+
+        let mut found = None;
+        for hash in self.all()? {
+            let hash = hash?;
+            if !has_prefix(hash, &prefix) || found == Some(hash) {
+                continue;
+            }
+            if found.is_some() {
+                return Ok(PrefixMatch::Ambiguous);
+            }
+            found = Some(hash);
+        }
+
+        Ok(found.map_or(PrefixMatch::NotFound, PrefixMatch::Unique))
+    }
+}
+
+/// Parse a prefix into its hexadecimal digits.
+fn parse_prefix(prefix: &str) -> Option<Vec<u8>>
+{
+    if prefix.len() > 64 {
+        return None;
+    }
+    prefix.bytes().map(|c| match c {
+        b'0' ..= b'9' => Some(c - b'0'),
+        b'a' ..= b'f' => Some(c - b'a' + 10),
+        _ => None,
+    }).collect()
+}
+
+/// Whether the hexadecimal format of the hash starts with the digits.
+fn has_prefix(hash: Hash, digits: &[u8]) -> bool
+{
+    digits.iter().enumerate().all(|(i, &digit)| {
+        let byte = hash.bytes[i / 2];
+        let nibble = if i % 2 == 0 { byte >> 4 } else { byte & 0xF };
+        nibble == digit
+    })
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use super::*;
+
+    #[test]
+    fn test_resolve_prefix()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_resolve_prefix").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+
+        // Insert objects until two share their first digit.
+        let mut hashes = Vec::new();
+        for i in 0u8 .. {
+            let hash = volume.insert_from_bytes(&[i]).unwrap();
+            let text = hash.to_string();
+            if hashes.iter().any(|h: &String| h[.. 1] == text[.. 1]) {
+                hashes.push(text);
+                break;
+            }
+            hashes.push(text);
+        }
+        let shared = &hashes.last().unwrap()[.. 1];
+        let unique = &hashes[0][.. 12];
+
+        // Check the results.
+        let resolve = |p| volume.resolve_prefix(p).unwrap();
+        assert_eq!(resolve(shared), PrefixMatch::Ambiguous);
+        assert_eq!(resolve(unique),
+                   PrefixMatch::Unique(hashes[0].parse().unwrap()));
+        assert_eq!(resolve(&hashes[0]),
+                   PrefixMatch::Unique(hashes[0].parse().unwrap()));
+        assert_eq!(resolve(&test_data.regular1_hash.to_string()),
+                   PrefixMatch::NotFound);
+        assert!(volume.resolve_prefix("ABC").is_err());
+        assert!(volume.resolve_prefix(&"0".repeat(65)).is_err());
+    }
+}
@@ -0,0 +1,286 @@
+use crate::Hash;
+use crate::Volume;
+use std::fs::Permissions;
+use std::io::Error;
+use std::io::ErrorKind::AlreadyExists;
+use std::io::ErrorKind::InvalidData;
+use std::io::ErrorKind::NotFound;
+use std::io::Read;
+use std::io::Result;
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use wallace_ed25519::PublicKey;
+use wallace_ed25519::SecretKey;
+use wallace_ed25519::Signature;
+use wallace_fsutil as fsutil;
+
+/// Signature by a producer vouching for an object.
+///
+/// The signature covers the hash of the object,
+/// as well as arbitrary metadata supplied by the producer.
+/// The volume does not interpret the metadata;
+/// it could be a timestamp, a build identifier, or anything else.
+///
+/// Provenance is stored in the volume directory
+/// at the path `provenance/<hash>/<public key>`,
+/// alongside the object it vouches for.
+/// An object can carry provenance from any number of producers,
+/// but at most one per public key.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Provenance
+{
+    /// The public key of the producer.
+    pub public_key: PublicKey,
+
+    /// The signature over the hash and the metadata.
+    pub signature: Signature,
+
+    /// The metadata that was signed along with the hash.
+    pub metadata: Vec<u8>,
+}
+
+impl Provenance
+{
+    /// Sign the hash of an object and the given metadata.
+    pub fn sign(hash: Hash, secret_key: &SecretKey, metadata: Vec<u8>) -> Self
+    {
+        let message = Self::message(hash, &metadata);
+        let public_key = secret_key.public_key();
+        let signature = secret_key.sign(&message);
+        Self{public_key, signature, metadata}
+    }
+
+    /// Check that the signature is valid for the given hash.
+    pub fn verify(&self, hash: Hash) -> bool
+    {
+        let message = Self::message(hash, &self.metadata);
+        self.public_key.verify(&message, &self.signature)
+    }
+
+    /// The message that is signed.
+    ///
+    /// It is prefixed with a fixed string so that signatures
+    /// cannot be confused with signatures made for other purposes.
+    fn message(hash: Hash, metadata: &[u8]) -> Vec<u8>
+    {
+        let mut message = Vec::new();
+        message.extend_from_slice(b"wallace provenance\0");
+        message.extend_from_slice(&hash.bytes);
+        message.extend_from_slice(metadata);
+        message
+    }
+}
+
+impl Volume
+{
+    /// Store provenance for an object in the volume.
+    ///
+    /// The object must already exist in the volume,
+    /// and the signature must be valid for its hash.
+    /// Otherwise, this method returns an error.
+    ///
+    /// If provenance by the same public key already exists,
+    /// the existing provenance is retained,
+    /// and the given provenance is ignored.
+    pub fn insert_provenance(&self, hash: Hash, provenance: &Provenance)
+        -> Result<()>
+    {
+        if !provenance.verify(hash) {
+            return Err(Error::new(InvalidData, "invalid signature"));
+        }
+
+        if !self.contains(hash)? {
+            return Err(Error::from_raw_os_error(libc::ENOENT));
+        }
+
+        // Create the directories if they do not yet exist.
+        // Volumes created before provenance existed lack them.
+        let dir_path = format!("provenance/{}", hash);
+        for path in &["provenance", &dir_path] {
+            match fsutil::mkdirat(&self.directory, path, 0o755) {
+                Ok(()) => (),
+                Err(err) if err.kind() == AlreadyExists => (),
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        // Write the provenance to an anonymous file,
+        // so that it never appears half-written.
+        // The file format is the signature followed by the metadata.
+        let mut tmpfile = self.create_tmpfile()?;
+        tmpfile.write_all(&provenance.signature.bytes)?;
+        tmpfile.write_all(&provenance.metadata)?;
+        tmpfile.set_permissions(Permissions::from_mode(0o400))?;
+
+        let path = format!("{}/{}", dir_path, provenance.public_key);
+        match fsutil::linkat_fd(&*tmpfile, &self.directory, path) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == AlreadyExists => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Retrieve all provenance stored for an object.
+    ///
+    /// If no provenance is stored for the object,
+    /// or the object does not exist,
+    /// this method returns an empty vector.
+    /// If any stored provenance has an invalid signature,
+    /// the volume is corrupt, and this method returns an error.
+    pub fn provenance(&self, hash: Hash) -> Result<Vec<Provenance>>
+    {
+        let dir_path = format!("provenance/{}", hash);
+        let open_flags = libc::O_RDONLY | libc::O_DIRECTORY;
+        let dir_result = fsutil::openat(&self.directory, &dir_path,
+                                        open_flags, 0);
+
+        let directory = match dir_result {
+            Ok(directory) => directory,
+            Err(err) if err.kind() == NotFound =>
+                return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut dir = fsutil::fdopendir(directory)?;
+        let mut provenances = Vec::new();
+        while let Some(dirent) = fsutil::readdir(&mut dir)? {
+            let filename = dirent.d_name().to_bytes();
+            let public_key = match PublicKey::from_ascii(filename) {
+                Ok(public_key) => public_key,
+                Err(_) => continue,
+            };
+
+            let open_flags = { use libc::*; O_RDONLY | O_CLOEXEC |
+                                            O_NOCTTY | O_NOFOLLOW };
+            let path = format!("{}/{}", dir_path, public_key);
+            let mut file = fsutil::openat(&self.directory, path,
+                                          open_flags, 0)?;
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents)?;
+
+            if contents.len() < 64 {
+                return Err(Error::new(InvalidData, "truncated provenance"));
+            }
+
+            let mut signature = Signature{bytes: [0; 64]};
+            signature.bytes.copy_from_slice(&contents[.. 64]);
+            let metadata = contents[64 ..].to_vec();
+            let provenance = Provenance{public_key, signature, metadata};
+
+            if !provenance.verify(hash) {
+                return Err(Error::new(InvalidData, "invalid signature"));
+            }
+
+            provenances.push(provenance);
+        }
+
+        Ok(provenances)
+    }
+
+    /// Remove all provenance stored for an object.
+    pub (crate) fn remove_provenance(&self, hash: Hash) -> Result<()>
+    {
+        let dir_path = format!("provenance/{}", hash);
+        let open_flags = libc::O_RDONLY | libc::O_DIRECTORY;
+        let dir_result = fsutil::openat(&self.directory, &dir_path,
+                                        open_flags, 0);
+
+        let directory = match dir_result {
+            Ok(directory) => directory,
+            Err(err) if err.kind() == NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut dir = fsutil::fdopendir(directory)?;
+        while let Some(dirent) = fsutil::readdir(&mut dir)? {
+            let filename = dirent.d_name().to_bytes();
+            if let Ok(public_key) = PublicKey::from_ascii(filename) {
+                let path = format!("{}/{}", dir_path, public_key);
+                fsutil::unlinkat(&self.directory, path, 0)?;
+            }
+        }
+
+        fsutil::unlinkat(&self.directory, dir_path, libc::AT_REMOVEDIR)?;
+        Ok(())
+    }
+
+    /// Retrieve provenance for an object by any of the trusted producers.
+    ///
+    /// If the object has no provenance by any of the trusted producers,
+    /// this method returns [`None`].
+    /// Consumers that require objects to be signed
+    /// should treat this as a reason to reject the object.
+    pub fn trusted_provenance(&self, hash: Hash, trusted: &[PublicKey])
+        -> Result<Option<Provenance>>
+    {
+        let provenance =
+            self.provenance(hash)?
+            .into_iter()
+            .find(|p| trusted.contains(&p.public_key));
+        Ok(provenance)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use super::*;
+
+    #[test]
+    fn test_provenance()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_provenance").unwrap();
+        let volume = Volume::open(test_data.volume1_path).unwrap();
+        let (pk1, sk1) = wallace_ed25519::keypair();
+        let (pk2, sk2) = wallace_ed25519::keypair();
+        let (pk3, _)   = wallace_ed25519::keypair();
+
+        // Insert the object and its provenance.
+        let hash = volume.insert_from_path(&test_data.regular1_path).unwrap();
+        let provenance1 = Provenance::sign(hash, &sk1, b"built".to_vec());
+        let provenance2 = Provenance::sign(hash, &sk2, b"".to_vec());
+        volume.insert_provenance(hash, &provenance1).unwrap();
+        volume.insert_provenance(hash, &provenance2).unwrap();
+        volume.insert_provenance(hash, &provenance1).unwrap();
+
+        // Check the results.
+        let mut all = volume.provenance(hash).unwrap();
+        all.sort_by_key(|p| p.public_key.bytes);
+        let mut expected = vec![provenance1.clone(), provenance2];
+        expected.sort_by_key(|p| p.public_key.bytes);
+        assert_eq!(all, expected);
+        assert_eq!(volume.trusted_provenance(hash, &[pk3, pk1]).unwrap(),
+                   Some(provenance1));
+        assert!(volume.trusted_provenance(hash, &[pk3]).unwrap().is_none());
+        assert!(volume.trusted_provenance(hash, &[pk2]).unwrap().is_some());
+
+        // Removing the object removes its provenance.
+        volume.remove(hash).unwrap();
+        assert!(volume.provenance(hash).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_insert_provenance_invalid()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_insert_provenance_invalid")
+            .unwrap();
+        let volume = Volume::open(test_data.volume1_path).unwrap();
+        let (_, sk) = wallace_ed25519::keypair();
+
+        // Signature for a different object.
+        let hash = volume.insert_from_path(&test_data.regular1_path).unwrap();
+        let provenance = Provenance::sign(test_data.regular2_hash, &sk,
+                                          Vec::new());
+        assert!(volume.insert_provenance(hash, &provenance).is_err());
+
+        // Signature for an object that does not exist.
+        let hash = test_data.regular2_hash;
+        assert!(volume.insert_provenance(hash, &provenance).is_err());
+
+        // Nothing was stored.
+        assert!(volume.provenance(hash).unwrap().is_empty());
+    }
+}
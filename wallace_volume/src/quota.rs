@@ -0,0 +1,178 @@
+use crate::Volume;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::io::ErrorKind::NotFound;
+use std::io::ErrorKind::QuotaExceeded as QuotaExceededKind;
+use std::io::Result;
+use wallace_fsutil as fsutil;
+
+/// Returned when an insert would exceed the quota of the volume.
+///
+/// The error is wrapped in an [`io::Error`]
+/// of kind [`QuotaExceeded`][`QuotaExceededKind`],
+/// from which it can be retrieved with [`io::Error::get_ref`].
+/// See [`Volume::set_quota`] for more information.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct QuotaExceeded
+{
+    /// The quota of the volume in bytes.
+    pub quota: u64,
+
+    /// The number of bytes stored in the volume.
+    pub used: u64,
+
+    /// The size in bytes of the object that was not inserted.
+    pub size: u64,
+}
+
+impl fmt::Display for QuotaExceeded
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "inserting {} bytes would exceed the volume quota \
+                   of {} bytes, of which {} are used",
+               self.size, self.quota, self.used)
+    }
+}
+
+impl Error for QuotaExceeded
+{
+}
+
+impl Volume
+{
+    /// The maximum number of bytes stored in the volume, if any.
+    pub fn quota(&self) -> Option<u64>
+    {
+        self.quota
+    }
+
+    /// Change the maximum number of bytes stored in the volume.
+    ///
+    /// Inserts that would make the total size of the objects
+    /// exceed the quota fail with a [`QuotaExceeded`] error,
+    /// and leave the volume untouched.
+    /// Inserts of objects that already exist never fail this way.
+    ///
+    /// The total size is computed with [`Volume::stats`]
+    /// when it is first needed, plus the size of packed objects,
+    /// and is then kept up to date by the inserts and removals
+    /// made through this handle.
+    /// Changes made through other handles are not noticed,
+    /// so the quota is not enforced across processes.
+    /// There is no quota by default.
+    pub fn set_quota(&mut self, quota: Option<u64>)
+    {
+        self.quota = quota;
+    }
+
+    /// The number of bytes stored in the volume,
+    /// as used for enforcing the quota.
+    pub fn used_bytes(&self) -> Result<u64>
+    {
+        let mut usage = self.usage.lock().unwrap();
+        match *usage {
+            Some(used) => Ok(used),
+            None => {
+                let used = self.stats()?.logical_bytes
+                         + self.packed_bytes()?;
+                *usage = Some(used);
+                Ok(used)
+            },
+        }
+    }
+
+    /// Account for an object of the given size that is about to be stored.
+    ///
+    /// If the object turns out to exist already,
+    /// the caller must call [`Volume::release_quota`].
+    pub (crate) fn reserve_quota(&self, size: u64) -> Result<()>
+    {
+        let quota = match self.quota {
+            Some(quota) => quota,
+            None => {
+                self.adjust_usage(|used| used + size);
+                return Ok(());
+            },
+        };
+
+        let used = self.used_bytes()?;
+        let mut usage = self.usage.lock().unwrap();
+        // Another thread may have reserved in the meantime.
+        let used = usage.unwrap_or(used);
+        if used.saturating_add(size) > quota {
+            let error = QuotaExceeded{quota, used, size};
+            return Err(io::Error::new(QuotaExceededKind, error));
+        }
+        *usage = Some(used + size);
+        Ok(())
+    }
+
+    /// Account for an object of the given size that is no longer stored.
+    pub (crate) fn release_quota(&self, size: u64)
+    {
+        self.adjust_usage(|used| used.saturating_sub(size));
+    }
+
+    /// The size to pass to [`Volume::release_quota`]
+    /// once the object at the given path has been removed.
+    ///
+    /// This is zero if the usage has not been computed yet
+    /// and nobody observes the volume, to avoid a pointless `stat`.
+    pub (crate) fn removal_size(&self, path: &str) -> Result<u64>
+    {
+        if self.usage.lock().unwrap().is_none() && !self.is_observed() {
+            return Ok(0);
+        }
+        match fsutil::fstatat(&self.directory, path,
+                              libc::AT_SYMLINK_NOFOLLOW) {
+            Ok(stat) => Ok(stat.st_size as u64),
+            Err(err) if err.kind() == NotFound => Ok(0),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Update the cached usage, if it was computed.
+    fn adjust_usage(&self, f: impl FnOnce(u64) -> u64)
+    {
+        let mut usage = self.usage.lock().unwrap();
+        *usage = usage.map(f);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use super::*;
+
+    #[test]
+    fn test_quota()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_quota").unwrap();
+        let mut volume = Volume::open(&test_data.volume1_path).unwrap();
+        volume.insert_from_bytes(b"Hello").unwrap();
+        volume.set_quota(Some(12));
+
+        // Insert the objects.
+        let hash = volume.insert_from_bytes(b"world!").unwrap();
+        let result1 = volume.insert_from_bytes(b"Too much");
+        volume.insert_from_bytes(b"world!").unwrap();
+        volume.remove(hash).unwrap();
+        let result2 = volume.insert_from_bytes(b"Just so");
+
+        // Check the results.
+        let error = result1.unwrap_err();
+        let quota_exceeded = error.get_ref()
+            .and_then(|e| e.downcast_ref::<QuotaExceeded>());
+        assert_eq!(error.kind(), QuotaExceededKind);
+        assert_eq!(quota_exceeded,
+                   Some(&QuotaExceeded{quota: 12, used: 11, size: 8}));
+        assert!(result2.is_ok());
+        assert_eq!(volume.used_bytes().unwrap(), 12);
+        assert_eq!(volume.used_bytes().unwrap(),
+                   volume.stats().unwrap().logical_bytes);
+    }
+}
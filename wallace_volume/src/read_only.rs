@@ -0,0 +1,133 @@
+use crate::Hash;
+use crate::HashAlgorithm;
+use crate::ObjectReader;
+use crate::ObjectStat;
+use crate::Provenance;
+use crate::VerifyReport;
+use crate::Volume;
+use std::io::Result;
+use std::path::Path;
+use wallace_ed25519::PublicKey;
+
+/// Handle to an opened volume that cannot be modified.
+///
+/// This type exposes only the methods of [`Volume`]
+/// that do not modify the volume.
+/// Servers that only serve objects can use it
+/// to rule out modifying the volume by mistake.
+///
+/// The directory of the volume is opened read-only,
+/// but note that this is not a security boundary:
+/// the process may still be able to modify the files by other means.
+pub struct ReadOnlyVolume
+{
+    inner: Volume,
+}
+
+impl ReadOnlyVolume
+{
+    /// Open the volume at the given path for reading only.
+    ///
+    /// See [`Volume::open`] for more information.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self>
+    {
+        Volume::open(path).map(Volume::into_read_only)
+    }
+
+    /// See [`Volume::hash_algorithm`].
+    pub fn hash_algorithm(&self) -> HashAlgorithm
+    {
+        self.inner.hash_algorithm()
+    }
+
+    /// See [`Volume::get`].
+    pub fn get(&self, hash: Hash) -> Result<Option<(ObjectReader, u64)>>
+    {
+        self.inner.get(hash)
+    }
+
+    /// See [`Volume::contains`].
+    pub fn contains(&self, hash: Hash) -> Result<bool>
+    {
+        self.inner.contains(hash)
+    }
+
+    /// See [`Volume::stat`].
+    pub fn stat(&self, hash: Hash) -> Result<Option<ObjectStat>>
+    {
+        self.inner.stat(hash)
+    }
+
+    /// See [`Volume::all`].
+    pub fn all(&self) -> Result<impl Iterator<Item=Result<Hash>>>
+    {
+        self.inner.all()
+    }
+
+    /// See [`Volume::provenance`].
+    pub fn provenance(&self, hash: Hash) -> Result<Vec<Provenance>>
+    {
+        self.inner.provenance(hash)
+    }
+
+    /// See [`Volume::trusted_provenance`].
+    pub fn trusted_provenance(&self, hash: Hash, trusted: &[PublicKey])
+        -> Result<Option<Provenance>>
+    {
+        self.inner.trusted_provenance(hash, trusted)
+    }
+
+    /// See [`Volume::verify_all`].
+    pub fn verify_all(&self) -> Result<VerifyReport>
+    {
+        self.inner.verify_all()
+    }
+}
+
+impl Volume
+{
+    /// Open the volume at the given path for reading only.
+    ///
+    /// This is the same as [`ReadOnlyVolume::open`].
+    pub fn open_read_only(path: impl AsRef<Path>) -> Result<ReadOnlyVolume>
+    {
+        ReadOnlyVolume::open(path)
+    }
+
+    /// Give up the ability to modify the volume.
+    pub fn into_read_only(self) -> ReadOnlyVolume
+    {
+        ReadOnlyVolume{inner: self}
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use std::io::Read;
+    use super::*;
+
+    #[test]
+    fn test_open_read_only()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_open_read_only").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+
+        // Insert the object.
+        let hash = volume.insert_from_path(&test_data.regular1_path).unwrap();
+
+        // Get the object.
+        let volume = Volume::open_read_only(&test_data.volume1_path).unwrap();
+        let (mut read, size) = volume.get(hash).unwrap().unwrap();
+        let mut data = Vec::new();
+        read.read_to_end(&mut data).unwrap();
+
+        // Check the results.
+        assert_eq!(data, test_data.regular1_contents);
+        assert_eq!(size, test_data.regular1_contents.len() as u64);
+        assert!(volume.contains(hash).unwrap());
+        assert_eq!(volume.all().unwrap().count(), 1);
+    }
+}
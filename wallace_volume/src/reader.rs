@@ -0,0 +1,493 @@
+use crate::Hash;
+use crate::HashAlgorithm;
+use crate::VolumeError;
+use crate::algorithm::Hasher;
+use crate::metrics::Metrics;
+use std::fs::File;
+use std::io::Error;
+use std::io::ErrorKind::InvalidInput;
+use std::io::Read;
+use std::io::Result;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::ops::Range;
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::BorrowedFd;
+use std::os::unix::io::RawFd;
+use std::sync::Arc;
+use std::sync::atomic::Ordering::Relaxed;
+use wallace_fsutil as fsutil;
+
+/// Reader for an object’s byte array.
+///
+/// Returned by [`Volume::get`].
+/// The object is either an entire file,
+/// or a slice of a pack file that holds many small objects.
+/// Reads are positional, so the file offset is never used.
+///
+/// The file descriptor of the file is available through [`AsFd`],
+/// so that servers can pass it to `sendfile`, `splice`, and the like.
+/// The object occupies [`ObjectReader::size`] bytes
+/// starting at [`ObjectReader::file_offset`] in the file.
+/// The file descriptor must not be used to modify the file,
+/// as that would corrupt the volume;
+/// it is opened read-only to make this hard to do by accident.
+///
+/// If the reader verifies the object as it is read,
+/// reading the final byte is followed by a
+/// [`CorruptObject`][`VolumeError::CorruptObject`] error
+/// if the object turns out to be corrupt.
+/// Verification only covers bytes read sequentially from the start;
+/// seeking elsewhere than the start, or using [`ObjectReader::read_at`],
+/// may cause the reader to reach the end without verifying the object.
+///
+/// [`Volume::get`]: `crate::Volume::get`
+pub struct ObjectReader
+{
+    file: File,
+    offset: u64,
+    size: u64,
+    position: u64,
+    verifier: Option<Verifier>,
+    metrics: Option<Arc<Metrics>>,
+}
+
+/// State for verifying an object as it is read.
+struct Verifier
+{
+    algorithm: HashAlgorithm,
+    expected: Hash,
+    hasher: Hasher,
+
+    /// The number of bytes hashed so far.
+    hashed: u64,
+}
+
+impl ObjectReader
+{
+    /// Read the `size` bytes starting at `offset` in the given file.
+    pub (crate) fn new(file: File, offset: u64, size: u64) -> Self
+    {
+        Self{file, offset, size, position: 0, verifier: None, metrics: None}
+    }
+
+    /// Count the bytes read into the given metrics.
+    pub (crate) fn count_into(mut self, metrics: Arc<Metrics>) -> Self
+    {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Verify the object against the given hash as it is read.
+    pub (crate) fn verify(mut self, algorithm: HashAlgorithm, expected: Hash)
+        -> Self
+    {
+        let hasher = algorithm.hasher();
+        self.verifier = Some(Verifier{algorithm, expected, hasher,
+                                      hashed: 0});
+        self
+    }
+
+    /// Whether the reader verifies the object as it is read.
+    ///
+    /// See [`Volume::set_verify_on_read`] for more information.
+    ///
+    /// [`Volume::set_verify_on_read`]: `crate::Volume::set_verify_on_read`
+    pub fn is_verifying(&self) -> bool
+    {
+        self.verifier.is_some()
+    }
+
+    /// The size of the object in bytes.
+    ///
+    /// For readers returned by [`Volume::get_range`],
+    /// this is the size of the range.
+    ///
+    /// [`Volume::get_range`]: `crate::Volume::get_range`
+    pub fn size(&self) -> u64
+    {
+        self.size
+    }
+
+    /// The offset in the file at which the object starts.
+    ///
+    /// This is zero unless the object is packed,
+    /// or the reader was returned by [`Volume::get_range`].
+    ///
+    /// [`Volume::get_range`]: `crate::Volume::get_range`
+    pub fn file_offset(&self) -> u64
+    {
+        self.offset
+    }
+
+    /// Read bytes starting at the given offset into the object,
+    /// without using or changing the position of the reader.
+    ///
+    /// Returns the number of bytes read,
+    /// which is zero at or beyond the end of the object.
+    pub fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize>
+    {
+        let remaining = self.size.saturating_sub(offset);
+        let n = (buf.len() as u64).min(remaining) as usize;
+        if n == 0 {
+            return Ok(0);
+        }
+        self.file.read_at(&mut buf[.. n], self.offset + offset)
+    }
+
+    /// Find the first byte at or after the given offset into the object
+    /// that is not in a hole, using `lseek` with `SEEK_DATA`.
+    ///
+    /// Returns [`None`] if the rest of the object is a hole.
+    /// File systems that do not track holes
+    /// report the entire object as data.
+    /// This changes the file offset of the file descriptor.
+    pub fn seek_data(&self, offset: u64) -> Result<Option<u64>>
+    {
+        if offset >= self.size {
+            return Ok(None);
+        }
+        let file_offset = (self.offset + offset) as libc::off_t;
+        match fsutil::lseek(&self.file, file_offset, libc::SEEK_DATA) {
+            Ok(data) if (data as u64) < self.offset + self.size =>
+                Ok(Some(data as u64 - self.offset)),
+            Ok(_) => Ok(None),
+            Err(err) if err.raw_os_error() == Some(libc::ENXIO) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Find the first byte at or after the given offset into the object
+    /// that is in a hole, using `lseek` with `SEEK_HOLE`.
+    ///
+    /// As with `SEEK_HOLE`, the end of the object counts as a hole,
+    /// so this returns the size of the object if there are no holes.
+    /// This changes the file offset of the file descriptor.
+    pub fn seek_hole(&self, offset: u64) -> Result<u64>
+    {
+        if offset >= self.size {
+            return Ok(self.size);
+        }
+        let file_offset = (self.offset + offset) as libc::off_t;
+        let hole = fsutil::lseek(&self.file, file_offset, libc::SEEK_HOLE)?;
+        Ok((hole as u64 - self.offset).min(self.size))
+    }
+
+    /// Return the ranges of the object that are not in holes,
+    /// as found by [`ObjectReader::seek_data`]
+    /// and [`ObjectReader::seek_hole`].
+    ///
+    /// Bytes outside these ranges read as zero,
+    /// so tools that copy objects can skip them
+    /// to preserve the sparseness of the file.
+    pub fn data_ranges(&self) -> Result<Vec<Range<u64>>>
+    {
+        let mut ranges = Vec::new();
+        let mut offset = 0;
+        while let Some(start) = self.seek_data(offset)? {
+            let end = self.seek_hole(start)?;
+            ranges.push(start .. end);
+            offset = end;
+        }
+        Ok(ranges)
+    }
+
+    /// Restrict the reader to a range of the object.
+    ///
+    /// The range is clamped to the end of the object,
+    /// and the position of the new reader is at the start of the range.
+    pub (crate) fn slice(self, offset: u64, len: u64) -> Self
+    {
+        let offset = offset.min(self.size);
+        let size = len.min(self.size - offset);
+        Self{metrics: self.metrics,
+             ..Self::new(self.file, self.offset + offset, size)}
+    }
+}
+
+impl Read for ObjectReader
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>
+    {
+        let position = self.position;
+        let n = self.read_at(buf, position)?;
+        self.position += n as u64;
+        if let Some(metrics) = &self.metrics {
+            metrics.bytes_read.fetch_add(n as u64, Relaxed);
+        }
+
+        if let Some(verifier) = &mut self.verifier {
+            if position == verifier.hashed {
+                verifier.hasher.update(&buf[.. n]);
+                verifier.hashed += n as u64;
+            }
+            // Check as soon as the last byte is read,
+            // so that callers that read exactly the size of the object
+            // without reading to the end still see corruption.
+            if verifier.hashed == self.size {
+                // Only report the outcome once;
+                // further reads at the end just return zero.
+                let Verifier{expected, hasher, ..} =
+                    self.verifier.take().unwrap();
+                let actual = hasher.finalize();
+                if !actual.ct_eq(&expected) {
+                    let hash = expected;
+                    return Err(VolumeError::CorruptObject{hash, actual}
+                               .into());
+                }
+            }
+        }
+
+        Ok(n)
+    }
+}
+
+impl AsFd for ObjectReader
+{
+    fn as_fd(&self) -> BorrowedFd<'_>
+    {
+        self.file.as_fd()
+    }
+}
+
+impl AsRawFd for ObjectReader
+{
+    fn as_raw_fd(&self) -> RawFd
+    {
+        self.file.as_raw_fd()
+    }
+}
+
+impl Seek for ObjectReader
+{
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64>
+    {
+        let position = match pos {
+            SeekFrom::Start(offset)   => Some(offset),
+            SeekFrom::End(offset)     => self.size.checked_add_signed(offset),
+            SeekFrom::Current(offset) =>
+                self.position.checked_add_signed(offset),
+        };
+
+        let position = position.ok_or_else(|| {
+            Error::new(InvalidInput, "invalid seek to a negative position")
+        })?;
+
+        // Bytes that are skipped or read twice cannot be hashed in order,
+        // but seeking back to the start keeps verification working.
+        // Seeking back to the start is common, for instance to sniff
+        // the type of an object before serving it.
+        if let Some(verifier) = &mut self.verifier {
+            if position == 0 {
+                verifier.hasher = verifier.algorithm.hasher();
+                verifier.hashed = 0;
+            }
+        }
+
+        self.position = position;
+        Ok(position)
+    }
+}
+
+/// Reader that verifies the bytes of an object as they are read.
+///
+/// This is for objects from sources that may corrupt them,
+/// such as volumes on other machines.
+/// Once the inner reader reaches its end,
+/// the bytes read from it are compared against the expected hash,
+/// and if they do not match, the read fails with a
+/// [`CorruptObject`][`VolumeError::CorruptObject`] error.
+/// This includes the case where the inner reader ends too early.
+pub struct VerifyingReader<R>
+{
+    inner: R,
+    expected: Hash,
+
+    /// This is [`None`] once the outcome was reported.
+    hasher: Option<Hasher>,
+}
+
+impl<R> VerifyingReader<R>
+{
+    /// Verify the bytes read from `inner` against the given hash.
+    pub fn new(inner: R, algorithm: HashAlgorithm, expected: Hash) -> Self
+    {
+        Self{inner, expected, hasher: Some(algorithm.hasher())}
+    }
+
+    /// The reader the bytes are read from.
+    pub fn get_ref(&self) -> &R
+    {
+        &self.inner
+    }
+}
+
+impl<R> Read for VerifyingReader<R>
+    where R: Read
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>
+    {
+        let n = self.inner.read(buf)?;
+        if n != 0 {
+            if let Some(hasher) = &mut self.hasher {
+                hasher.update(&buf[.. n]);
+            }
+        } else if !buf.is_empty() {
+            // Only report the outcome once;
+            // further reads at the end just return zero.
+            if let Some(hasher) = self.hasher.take() {
+                let actual = hasher.finalize();
+                if !actual.ct_eq(&self.expected) {
+                    let hash = self.expected;
+                    return Err(VolumeError::CorruptObject{hash, actual}
+                               .into());
+                }
+            }
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use crate::Volume;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use super::*;
+
+    #[test]
+    fn test_object_reader_fd()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_object_reader_fd").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+
+        // Insert the object.
+        let hash = volume.insert_from_bytes(b"Hello, world!").unwrap();
+
+        // Read the object through the file descriptor.
+        let reader = volume.get_range(hash, 7, 5).unwrap().unwrap();
+        let mut buf = [0u8; 5];
+        // SAFETY: The buffer is valid for the given length.
+        let n = unsafe {
+            libc::pread(reader.as_raw_fd(), buf.as_mut_ptr().cast(),
+                        buf.len(), reader.file_offset() as libc::off_t)
+        };
+
+        // Check the results.
+        assert_eq!(n, 5);
+        assert_eq!(&buf, b"world");
+    }
+
+    #[test]
+    fn test_data_ranges()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_data_ranges").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+        let sparse_path = test_data.root_path.join("sparse");
+
+        // Insert a file with data surrounded by holes.
+        let file = fs::File::create(&sparse_path).unwrap();
+        file.set_len(1 << 20).unwrap();
+        file.write_all_at(&[1; 4096], 1 << 19).unwrap();
+        let hash = volume.insert_from_path(&sparse_path).unwrap();
+
+        // Find the data.
+        let (reader, _) = volume.get(hash).unwrap().unwrap();
+        let ranges = reader.data_ranges().unwrap();
+
+        // Check the results.
+        assert_eq!(ranges, vec![Range{start: 1 << 19, end: (1 << 19) + 4096}]);
+        assert_eq!(reader.seek_data(0).unwrap(), Some(1 << 19));
+        assert_eq!(reader.seek_hole(1 << 19).unwrap(), (1 << 19) + 4096);
+        assert_eq!(reader.seek_data((1 << 19) + 4096).unwrap(), None);
+    }
+
+    #[test]
+    fn test_verify_on_read()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_verify_on_read").unwrap();
+        let mut volume = Volume::open(&test_data.volume1_path).unwrap();
+        volume.set_verify_on_read(true);
+
+        // Insert the objects.
+        let hash1 = volume.insert_from_bytes(b"Hello, world!").unwrap();
+        let hash2 = volume.insert_from_bytes(b"Corrupt me!").unwrap();
+
+        // Corrupt an object.
+        let objects_path = test_data.volume1_path.join("objects");
+        let path2 = objects_path.join(hash2.to_string());
+        fs::set_permissions(&path2, fs::Permissions::from_mode(0o600))
+            .unwrap();
+        fs::write(&path2, b"Corrupted!!").unwrap();
+
+        // Read the objects.
+        let read = |hash| {
+            let (mut reader, _) = volume.get(hash).unwrap().unwrap();
+            assert!(reader.is_verifying());
+            let mut prefix = [0; 4];
+            reader.read_exact(&mut prefix)?;
+            reader.rewind()?;
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data).map(|_| data)
+        };
+
+        // Check the results.
+        assert_eq!(read(hash1).unwrap(), b"Hello, world!");
+        assert_eq!(read(hash2).unwrap_err().kind(),
+                   std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_verify_on_read_exact()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_verify_on_read_exact").unwrap();
+        let mut volume = Volume::open(&test_data.volume1_path).unwrap();
+        volume.set_verify_on_read(true);
+
+        // Insert and corrupt an object.
+        let hash = volume.insert_from_bytes(b"Corrupt me!").unwrap();
+        let objects_path = test_data.volume1_path.join("objects");
+        let path = objects_path.join(hash.to_string());
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))
+            .unwrap();
+        fs::write(&path, b"Corrupted!!").unwrap();
+
+        // Read exactly the size of the object, but not to the end.
+        let (mut reader, size) = volume.get(hash).unwrap().unwrap();
+        let mut data = vec![0; size as usize];
+        let result = reader.read_exact(&mut data);
+
+        // Check the results.
+        assert_eq!(result.unwrap_err().kind(),
+                   std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_verifying_reader()
+    {
+        // Prepare the test.
+        let algorithm = HashAlgorithm::Sha256;
+        let hash = algorithm.compute_from_bytes(b"Hello, world!");
+
+        // Read intact, truncated, and corrupt objects.
+        let read = |bytes: &[u8]| {
+            let mut reader = VerifyingReader::new(bytes, algorithm, hash);
+            reader.read_to_end(&mut Vec::new())
+        };
+
+        // Check the results.
+        assert_eq!(read(b"Hello, world!").unwrap(), 13);
+        assert_eq!(read(b"Hello").unwrap_err().kind(),
+                   std::io::ErrorKind::InvalidData);
+        assert_eq!(read(b"Hello, World!").unwrap_err().kind(),
+                   std::io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,236 @@
+use crate::Hash;
+use crate::VerifyReport;
+use crate::Volume;
+use std::io::Error;
+use std::io::ErrorKind::InvalidInput;
+use std::io::ErrorKind::NotFound;
+use std::io::Read;
+use std::io::Result;
+use std::io::Write;
+use wallace_fsutil as fsutil;
+
+/// Incremental verification of the objects in a volume.
+///
+/// Unlike [`Volume::verify_all`], the scrubber verifies objects
+/// in batches of bounded size, so that it can be interleaved with
+/// other work, or spread out over a long period of time.
+/// Objects are visited in order of their hashes.
+///
+/// After each batch, the scrubber persists a cursor in the volume,
+/// at the path `scrub-cursor` in the volume’s directory.
+/// A scrubber created later, perhaps after a restart,
+/// resumes where the previous one left off.
+///
+/// The objects are listed once per pass rather than once per batch,
+/// and the hashes of the objects that are yet to be visited
+/// are kept in memory until the pass is over.
+/// Objects inserted during a pass may not be visited until the next pass.
+pub struct Scrubber<'a, F>
+{
+    volume: &'a Volume,
+    batch_size: usize,
+    on_progress: F,
+    progress: ScrubProgress,
+
+    /// The objects yet to be visited in the current pass,
+    /// in descending order so that the next one is at the end.
+    /// This is [`None`] if the objects were not yet listed.
+    remaining: Option<Vec<Hash>>,
+}
+
+/// Passed to the progress callback of a [`Scrubber`].
+///
+/// The counts are cumulative since the scrubber was created.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ScrubProgress
+{
+    /// The number of objects that were verified.
+    pub objects: u64,
+
+    /// The total size in bytes of the objects that were verified.
+    pub bytes: u64,
+
+    /// The number of objects that turned out to be corrupt.
+    pub corrupt: u64,
+}
+
+impl<'a, F> Scrubber<'a, F>
+    where F: FnMut(&ScrubProgress)
+{
+    /// Create a scrubber that verifies
+    /// at most `batch_size` objects per batch,
+    /// and calls `on_progress` after each batch.
+    ///
+    /// If `batch_size` is zero, this function fails with [`InvalidInput`],
+    /// as the scrubber would never make progress.
+    pub fn new(volume: &'a Volume, batch_size: usize, on_progress: F)
+        -> Result<Self>
+    {
+        if batch_size == 0 {
+            return Err(Error::new(InvalidInput, "scrub batch size is zero"));
+        }
+        let progress = ScrubProgress::default();
+        Ok(Self{volume, batch_size, on_progress, progress, remaining: None})
+    }
+
+    /// Verify the next batch of objects.
+    ///
+    /// Returns a report for the objects in the batch.
+    /// When every object has been visited,
+    /// the cursor is reset and this method returns [`None`].
+    /// Calling it again then starts a new pass over the volume.
+    pub fn step(&mut self) -> Result<Option<VerifyReport>>
+    {
+        let batch = self.next_batch()?;
+
+        let last = match batch.last() {
+            Some(&last) => last,
+            None => {
+                self.remaining = None;
+                self.remove_cursor()?;
+                return Ok(None);
+            },
+        };
+
+        let mut report = VerifyReport::default();
+        for &hash in &batch {
+            self.volume.verify_one(hash, &mut report)?;
+        }
+
+        self.write_cursor(last)?;
+
+        self.progress.objects += batch.len() as u64;
+        self.progress.bytes   += report.verified_bytes;
+        self.progress.corrupt += (report.mismatches.len() +
+                                  report.not_regular.len()) as u64;
+        (self.on_progress)(&self.progress);
+
+        Ok(Some(report))
+    }
+
+    /// Take the next objects of the current pass,
+    /// limited to the batch size.
+    fn next_batch(&mut self) -> Result<Vec<Hash>>
+    {
+        let remaining = match &mut self.remaining {
+            Some(remaining) => remaining,
+            None => self.remaining.insert(self.list_remaining()?),
+        };
+        let at = remaining.len().saturating_sub(self.batch_size);
+        let mut batch = remaining.split_off(at);
+        batch.reverse();
+        Ok(batch)
+    }
+
+    /// List the objects that come after the cursor, in descending order.
+    fn list_remaining(&self) -> Result<Vec<Hash>>
+    {
+        let cursor = self.read_cursor()?;
+        let mut remaining = Vec::new();
+        for hash in self.volume.all()? {
+            let hash = hash?;
+            if cursor.is_none_or(|c| hash > c) {
+                remaining.push(hash);
+            }
+        }
+        remaining.sort_unstable_by(|a, b| b.cmp(a));
+        Ok(remaining)
+    }
+
+    fn read_cursor(&self) -> Result<Option<Hash>>
+    {
+        let open_flags = libc::O_RDONLY | libc::O_CLOEXEC | libc::O_NOFOLLOW;
+        let mut file = match fsutil::openat(&self.volume.directory,
+                                            "scrub-cursor", open_flags, 0) {
+            Ok(file) => file,
+            Err(err) if err.kind() == NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        // An unparseable cursor is not worth failing over;
+        // the scrubber simply starts from the beginning.
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+        Ok(Hash::from_ascii(&contents).ok())
+    }
+
+    fn write_cursor(&self, cursor: Hash) -> Result<()>
+    {
+        // Write the cursor to a temporary file and rename it,
+        // so that the cursor is never observed half-written.
+        let open_flags = { use libc::*; O_WRONLY | O_CREAT | O_TRUNC |
+                                        O_CLOEXEC | O_NOFOLLOW };
+        let mut file = fsutil::openat(&self.volume.directory,
+                                      "scrub-cursor.tmp", open_flags, 0o644)?;
+        write!(file, "{}", cursor)?;
+        fsutil::renameat(&self.volume.directory, "scrub-cursor.tmp",
+                         &self.volume.directory, "scrub-cursor")?;
+        Ok(())
+    }
+
+    fn remove_cursor(&self) -> Result<()>
+    {
+        match fsutil::unlinkat(&self.volume.directory, "scrub-cursor", 0) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == NotFound => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use super::*;
+
+    #[test]
+    fn test_scrubber()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_scrubber").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+
+        // Insert the objects.
+        volume.insert_from_path(&test_data.regular1_path).unwrap();
+        volume.insert_from_path(&test_data.regular2_path).unwrap();
+        volume.insert_from_reader(&mut &b"scrub"[..]).unwrap();
+
+        // Scrub one batch, then resume with a new scrubber.
+        let mut progress = Vec::new();
+        let report1 = Scrubber::new(&volume, 2, |p| progress.push(*p))
+            .unwrap().step().unwrap().unwrap();
+        let mut scrubber = Scrubber::new(&volume, 2, |p| progress.push(*p))
+            .unwrap();
+        let report2 = scrubber.step().unwrap().unwrap();
+        let report3 = scrubber.step().unwrap();
+        let report4 = scrubber.step().unwrap().unwrap();
+
+        // Check the results.
+        assert!(report1.is_ok());
+        assert_eq!(report1.verified, 2);
+        assert_eq!(report2.verified, 1);
+        assert!(report3.is_none());
+        assert_eq!(report4.verified, 2);
+        assert_eq!(progress.len(), 3);
+        assert_eq!(progress[0].objects, 2);
+        assert_eq!(progress[1].objects, 1);
+        assert_eq!(progress[2].objects, 3);
+        assert_eq!(progress[0].bytes + progress[1].bytes, 16);
+    }
+
+    #[test]
+    fn test_scrubber_zero_batch_size()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_scrubber_zero_batch_size")
+            .unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+
+        // Create the scrubber.
+        let result = Scrubber::new(&volume, 0, |_| ());
+
+        // Check the results.
+        assert_eq!(result.err().map(|e| e.kind()), Some(InvalidInput));
+    }
+}
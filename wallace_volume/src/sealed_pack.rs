@@ -0,0 +1,258 @@
+use crate::Hash;
+use crate::Volume;
+use std::convert::TryInto;
+use std::io::Error;
+use std::io::ErrorKind::InvalidData;
+use std::io::ErrorKind::UnexpectedEof;
+use std::io::Read;
+use std::io::Result;
+use std::io::Write;
+use wallace_sealedbox::PublicKey;
+use wallace_sealedbox::SEALBYTES;
+use wallace_sealedbox::SecretKey;
+use wallace_secretstream::ABYTES;
+use wallace_secretstream::Decryptor;
+use wallace_secretstream::Encryptor;
+use wallace_secretstream::HEADERBYTES;
+use wallace_secretstream::Key;
+
+/// Magic bytes at the start of every sealed pack.
+const SEALED_PACK_MAGIC: &[u8; 8] = b"WLCSEAL1";
+
+/// Number of plaintext bytes per encrypted chunk.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+impl Volume
+{
+    /// Write the given objects to a pack that only the recipient can read.
+    ///
+    /// This is [`Volume::export_pack`] with end-to-end encryption,
+    /// so that packs can be moved through untrusted relays.
+    /// A fresh stream key is sealed for the recipient
+    /// with [`wallace_sealedbox`], and the pack is encrypted
+    /// with that key in chunks of 64 KiB using [`wallace_secretstream`].
+    /// Relays learn neither the contents nor the hashes of the objects.
+    ///
+    /// The sealed pack starts with the magic bytes `WLCSEAL1`,
+    /// followed by the sealed stream key and the stream header.
+    /// Each chunk is written as its size
+    /// as a 32-bit big-endian integer, and its bytes.
+    /// The last chunk is marked as final.
+    pub fn export_sealed_pack<I>(&self, writer: &mut impl Write, hashes: I,
+                                 recipient: &PublicKey) -> Result<()>
+        where I: IntoIterator<Item=Hash>
+    {
+        let key = Key::generate();
+        let (encryptor, header) = Encryptor::new(&key);
+
+        writer.write_all(SEALED_PACK_MAGIC)?;
+        writer.write_all(&wallace_sealedbox::seal(recipient, &key.bytes))?;
+        writer.write_all(&header)?;
+
+        let mut writer = SealingWriter{inner: writer, encryptor,
+                                       buffer: Vec::new()};
+        self.export_pack(&mut writer, hashes)?;
+        writer.finish()
+    }
+
+    /// Insert the objects in a sealed pack into the volume.
+    ///
+    /// See [`Volume::export_sealed_pack`] for the format of sealed packs,
+    /// and [`Volume::import_pack`] for how the objects are inserted.
+    /// If the pack was sealed for someone else, was tampered with,
+    /// or was truncated, this method fails with [`InvalidData`].
+    pub fn import_sealed_pack(&self, reader: &mut impl Read,
+                              recipient: &SecretKey) -> Result<Vec<Hash>>
+    {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != SEALED_PACK_MAGIC {
+            return Err(invalid_sealed_pack("bad magic bytes"));
+        }
+
+        let mut sealed_key = [0; 32 + SEALBYTES];
+        reader.read_exact(&mut sealed_key)?;
+        let key = wallace_sealedbox::open(recipient, &sealed_key)
+            .map_err(|_| invalid_sealed_pack("cannot open stream key"))?;
+        let key = Key{bytes: key[..].try_into().unwrap()};
+
+        let mut header = [0; HEADERBYTES];
+        reader.read_exact(&mut header)?;
+        let decryptor = Decryptor::new(&key, &header)
+            .map_err(|_| invalid_sealed_pack("bad stream header"))?;
+
+        let mut reader = UnsealingReader{inner: reader, decryptor,
+                                         chunk: Vec::new(), offset: 0,
+                                         finished: false};
+        let hashes = self.import_pack(&mut reader)?;
+
+        // The pack must be followed by the final chunk and nothing else,
+        // or else the sealed pack could have been truncated.
+        if reader.read(&mut [0])? != 0 {
+            return Err(invalid_sealed_pack("data after pack"));
+        }
+
+        Ok(hashes)
+    }
+}
+
+/// Writer that encrypts the bytes written to it chunk by chunk.
+struct SealingWriter<W>
+{
+    inner: W,
+    encryptor: Encryptor,
+
+    /// Plaintext that does not yet fill a chunk.
+    buffer: Vec<u8>,
+}
+
+impl<W> SealingWriter<W>
+    where W: Write
+{
+    fn write_chunk(&mut self, plaintext: &[u8], last: bool) -> Result<()>
+    {
+        let chunk = self.encryptor.push(plaintext, b"", last);
+        self.inner.write_all(&(chunk.len() as u32).to_be_bytes())?;
+        self.inner.write_all(&chunk)
+    }
+
+    /// Encrypt the remaining bytes as the final chunk.
+    fn finish(mut self) -> Result<()>
+    {
+        let buffer = std::mem::take(&mut self.buffer);
+        self.write_chunk(&buffer, true)?;
+        self.inner.flush()
+    }
+}
+
+impl<W> Write for SealingWriter<W>
+    where W: Write
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize>
+    {
+        let n = buf.len().min(CHUNK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[.. n]);
+        if self.buffer.len() == CHUNK_SIZE {
+            let buffer = std::mem::take(&mut self.buffer);
+            self.write_chunk(&buffer, false)?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<()>
+    {
+        // Partial chunks are only written by finish,
+        // as every chunk but the last must be full.
+        self.inner.flush()
+    }
+}
+
+/// Reader that decrypts a sealed pack chunk by chunk.
+struct UnsealingReader<R>
+{
+    inner: R,
+    decryptor: Decryptor,
+
+    /// The current decrypted chunk and the offset into it.
+    chunk: Vec<u8>,
+    offset: usize,
+
+    /// Whether the final chunk was decrypted.
+    finished: bool,
+}
+
+impl<R> Read for UnsealingReader<R>
+    where R: Read
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>
+    {
+        while self.offset == self.chunk.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            self.next_chunk()?;
+        }
+
+        let n = buf.len().min(self.chunk.len() - self.offset);
+        buf[.. n].copy_from_slice(&self.chunk[self.offset .. self.offset + n]);
+        self.offset += n;
+        Ok(n)
+    }
+}
+
+impl<R> UnsealingReader<R>
+    where R: Read
+{
+    fn next_chunk(&mut self) -> Result<()>
+    {
+        let mut size = [0; 4];
+        self.inner.read_exact(&mut size).map_err(|err| {
+            if err.kind() == UnexpectedEof {
+                invalid_sealed_pack("missing final chunk")
+            } else {
+                err
+            }
+        })?;
+        let size = u32::from_be_bytes(size) as usize;
+        if !(ABYTES ..= CHUNK_SIZE + ABYTES).contains(&size) {
+            return Err(invalid_sealed_pack("bad chunk size"));
+        }
+
+        let mut encrypted = vec![0; size];
+        self.inner.read_exact(&mut encrypted)?;
+        let (chunk, finished) = self.decryptor.pull(&encrypted, b"")
+            .map_err(|_| invalid_sealed_pack("chunk cannot be decrypted"))?;
+
+        self.chunk = chunk;
+        self.offset = 0;
+        self.finished = finished;
+        Ok(())
+    }
+}
+
+fn invalid_sealed_pack(message: &str) -> Error
+{
+    Error::new(InvalidData, format!("invalid sealed pack: {}", message))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use super::*;
+
+    #[test]
+    fn test_export_import_sealed_pack()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_export_import_sealed_pack")
+            .unwrap();
+        let volume1 = Volume::open(&test_data.volume1_path).unwrap();
+        let volume2 = Volume::open(&test_data.volume2_path).unwrap();
+        let (public_key, secret_key) = wallace_sealedbox::keypair();
+        let (_, other_key) = wallace_sealedbox::keypair();
+
+        // Insert the objects, one of which spans several chunks.
+        let large = vec![0xAB; 3 * CHUNK_SIZE / 2];
+        let hash1 = volume1.insert_from_bytes(b"Hello").unwrap();
+        let hash2 = volume1.insert_from_bytes(&large).unwrap();
+
+        // Move the objects through a sealed pack.
+        let mut pack = Vec::new();
+        volume1.export_sealed_pack(&mut pack, vec![hash1, hash2],
+                                   &public_key).unwrap();
+        let wrong_key = volume2.import_sealed_pack(&mut &pack[..],
+                                                   &other_key);
+        let truncated = volume2.import_sealed_pack(
+            &mut &pack[.. pack.len() - 1], &secret_key);
+        let hashes = volume2.import_sealed_pack(&mut &pack[..], &secret_key)
+            .unwrap();
+
+        // Check the results.
+        assert_eq!(hashes, [hash1, hash2]);
+        assert!(volume2.contains(hash2).unwrap());
+        assert!(!pack.windows(5).any(|w| w == b"Hello"));
+        assert_eq!(wrong_key.err().map(|e| e.kind()), Some(InvalidData));
+        assert!(truncated.is_err());
+    }
+}
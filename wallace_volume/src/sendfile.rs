@@ -0,0 +1,178 @@
+use crate::Hash;
+use crate::Volume;
+use crate::VolumeError;
+use std::fs::File;
+use std::io::Error;
+use std::io::ErrorKind::Interrupted;
+use std::io::ErrorKind::NotFound;
+use std::io::ErrorKind::UnexpectedEof;
+use std::io::Result;
+use std::io::Write;
+use std::io::copy;
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::AsFd;
+use std::sync::atomic::Ordering::Relaxed;
+use wallace_fsutil as fsutil;
+
+impl Volume
+{
+    /// Write the contents of an object to the given writer,
+    /// and return the size of the object in bytes.
+    ///
+    /// The writer is typically a file or a socket.
+    /// The kernel copies the bytes directly with `sendfile`,
+    /// without them passing through a buffer in this process,
+    /// which makes this cheaper than [`Volume::get`] followed by a copy.
+    /// If the kernel cannot copy between the file descriptors,
+    /// the bytes are copied through a buffer after all.
+    /// The writer is flushed before anything is written.
+    ///
+    /// If [verify-on-read][`Volume::set_verify_on_read`] is enabled,
+    /// the object is copied through its verifying reader instead,
+    /// so that corruption is still detected,
+    /// albeit after the corrupt bytes were written.
+    ///
+    /// If the object does not exist, this method returns [`None`],
+    /// and nothing is written.
+    pub fn get_to_writer<W>(&self, hash: Hash, writer: &mut W)
+        -> Result<Option<u64>>
+        where W: Write + AsFd
+    {
+        if self.verify_on_read() {
+            return match self.get(hash)? {
+                Some((mut reader, _)) => copy(&mut reader, writer).map(Some),
+                None => Ok(None),
+            };
+        }
+
+        let (file, offset, size) = match self.open_for_sendfile(hash)? {
+            Some(location) => location,
+            None => {
+                self.metrics.misses.fetch_add(1, Relaxed);
+                return Ok(None);
+            },
+        };
+        self.metrics.gets.fetch_add(1, Relaxed);
+
+        writer.flush()?;
+        let end = offset + size;
+        let mut position = offset as libc::off_t;
+        while (position as u64) < end {
+            let count = (end - position as u64).min(1 << 30) as usize;
+            let sent = fsutil::sendfile(&*writer, &file,
+                                        Some(&mut position), count);
+            match sent {
+                Ok(0) => return Err(truncated()),
+                Ok(n) => {
+                    self.metrics.bytes_read.fetch_add(n as u64, Relaxed);
+                },
+                Err(err) if err.kind() == Interrupted => (),
+                Err(err) if matches!(err.raw_os_error(),
+                                     Some(libc::EINVAL) | Some(libc::ENOSYS))
+                            && position as u64 == offset => {
+                    self.copy_buffered(&file, offset, end, writer)?;
+                    break;
+                },
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Some(size))
+    }
+
+    /// Open the file holding an object, returning it
+    /// along with the offset and size of the object in it.
+    fn open_for_sendfile(&self, hash: Hash)
+        -> Result<Option<(File, u64, u64)>>
+    {
+        let open_flags = { use libc::*; O_RDONLY | O_CLOEXEC |
+                                        O_NOCTTY | O_NOFOLLOW };
+        let path = format!("objects/{}", hash);
+        match fsutil::openat(&self.directory, path, open_flags, 0) {
+            Ok(file) => {
+                let metadata = file.metadata()?;
+                if !metadata.is_file() {
+                    return Err(VolumeError::NotRegularFile.into());
+                }
+                Ok(Some((file, 0, metadata.len())))
+            },
+            Err(err) if err.kind() == NotFound => {
+                let entry = match self.find_packed(hash)? {
+                    Some(entry) => entry,
+                    None => return Ok(None),
+                };
+                let data = fsutil::openat(&self.directory, "packs/data",
+                                          open_flags, 0)?;
+                Ok(Some((data, entry.offset, entry.size)))
+            },
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Copy a range of a file to a writer through a buffer.
+    fn copy_buffered(&self, file: &File, mut offset: u64, end: u64,
+                     writer: &mut impl Write) -> Result<()>
+    {
+        let mut buf = vec![0; 64 * 1024];
+        while offset < end {
+            let len = (end - offset).min(buf.len() as u64) as usize;
+            let n = file.read_at(&mut buf[.. len], offset)?;
+            if n == 0 {
+                return Err(truncated());
+            }
+            writer.write_all(&buf[.. n])?;
+            offset += n as u64;
+            self.metrics.bytes_read.fetch_add(n as u64, Relaxed);
+        }
+        Ok(())
+    }
+}
+
+fn truncated() -> Error
+{
+    Error::new(UnexpectedEof, "object file is truncated")
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use std::fs;
+    use std::io::Read;
+    use std::os::unix::net::UnixStream;
+    use super::*;
+
+    #[test]
+    fn test_get_to_writer()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_get_to_writer").unwrap();
+        let mut volume = Volume::open(&test_data.volume1_path).unwrap();
+        let output_path = test_data.root_path.join("output");
+        let mut output = File::create(&output_path).unwrap();
+        let (mut socket1, mut socket2) = UnixStream::pair().unwrap();
+
+        // Insert the objects, packing one of them.
+        let hash1 = volume.insert_from_path(&test_data.regular1_path)
+            .unwrap();
+        volume.set_pack_threshold(8);
+        let hash2 = volume.insert_from_bytes(b"small").unwrap();
+
+        // Copy the objects to a file and to a socket.
+        let size1 = volume.get_to_writer(hash1, &mut output).unwrap();
+        let size2 = volume.get_to_writer(hash2, &mut socket1).unwrap();
+        let size3 = volume.get_to_writer(test_data.regular2_hash,
+                                         &mut output).unwrap();
+        drop(socket1);
+        let mut received = Vec::new();
+        socket2.read_to_end(&mut received).unwrap();
+
+        // Check the results.
+        let written = fs::read(&output_path).unwrap();
+        assert_eq!(size1, Some(test_data.regular1_contents.len() as u64));
+        assert_eq!(size2, Some(5));
+        assert_eq!(size3, None);
+        assert_eq!(written, test_data.regular1_contents);
+        assert_eq!(received, b"small");
+    }
+}
@@ -0,0 +1,249 @@
+use crate::Hash;
+use crate::Hashes;
+use crate::ObjectReader;
+use crate::ObjectStore;
+use crate::Volume;
+use crate::union_all;
+use crate::union_get;
+use std::io::Error;
+use std::io::ErrorKind::InvalidInput;
+use std::io::ErrorKind::Unsupported;
+use std::io::Read;
+use std::io::Result;
+
+/// Volumes that together store objects, each a range of hashes.
+///
+/// This can be used to spread objects across several disks.
+/// The range of hashes of each shard is determined by the number of shards:
+/// the first two bytes of a hash are taken as a number,
+/// and the range of such numbers is divided evenly among the shards,
+/// in the order in which they are given.
+///
+/// When shards are added, removed, or reordered,
+/// existing objects are no longer in the shard for their hash.
+/// They are still found, as reads fall back to the other shards,
+/// but [`ShardedVolume::rebalance`] should be called
+/// to move them to where they belong.
+pub struct ShardedVolume
+{
+    shards: Vec<Volume>,
+}
+
+impl ShardedVolume
+{
+    /// Combine the given volumes into a sharded volume.
+    ///
+    /// There must be at least one volume,
+    /// and all volumes must use the same hash function.
+    pub fn new(shards: Vec<Volume>) -> Result<Self>
+    {
+        let first = shards.first().ok_or_else(|| {
+            Error::new(InvalidInput, "sharded volume has no shards")
+        })?;
+        if shards.iter().any(|s| s.hash_algorithm != first.hash_algorithm) {
+            return Err(Error::new(InvalidInput,
+                                  "shards use different hash functions"));
+        }
+        Ok(Self{shards})
+    }
+
+    /// The underlying volumes, in order.
+    pub fn shards(&self) -> &[Volume]
+    {
+        &self.shards
+    }
+
+    /// Give up the sharded volume, returning the underlying volumes.
+    pub fn into_shards(self) -> Vec<Volume>
+    {
+        self.shards
+    }
+
+    /// The index of the shard that the object belongs in.
+    pub fn shard_index(&self, hash: Hash) -> usize
+    {
+        let prefix = u16::from_be_bytes([hash.bytes[0], hash.bytes[1]]);
+        (prefix as usize * self.shards.len()) >> 16
+    }
+
+    /// The shard that the object belongs in.
+    pub fn shard(&self, hash: Hash) -> &Volume
+    {
+        &self.shards[self.shard_index(hash)]
+    }
+
+    /// Retrieve a reader for an object’s byte array,
+    /// as well as the size of the object in bytes.
+    ///
+    /// The shard that the object belongs in is tried first,
+    /// followed by the other shards.
+    /// If the object does not exist, this method returns [`None`].
+    pub fn get(&self, hash: Hash) -> Result<Option<(ObjectReader, u64)>>
+    {
+        union_get(self.probe_order(hash), hash)
+    }
+
+    /// Check whether an object exists in any of the shards.
+    pub fn contains(&self, hash: Hash) -> Result<bool>
+    {
+        for shard in self.probe_order(hash) {
+            if shard.contains(hash)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Return an iterator over the objects in all the shards.
+    ///
+    /// Objects that are in more than one shard,
+    /// which happens while rebalancing, are yielded more than once.
+    pub fn all(&self) -> impl '_ + Iterator<Item=Result<Hash>>
+    {
+        union_all(&self.shards)
+    }
+
+    /// Drain the given reader into the shard the object belongs in,
+    /// and return its hash.
+    ///
+    /// The hash is not known until the reader is drained,
+    /// so the object is inserted into the first shard,
+    /// and moved to the right shard afterwards
+    /// as with [`Volume::copy_object_to`].
+    pub fn insert_from_reader(&self, reader: &mut impl Read) -> Result<Hash>
+    {
+        let staging = &self.shards[0];
+        let hash = staging.insert_from_reader(reader)?;
+        let index = self.shard_index(hash);
+        if index != 0 {
+            self.move_object(0, index, hash)?;
+        }
+        Ok(hash)
+    }
+
+    /// Insert the given bytes into the shard the object belongs in,
+    /// and return its hash.
+    pub fn insert_from_bytes(&self, bytes: &[u8]) -> Result<Hash>
+    {
+        let hash = self.shards[0].hash_algorithm.compute_from_bytes(bytes);
+        self.shard(hash).insert_from_bytes(bytes)
+    }
+
+    /// Move every object that is not in the shard it belongs in
+    /// to that shard, after the set of shards has changed.
+    ///
+    /// Packed objects cannot be removed individually,
+    /// so those are copied and also left where they were.
+    /// Returns the number of objects copied.
+    /// Rebalancing is idempotent, so if it is interrupted,
+    /// it can simply be run again.
+    pub fn rebalance(&self) -> Result<u64>
+    {
+        let mut copied = 0;
+        for (index, shard) in self.shards.iter().enumerate() {
+            for hash in shard.all()? {
+                let hash = hash?;
+                let target = self.shard_index(hash);
+                if target != index && self.move_object(index, target, hash)? {
+                    copied += 1;
+                }
+            }
+        }
+        Ok(copied)
+    }
+
+    /// Move an object from one shard to another,
+    /// returning whether it had to be copied.
+    fn move_object(&self, from: usize, to: usize, hash: Hash) -> Result<bool>
+    {
+        let (src, dst) = (&self.shards[from], &self.shards[to]);
+        let copied = !dst.contains(hash)? && src.copy_object_to(hash, dst)?;
+        match src.remove(hash) {
+            Err(err) if err.kind() == Unsupported => (),
+            result => { result?; },
+        }
+        Ok(copied)
+    }
+
+    /// The shards, starting with the one the object belongs in.
+    fn probe_order(&self, hash: Hash) -> impl Iterator<Item=&Volume>
+    {
+        let index = self.shard_index(hash);
+        let (before, after) = self.shards.split_at(index);
+        after.iter().chain(before)
+    }
+}
+
+impl ObjectStore for ShardedVolume
+{
+    type Reader = ObjectReader;
+
+    fn get(&self, hash: Hash) -> Result<Option<(ObjectReader, u64)>>
+    {
+        ShardedVolume::get(self, hash)
+    }
+
+    fn contains(&self, hash: Hash) -> Result<bool>
+    {
+        ShardedVolume::contains(self, hash)
+    }
+
+    fn all(&self) -> Result<Hashes<'_>>
+    {
+        Ok(Box::new(ShardedVolume::all(self)))
+    }
+
+    fn insert_from_reader(&self, reader: &mut dyn Read) -> Result<Hash>
+    {
+        ShardedVolume::insert_from_reader(self, &mut &mut *reader)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use super::*;
+
+    #[test]
+    fn test_sharded_volume()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_sharded_volume").unwrap();
+        let open = |path| Volume::open(path).unwrap();
+        let sharded = ShardedVolume::new(vec![open(&test_data.volume1_path)])
+            .unwrap();
+
+        // Insert the objects into a single shard.
+        let hashes: Vec<Hash> = (0 .. 16u8)
+            .map(|i| sharded.insert_from_bytes(&[i]).unwrap())
+            .collect();
+        let hash = sharded
+            .insert_from_reader(&mut &test_data.regular1_contents[..])
+            .unwrap();
+
+        // Add a shard and rebalance.
+        let mut shards = sharded.into_shards();
+        shards.push(open(&test_data.volume2_path));
+        let sharded = ShardedVolume::new(shards).unwrap();
+        let found_before = hashes.iter()
+            .all(|&h| sharded.contains(h).unwrap());
+        let copied = sharded.rebalance().unwrap();
+
+        // Check the results.
+        let moved = hashes.iter().chain([&hash])
+            .filter(|&&h| sharded.shard_index(h) == 1)
+            .count();
+        assert!(found_before);
+        assert_ne!(moved, 0);
+        assert_eq!(copied, moved as u64);
+        assert_eq!(sharded.rebalance().unwrap(), 0);
+        for &h in hashes.iter().chain([&hash]) {
+            let index = sharded.shard_index(h);
+            assert!(sharded.shards()[index].contains(h).unwrap());
+            assert!(!sharded.shards()[1 - index].contains(h).unwrap());
+            assert!(sharded.get(h).unwrap().is_some());
+        }
+        assert_eq!(sharded.all().count(), 17);
+    }
+}
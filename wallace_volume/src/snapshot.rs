@@ -0,0 +1,113 @@
+use crate::Volume;
+use crate::VolumeBuilder;
+use crate::layout;
+use crate::volume::copy_file_contents;
+use std::io::ErrorKind::NotFound;
+use std::io::Result;
+use std::os::unix::io::AsFd;
+use std::path::Path;
+use wallace_fsutil as fsutil;
+
+impl Volume
+{
+    /// Create a new volume at the given path,
+    /// which must not yet exist, holding the objects of this volume.
+    ///
+    /// The files backing loose objects are hard-linked,
+    /// which is cheap, and safe because objects are immutable.
+    /// The path must therefore be on the same file system.
+    /// Removing objects from either volume, for instance through
+    /// garbage collection, does not affect the other.
+    /// The volumes share the files,
+    /// so [`Volume::audit_links`] reports the objects in both.
+    ///
+    /// Packs are appended to in place, so they are copied instead,
+    /// with the cheapest method available.
+    /// Provenance, pins, and the trash are not part of the snapshot,
+    /// and the new volume gets an identifier of its own.
+    /// Objects inserted or removed during the snapshot
+    /// may or may not be part of it.
+    pub fn snapshot_to(&self, path: impl AsRef<Path>) -> Result<()>
+    {
+        let path = path.as_ref();
+        VolumeBuilder::new()
+            .hash_algorithm(self.hash_algorithm)
+            .create_layout(path)?;
+        let directory = layout::open_directory(path)?;
+
+        for hash in self.all_loose()? {
+            let path = format!("objects/{}", hash?);
+            match fsutil::linkat(&self.directory, &path,
+                                 &directory, &path, 0) {
+                Ok(()) => (),
+                Err(err) if err.kind() == NotFound => (),
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let open_flags = libc::O_RDONLY | libc::O_CLOEXEC | libc::O_NOFOLLOW;
+        let index = match fsutil::openat(&self.directory, "packs/index",
+                                         open_flags, 0) {
+            Ok(index) => index,
+            Err(err) if err.kind() == NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+
+        // Keep other processes from appending while copying,
+        // so that the copied index only refers to copied data.
+        let _lock = fsutil::FlockGuard::lock(index.as_fd(), libc::LOCK_EX)?;
+
+        fsutil::mkdirat(&directory, "packs", 0o755)?;
+        let create_flags = { use libc::*; O_WRONLY | O_CREAT | O_EXCL |
+                                          O_CLOEXEC | O_NOFOLLOW };
+        for name in &["packs/data", "packs/index"] {
+            let src = fsutil::openat(&self.directory, name, open_flags, 0)?;
+            let mut dest = fsutil::openat(&directory, name,
+                                          create_flags, 0o644)?;
+            copy_file_contents(&src, &mut dest)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::GcRoots;
+    use crate::TestData;
+    use std::fs;
+    use super::*;
+
+    #[test]
+    fn test_snapshot_to()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_snapshot_to").unwrap();
+        let mut volume = Volume::open(&test_data.volume1_path).unwrap();
+        let snapshot_path = test_data.root_path.join("snapshot");
+
+        // Insert the objects, packing one of them.
+        let hash1 = volume.insert_from_path(&test_data.regular1_path)
+            .unwrap();
+        fs::remove_file(&test_data.regular1_path).unwrap();
+        volume.set_pack_threshold(8);
+        let hash2 = volume.insert_from_bytes(b"small").unwrap();
+
+        // Take the snapshot, and change the original afterwards.
+        volume.snapshot_to(&snapshot_path).unwrap();
+        let hash3 = volume.insert_from_bytes(b"tiny").unwrap();
+        volume.collect_garbage(GcRoots::new(), false).unwrap();
+        let snapshot = Volume::open(&snapshot_path).unwrap();
+
+        // Check the results.
+        let stat1 = volume.stat(hash1).unwrap();
+        let stat2 = snapshot.stat(hash1).unwrap().unwrap();
+        assert_eq!(stat1, None);
+        assert_eq!(stat2.nlink, 1);
+        assert!(snapshot.contains(hash2).unwrap());
+        assert!(!snapshot.contains(hash3).unwrap());
+        assert_ne!(snapshot.id(), volume.id());
+        assert_eq!(snapshot.all().unwrap().count(), 2);
+    }
+}
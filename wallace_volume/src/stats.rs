@@ -0,0 +1,98 @@
+use crate::Hash;
+use crate::Volume;
+use std::io::ErrorKind::NotFound;
+use std::io::Result;
+use wallace_fsutil as fsutil;
+
+/// Summary of the objects in a volume.
+///
+/// Returned by [`Volume::stats`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct VolumeStats
+{
+    /// The number of objects in the volume.
+    pub objects: u64,
+
+    /// The total size in bytes of the objects in the volume.
+    pub logical_bytes: u64,
+
+    /// The total number of bytes allocated on disk for the objects,
+    /// as reported by the `st_blocks` field of `stat`.
+    /// This may be smaller than the logical size
+    /// if files are sparse or compressed by the file system.
+    pub disk_bytes: u64,
+
+    /// The largest object in the volume and its size in bytes,
+    /// or [`None`] if the volume is empty.
+    pub largest: Option<(Hash, u64)>,
+}
+
+impl Volume
+{
+    /// Compute statistics about the objects in the volume.
+    ///
+    /// This lists the objects directory once
+    /// and stats every object in it.
+    /// Objects that are removed concurrently are skipped.
+    /// Objects that are hard linked elsewhere
+    /// are still counted in full towards [`VolumeStats::disk_bytes`].
+    /// Packed objects are not counted at all.
+    pub fn stats(&self) -> Result<VolumeStats>
+    {
+        let mut stats = VolumeStats::default();
+
+        for hash in self.all_loose()? {
+            let hash = hash?;
+
+            let path = format!("objects/{}", hash);
+            let stat_result = fsutil::fstatat(&self.directory, path,
+                                              libc::AT_SYMLINK_NOFOLLOW);
+            let stat = match stat_result {
+                Ok(stat) => stat,
+                Err(err) if err.kind() == NotFound => continue,
+                Err(err) => return Err(err.into()),
+            };
+
+            let size = stat.st_size as u64;
+            stats.objects       += 1;
+            stats.logical_bytes += size;
+            stats.disk_bytes    += stat.st_blocks as u64 * 512;
+            if stats.largest.is_none_or(|(_, largest)| size > largest) {
+                stats.largest = Some((hash, size));
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use super::*;
+
+    #[test]
+    fn test_stats()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_stats").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+        let empty = volume.stats().unwrap();
+
+        // Insert the objects.
+        volume.insert_from_path(&test_data.regular1_path).unwrap();
+        volume.insert_from_path(&test_data.regular2_path).unwrap();
+        let stats = volume.stats().unwrap();
+
+        // Check the results.
+        let size1 = test_data.regular1_contents.len() as u64;
+        let size2 = test_data.regular2_contents.len() as u64;
+        let largest = if size1 >= size2 { (test_data.regular1_hash, size1) }
+                      else { (test_data.regular2_hash, size2) };
+        assert_eq!(empty, VolumeStats::default());
+        assert_eq!(stats.objects, 2);
+        assert_eq!(stats.logical_bytes, size1 + size2);
+        assert_eq!(stats.largest.map(|(_, size)| size), Some(largest.1));
+    }
+}
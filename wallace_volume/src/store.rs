@@ -0,0 +1,93 @@
+use crate::Hash;
+use crate::ObjectReader;
+use crate::Volume;
+use std::io::Read;
+use std::io::Result;
+
+/// Iterator over the hashes of the objects in an object store.
+pub type Hashes<'a> = Box<dyn 'a + Iterator<Item=Result<Hash>>>;
+
+/// Storage backend for objects.
+///
+/// This trait captures the operations that every backend supports,
+/// so that code such as [`union_get`][`crate::union_get`]
+/// can be written once for all of them.
+/// [`Volume`] is the canonical implementation;
+/// its inherent methods offer more operations than this trait does.
+pub trait ObjectStore
+{
+    /// Reader returned by [`ObjectStore::get`].
+    type Reader: Read;
+
+    /// Retrieve a reader for an object’s byte array,
+    /// as well as the size of the object in bytes.
+    ///
+    /// If the object does not exist, this method returns [`None`].
+    fn get(&self, hash: Hash) -> Result<Option<(Self::Reader, u64)>>;
+
+    /// Check whether an object exists in the store.
+    fn contains(&self, hash: Hash) -> Result<bool>;
+
+    /// Return an iterator over the hashes of the objects in the store.
+    fn all(&self) -> Result<Hashes<'_>>;
+
+    /// Drain the given reader into the store, and return its hash.
+    fn insert_from_reader(&self, reader: &mut dyn Read) -> Result<Hash>;
+}
+
+impl ObjectStore for Volume
+{
+    type Reader = ObjectReader;
+
+    fn get(&self, hash: Hash) -> Result<Option<(ObjectReader, u64)>>
+    {
+        Volume::get(self, hash)
+    }
+
+    fn contains(&self, hash: Hash) -> Result<bool>
+    {
+        Volume::contains(self, hash)
+    }
+
+    fn all(&self) -> Result<Hashes<'_>>
+    {
+        Ok(Box::new(Volume::all(self)?))
+    }
+
+    fn insert_from_reader(&self, reader: &mut dyn Read) -> Result<Hash>
+    {
+        Volume::insert_from_reader(self, &mut &mut *reader)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use super::*;
+
+    /// Exercise a store only through the trait.
+    fn roundtrip(store: &impl ObjectStore, data: &[u8]) -> Result<Vec<u8>>
+    {
+        let hash = store.insert_from_reader(&mut &data[..])?;
+        assert!(store.contains(hash)?);
+        assert_eq!(store.all()?.collect::<Result<Vec<_>>>()?, [hash]);
+        let (mut reader, size) = store.get(hash)?.unwrap();
+        let mut actual = Vec::new();
+        reader.read_to_end(&mut actual)?;
+        assert_eq!(size, actual.len() as u64);
+        Ok(actual)
+    }
+
+    #[test]
+    fn test_object_store_volume()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_object_store_volume").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+
+        // Check the results.
+        let data = roundtrip(&volume, &test_data.regular1_contents).unwrap();
+        assert_eq!(data, test_data.regular1_contents);
+    }
+}
@@ -0,0 +1,235 @@
+use crate::Hash;
+use crate::HashAlgorithm;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Maximum length of the digest in a [`TaggedHash`], in bytes.
+pub const MAX_DIGEST_LEN: usize = 64;
+
+/// Hash that records which hash function computed it.
+///
+/// Unlike [`struct@Hash`], which is always 32 bytes
+/// and relies on context for its hash function,
+/// a tagged hash carries the [`HashAlgorithm`] that computed it,
+/// and a digest of between 1 and [`MAX_DIGEST_LEN`] bytes.
+/// This lets hashes from volumes with different hash functions
+/// be told apart in manifests, paths, and other external formats.
+///
+/// The [`Display`][`fmt::Display`] impl formats the tagged hash
+/// as the name of the hash function, a colon,
+/// and the digest in lowercase hexadecimal, e.g. `sha256:e3b0…`.
+/// The [`FromStr`] impl parses this format,
+/// and also the untagged format of [`struct@Hash`],
+/// which it takes to be computed with SHA-256,
+/// so that existing hashes remain valid.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct TaggedHash
+{
+    algorithm: HashAlgorithm,
+    len: u8,
+
+    /// The digest, padded with zeros, so that the derives agree.
+    bytes: [u8; MAX_DIGEST_LEN],
+}
+
+impl TaggedHash
+{
+    /// Create a tagged hash from a hash function and its digest.
+    ///
+    /// Returns [`None`] if the digest is empty
+    /// or longer than [`MAX_DIGEST_LEN`] bytes.
+    pub fn new(algorithm: HashAlgorithm, digest: &[u8]) -> Option<Self>
+    {
+        if digest.is_empty() || digest.len() > MAX_DIGEST_LEN {
+            return None;
+        }
+        let mut bytes = [0; MAX_DIGEST_LEN];
+        bytes[.. digest.len()].copy_from_slice(digest);
+        Some(Self{algorithm, len: digest.len() as u8, bytes})
+    }
+
+    /// Tag a hash with the hash function that computed it.
+    pub fn from_hash(algorithm: HashAlgorithm, hash: Hash) -> Self
+    {
+        let mut bytes = [0; MAX_DIGEST_LEN];
+        bytes[.. 32].copy_from_slice(&hash.bytes);
+        Self{algorithm, len: 32, bytes}
+    }
+
+    /// The hash function that computed the hash.
+    pub fn algorithm(&self) -> HashAlgorithm
+    {
+        self.algorithm
+    }
+
+    /// The digest computed by the hash function.
+    pub fn digest(&self) -> &[u8]
+    {
+        &self.bytes[.. self.len as usize]
+    }
+
+    /// The digest as an untagged hash,
+    /// or [`None`] if it is not 32 bytes long.
+    ///
+    /// The hash function is lost,
+    /// so check [`TaggedHash::algorithm`] first.
+    pub fn to_hash(&self) -> Option<Hash>
+    {
+        if self.len != 32 {
+            return None;
+        }
+        let mut bytes = [0; 32];
+        bytes.copy_from_slice(self.digest());
+        Some(Hash{bytes})
+    }
+}
+
+impl fmt::Display for TaggedHash
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{}:", self.algorithm.name())?;
+        for byte in self.digest() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returned when a tagged hash could not be parsed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InvalidTaggedHash
+{
+    /// The name of the hash function is not known.
+    UnknownAlgorithm,
+
+    /// The digest is empty, too long, or has an odd number of digits.
+    InvalidLength
+    {
+        /// The length of the digest in the text, in bytes.
+        found: usize,
+    },
+
+    /// The text contains a byte that is not valid in the digest.
+    InvalidCharacter
+    {
+        /// The offset of the byte in the text.
+        position: usize,
+
+        /// The byte itself.
+        byte: u8,
+    },
+}
+
+impl fmt::Display for InvalidTaggedHash
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self {
+            Self::UnknownAlgorithm =>
+                write!(f, "hash has unknown algorithm"),
+            Self::InvalidLength{found} =>
+                write!(f, "hash has digest of invalid length {}", found),
+            Self::InvalidCharacter{position, byte} =>
+                write!(f, "hash has invalid character '{}' at position {}",
+                       std::ascii::escape_default(*byte), position),
+        }
+    }
+}
+
+impl Error for InvalidTaggedHash
+{
+}
+
+impl FromStr for TaggedHash
+{
+    type Err = InvalidTaggedHash;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        let (algorithm, digest, offset) = match s.find(':') {
+            Some(colon) => {
+                let algorithm = HashAlgorithm::from_name(&s[.. colon])
+                    .ok_or(InvalidTaggedHash::UnknownAlgorithm)?;
+                (algorithm, &s.as_bytes()[colon + 1 ..], colon + 1)
+            },
+            None => (HashAlgorithm::Sha256, s.as_bytes(), 0),
+        };
+
+        if digest.is_empty() || digest.len() % 2 != 0
+            || digest.len() > 2 * MAX_DIGEST_LEN {
+            return Err(InvalidTaggedHash::InvalidLength{found: digest.len()});
+        }
+
+        let hex = |i: usize| -> Result<u8, InvalidTaggedHash> {
+            match digest[i] {
+                c @ b'0' ..= b'9' => Ok(c - b'0'),
+                c @ b'a' ..= b'f' => Ok(c - b'a' + 10),
+                byte => Err(InvalidTaggedHash::InvalidCharacter{
+                    position: offset + i,
+                    byte,
+                }),
+            }
+        };
+
+        let len = digest.len() / 2;
+        let mut bytes = [0; MAX_DIGEST_LEN];
+        for (i, byte) in bytes[.. len].iter_mut().enumerate() {
+            *byte = hex(2 * i)? << 4 | hex(2 * i + 1)?;
+        }
+
+        let len = len as u8;
+        Ok(Self{algorithm, len, bytes})
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_tagged_hash()
+    {
+        let hash = Hash::compute_from_bytes(b"Hello, world!");
+        let untagged = hash.to_string();
+        let tagged = format!("sha256:{}", untagged);
+
+        let sha256 = TaggedHash::from_hash(HashAlgorithm::Sha256, hash);
+        assert_eq!(sha256.to_string(), tagged);
+        assert_eq!(tagged.parse(), Ok(sha256));
+        assert_eq!(untagged.parse(), Ok(sha256));
+        assert_eq!(sha256.to_hash(), Some(hash));
+
+        let blake3 = TaggedHash::new(HashAlgorithm::Blake3, &[0xAB; 48])
+            .unwrap();
+        let text = format!("blake3:{}", "ab".repeat(48));
+        assert_eq!(blake3.to_string(), text);
+        assert_eq!(text.parse(), Ok(blake3));
+        assert_eq!(blake3.digest(), &[0xAB; 48]);
+        assert_eq!(blake3.to_hash(), None);
+        assert_ne!(TaggedHash::from_hash(HashAlgorithm::Blake3, hash),
+                   sha256);
+
+        assert_eq!(TaggedHash::new(HashAlgorithm::Sha256, &[]), None);
+        assert_eq!(TaggedHash::new(HashAlgorithm::Sha256, &[0; 65]), None);
+
+        let examples = &[
+            ("md5:00", InvalidTaggedHash::UnknownAlgorithm),
+            ("sha256:", InvalidTaggedHash::InvalidLength{found: 0}),
+            ("sha256:abc", InvalidTaggedHash::InvalidLength{found: 3}),
+            ("sha256:abcg", InvalidTaggedHash::InvalidCharacter{
+                position: 10,
+                byte: b'g',
+            }),
+            ("AB", InvalidTaggedHash::InvalidCharacter{
+                position: 0,
+                byte: b'A',
+            }),
+        ];
+        for (input, expected) in examples {
+            assert_eq!(input.parse::<TaggedHash>(), Err(*expected));
+        }
+    }
+}
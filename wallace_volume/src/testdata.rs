@@ -6,6 +6,7 @@ use std::io::Result;
 use std::os::unix;
 use std::path::PathBuf;
 use wallace_fsutil as fsutil;
+use wallace_fsutil::FileType;
 
 pub struct TestData
 {
@@ -62,8 +63,9 @@ impl TestData
         let socket1_path    = root_path.join("socket1");
         let symlink1_path   = root_path.join("symlink1");
         fs::create_dir(&directory1_path)?;
-        fsutil::mknod(&fifo1_path, libc::S_IFIFO | 0o644, 0)?;
-        fsutil::mknod(&socket1_path, libc::S_IFSOCK | 0o644, 0)?;
+        fsutil::mknod(&fifo1_path, FileType::Fifo.to_st_mode() | 0o644, 0)?;
+        fsutil::mknod(&socket1_path, FileType::Socket.to_st_mode() | 0o644,
+                      0)?;
         unix::fs::symlink("/etc/passwd", &symlink1_path)?;
 
         Ok(
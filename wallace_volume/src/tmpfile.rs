@@ -0,0 +1,156 @@
+use crate::Volume;
+use std::fs::File;
+use std::io::ErrorKind::AlreadyExists;
+use std::io::Result;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::process;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering::Relaxed;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use wallace_fsutil as fsutil;
+
+/// Temporary file on the file system of a volume,
+/// to be linked into the volume with [`Volume::link_object`].
+///
+/// Preferably the file has no name at all.
+/// If it does have a name, the name is removed on drop.
+/// Linking the file into the volume beforehand keeps the file alive.
+pub (crate) struct TmpFile<'a>
+{
+    volume: &'a Volume,
+    file: File,
+    path: Option<String>,
+}
+
+impl Volume
+{
+    /// Create a temporary file on the file system of the volume.
+    ///
+    /// If the file system does not support `O_TMPFILE`,
+    /// this falls back to creating a named file
+    /// in the `tmp` directory of the volume.
+    pub (crate) fn create_tmpfile(&self) -> Result<TmpFile<'_>>
+    {
+        // By using O_TMPFILE, Linux will create a file with no path.
+        // We can then write this file and link it into the volume.
+        let open_flags = libc::O_RDWR | libc::O_TMPFILE;
+
+        // We must write the file and then read it,
+        // so we will use this open mode.
+        let open_mode = 0o600;
+
+        // We must still pass some path to openat.
+        // Linux uses this to determine the file system
+        // on which the file is to be stored.
+        // We pass the path to the volume directory.
+        let result = fsutil::openat(&self.directory, ".",
+                                    open_flags, open_mode);
+
+        // File systems without O_TMPFILE support report EOPNOTSUPP.
+        // Kernels that predate O_TMPFILE ignore the unknown bits,
+        // and then complain that the path is a directory.
+        match result {
+            Ok(file) => Ok(TmpFile{volume: self, file, path: None}),
+            Err(err) if matches!(err.raw_os_error(),
+                                 Some(libc::EOPNOTSUPP) |
+                                 Some(libc::EISDIR)) =>
+                self.create_named_tmpfile(),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Create a temporary file with a unique name
+    /// in the `tmp` directory of the volume.
+    pub (crate) fn create_named_tmpfile(&self) -> Result<TmpFile<'_>>
+    {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        // Volumes created before this fallback existed lack the directory.
+        match fsutil::mkdirat(&self.directory, "tmp", 0o755) {
+            Ok(()) => (),
+            Err(err) if err.kind() == AlreadyExists => (),
+            Err(err) => return Err(err.into()),
+        }
+
+        let open_flags = { use libc::*; O_RDWR | O_CREAT | O_EXCL |
+                                        O_CLOEXEC | O_NOFOLLOW };
+        loop {
+            // The name need not be unpredictable, only unique.
+            // O_EXCL takes care of collisions with other processes.
+            let nanos = SystemTime::now().duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.subsec_nanos());
+            let path = format!("tmp/{}-{}-{}", process::id(), nanos,
+                               COUNTER.fetch_add(1, Relaxed));
+            match fsutil::openat(&self.directory, &path, open_flags, 0o600) {
+                Ok(file) => return Ok(TmpFile{volume: self, file,
+                                              path: Some(path)}),
+                Err(err) if err.kind() == AlreadyExists => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+impl<'a> Deref for TmpFile<'a>
+{
+    type Target = File;
+
+    fn deref(&self) -> &File
+    {
+        &self.file
+    }
+}
+
+impl<'a> DerefMut for TmpFile<'a>
+{
+    fn deref_mut(&mut self) -> &mut File
+    {
+        &mut self.file
+    }
+}
+
+impl<'a> Drop for TmpFile<'a>
+{
+    fn drop(&mut self)
+    {
+        if let Some(path) = &self.path {
+            // There is nothing sensible to do if this fails.
+            let _ = fsutil::unlinkat(&self.volume.directory, path, 0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::Hash;
+    use crate::TestData;
+    use std::fs;
+    use std::io::Write;
+    use super::*;
+
+    #[test]
+    fn test_create_named_tmpfile()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_create_named_tmpfile").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+        let tmp_path = test_data.volume1_path.join("tmp");
+
+        // Insert the object through a named temporary file.
+        let mut tmpfile = volume.create_named_tmpfile().unwrap();
+        tmpfile.write_all(&test_data.regular1_contents).unwrap();
+        let hash = Hash::compute_from_bytes(&test_data.regular1_contents);
+        volume.link_object(&tmpfile, hash).unwrap();
+        let entries_before = fs::read_dir(&tmp_path).unwrap().count();
+        drop(tmpfile);
+        let entries_after = fs::read_dir(&tmp_path).unwrap().count();
+
+        // Check the results.
+        assert_eq!(entries_before, 1);
+        assert_eq!(entries_after, 0);
+        assert!(volume.contains(test_data.regular1_hash).unwrap());
+    }
+}
@@ -0,0 +1,148 @@
+use crate::Hash;
+use crate::ObjectReader;
+use crate::Volume;
+use crate::volume::copy_file_contents;
+use std::io::Error;
+use std::io::ErrorKind::InvalidInput;
+use std::io::ErrorKind::NotFound;
+use std::io::Read;
+use std::io::Result;
+use std::io::copy;
+use std::time::Instant;
+use wallace_fsutil as fsutil;
+
+impl Volume
+{
+    /// Insert an object from this volume into another volume.
+    ///
+    /// The object is not hashed again, and if both volumes are
+    /// on the same file system, the file backing the object
+    /// is hard-linked into the other volume rather than copied.
+    /// The volumes then share the file,
+    /// so [`Volume::audit_links`] reports the object in both.
+    /// Otherwise the object is copied as in
+    /// [`Volume::insert_copy_from_file`].
+    /// Packed objects, and objects that the other volume packs,
+    /// are always copied.
+    ///
+    /// The object is trusted to match its hash, even if
+    /// [verification on read][`Volume::set_verify_on_read`] is enabled.
+    /// The volumes must use the same hash function.
+    /// Returns whether the object exists in this volume.
+    pub fn copy_object_to(&self, hash: Hash, dst: &Volume) -> Result<bool>
+    {
+        if self.hash_algorithm != dst.hash_algorithm {
+            return Err(Error::new(InvalidInput,
+                                  "volumes use different hash algorithms"));
+        }
+
+        if dst.contains(hash)? {
+            return self.contains(hash);
+        }
+
+        let start = Instant::now();
+
+        let path = format!("objects/{}", hash);
+        let open_flags = { use libc::*; O_RDONLY | O_CLOEXEC |
+                                        O_NOCTTY | O_NOFOLLOW };
+        let file = match fsutil::openat(&self.directory, path,
+                                        open_flags, 0) {
+            Ok(file) => Some(file),
+            Err(err) if err.kind() == NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        match file {
+            Some(file) if !dst.should_pack(file.metadata()?.len()) => {
+                match dst.link_object(&file, hash) {
+                    Ok(_) => (),
+                    Err(err) if fsutil::FsError::raw_os_error_of(&err)
+                                == Some(libc::EXDEV) => {
+                        let mut tmpfile = dst.create_tmpfile()?;
+                        copy_file_contents(&file, &mut tmpfile)?;
+                        dst.link_object(&tmpfile, hash)?;
+                    },
+                    Err(err) => return Err(err),
+                }
+            },
+            Some(file) => {
+                let size = file.metadata()?.len();
+                let reader = ObjectReader::new(file, 0, size);
+                self.copy_reader_to(reader, hash, dst)?;
+            },
+            None => {
+                let reader = match self.get_packed(hash)? {
+                    Some(reader) => reader,
+                    None => return Ok(false),
+                };
+                self.copy_reader_to(reader, hash, dst)?;
+            },
+        }
+
+        dst.record_insert(start.elapsed());
+        Ok(true)
+    }
+
+    /// Insert an object into another volume by reading it.
+    fn copy_reader_to(&self, mut reader: ObjectReader, hash: Hash,
+                      dst: &Volume) -> Result<()>
+    {
+        let size = reader.size();
+        if dst.should_pack(size) {
+            let mut bytes = Vec::with_capacity(size as usize);
+            reader.read_to_end(&mut bytes)?;
+            dst.insert_packed(hash, &bytes)?;
+        } else {
+            let mut tmpfile = dst.create_tmpfile()?;
+            copy(&mut reader, &mut *tmpfile)?;
+            dst.link_object(&tmpfile, hash)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::HashAlgorithm;
+    use crate::TestData;
+    use super::*;
+
+    #[test]
+    fn test_copy_object_to()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_copy_object_to").unwrap();
+        let mut volume1 = Volume::open(&test_data.volume1_path).unwrap();
+        let volume2 = Volume::open(&test_data.volume2_path).unwrap();
+        let blake3_path = test_data.root_path.join("blake3");
+        Volume::create_with_hash_algorithm(&blake3_path, HashAlgorithm::Blake3)
+            .unwrap();
+        let volume3 = Volume::open(&blake3_path).unwrap();
+
+        // Insert the objects, packing one of them.
+        let hash1 = volume1.insert_from_path(&test_data.regular1_path)
+            .unwrap();
+        volume1.set_pack_threshold(8);
+        let hash2 = volume1.insert_from_bytes(b"small").unwrap();
+
+        // Copy the objects.
+        let copied1 = volume1.copy_object_to(hash1, &volume2).unwrap();
+        let copied2 = volume1.copy_object_to(hash2, &volume2).unwrap();
+        let copied3 = volume1.copy_object_to(hash1, &volume2).unwrap();
+        let copied4 = volume1.copy_object_to(test_data.regular2_hash,
+                                             &volume2).unwrap();
+        let result = volume1.copy_object_to(hash1, &volume3);
+
+        // Check the results.
+        let stat1 = volume1.stat(hash1).unwrap().unwrap();
+        let stat2 = volume2.stat(hash1).unwrap().unwrap();
+        let mut contents = Vec::new();
+        volume2.get(hash2).unwrap().unwrap().0
+            .read_to_end(&mut contents).unwrap();
+        assert!(copied1 && copied2 && copied3 && !copied4);
+        assert_eq!(stat1.inode, stat2.inode);
+        assert_eq!(contents, b"small");
+        assert_eq!(result.err().map(|e| e.kind()), Some(InvalidInput));
+    }
+}
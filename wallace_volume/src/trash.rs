@@ -0,0 +1,186 @@
+use crate::Hash;
+use crate::Volume;
+use crate::VolumeEvent;
+use std::io::ErrorKind::AlreadyExists;
+use std::io::ErrorKind::NotFound;
+use std::io::Result;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use wallace_fsutil as fsutil;
+
+impl Volume
+{
+    /// Whether [`Volume::remove`] moves objects to the trash.
+    pub fn trash(&self) -> bool
+    {
+        self.trash
+    }
+
+    /// Change whether [`Volume::remove`] moves objects to the trash.
+    ///
+    /// If enabled, removed objects are moved to the `trash` directory
+    /// in the volume’s directory, named after their hash
+    /// and the time of removal in seconds since the Unix epoch.
+    /// They can be restored with [`Volume::undelete`],
+    /// and are removed for good by [`Volume::purge_trash`].
+    /// Their provenance is retained until they are purged.
+    /// Objects in the trash do not count towards the quota.
+    /// The trash is disabled by default.
+    pub fn set_trash(&mut self, trash: bool)
+    {
+        self.trash = trash;
+    }
+
+    /// Move an object from the trash back into the volume.
+    ///
+    /// If the object was removed several times,
+    /// the most recently removed copy is restored.
+    /// Returns whether the object was in the trash.
+    pub fn undelete(&self, hash: Hash) -> Result<bool>
+    {
+        let latest = self.trashed()?.into_iter()
+            .filter(|&(h, _)| h == hash)
+            .map(|(_, removed)| removed)
+            .max();
+        let removed = match latest {
+            Some(removed) => removed,
+            None => return Ok(false),
+        };
+
+        let trash_path = format!("trash/{}.{}", hash, removed);
+        let size = fsutil::fstatat(&self.directory, &trash_path,
+                                   libc::AT_SYMLINK_NOFOLLOW)?.st_size;
+        self.reserve_quota(size as u64)?;
+
+        // If the object was inserted again in the meantime,
+        // replacing it is harmless, as the contents are the same.
+        let path = format!("objects/{}", hash);
+        let result = fsutil::renameat(&self.directory, trash_path,
+                                      &self.directory, path);
+        if let Err(err) = result {
+            self.release_quota(size as u64);
+            return Err(err.into());
+        }
+
+        self.record_in_bloom_filter(hash);
+        self.notify(VolumeEvent::Inserted{hash, size: size as u64});
+        Ok(true)
+    }
+
+    /// Remove objects for good that were moved to the trash
+    /// at least the given duration ago.
+    ///
+    /// Provenance is removed for objects that are no longer in the volume.
+    /// Returns the number of objects that were purged.
+    pub fn purge_trash(&self, older_than: Duration) -> Result<u64>
+    {
+        let cutoff = SystemTime::now() - older_than;
+        let cutoff = cutoff.duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+
+        let mut purged = 0;
+        for (hash, removed) in self.trashed()? {
+            if removed > cutoff {
+                continue;
+            }
+            let path = format!("trash/{}.{}", hash, removed);
+            match fsutil::unlinkat(&self.directory, path, 0) {
+                Ok(()) => purged += 1,
+                Err(err) if err.kind() == NotFound => continue,
+                Err(err) => return Err(err.into()),
+            }
+            if !self.contains(hash)? {
+                self.remove_provenance(hash)?;
+            }
+        }
+        Ok(purged)
+    }
+
+    /// Unlink the file backing an object,
+    /// or move it to the trash if that is enabled.
+    pub (crate) fn discard_object(&self, hash: Hash) -> Result<()>
+    {
+        let path = format!("objects/{}", hash);
+        if !self.trash {
+            fsutil::unlinkat(&self.directory, path, 0)?;
+            return Ok(());
+        }
+
+        match fsutil::mkdirat(&self.directory, "trash", 0o755) {
+            Ok(()) => (),
+            Err(err) if err.kind() == AlreadyExists => (),
+            Err(err) => return Err(err.into()),
+        }
+
+        let removed = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let trash_path = format!("trash/{}.{}", hash, removed);
+        fsutil::renameat(&self.directory, path, &self.directory, trash_path)?;
+        Ok(())
+    }
+
+    /// Return the objects in the trash with their times of removal.
+    fn trashed(&self) -> Result<Vec<(Hash, u64)>>
+    {
+        let open_flags = libc::O_RDONLY | libc::O_DIRECTORY;
+        let directory = match fsutil::openat(&self.directory, "trash",
+                                             open_flags, 0) {
+            Ok(directory) => directory,
+            Err(err) if err.kind() == NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut trashed = Vec::new();
+        let mut dir = fsutil::fdopendir(directory)?;
+        while let Some(dirent) = fsutil::readdir(&mut dir)? {
+            let filename = dirent.d_name().to_bytes();
+            if filename.len() < 66 || filename[64] != b'.' {
+                continue;
+            }
+            let hash = Hash::from_ascii(&filename[.. 64]);
+            let removed = std::str::from_utf8(&filename[65 ..]).ok()
+                .and_then(|s| s.parse().ok());
+            if let (Ok(hash), Some(removed)) = (hash, removed) {
+                trashed.push((hash, removed));
+            }
+        }
+        Ok(trashed)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use super::*;
+
+    #[test]
+    fn test_trash()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_trash").unwrap();
+        let mut volume = Volume::open(&test_data.volume1_path).unwrap();
+        volume.set_trash(true);
+
+        // Insert and remove the objects.
+        let hash1 = volume.insert_from_bytes(b"Hello").unwrap();
+        let hash2 = volume.insert_from_bytes(b"world").unwrap();
+        let removed1 = volume.remove(hash1).unwrap();
+        let removed2 = volume.remove(hash2).unwrap();
+
+        // Restore one object and purge the other.
+        let undeleted1 = volume.undelete(hash1).unwrap();
+        let undeleted2 = volume.undelete(hash1).unwrap();
+        let purged1 = volume.purge_trash(Duration::from_secs(3600)).unwrap();
+        let purged2 = volume.purge_trash(Duration::ZERO).unwrap();
+        let undeleted3 = volume.undelete(hash2).unwrap();
+
+        // Check the results.
+        assert!(removed1 && removed2);
+        assert!(undeleted1 && !undeleted2 && !undeleted3);
+        assert_eq!((purged1, purged2), (0, 1));
+        assert!(volume.contains(hash1).unwrap());
+        assert!(!volume.contains(hash2).unwrap());
+    }
+}
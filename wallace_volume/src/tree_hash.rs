@@ -0,0 +1,158 @@
+use crate::Hash;
+use std::fs::File;
+use std::io::Error;
+use std::io::ErrorKind::UnexpectedEof;
+use std::io::Result;
+use std::os::unix::fs::FileExt;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use std::thread;
+use wallace_sha256::Sha256;
+
+/// Size of the chunks hashed by [`tree_hash_file`], in bytes.
+pub const TREE_HASH_CHUNK_SIZE: u64 = 1 << 20;
+
+/// Compute the tree hash of a file, from the given number of threads.
+///
+/// The tree hash is a fingerprint of the contents of the file
+/// that, unlike the flat hash of an object, can be computed in parallel.
+/// This makes it useful for tooling that needs to recognize
+/// identical large files quickly, such as deduplication before an import.
+/// It is **not** the hash of the object; the two must never be mixed.
+///
+/// The tree hash is computed as follows, using SHA-256 throughout,
+/// whatever the [hash algorithm][`crate::HashAlgorithm`] of any volume.
+/// The file is split into chunks of [`TREE_HASH_CHUNK_SIZE`] bytes,
+/// the last of which may be shorter; an empty file has no chunks.
+/// The hash of each chunk is that of the byte 0x00 followed by the chunk.
+/// The tree hash is the hash of the byte 0x01,
+/// followed by the size of the file as a big-endian 64-bit integer,
+/// followed by the hashes of the chunks in order.
+///
+/// The file is read with positioned reads,
+/// so its offset is not changed.
+/// If the file shrinks while it is hashed,
+/// this function returns an error.
+pub fn tree_hash_file(file: &File, threads: usize) -> Result<Hash>
+{
+    tree_hash_file_with(file, TREE_HASH_CHUNK_SIZE, threads)
+}
+
+fn tree_hash_file_with(file: &File, chunk_size: u64, threads: usize)
+    -> Result<Hash>
+{
+    let size = file.metadata()?.len();
+    let chunks = size.div_ceil(chunk_size) as usize;
+
+    let next = AtomicUsize::new(0);
+    let leaves = Mutex::new(vec![[0; 32]; chunks]);
+    let failure = Mutex::new(None);
+
+    thread::scope(|scope| {
+        for _ in 0 .. threads.max(1).min(chunks) {
+            scope.spawn(|| {
+                let mut buf = vec![0; chunk_size as usize];
+                loop {
+                    // Stop early if another worker failed.
+                    if failure.lock().unwrap().is_some() {
+                        break;
+                    }
+
+                    let index = next.fetch_add(1, Relaxed);
+                    if index >= chunks {
+                        break;
+                    }
+
+                    let offset = index as u64 * chunk_size;
+                    let len = (size - offset).min(chunk_size) as usize;
+                    match hash_chunk(file, offset, &mut buf[.. len]) {
+                        Ok(leaf) => leaves.lock().unwrap()[index] = leaf,
+                        Err(err) => {
+                            failure.lock().unwrap().get_or_insert(err);
+                            break;
+                        },
+                    }
+                }
+            });
+        }
+    });
+
+    if let Some(err) = failure.into_inner().unwrap() {
+        return Err(err);
+    }
+
+    let mut sha256 = Sha256::new();
+    sha256.update(&[0x01]);
+    sha256.update(&size.to_be_bytes());
+    for leaf in leaves.into_inner().unwrap() {
+        sha256.update(&leaf);
+    }
+    Ok(Hash{bytes: sha256.finalize()})
+}
+
+/// Read a chunk into the buffer and hash it.
+fn hash_chunk(file: &File, offset: u64, buf: &mut [u8]) -> Result<[u8; 32]>
+{
+    file.read_exact_at(buf, offset).map_err(|err| {
+        if err.kind() == UnexpectedEof {
+            Error::new(UnexpectedEof, "file shrank while it was hashed")
+        } else {
+            err
+        }
+    })?;
+    let mut sha256 = Sha256::new();
+    sha256.update(&[0x00]);
+    sha256.update(buf);
+    Ok(sha256.finalize())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use std::fs;
+    use super::*;
+
+    #[test]
+    fn test_tree_hash_file()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_tree_hash_file").unwrap();
+        let path = test_data.root_path.join("large");
+        let contents: Vec<u8> = (0 .. 2500u32).map(|i| i as u8).collect();
+        fs::write(&path, &contents).unwrap();
+        let file = File::open(&path).unwrap();
+
+        // Compute the expected tree hash by hand.
+        let mut expected = Sha256::new();
+        expected.update(&[0x01]);
+        expected.update(&2500u64.to_be_bytes());
+        for chunk in contents.chunks(1000) {
+            let mut leaf = Sha256::new();
+            leaf.update(&[0x00]);
+            leaf.update(chunk);
+            expected.update(&leaf.finalize());
+        }
+        let expected = Hash{bytes: expected.finalize()};
+
+        // Check the results.
+        for threads in &[1, 2, 8] {
+            let actual = tree_hash_file_with(&file, 1000, *threads).unwrap();
+            assert_eq!(actual, expected);
+        }
+        assert_ne!(tree_hash_file(&file, 2).unwrap(), expected);
+        assert_ne!(tree_hash_file(&file, 2).unwrap(),
+                   Hash::compute_from_bytes(&contents));
+
+        // An empty file has no chunks.
+        let empty_path = test_data.root_path.join("empty");
+        fs::write(&empty_path, b"").unwrap();
+        let empty = File::open(&empty_path).unwrap();
+        let mut expected = Sha256::new();
+        expected.update(&[0x01]);
+        expected.update(&0u64.to_be_bytes());
+        assert_eq!(tree_hash_file(&empty, 4).unwrap().bytes,
+                   expected.finalize());
+    }
+}
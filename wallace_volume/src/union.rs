@@ -1,38 +1,38 @@
 use crate::Hash;
-use crate::Volume;
-use std::io::Read;
+use crate::ObjectStore;
 use std::io::Result;
-use std::io::Seek;
 use wallace_iterutil::iter_result_iter;
 
-/// Retrieve a read-only handle to an object’s byte array,
+/// Retrieve a reader for an object’s byte array,
 /// as well as the size of the object in bytes,
-/// from the first given volume that has it.
-pub fn union_get<'a, I>(volumes: I, hash: Hash)
-    -> Result<Option<(impl Read + Seek, u64)>>
-    where I: IntoIterator<Item=&'a Volume>
+/// from the first given store that has it.
+pub fn union_get<'a, S, I>(stores: I, hash: Hash)
+    -> Result<Option<(S::Reader, u64)>>
+    where S: 'a + ObjectStore + ?Sized
+        , I: IntoIterator<Item=&'a S>
 {
-    volumes
+    stores
         .into_iter  ()
-        .map        (|v| v.get(hash))
+        .map        (|s| s.get(hash))
         .filter_map (|r| r.transpose())
         .next       ()
         .transpose  ()
 }
 
-/// Return an iterator over the objects in all the given volumes.
+/// Return an iterator over the objects in all the given stores.
 ///
 /// This iterator will not open the objects,
 /// it will only yield their hashes.
-pub fn union_all<'a, I>(volumes: I)
+pub fn union_all<'a, S, I>(stores: I)
     -> impl 'a + Iterator<Item=Result<Hash>>
-    where I: IntoIterator<Item=&'a Volume>
+    where S: 'a + ObjectStore + ?Sized
+        , I: IntoIterator<Item=&'a S>
         , I::IntoIter: 'a
 {
-    volumes
+    stores
         .into_iter ()
-        .map       (|v| v.all())
-        .flat_map  (|i| iter_result_iter(i))
+        .map       (|s| s.all())
+        .flat_map  (iter_result_iter)
         .map       (|r| r.unwrap_or_else(Err))
 }
 
@@ -40,6 +40,8 @@ pub fn union_all<'a, I>(volumes: I)
 mod tests
 {
     use crate::TestData;
+    use crate::Volume;
+    use std::io::Read;
     use super::*;
 
     #[test]
@@ -93,8 +95,8 @@ mod tests
 
         // Check the results.
         let mut expected = [hash1, hash2];
-        expected.sort_by_key(|h| h.bytes);
-        actual.sort_by_key(|h| h.bytes);
+        expected.sort();
+        actual.sort();
         assert_eq!(actual, expected);
     }
 }
@@ -0,0 +1,265 @@
+use crate::Hash;
+use crate::Volume;
+use std::ffi::OsStr;
+use std::ffi::OsString;
+use std::io::ErrorKind::NotFound;
+use std::io::Result;
+use std::os::unix::ffi::OsStrExt;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::Relaxed;
+use wallace_fsutil as fsutil;
+
+/// Returned by [`Volume::verify_all`].
+///
+/// The report describes every problem that was found.
+/// Problems with individual objects do not abort verification;
+/// only errors that prevent verification from continuing do.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VerifyReport
+{
+    /// The number of objects whose contents match their hash.
+    pub verified: u64,
+
+    /// The total size in bytes of the objects that were read.
+    pub verified_bytes: u64,
+
+    /// Objects whose contents do not match their hash.
+    pub mismatches: Vec<HashMismatch>,
+
+    /// Objects whose backing files are not regular files.
+    pub not_regular: Vec<Hash>,
+
+    /// Names of entries in the objects directory
+    /// that are not valid hashes.
+    pub invalid_names: Vec<OsString>,
+
+    /// The number of verified objects whose backing files
+    /// have fs-verity enabled; see [`Volume::set_fs_verity`].
+    pub verity: u64,
+
+    /// Whether verification was cancelled before all objects
+    /// were verified; see [`Volume::verify_all_cancellable`].
+    pub cancelled: bool,
+}
+
+/// An object whose contents do not match its hash.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct HashMismatch
+{
+    /// The hash the object is stored under.
+    pub expected: Hash,
+
+    /// The hash of the contents of the object.
+    pub actual: Hash,
+}
+
+impl VerifyReport
+{
+    /// Whether no problems were found.
+    ///
+    /// If verification was cancelled,
+    /// this says nothing about the objects that were not verified.
+    pub fn is_ok(&self) -> bool
+    {
+        self.mismatches.is_empty() &&
+        self.not_regular.is_empty() &&
+        self.invalid_names.is_empty()
+    }
+}
+
+impl Volume
+{
+    /// Re-hash every object in the volume
+    /// and report any corruption that was found.
+    ///
+    /// This reads every object in its entirety,
+    /// so it may take a long time on large volumes.
+    pub fn verify_all(&self) -> Result<VerifyReport>
+    {
+        self.verify_all_cancellable(&AtomicBool::new(false))
+    }
+
+    /// Like [`Volume::verify_all`], but stop early once the flag is set.
+    ///
+    /// The flag is checked before each object is verified,
+    /// so services can set it from another thread to shut down promptly.
+    /// The report then covers only the objects verified so far,
+    /// and has [`VerifyReport::cancelled`] set.
+    pub fn verify_all_cancellable(&self, cancel: &AtomicBool)
+        -> Result<VerifyReport>
+    {
+        let mut objects_dir =
+            fsutil::opendir_at(&self.directory, "objects")?;
+
+        let mut report = VerifyReport::default();
+        while let Some(dirent) = fsutil::readdir(&mut objects_dir)? {
+            let filename = dirent.d_name().to_bytes();
+            if filename == b"." || filename == b".." {
+                continue;
+            }
+            if cancel.load(Relaxed) {
+                report.cancelled = true;
+                return Ok(report);
+            }
+            match Hash::from_ascii(filename) {
+                Ok(hash) => self.verify_one(hash, &mut report)?,
+                Err(_) => report.invalid_names.push(
+                    OsStr::from_bytes(filename).to_os_string()
+                ),
+            }
+        }
+
+        for hash in self.all_packed()? {
+            if cancel.load(Relaxed) {
+                report.cancelled = true;
+                break;
+            }
+            self.verify_packed(hash, &mut report)?;
+        }
+
+        Ok(report)
+    }
+
+    /// Verify a single object and record the outcome in the report.
+    ///
+    /// If the object does not exist, nothing is recorded.
+    pub (crate) fn verify_one(&self, hash: Hash, report: &mut VerifyReport)
+        -> Result<()>
+    {
+        // O_NONBLOCK prevents blocking if a fifo found its way in.
+        let open_flags = { use libc::*; O_RDONLY | O_CLOEXEC | O_NOCTTY |
+                                        O_NOFOLLOW | O_NONBLOCK };
+        let path = format!("objects/{}", hash);
+        let mut file = match fsutil::openat(&self.directory, path,
+                                            open_flags, 0) {
+            Ok(file) => file,
+            Err(err) if err.raw_os_error() == Some(libc::ELOOP) => {
+                report.not_regular.push(hash);
+                return Ok(());
+            },
+            Err(err) if err.kind() == NotFound => {
+                return self.verify_packed(hash, report);
+            },
+            Err(err) => return Err(err.into()),
+        };
+
+        let metadata = file.metadata()?;
+        if !metadata.is_file() {
+            report.not_regular.push(hash);
+            return Ok(());
+        }
+        report.verified_bytes += metadata.len();
+
+        let actual = self.hash_algorithm.compute_from_reader(&mut file)?;
+        if actual == hash {
+            report.verified += 1;
+            if fsutil::is_verity(&file).unwrap_or(false) {
+                report.verity += 1;
+            }
+        } else {
+            report.mismatches.push(HashMismatch{expected: hash, actual});
+        }
+
+        Ok(())
+    }
+
+    fn verify_packed(&self, hash: Hash, report: &mut VerifyReport)
+        -> Result<()>
+    {
+        let mut reader = match self.get_packed(hash)? {
+            Some(reader) => reader,
+            None => return Ok(()),
+        };
+        report.verified_bytes += reader.size();
+
+        let actual = self.hash_algorithm.compute_from_reader(&mut reader)?;
+        if actual == hash {
+            report.verified += 1;
+        } else {
+            report.mismatches.push(HashMismatch{expected: hash, actual});
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use std::fs;
+    use super::*;
+
+    #[test]
+    fn test_verify_all()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_verify_all").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+        let objects_path = test_data.volume1_path.join("objects");
+
+        // Insert the objects.
+        volume.insert_from_path(&test_data.regular1_path).unwrap();
+        volume.insert_from_path(&test_data.regular2_path).unwrap();
+        let report1 = volume.verify_all().unwrap();
+
+        // Corrupt the volume.
+        fs::write(objects_path.join("bogus"), b"").unwrap();
+        let not_regular = Hash{bytes: [0; 32]};
+        fs::create_dir(objects_path.join(format!("{}", not_regular))).unwrap();
+        let report2 = volume.verify_all().unwrap();
+
+        // Check the results.
+        assert!(report1.is_ok());
+        assert_eq!(report1.verified, 2);
+        assert_eq!(report1.verified_bytes, 11);
+        assert!(!report2.is_ok());
+        assert_eq!(report2.verified, 2);
+        assert_eq!(report2.not_regular, vec![not_regular]);
+        assert_eq!(report2.invalid_names, vec![OsString::from("bogus")]);
+    }
+
+    #[test]
+    fn test_verify_all_mismatch()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_verify_all_mismatch").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+        let objects_path = test_data.volume1_path.join("objects");
+
+        // Store an object under the wrong hash.
+        let expected = test_data.regular2_hash;
+        fs::write(objects_path.join(format!("{}", expected)),
+                  &test_data.regular1_contents).unwrap();
+        let report = volume.verify_all().unwrap();
+
+        // Check the results.
+        let actual = test_data.regular1_hash;
+        assert_eq!(report.verified, 0);
+        assert_eq!(report.mismatches, vec![HashMismatch{expected, actual}]);
+    }
+
+    #[test]
+    fn test_verify_all_cancellable()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_verify_all_cancellable").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+
+        // Insert the objects.
+        volume.insert_from_path(&test_data.regular1_path).unwrap();
+        volume.insert_from_path(&test_data.regular2_path).unwrap();
+
+        // Verify the objects, with and without cancellation.
+        let report1 = volume.verify_all_cancellable(&AtomicBool::new(true))
+            .unwrap();
+        let report2 = volume.verify_all_cancellable(&AtomicBool::new(false))
+            .unwrap();
+
+        // Check the results.
+        assert!(report1.cancelled);
+        assert_eq!(report1.verified, 0);
+        assert!(!report2.cancelled);
+        assert_eq!(report2.verified, 2);
+    }
+}
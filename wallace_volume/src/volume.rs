@@ -1,23 +1,47 @@
 use crate::Hash;
-use crate::InvalidHash;
+use crate::HashAlgorithm;
+use crate::ObjectReader;
+use crate::ReadBackend;
+use crate::VolumeBuilder;
+use crate::VolumeError;
+use crate::VolumeEvent;
+use crate::VolumeId;
+use crate::algorithm;
+use crate::bloom::BloomFilter;
+use crate::events::Observer;
+use crate::identity;
+use crate::metrics::Metrics;
+use crate::layout;
+use crate::packfile::PackIndex;
 use std::fs::File;
 use std::fs::OpenOptions;
 use std::fs::Permissions;
-use std::fs::create_dir;
 use std::io::Error;
 use std::io::ErrorKind::AlreadyExists;
 use std::io::ErrorKind::NotFound;
+use std::io::ErrorKind::Unsupported;
 use std::io::Read;
 use std::io::Result;
 use std::io::Seek;
 use std::io::SeekFrom;
+use std::io::Write;
 use std::io::copy;
+use std::os::unix::fs::FileExt;
 use std::os::unix::fs::OpenOptionsExt;
 use std::os::unix::fs::PermissionsExt;
-use std::os::unix::io::AsRawFd;
+use std::os::unix::io::OwnedFd;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::RwLock;
+use std::sync::atomic::Ordering::Relaxed;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
 use wallace_fsutil as fsutil;
+use wallace_fsutil::FileType;
 
 /// Handle to an opened volume.
 ///
@@ -30,7 +54,102 @@ use wallace_fsutil as fsutil;
 /// (although this is a rather obscure use case).
 pub struct Volume
 {
-    directory: File,
+    pub (crate) directory: File,
+    pub (crate) durability: Durability,
+    pub (crate) hash_algorithm: HashAlgorithm,
+    pub (crate) id: Option<VolumeId>,
+    pub (crate) pack_threshold: u64,
+    pub (crate) quota: Option<u64>,
+    pub (crate) trash: bool,
+    pub (crate) read_backend: ReadBackend,
+    pub (crate) verify_on_read: bool,
+    pub (crate) fs_verity: bool,
+    pub (crate) packs: Mutex<PackIndex>,
+    pub (crate) usage: Mutex<Option<u64>>,
+    pub (crate) observers: Vec<Observer>,
+    pub (crate) metrics: Arc<Metrics>,
+    pub (crate) bloom: Option<RwLock<BloomFilter>>,
+}
+
+/// How hard inserts try to ensure that objects survive a crash.
+///
+/// Without synchronization, an object that was inserted
+/// shortly before the operating system crashed may be lost,
+/// or, worse, be present but with incomplete contents.
+/// Synchronization prevents this at the cost of insert latency.
+///
+/// The durability policy of a volume is set with
+/// [`Volume::set_durability`], and applies to all insert methods.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Durability
+{
+    /// Do not synchronize anything.
+    /// This is the default.
+    #[default]
+    None,
+
+    /// Synchronize the file backing the object before linking it.
+    /// After a crash, an object is either missing or complete.
+    SyncFile,
+
+    /// Also synchronize the objects directory after linking the file.
+    /// After a crash, an inserted object is present and complete.
+    SyncFileAndDirectory,
+}
+
+
+/// Returned by the `try_insert_*` methods of [`Volume`].
+///
+/// The plain insert methods only return the hash,
+/// which suffices for most callers.
+/// Ingest pipelines can use the outcome to report deduplication ratios.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct InsertOutcome
+{
+    /// The hash of the object.
+    pub hash: Hash,
+
+    /// Whether the object was absent from the volume prior to the insert.
+    /// If not, the volume was left untouched.
+    pub newly_inserted: bool,
+}
+
+/// Information about the file backing an object.
+///
+/// Returned by [`Volume::stat`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ObjectStat
+{
+    /// The size of the object in bytes.
+    pub size: u64,
+
+    /// The inode number of the file backing the object.
+    pub inode: u64,
+
+    /// The number of hard links to the file backing the object.
+    pub nlink: u64,
+
+    /// The modification time of the file backing the object.
+    /// Because objects are immutable,
+    /// this is usually the time at which the object was written.
+    pub mtime: SystemTime,
+}
+
+impl ObjectStat
+{
+    fn from_stat(stat: &libc::stat) -> Self
+    {
+        let mtime_offset = Duration::new(stat.st_mtime.unsigned_abs(),
+                                         stat.st_mtime_nsec as u32);
+        let mtime = if stat.st_mtime >= 0 { UNIX_EPOCH + mtime_offset }
+                    else { UNIX_EPOCH - mtime_offset };
+        Self{
+            size:  stat.st_size as u64,
+            inode: stat.st_ino,
+            nlink: stat.st_nlink,
+            mtime,
+        }
+    }
 }
 
 impl Volume
@@ -38,31 +157,117 @@ impl Volume
     /// Create a new volume at the given path,
     /// which must not yet exist.
     ///
-    /// The volume starts out with no objects stored in it.
+    /// The volume starts out with no objects stored in it,
+    /// and uses the current [layout version][crate::LAYOUT_VERSION].
     /// You can open the volume with [`Volume::open`].
+    ///
+    /// Objects are hashed with SHA-256;
+    /// see [`Volume::create_with_hash_algorithm`] for other options,
+    /// and [`VolumeBuilder`] for more.
     pub fn create(path: impl Into<PathBuf>) -> Result<()>
     {
-        let mut pathbuf = path.into();
-
-        create_dir(&pathbuf)?;
-
-        pathbuf.push("objects");
-        create_dir(&pathbuf)?;
+        Self::create_with_hash_algorithm(path, HashAlgorithm::default())
+    }
 
-        Ok(())
+    /// Create a new volume at the given path,
+    /// whose objects are hashed with the given hash function.
+    ///
+    /// The hash function cannot be changed after the volume is created,
+    /// as that would change the hashes of all objects.
+    pub fn create_with_hash_algorithm(path: impl Into<PathBuf>,
+                                      hash_algorithm: HashAlgorithm)
+        -> Result<()>
+    {
+        VolumeBuilder::new()
+            .hash_algorithm(hash_algorithm)
+            .create_layout(&path.into())
     }
 
     /// Open the volume at the given path,
     /// which must already be created previously
     /// using the [`Volume::create`] method.
+    ///
+    /// Volumes with an older [layout version][crate::LAYOUT_VERSION]
+    /// are opened as they are; see [`Volume::migrate`] to upgrade them.
+    /// If the volume has a newer layout version,
+    /// this method returns an error.
     pub fn open(path: impl AsRef<Path>) -> Result<Self>
     {
-        let directory =
-            OpenOptions::new()
-            .custom_flags(libc::O_DIRECTORY)
-            .read(true)
-            .open(path)?;
-        Ok(Self{directory})
+        let directory = layout::open_directory(path.as_ref())?;
+        layout::check_is_volume(&directory)?;
+        let version = layout::read_layout_version(&directory)?;
+        layout::check_layout_version(version)?;
+        let hash_algorithm = algorithm::read_hash_algorithm(&directory)?;
+        let id = identity::read_volume_id(&directory)?;
+        let durability = Durability::default();
+        let pack_threshold = 0;
+        let packs = Mutex::default();
+        let read_backend = ReadBackend::default();
+        Ok(Self{directory, durability, hash_algorithm, id, pack_threshold,
+                quota: None, trash: false, read_backend,
+                verify_on_read: false, fs_verity: false, packs,
+                usage: Mutex::default(), observers: Vec::new(),
+                metrics: Arc::default(), bloom: None})
+    }
+
+    /// The hash function with which objects are hashed.
+    pub fn hash_algorithm(&self) -> HashAlgorithm
+    {
+        self.hash_algorithm
+    }
+
+    /// The durability policy that applies to inserts.
+    pub fn durability(&self) -> Durability
+    {
+        self.durability
+    }
+
+    /// Change the durability policy that applies to inserts.
+    pub fn set_durability(&mut self, durability: Durability)
+    {
+        self.durability = durability;
+    }
+
+    /// Whether readers returned by [`Volume::get`] verify objects.
+    pub fn verify_on_read(&self) -> bool
+    {
+        self.verify_on_read
+    }
+
+    /// Change whether readers returned by [`Volume::get`] verify objects.
+    ///
+    /// If enabled, the reader hashes the object as it is read,
+    /// and fails with an error at the end if the hash does not match.
+    /// This protects consumers against corrupt or tampered volumes,
+    /// without the need for a separate pass over the object.
+    /// See [`ObjectReader`] for the details.
+    /// Verification is disabled by default.
+    pub fn set_verify_on_read(&mut self, verify_on_read: bool)
+    {
+        self.verify_on_read = verify_on_read;
+    }
+
+    /// Whether fs-verity is used to protect objects.
+    pub fn fs_verity(&self) -> bool
+    {
+        self.fs_verity
+    }
+
+    /// Change whether fs-verity is used to protect objects.
+    ///
+    /// If enabled, [`Volume::insert_from_file`] enables fs-verity
+    /// on the files backing newly inserted objects,
+    /// after which the kernel refuses to modify them,
+    /// even by their owner, instead of relying on their permissions.
+    /// [`Volume::get`] then leaves verification of such objects
+    /// to the kernel, even if verify-on-read is enabled,
+    /// as the kernel checks every block against a Merkle tree
+    /// computed when the object was inserted.
+    /// File systems without fs-verity support are silently tolerated.
+    /// This is disabled by default.
+    pub fn set_fs_verity(&mut self, fs_verity: bool)
+    {
+        self.fs_verity = fs_verity;
     }
 
     /// Insert an object into the volume by
@@ -94,75 +299,265 @@ impl Volume
     /// Such modifications may result in a corrupted volume.
     /// This includes modifications to any existing hard links.
     ///
+    /// Files that support sealing, namely those created with
+    /// `memfd_create` and `MFD_ALLOW_SEALING`, are sealed against
+    /// writes, growing, and shrinking before they are hashed,
+    /// which guarantees that they are not modified.
+    /// Such files cannot be hard linked into the volume,
+    /// so their contents are copied into it instead.
+    ///
     /// If the object already exists in the volume,
     /// the existing file is retained, and the given file is ignored.
     /// However, the given file will still be read to compute its hash.
-    pub fn insert_from_file(&self, mut file: File) -> Result<Hash>
+    pub fn insert_from_file(&self, file: File) -> Result<Hash>
     {
+        self.try_insert_from_file(file).map(|o| o.hash)
+    }
+
+    /// Like [`Volume::insert_from_file`],
+    /// but also report whether the object was newly inserted.
+    pub fn try_insert_from_file(&self, mut file: File)
+        -> Result<InsertOutcome>
+    {
+        let start = Instant::now();
+
         // Verify that the file is a regular file.
         // If not, we cannot hard link it as an object.
         let metadata = file.metadata()?;
         if !metadata.is_file() {
-            // TODO: Return a more descriptive error.
-            return Err(Error::from_raw_os_error(libc::EISDIR));
+            return Err(VolumeError::NotRegularFile.into());
         }
 
+        let sealed = seal_immutable(&file)?;
+
         // We must seek the file to the beginning to start hashing it.
         // The file offset may be positioned anywhere prior to the call.
         file.seek(SeekFrom::Start(0))?;
-        let hash = Hash::compute_from_reader(&mut file)?;
-        let path = format!("objects/{}", hash);
+        let hash = self.hash_algorithm.compute_from_reader(&mut file)?;
 
-        // Unfortunately, the AT_EMPTY_PATH flag requires a special capability.
-        // Fortunately, if /proc is available, we can apply this cute trick.
-        // It is documented in the linkat(2) man page.
-        let proc_path = format!("/proc/self/fd/{}", file.as_raw_fd());
-        let linkat_result = fsutil::linkat(
-            &libc::AT_FDCWD, proc_path, // old path
-            &self.directory, path,      // new path
-            libc::AT_SYMLINK_FOLLOW,    // see linkat(2)
-        );
+        let (newly_inserted, copied) = match self.link_object(&file, hash) {
+            // Sealed files live in memory, on another file system.
+            // Copying them is safe, as they can no longer change.
+            Err(err) if sealed && fsutil::FsError::raw_os_error_of(&err)
+                                  == Some(libc::EXDEV) => {
+                file.seek(SeekFrom::Start(0))?;
+                let mut writer = self.start_insert()?;
+                copy(&mut file, &mut writer)?;
+                (writer.try_finish()?.newly_inserted, true)
+            },
+            result => (result?, false),
+        };
 
-        // If the object already exists, then that is totally fine.
-        // We will not touch this file anymore, and use the existing one.
-        match linkat_result {
-            Ok(()) => (),
-            Err(err) if err.kind() == AlreadyExists => (),
-            Err(err) => return Err(err),
+        // fs-verity cannot be enabled while the file is open for writing.
+        if self.fs_verity && newly_inserted {
+            drop(file);
+            self.enable_fs_verity(hash)?;
         }
 
-        // Make the file read-only to prevent tampering.
-        // Not fool proof, as it can be chmodded again,
-        // but that would be PEBKAC and not our problem.
-        let readonly = Permissions::from_mode(0o400);
-        file.set_permissions(readonly)?;
+        // The writer already recorded the insert if the file was copied.
+        if !copied {
+            self.record_insert(start.elapsed());
+        }
+        Ok(InsertOutcome{hash, newly_inserted})
+    }
 
-        Ok(hash)
+    /// Insert an object into the volume by
+    /// creating a hard link to the file with the given file descriptor.
+    ///
+    /// This is [`Volume::insert_from_file`] for file descriptors
+    /// that did not come from opening a path,
+    /// most notably those received over a Unix domain socket
+    /// in an `SCM_RIGHTS` control message.
+    /// Ingest daemons can insert such files as they are,
+    /// without reopening them through `/proc/self/fd`.
+    /// The file descriptor must refer to a regular file,
+    /// and the preconditions of [`Volume::insert_from_file`] apply.
+    /// The file descriptor is closed when this method returns.
+    ///
+    /// See `examples/ingest.rs` for a daemon that receives files this way.
+    pub fn insert_from_fd(&self, fd: OwnedFd) -> Result<Hash>
+    {
+        self.insert_from_file(File::from(fd))
     }
 
     /// Drain the given reader into a temporary file,
-    /// and proceed as in [`Volume::insert_from_file`].
+    /// and insert it as in [`Volume::insert_from_file`].
+    ///
+    /// The bytes are hashed while they are copied,
+    /// so they need not be read back from the temporary file.
     pub fn insert_from_reader(&self, reader: &mut impl Read) -> Result<Hash>
     {
-        // By using O_TMPFILE, Linux will create a file with no path.
-        // We can then write this file and pass it to insert_from_file.
-        let open_flags = libc::O_RDWR | libc::O_TMPFILE;
+        self.try_insert_from_reader(reader).map(|o| o.hash)
+    }
+
+    /// Like [`Volume::insert_from_reader`],
+    /// but also report whether the object was newly inserted.
+    pub fn try_insert_from_reader(&self, reader: &mut impl Read)
+        -> Result<InsertOutcome>
+    {
+        let mut writer = self.start_insert()?;
+        copy(reader, &mut writer)?;
+        writer.try_finish()
+    }
+
+    /// Write the given bytes to a temporary file,
+    /// and insert it as in [`Volume::insert_from_file`].
+    ///
+    /// Unlike [`Volume::insert_from_reader`],
+    /// this method computes the hash directly from the bytes,
+    /// rather than reading them back from the temporary file.
+    ///
+    /// If the object is small enough, it is packed instead;
+    /// see [`Volume::set_pack_threshold`].
+    pub fn insert_from_bytes(&self, bytes: &[u8]) -> Result<Hash>
+    {
+        self.try_insert_from_bytes(bytes).map(|o| o.hash)
+    }
+
+    /// Like [`Volume::insert_from_bytes`],
+    /// but also report whether the object was newly inserted.
+    pub fn try_insert_from_bytes(&self, bytes: &[u8]) -> Result<InsertOutcome>
+    {
+        let start = Instant::now();
+        let hash = self.hash_algorithm.compute_from_bytes(bytes);
+
+        // Skip writing the file if the object already exists.
+        if self.contains(hash)? {
+            self.record_insert(start.elapsed());
+            return Ok(InsertOutcome{hash, newly_inserted: false});
+        }
+
+        let newly_inserted = if self.should_pack(bytes.len() as u64) {
+            self.insert_packed(hash, bytes)?
+        } else {
+            let mut tmpfile = self.create_tmpfile()?;
+            tmpfile.write_all(bytes)?;
+            self.link_object(&tmpfile, hash)?
+        };
+
+        self.record_insert(start.elapsed());
+        Ok(InsertOutcome{hash, newly_inserted})
+    }
+
+    /// Copy the given file into a temporary file,
+    /// and insert it as in [`Volume::insert_from_file`].
+    ///
+    /// Unlike [`Volume::insert_from_file`],
+    /// the given file is not linked into the volume,
+    /// so it may be modified afterwards, and it is not made read-only.
+    /// The file offset of the given file is not used nor changed.
+    ///
+    /// On file systems that support reflinks, such as Btrfs and XFS,
+    /// the copy shares storage with the given file,
+    /// which makes copying nearly free.
+    /// Otherwise the copy is made by the kernel using `copy_file_range`,
+    /// and if that is not possible either, by reading and writing.
+    pub fn insert_copy_from_file(&self, file: &File) -> Result<Hash>
+    {
+        self.try_insert_copy_from_file(file).map(|o| o.hash)
+    }
+
+    /// Like [`Volume::insert_copy_from_file`],
+    /// but also report whether the object was newly inserted.
+    pub fn try_insert_copy_from_file(&self, file: &File)
+        -> Result<InsertOutcome>
+    {
+        let start = Instant::now();
+
+        let metadata = file.metadata()?;
+        if !metadata.is_file() {
+            return Err(VolumeError::NotRegularFile.into());
+        }
+
+        let mut tmpfile = self.create_tmpfile()?;
+        copy_file_contents(file, &mut tmpfile)?;
+
+        // The temporary file may have a name that is removed on drop,
+        // so we cannot pass it to insert_from_file by value.
+        tmpfile.seek(SeekFrom::Start(0))?;
+        let hash = self.hash_algorithm
+            .compute_from_reader(&mut *tmpfile)?;
+        let newly_inserted = self.link_object(&tmpfile, hash)?;
+
+        self.record_insert(start.elapsed());
+        Ok(InsertOutcome{hash, newly_inserted})
+    }
+
+    /// Enable fs-verity on the file backing an object, if possible.
+    ///
+    /// Failure because the file system does not support fs-verity,
+    /// or because the file is still open for writing, is ignored.
+    fn enable_fs_verity(&self, hash: Hash) -> Result<()>
+    {
+        let open_flags = libc::O_RDONLY | libc::O_CLOEXEC | libc::O_NOFOLLOW;
+        let path = format!("objects/{}", hash);
+        let file = fsutil::openat(&self.directory, path, open_flags, 0)?;
+        match fsutil::enable_verity(&file) {
+            Ok(()) => Ok(()),
+            Err(err) => match err.raw_os_error() {
+                Some(libc::EEXIST) | Some(libc::ENOTTY) |
+                Some(libc::EOPNOTSUPP) | Some(libc::ETXTBSY) => Ok(()),
+                _ => Err(err.into()),
+            },
+        }
+    }
+
+    /// Link a file whose hash is already known into the volume,
+    /// and make it read-only.
+    ///
+    /// The caller is responsible for the hash being correct!
+    /// Returns whether the object was newly inserted.
+    pub (crate) fn link_object(&self, file: &File, hash: Hash) -> Result<bool>
+    {
+        let path = format!("objects/{}", hash);
+
+        // The contents must be on disk before the name is,
+        // otherwise a crash could leave behind a truncated object.
+        if self.durability != Durability::None {
+            file.sync_data()?;
+        }
+
+        // Account for the object before it becomes visible,
+        // so that concurrent inserts cannot exceed the quota together.
+        let size = file.metadata()?.len();
+        self.reserve_quota(size)?;
+
+        // Give the file its name in the objects directory.
+        let linkat_result = fsutil::linkat_fd(file, &self.directory, path);
 
-        // We must write the file and then read it,
-        // so we will use this open mode.
-        let open_mode = 0o600;
+        // If the object already exists, then that is totally fine.
+        // We will not touch this file anymore, and use the existing one.
+        let newly_inserted = match linkat_result {
+            Ok(()) => true,
+            Err(err) => {
+                self.release_quota(size);
+                if err.kind() != AlreadyExists {
+                    return Err(err.into());
+                }
+                false
+            },
+        };
+
+        // Persist the new directory entry.
+        if self.durability == Durability::SyncFileAndDirectory {
+            let open_flags = libc::O_RDONLY | libc::O_DIRECTORY;
+            fsutil::openat(&self.directory, "objects", open_flags, 0)?
+                .sync_all()?;
+        }
 
-        // We must still pass some path to openat.
-        // Linux uses this to determine the file system
-        // on which the file is to be stored.
-        // We pass the path to the volume directory.
-        let mut tmpfile = fsutil::openat(&self.directory, ".",
-                                         open_flags, open_mode)?;
+        // Make the file read-only to prevent tampering.
+        // Not fool proof, as it can be chmodded again,
+        // but that would be PEBKAC and not our problem.
+        let readonly = Permissions::from_mode(0o400);
+        file.set_permissions(readonly)?;
 
-        // Drain the entire reader into the temporary file.
-        copy(reader, &mut tmpfile)?;
+        self.record_in_bloom_filter(hash);
+        if newly_inserted {
+            self.record_written(size);
+            self.notify(VolumeEvent::Inserted{hash, size});
+        }
 
-        self.insert_from_file(tmpfile)
+        Ok(newly_inserted)
     }
 
     /// Open the file at the given path,
@@ -172,6 +567,14 @@ impl Volume
     /// It will immediately return an error because fifos are not regular files.
     /// Similar shenanigans with other exotic file types are also avoided.
     pub fn insert_from_path(&self, path: impl AsRef<Path>) -> Result<Hash>
+    {
+        self.try_insert_from_path(path).map(|o| o.hash)
+    }
+
+    /// Like [`Volume::insert_from_path`],
+    /// but also report whether the object was newly inserted.
+    pub fn try_insert_from_path(&self, path: impl AsRef<Path>)
+        -> Result<InsertOutcome>
     {
         // First we are going to open the file.
         // Then we will proceed as in insert_from_file.
@@ -193,20 +596,20 @@ impl Volume
         // Switch the file back to blocking mode.
         // We only needed non-blocking mode to
         // open a potential fifo without blocking.
-        let fd_flags = fsutil::fcntl_getfd(&file)?;
-        fsutil::fcntl_setfd(&file, fd_flags & !libc::O_NONBLOCK)?;
+        let status_flags = fsutil::fcntl_getfl(&file)?;
+        fsutil::fcntl_setfl(&file, status_flags & !libc::O_NONBLOCK)?;
 
-        self.insert_from_file(file)
+        self.try_insert_from_file(file)
     }
 
     /// Retrieve a read-only handle to an object’s byte array,
     /// as well as the size of the object in bytes.
     ///
     /// If the object does not exist, this method returns [`None`].
-    /// The returned reader/seeker is backed by a file,
-    /// but this fact is hidden using impl trait
-    /// because the file should not be modified.
-    pub fn get(&self, hash: Hash) -> Result<Option<(impl Read + Seek, u64)>>
+    /// The returned reader is backed by a file,
+    /// whose file descriptor is exposed for use with `sendfile` and such;
+    /// see [`ObjectReader`] for the rules that come with that.
+    pub fn get(&self, hash: Hash) -> Result<Option<(ObjectReader, u64)>>
     {
         // Prevent any funny business from happening.
         // O_CLOEXEC:  Close the file if we spawn a subprocess.
@@ -224,12 +627,20 @@ impl Volume
         let file_result = fsutil::openat(&self.directory, path,
                                          open_flags, open_mode);
 
-        // If the file does not exist, return None.
+        // If the file does not exist, the object may be packed.
         let file = match file_result {
             Ok(file) => file,
-            Err(err) if err.kind() == NotFound =>
-                return Ok(None),
-            Err(err) => return Err(err),
+            Err(err) if err.kind() == NotFound => {
+                let reader = self.get_packed(hash)?;
+                if reader.is_none() {
+                    self.metrics.misses.fetch_add(1, Relaxed);
+                }
+                return Ok(reader.map(|r| {
+                    let size = r.size();
+                    (self.prepare_reader(r, hash, false), size)
+                }));
+            },
+            Err(err) => return Err(err.into()),
         };
 
         // Find the size of the file.
@@ -239,48 +650,248 @@ impl Volume
         // Check that the file is regular.
         // If not, the volume is corrupt.
         if !metadata.is_file() {
-            return Err(Error::from_raw_os_error(libc::EISDIR));
+            return Err(VolumeError::NotRegularFile.into());
         }
 
-        Ok(Some((file, size)))
+        // The kernel verifies files with fs-verity enabled by itself.
+        let verity = self.fs_verity && fsutil::is_verity(&file)
+            .unwrap_or(false);
+
+        let reader = ObjectReader::new(file, 0, size);
+        Ok(Some((self.prepare_reader(reader, hash, verity), size)))
+    }
+
+    /// Count the retrieval of an object,
+    /// and configure its reader according to the options of the volume.
+    fn prepare_reader(&self, reader: ObjectReader, hash: Hash, verity: bool)
+        -> ObjectReader
+    {
+        self.metrics.gets.fetch_add(1, Relaxed);
+        let reader = reader.count_into(self.metrics.clone());
+        if self.verify_on_read && !verity {
+            reader.verify(self.hash_algorithm, hash)
+        } else {
+            reader
+        }
+    }
+
+    /// Retrieve a reader for a range of an object’s byte array.
+    ///
+    /// The reader yields at most `len` bytes starting at `offset`,
+    /// and fewer if the range extends beyond the end of the object.
+    /// Its position is relative to the start of the range.
+    /// If the object does not exist, this method returns [`None`].
+    ///
+    /// This is useful for serving positional reads,
+    /// such as HTTP range requests.
+    /// The reader does not verify the object, even if
+    /// [verify-on-read][`Volume::set_verify_on_read`] is enabled.
+    pub fn get_range(&self, hash: Hash, offset: u64, len: u64)
+        -> Result<Option<ObjectReader>>
+    {
+        let reader = self.get(hash)?;
+        Ok(reader.map(|(reader, _)| reader.slice(offset, len)))
+    }
+
+    /// Check whether an object exists in the volume.
+    ///
+    /// This is cheaper than [`Volume::get`],
+    /// as it does not open the file backing the object.
+    /// If a [Bloom filter][`Volume::load_bloom_filter`] was loaded,
+    /// objects that it rules out are reported missing
+    /// without making any system calls.
+    pub fn contains(&self, hash: Hash) -> Result<bool>
+    {
+        if !self.might_contain(hash) {
+            return Ok(false);
+        }
+        Ok(self.stat(hash)?.is_some())
+    }
+
+    /// Retrieve information about the file backing an object,
+    /// without opening the file.
+    ///
+    /// If the object does not exist, this method returns [`None`].
+    /// For packed objects, the information is about the pack file,
+    /// except for the size, which is that of the object.
+    pub fn stat(&self, hash: Hash) -> Result<Option<ObjectStat>>
+    {
+        let path = format!("objects/{}", hash);
+        let stat_result = fsutil::fstatat(&self.directory, path,
+                                          libc::AT_SYMLINK_NOFOLLOW);
+
+        let stat = match stat_result {
+            Ok(stat) => stat,
+            Err(err) if err.kind() == NotFound =>
+                return self.stat_packed(hash),
+            Err(err) => return Err(err.into()),
+        };
+
+        // Check that the file is regular.
+        // If not, the volume is corrupt.
+        if FileType::from_st_mode(stat.st_mode) != FileType::Regular {
+            return Err(VolumeError::NotRegularFile.into());
+        }
+
+        Ok(Some(ObjectStat::from_stat(&stat)))
+    }
+
+    fn stat_packed(&self, hash: Hash) -> Result<Option<ObjectStat>>
+    {
+        let entry = match self.find_packed(hash)? {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let stat = fsutil::fstatat(&self.directory, "packs/data",
+                                   libc::AT_SYMLINK_NOFOLLOW)?;
+        Ok(Some(ObjectStat{size: entry.size, ..ObjectStat::from_stat(&stat)}))
+    }
+
+    /// Remove an object from the volume.
+    ///
+    /// Any provenance stored for the object is removed as well.
+    /// Returns whether the object existed prior to the call.
+    /// If the trash is enabled, the object is moved there instead;
+    /// see [`Volume::set_trash`].
+    ///
+    /// Readers obtained through [`Volume::get`] remain usable,
+    /// as the file backing the object is merely unlinked.
+    ///
+    /// Packed objects cannot be removed individually,
+    /// and attempting to do so fails with [`Unsupported`].
+    pub fn remove(&self, hash: Hash) -> Result<bool>
+    {
+        let path = format!("objects/{}", hash);
+        let size = self.removal_size(&path)?;
+        let existed = match self.discard_object(hash) {
+            Ok(()) => {
+                self.release_quota(size);
+                true
+            },
+            Err(err) if err.kind() == NotFound => {
+                if self.find_packed(hash)?.is_some() {
+                    let message = "packed objects cannot be removed";
+                    return Err(Error::new(Unsupported, message));
+                }
+                false
+            },
+            Err(err) => return Err(err),
+        };
+
+        // Provenance is retained for objects in the trash,
+        // in case they are restored.
+        if !self.trash {
+            self.remove_provenance(hash)?;
+        }
+
+        if existed {
+            self.notify(VolumeEvent::Removed{hash, size});
+        }
+
+        Ok(existed)
     }
 
     /// Return an iterator over the objects in the volume.
     ///
     /// This iterator will not open the objects,
     /// it will only yield their hashes.
+    /// Packed objects are listed after the other objects.
     pub fn all(&self) -> Result<impl Iterator<Item=Result<Hash>>>
     {
-        struct All
-        {
-            objects_dir: fsutil::Dir,
+        // An object may be both packed and stored in a file of its own,
+        // if it was inserted in both ways; list it only once.
+        let mut packed = Vec::new();
+        for hash in self.all_packed()? {
+            let path = format!("objects/{}", hash);
+            match fsutil::fstatat(&self.directory, path,
+                                  libc::AT_SYMLINK_NOFOLLOW) {
+                Ok(_) => (),
+                Err(err) if err.kind() == NotFound => packed.push(Ok(hash)),
+                Err(err) => return Err(err.into()),
+            }
         }
 
-        impl Iterator for All
-        {
-            type Item = Result<Hash>;
-
-            fn next(&mut self) -> Option<Self::Item>
-            {
-                match fsutil::readdir(&mut self.objects_dir) {
-                    Err(err) => Some(Err(err)),
-                    Ok(None) => None,
-                    Ok(Some(dirent)) => {
-                        let filename = dirent.d_name().to_bytes();
-                        match Hash::from_ascii(filename) {
-                            Ok(hash) => Some(Ok(hash)),
-                            Err(InvalidHash) => self.next(),
-                        }
-                    },
-                }
+        Ok(self.all_loose()?.chain(packed))
+    }
+
+    /// Like [`Volume::all`], but without packed objects.
+    pub (crate) fn all_loose(&self)
+        -> Result<impl Iterator<Item=Result<Hash>>>
+    {
+        let objects_dir = fsutil::opendir_at(&self.directory, "objects")?;
+        Ok(objects_dir.into_iter().filter_map(|dirent| {
+            match dirent {
+                Ok(dirent) => Hash::from_ascii(dirent.d_name().to_bytes())
+                                  .ok().map(Ok),
+                Err(err) => Some(Err(err.into())),
             }
+        }))
+    }
+}
+
+/// Copy the contents of one file to another,
+/// using the cheapest method available.
+pub (crate) fn copy_file_contents(src: &File, dest: &mut File) -> Result<()>
+{
+    // These errors indicate that a method is not available
+    // for the given files, rather than that something went wrong.
+    let unsupported = |err: &fsutil::FsError| matches!(
+        err.raw_os_error(),
+        Some(libc::EOPNOTSUPP) | Some(libc::EXDEV) | Some(libc::EINVAL) |
+        Some(libc::ENOTTY) | Some(libc::ENOSYS)
+    );
+
+    // Try to share extents with the source file.
+    match fsutil::ficlone(&*dest, src) {
+        Ok(()) => return Ok(()),
+        Err(err) if unsupported(&err) => (),
+        Err(err) => return Err(err.into()),
+    }
+
+    // Try to have the kernel copy the bytes.
+    // Only fall back if nothing was copied yet.
+    let mut offset = 0;
+    loop {
+        match fsutil::copy_file_range(src, Some(&mut offset), &*dest, None,
+                                      1 << 30) {
+            Ok(0) => return Ok(()),
+            Ok(_) => (),
+            Err(err) if unsupported(&err) && offset == 0 => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    // Copy the bytes ourselves.
+    // Use positional reads so as to not change the file offset.
+    let mut buf = vec![0; 64 * 1024];
+    let mut offset = 0;
+    loop {
+        let n = src.read_at(&mut buf, offset)?;
+        if n == 0 {
+            return Ok(());
         }
+        dest.write_all(&buf[.. n])?;
+        offset += n as u64;
+    }
+}
 
-        let open_flags = libc::O_RDONLY | libc::O_DIRECTORY;
-        let objects_directory =
-            fsutil::openat(&self.directory, "objects", open_flags, 0)?;
-        let objects_dir = fsutil::fdopendir(objects_directory)?;
-        Ok(All{objects_dir})
+/// Seal a file against modification, if the file supports sealing.
+///
+/// Returns whether the file is sealed.
+fn seal_immutable(file: &File) -> Result<bool>
+{
+    let seals = libc::F_SEAL_WRITE | libc::F_SEAL_GROW | libc::F_SEAL_SHRINK;
+    match fsutil::fcntl_add_seals(file, seals) {
+        Ok(()) => return Ok(true),
+        // The file does not support sealing, or it is already sealed.
+        Err(err) if matches!(err.raw_os_error(),
+                             Some(libc::EINVAL) | Some(libc::EPERM)) => (),
+        Err(err) => return Err(err.into()),
+    }
+    match fsutil::fcntl_get_seals(file) {
+        Ok(present) => Ok(present & seals == seals),
+        Err(err) if err.raw_os_error() == Some(libc::EINVAL) => Ok(false),
+        Err(err) => Err(err.into()),
     }
 }
 
@@ -290,6 +901,8 @@ mod tests
     use crate::TestData;
     use std::io::Cursor;
     use std::io::ErrorKind::AlreadyExists;
+    use std::os::unix::fs::MetadataExt;
+    use std::os::unix::fs::PermissionsExt;
     use super::*;
 
     #[test]
@@ -339,6 +952,133 @@ mod tests
         assert_eq!(size, test_data.regular1_contents.len() as u64);
     }
 
+    #[test]
+    fn test_insert_from_bytes()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_insert_from_bytes").unwrap();
+        let volume = Volume::open(test_data.volume1_path).unwrap();
+
+        // Insert the object, twice.
+        let hash1 = volume.insert_from_bytes(&test_data.regular1_contents)
+            .unwrap();
+        let hash2 = volume.insert_from_bytes(&test_data.regular1_contents)
+            .unwrap();
+
+        // Get the object.
+        let (mut read, size) = volume.get(hash1).unwrap().unwrap();
+        let mut data = Vec::new();
+        read.read_to_end(&mut data).unwrap();
+
+        // Check the results.
+        assert_eq!(hash1, test_data.regular1_hash);
+        assert_eq!(hash2, test_data.regular1_hash);
+        assert_eq!(data, test_data.regular1_contents);
+        assert_eq!(size, test_data.regular1_contents.len() as u64);
+    }
+
+    #[test]
+    fn test_try_insert()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_try_insert").unwrap();
+        let volume = Volume::open(test_data.volume1_path).unwrap();
+
+        // Insert the objects, some twice.
+        let outcome1 = volume.try_insert_from_path(&test_data.regular1_path)
+            .unwrap();
+        let outcome2 = volume
+            .try_insert_from_bytes(&test_data.regular1_contents)
+            .unwrap();
+        let outcome3 = volume.try_insert_from_reader(&mut &b"new"[..])
+            .unwrap();
+        let outcome4 = volume.try_insert_from_reader(&mut &b"new"[..])
+            .unwrap();
+
+        // Check the results.
+        assert_eq!(outcome1.hash, test_data.regular1_hash);
+        assert_eq!(outcome2.hash, test_data.regular1_hash);
+        assert_eq!(outcome3.hash, outcome4.hash);
+        assert!(outcome1.newly_inserted);
+        assert!(!outcome2.newly_inserted);
+        assert!(outcome3.newly_inserted);
+        assert!(!outcome4.newly_inserted);
+    }
+
+    #[test]
+    fn test_get_range()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_get_range").unwrap();
+        let volume = Volume::open(test_data.volume1_path).unwrap();
+
+        // Insert the object.
+        let hash = volume.insert_from_bytes(b"Hello, world!").unwrap();
+
+        // Get ranges of the object.
+        let read = |offset, len| {
+            let mut reader = volume.get_range(hash, offset, len)
+                .unwrap().unwrap();
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data).unwrap();
+            data
+        };
+        let reader = volume.get_range(hash, 7, 5).unwrap().unwrap();
+        let mut buf = [0; 3];
+        let n = reader.read_at(&mut buf, 1).unwrap();
+
+        // Check the results.
+        assert_eq!(read(7, 5), b"world");
+        assert_eq!(read(7, 100), b"world!");
+        assert_eq!(read(100, 5), b"");
+        assert_eq!(&buf[.. n], b"orl");
+        assert_eq!(reader.size(), 5);
+    }
+
+    #[test]
+    fn test_insert_copy_from_file()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_insert_copy_from_file").unwrap();
+        let volume = Volume::open(test_data.volume1_path).unwrap();
+
+        // Insert the object.
+        let file = File::open(&test_data.regular2_path).unwrap();
+        let hash = volume.insert_copy_from_file(&file).unwrap();
+
+        // Get the object.
+        let (mut read, size) = volume.get(hash).unwrap().unwrap();
+        let mut data = Vec::new();
+        read.read_to_end(&mut data).unwrap();
+
+        // Check the results.
+        let metadata = file.metadata().unwrap();
+        assert_eq!(hash, test_data.regular2_hash);
+        assert_eq!(data, test_data.regular2_contents);
+        assert_eq!(size, test_data.regular2_contents.len() as u64);
+        assert_eq!(metadata.nlink(), 1);
+        assert_ne!(metadata.permissions().mode() & 0o777, 0o400);
+    }
+
+    #[test]
+    fn test_insert_durability()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_insert_durability").unwrap();
+        let mut volume = Volume::open(test_data.volume1_path).unwrap();
+
+        // Insert the objects with each policy.
+        volume.set_durability(Durability::SyncFile);
+        let hash1 = volume.insert_from_path(&test_data.regular1_path).unwrap();
+        volume.set_durability(Durability::SyncFileAndDirectory);
+        let hash2 = volume.insert_from_path(&test_data.regular2_path).unwrap();
+
+        // Check the results.
+        assert_eq!(volume.durability(), Durability::SyncFileAndDirectory);
+        assert!(volume.contains(hash1).unwrap());
+        assert!(volume.contains(hash2).unwrap());
+    }
+
     #[test]
     fn test_insert_from_path()
     {
@@ -367,6 +1107,140 @@ mod tests
         assert_eq!(size2, test_data.regular2_contents.len() as u64);
     }
 
+    #[test]
+    fn test_insert_from_fd()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_insert_from_fd").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+        let open = |path| OwnedFd::from(File::open(path).unwrap());
+
+        // Insert the objects.
+        let hash = volume.insert_from_fd(open(&test_data.regular1_path));
+        let err = volume.insert_from_fd(open(&test_data.root_path));
+
+        // Check the results.
+        let err = err.unwrap_err();
+        assert_eq!(hash.unwrap(), test_data.regular1_hash);
+        assert_eq!(err.raw_os_error(), Some(libc::EISDIR));
+        assert!(matches!(VolumeError::from(err),
+                         VolumeError::NotRegularFile));
+    }
+
+    #[test]
+    fn test_insert_from_sealed_memfd()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_insert_from_sealed_memfd")
+            .unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+        let mut file = fsutil::memfd_create("object",
+                                            libc::MFD_ALLOW_SEALING)
+            .unwrap();
+        file.write_all(&test_data.regular1_contents).unwrap();
+        let writable = file.try_clone().unwrap();
+
+        // Insert the object.
+        let hash = volume.insert_from_file(file).unwrap();
+
+        // Check the results.
+        let seals = fsutil::fcntl_get_seals(&writable).unwrap();
+        let (mut reader, _) = volume.get(hash).unwrap().unwrap();
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).unwrap();
+        assert_eq!(hash, test_data.regular1_hash);
+        assert_eq!(data, test_data.regular1_contents);
+        assert_ne!(seals & libc::F_SEAL_WRITE, 0);
+        assert!((&writable).write_all(b"tamper").is_err());
+        assert_eq!(volume.metrics().inserts, 1);
+    }
+
+    #[test]
+    fn test_fs_verity()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_fs_verity").unwrap();
+        let mut volume = Volume::open(&test_data.volume1_path).unwrap();
+        volume.set_fs_verity(true);
+        volume.set_verify_on_read(true);
+
+        // Insert the object.
+        let file = File::open(&test_data.regular1_path).unwrap();
+        let hash = volume.insert_from_file(file).unwrap();
+
+        // Read the object back.
+        let (mut reader, _) = volume.get(hash).unwrap().unwrap();
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).unwrap();
+        let report = volume.verify_all().unwrap();
+
+        // Check the results, which depend on the file system.
+        let file = File::open(&test_data.regular1_path).unwrap();
+        let verity = fsutil::is_verity(&file).unwrap_or(false);
+        assert_eq!(data, test_data.regular1_contents);
+        assert_eq!(report.verified, 1);
+        assert_eq!(report.verity, verity as u64);
+    }
+
+    #[test]
+    fn test_contains()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_contains").unwrap();
+        let volume = Volume::open(test_data.volume1_path).unwrap();
+
+        // Insert the object.
+        let hash = volume.insert_from_path(&test_data.regular1_path).unwrap();
+
+        // Check the results.
+        assert!(volume.contains(hash).unwrap());
+        assert!(!volume.contains(test_data.regular2_hash).unwrap());
+    }
+
+    #[test]
+    fn test_stat()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_stat").unwrap();
+        let volume = Volume::open(test_data.volume1_path).unwrap();
+
+        // Insert the object.
+        let hash = volume.insert_from_path(&test_data.regular1_path).unwrap();
+        let stat = volume.stat(hash).unwrap().unwrap();
+
+        // Check the results.
+        let metadata = std::fs::metadata(&test_data.regular1_path).unwrap();
+        assert_eq!(stat.size, test_data.regular1_contents.len() as u64);
+        assert_eq!(stat.inode, metadata.ino());
+        assert_eq!(stat.nlink, 2);
+        assert_eq!(stat.mtime, metadata.modified().unwrap());
+        assert!(volume.stat(test_data.regular2_hash).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_remove()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_remove").unwrap();
+        let volume = Volume::open(test_data.volume1_path).unwrap();
+
+        // Insert and remove the object.
+        let hash = volume.insert_from_path(&test_data.regular1_path).unwrap();
+        let (mut read, _) = volume.get(hash).unwrap().unwrap();
+        let existed1 = volume.remove(hash).unwrap();
+        let existed2 = volume.remove(hash).unwrap();
+
+        // Readers remain usable after removal.
+        let mut data = Vec::new();
+        read.read_to_end(&mut data).unwrap();
+
+        // Check the results.
+        assert!(existed1);
+        assert!(!existed2);
+        assert!(volume.get(hash).unwrap().is_none());
+        assert_eq!(data, test_data.regular1_contents);
+    }
+
     #[test]
     fn test_all()
     {
@@ -384,8 +1258,8 @@ mod tests
 
         // Check the results..
         let mut expected = [hash1, hash2];
-        expected.sort_by_key(|h| h.bytes);
-        actual.sort_by_key(|h| h.bytes);
+        expected.sort();
+        actual.sort();
         assert_eq!(actual, expected);
     }
 }
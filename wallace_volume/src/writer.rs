@@ -0,0 +1,225 @@
+use crate::Hash;
+use crate::InsertOutcome;
+use crate::Volume;
+use crate::algorithm::Hasher;
+use crate::tmpfile::TmpFile;
+use std::io::Read;
+use std::io::Result;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::io::copy;
+use std::time::Instant;
+use wallace_fsutil as fsutil;
+
+/// Handle for inserting an object incrementally.
+///
+/// Returned by [`Volume::start_insert`].
+/// Bytes written to the handle are written to a temporary file,
+/// and hashed as they arrive.
+/// Call [`ObjectWriter::finish`] to insert the object into the volume.
+/// If the handle is dropped without calling it,
+/// the temporary file is discarded and the volume is left untouched.
+pub struct ObjectWriter<'a>
+{
+    volume: &'a Volume,
+    tmpfile: TmpFile<'a>,
+    hasher: Hasher,
+    size: u64,
+    preallocated: u64,
+    start: Instant,
+}
+
+impl Volume
+{
+    /// Start inserting an object incrementally.
+    ///
+    /// This is useful when the bytes of the object
+    /// arrive in pieces, for instance from a network upload.
+    /// Unlike [`Volume::insert_from_reader`],
+    /// the bytes are hashed while they are written,
+    /// so they need not be read back from the temporary file.
+    pub fn start_insert(&self) -> Result<ObjectWriter<'_>>
+    {
+        let tmpfile = self.create_tmpfile()?;
+        let hasher = self.hash_algorithm.hasher();
+        let start = Instant::now();
+        Ok(ObjectWriter{volume: self, tmpfile, hasher, size: 0,
+                        preallocated: 0, start})
+    }
+
+    /// Like [`Volume::start_insert`],
+    /// but reserve disk space for an object of the expected size.
+    ///
+    /// This is useful when the size is known up front,
+    /// for instance from the `Content-Length` of an upload.
+    /// Allocating the space at once reduces fragmentation,
+    /// and reports a full disk before any bytes are written.
+    /// The object may turn out smaller or larger than expected;
+    /// unused space is released when the object is inserted.
+    /// Objects that will be packed are not preallocated,
+    /// nor are objects on file systems that do not support `fallocate`,
+    /// or the mode it is called with;
+    /// those are simply written without preallocation.
+    pub fn start_insert_with_size(&self, expected_size: u64)
+        -> Result<ObjectWriter<'_>>
+    {
+        let mut writer = self.start_insert()?;
+        if !self.should_pack(expected_size) {
+            let result = fsutil::fallocate(&*writer.tmpfile,
+                                           libc::FALLOC_FL_KEEP_SIZE,
+                                           0, expected_size as libc::off_t);
+            match result {
+                Ok(()) => writer.preallocated = expected_size,
+                // The kernel, file system, or mode does not support it,
+                // or the expected size is zero. Only errors such as ENOSPC
+                // would also make the plain write fail.
+                Err(err) if matches!(err.raw_os_error(),
+                                     Some(libc::EOPNOTSUPP | libc::ENOSYS |
+                                          libc::EINVAL)) => (),
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(writer)
+    }
+
+    /// Like [`Volume::insert_from_reader`],
+    /// but preallocate as in [`Volume::start_insert_with_size`].
+    pub fn insert_from_reader_with_size(&self, reader: &mut impl Read,
+                                        expected_size: u64) -> Result<Hash>
+    {
+        let mut writer = self.start_insert_with_size(expected_size)?;
+        copy(reader, &mut writer)?;
+        writer.finish()
+    }
+}
+
+impl<'a> ObjectWriter<'a>
+{
+    /// Insert the object into the volume, and return its hash.
+    ///
+    /// If the object already exists in the volume,
+    /// the existing file is retained, and the written bytes are discarded.
+    ///
+    /// If the object is small enough, it is packed instead;
+    /// see [`Volume::set_pack_threshold`].
+    pub fn finish(self) -> Result<Hash>
+    {
+        self.try_finish().map(|o| o.hash)
+    }
+
+    /// Like [`ObjectWriter::finish`],
+    /// but also report whether the object was newly inserted.
+    pub fn try_finish(mut self) -> Result<InsertOutcome>
+    {
+        let hash = self.hasher.finalize();
+
+        // Release space that was preallocated but not written.
+        if self.preallocated > self.size {
+            self.tmpfile.set_len(self.size)?;
+        }
+
+        let newly_inserted = if !self.volume.should_pack(self.size) {
+            self.volume.link_object(&self.tmpfile, hash)?
+        } else if self.volume.contains(hash)? {
+            false
+        } else {
+            let mut bytes = Vec::new();
+            self.tmpfile.seek(SeekFrom::Start(0))?;
+            self.tmpfile.read_to_end(&mut bytes)?;
+            self.volume.insert_packed(hash, &bytes)?
+        };
+
+        self.volume.record_insert(self.start.elapsed());
+        Ok(InsertOutcome{hash, newly_inserted})
+    }
+}
+
+impl<'a> Write for ObjectWriter<'a>
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize>
+    {
+        // Only hash the bytes that were actually written,
+        // which may be fewer than the given bytes.
+        let n = self.tmpfile.write(buf)?;
+        self.hasher.update(&buf[.. n]);
+        self.size += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<()>
+    {
+        self.tmpfile.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::TestData;
+    use std::fs;
+    use std::io::Read;
+    use std::os::unix::fs::MetadataExt;
+    use super::*;
+
+    #[test]
+    fn test_start_insert()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_start_insert").unwrap();
+        let volume = Volume::open(test_data.volume1_path).unwrap();
+
+        // Insert the object in pieces.
+        let (piece1, piece2) = test_data.regular2_contents.split_at(2);
+        let mut writer = volume.start_insert().unwrap();
+        writer.write_all(piece1).unwrap();
+        writer.write_all(piece2).unwrap();
+        let hash = writer.finish().unwrap();
+
+        // Abandon another insert.
+        let mut writer = volume.start_insert().unwrap();
+        writer.write_all(&test_data.regular1_contents).unwrap();
+        drop(writer);
+
+        // Get the object.
+        let (mut read, size) = volume.get(hash).unwrap().unwrap();
+        let mut data = Vec::new();
+        read.read_to_end(&mut data).unwrap();
+
+        // Check the results.
+        assert_eq!(hash, test_data.regular2_hash);
+        assert_eq!(data, test_data.regular2_contents);
+        assert_eq!(size, test_data.regular2_contents.len() as u64);
+        assert!(!volume.contains(test_data.regular1_hash).unwrap());
+    }
+
+    #[test]
+    fn test_start_insert_with_size()
+    {
+        // Prepare the test.
+        let test_data = TestData::new("test_start_insert_with_size").unwrap();
+        let volume = Volume::open(&test_data.volume1_path).unwrap();
+
+        // Insert objects smaller and larger than expected.
+        let hash1 = volume.insert_from_reader_with_size(&mut &b"Hello"[..],
+                                                        1 << 20).unwrap();
+        let hash2 = volume.insert_from_reader_with_size(&mut &b"Hello"[..],
+                                                        1).unwrap();
+
+        // Insert an object that is expected to be empty,
+        // for which fallocate fails with EINVAL.
+        let hash3 = volume.insert_from_reader_with_size(&mut &b""[..], 0)
+            .unwrap();
+
+        // Check the results.
+        let stat = volume.stat(hash1).unwrap().unwrap();
+        let path = test_data.volume1_path.join("objects")
+            .join(hash1.to_string());
+        let blocks = fs::metadata(path).unwrap().blocks();
+        assert_eq!(hash1, Hash::compute_from_bytes(b"Hello"));
+        assert_eq!(hash1, hash2);
+        assert_eq!(hash3, Hash::compute_from_bytes(b""));
+        assert_eq!(stat.size, 5);
+        assert!(blocks < (1 << 20) / 512);
+    }
+}
@@ -0,0 +1,634 @@
+//! Client for volumes that live on another machine.
+//!
+//! The remote machine serves the objects of a volume over HTTP/1.1.
+//! The protocol consists of the following requests,
+//! where `<hash>` is the hash of an object in hexadecimal:
+//!
+//! | Request                  | Response                                    |
+//! | ------------------------ | ------------------------------------------- |
+//! | `GET /objects/`          | The hashes of all objects, one per line.    |
+//! | `GET /objects/<hash>`    | The object, or status 404 if it is absent.  |
+//! | `HEAD /objects/<hash>`   | Status 200, or status 404 if it is absent.  |
+//! | `PUT /objects/<hash>`    | Inserts the object given as the body.       |
+//!
+//! `GET /objects/<hash>` supports the `Range` header
+//! with a single range of the form `bytes=<first>-<last>`.
+//! A response with status 206 must have a `Content-Range` header
+//! for the range that was requested,
+//! cut short only where the object ends.
+//! The server must verify that the body of a `PUT` request
+//! hashes to the hash in the path.
+//! Likewise, the client verifies the body of a `GET` response
+//! for an entire object, so that a corrupt object is never accepted.
+//!
+//! Each request uses a fresh connection,
+//! which keeps the client simple at the cost of some latency.
+//! Responses must have a `Content-Length` header, except where HTTP
+//! forbids a body; the client does not support `Transfer-Encoding`.
+
+#![doc(html_favicon_url = "../../../marketing/logo.svg")]
+#![doc(html_logo_url = "../../../marketing/logo.svg")]
+
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Error;
+use std::io::ErrorKind::InvalidData;
+use std::io::ErrorKind::InvalidInput;
+use std::io::Read;
+use std::io::Result;
+use std::io::Take;
+use std::io::Write;
+use std::io::copy;
+use std::io::sink;
+use std::net::TcpStream;
+use std::net::ToSocketAddrs;
+use std::ops::Range;
+use std::time::Duration;
+use wallace_volume::Hash;
+use wallace_volume::HashAlgorithm;
+use wallace_volume::Hashes;
+use wallace_volume::ObjectStore;
+use wallace_volume::VerifyingReader;
+
+/// Reader for the body of a response.
+pub type Body = Take<BufReader<TcpStream>>;
+
+/// Reader for an entire object, verified against its hash.
+pub type ObjectBody = VerifyingReader<Body>;
+
+/// Handle to a volume served over HTTP.
+#[derive(Clone, Debug)]
+pub struct HttpVolume
+{
+    authority: String,
+    hash_algorithm: HashAlgorithm,
+    timeout: Duration,
+}
+
+/// Status line and body of a response.
+struct Response
+{
+    status: u16,
+    body: Body,
+    content_length: u64,
+    content_range: Option<String>,
+}
+
+impl HttpVolume
+{
+    /// Create a handle to the volume served at the given authority,
+    /// which is a host name or address followed by a port number,
+    /// such as `localhost:8080`.
+    ///
+    /// No connection is made until the first request.
+    /// The remote volume must hash objects with SHA-256;
+    /// see [`HttpVolume::with_hash_algorithm`] for other options.
+    pub fn new(authority: impl Into<String>) -> Self
+    {
+        Self::with_hash_algorithm(authority, HashAlgorithm::default())
+    }
+
+    /// Create a handle to the volume served at the given authority,
+    /// whose objects are hashed with the given hash function.
+    ///
+    /// The hash function must match that of the remote volume,
+    /// or else every object would fail verification.
+    pub fn with_hash_algorithm(authority: impl Into<String>,
+                               hash_algorithm: HashAlgorithm) -> Self
+    {
+        let timeout = Duration::from_secs(30);
+        Self{authority: authority.into(), hash_algorithm, timeout}
+    }
+
+    /// The hash function with which objects are hashed.
+    pub fn hash_algorithm(&self) -> HashAlgorithm
+    {
+        self.hash_algorithm
+    }
+
+    /// Set the timeout for connecting to the server,
+    /// and for each read from and write to the connection.
+    ///
+    /// The default is 30 seconds.
+    /// A request that takes longer in total does not time out,
+    /// as long as the server keeps making progress.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the timeout is zero.
+    pub fn set_timeout(&mut self, timeout: Duration)
+    {
+        assert!(!timeout.is_zero(), "HTTP volume timeout is zero");
+        self.timeout = timeout;
+    }
+
+    /// Retrieve a reader for an object’s byte array,
+    /// as well as the size of the object in bytes.
+    ///
+    /// If the object does not exist, this method returns [`None`].
+    /// The object is streamed from the server as it is read,
+    /// and verified against its hash;
+    /// see [`VerifyingReader`] for how corruption is reported.
+    pub fn get(&self, hash: Hash) -> Result<Option<(ObjectBody, u64)>>
+    {
+        let path = format!("/objects/{}", hash);
+        let response = self.request("GET", &path, &[], &[])?;
+        match response.status {
+            200 => {
+                let body = VerifyingReader::new(response.body,
+                                                self.hash_algorithm, hash);
+                Ok(Some((body, response.content_length)))
+            },
+            404 => Ok(None),
+            _   => Err(unexpected_status(response.status)),
+        }
+    }
+
+    /// Retrieve a reader for a range of an object’s byte array.
+    ///
+    /// If the object does not exist, this method returns [`None`].
+    /// If the range extends beyond the end of the object,
+    /// the reader yields only the bytes that exist.
+    /// Unlike with [`HttpVolume::get`], the bytes are not verified,
+    /// as a range cannot be verified without the rest of the object.
+    pub fn get_range(&self, hash: Hash, range: Range<u64>)
+        -> Result<Option<Body>>
+    {
+        // HTTP ranges are inclusive and cannot be empty.
+        if range.start >= range.end {
+            let path = format!("/objects/{}", hash);
+            let response = self.request("HEAD", &path, &[], &[])?;
+            return match response.status {
+                200 => Ok(Some(response.body)),
+                404 => Ok(None),
+                _   => Err(unexpected_status(response.status)),
+            };
+        }
+
+        let path = format!("/objects/{}", hash);
+        let header = format!("Range: bytes={}-{}", range.start, range.end - 1);
+        let response = self.request("GET", &path, &[&header], &[])?;
+        match response.status {
+            200 => {
+                // The server ignored the range; skip to the start.
+                let mut body = response.body;
+                copy(&mut (&mut body).take(range.start), &mut sink())?;
+                let length = range.end - range.start;
+                let limit = body.limit().min(length);
+                body.set_limit(limit);
+                Ok(Some(body))
+            },
+            206 => {
+                // Bytes from another range would be returned silently,
+                // as ranges are not verified.
+                let actual = response.content_range.as_deref()
+                    .and_then(parse_content_range)
+                    .ok_or_else(|| invalid_response("invalid content range"))?;
+                if actual.start != range.start || actual.end > range.end ||
+                   actual.end - actual.start != response.content_length {
+                    return Err(invalid_response("unexpected content range"));
+                }
+                Ok(Some(response.body))
+            },
+            404 => Ok(None),
+            416 => {
+                // The range starts beyond the end of the object.
+                let mut body = response.body;
+                body.set_limit(0);
+                Ok(Some(body))
+            },
+            _   => Err(unexpected_status(response.status)),
+        }
+    }
+
+    /// Check whether an object exists in the volume.
+    pub fn contains(&self, hash: Hash) -> Result<bool>
+    {
+        let path = format!("/objects/{}", hash);
+        let response = self.request("HEAD", &path, &[], &[])?;
+        match response.status {
+            200 => Ok(true),
+            404 => Ok(false),
+            _   => Err(unexpected_status(response.status)),
+        }
+    }
+
+    /// Return the hashes of the objects in the volume.
+    pub fn all(&self) -> Result<Vec<Hash>>
+    {
+        let response = self.request("GET", "/objects/", &[], &[])?;
+        if response.status != 200 {
+            return Err(unexpected_status(response.status));
+        }
+
+        let mut hashes = Vec::new();
+        for line in response.body.lines() {
+            let hash = line?.parse().map_err(|_| {
+                Error::new(InvalidData, "server listed invalid hash")
+            })?;
+            hashes.push(hash);
+        }
+        Ok(hashes)
+    }
+
+    /// Insert the given bytes into the volume, and return their hash.
+    pub fn insert_from_bytes(&self, bytes: &[u8]) -> Result<Hash>
+    {
+        let hash = self.hash_algorithm.compute_from_bytes(bytes);
+        let path = format!("/objects/{}", hash);
+        let header = format!("Content-Length: {}", bytes.len());
+        let response = self.request("PUT", &path, &[&header], bytes)?;
+        match response.status {
+            200 | 201 | 204 => Ok(hash),
+            _               => Err(unexpected_status(response.status)),
+        }
+    }
+
+    /// Drain the given reader into the volume, and return its hash.
+    ///
+    /// The hash is part of the request,
+    /// so the bytes are buffered in memory before they are sent.
+    pub fn insert_from_reader(&self, reader: &mut impl Read) -> Result<Hash>
+    {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        self.insert_from_bytes(&bytes)
+    }
+
+    /// Send a request and read the head of the response.
+    fn request(&self, method: &str, path: &str, headers: &[&str], body: &[u8])
+        -> Result<Response>
+    {
+        let mut stream = self.connect()?;
+
+        let mut head = format!("{} {} HTTP/1.1\r\n\
+                                Host: {}\r\n\
+                                Connection: close\r\n",
+                               method, path, self.authority);
+        for header in headers {
+            head.push_str(header);
+            head.push_str("\r\n");
+        }
+        head.push_str("\r\n");
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(body)?;
+
+        let mut reader = BufReader::new(stream);
+
+        // Parse the status line, e.g. "HTTP/1.1 200 OK".
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let status = line.split(' ').nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| invalid_response("invalid status line"))?;
+
+        // Parse the headers that determine the length of the body,
+        // and the range of the object that it holds.
+        let mut content_length = None;
+        let mut content_range = None;
+        loop {
+            line.clear();
+            reader.read_line(&mut line)?;
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            let (name, value) = match line.split_once(':') {
+                Some(header) => header,
+                None => continue,
+            };
+            if name.eq_ignore_ascii_case("transfer-encoding") {
+                return Err(invalid_response("unsupported transfer encoding"));
+            }
+            if name.eq_ignore_ascii_case("content-length") {
+                let length = value.trim().parse().map_err(|_| {
+                    invalid_response("invalid content length")
+                })?;
+                if content_length.is_some_and(|l| l != length) {
+                    return Err(invalid_response("conflicting content length"));
+                }
+                content_length = Some(length);
+            }
+            if name.eq_ignore_ascii_case("content-range") {
+                content_range = Some(value.trim().to_owned());
+            }
+        }
+
+        // Without a content length, the body would extend
+        // until the connection is closed, and a truncated body
+        // could not be told apart from a complete one.
+        // Responses to HEAD requests, and some statuses, never have a body.
+        let has_body = method != "HEAD" &&
+                       !matches!(status, 100 ..= 199 | 204 | 304);
+        let content_length = match (has_body, content_length) {
+            (false, _)           => 0,
+            (true, Some(length)) => length,
+            (true, None)         =>
+                return Err(invalid_response("missing content length")),
+        };
+
+        let body = reader.take(content_length);
+        Ok(Response{status, body, content_length, content_range})
+    }
+
+    /// Connect to the server, trying each of its addresses in turn.
+    fn connect(&self) -> Result<TcpStream>
+    {
+        let mut last_err = None;
+        for addr in self.authority.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, self.timeout) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(self.timeout))?;
+                    stream.set_write_timeout(Some(self.timeout))?;
+                    return Ok(stream);
+                },
+                Err(err) => last_err = Some(err),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| {
+            Error::new(InvalidInput, "authority resolved to no addresses")
+        }))
+    }
+}
+
+impl ObjectStore for HttpVolume
+{
+    type Reader = ObjectBody;
+
+    fn get(&self, hash: Hash) -> Result<Option<(ObjectBody, u64)>>
+    {
+        HttpVolume::get(self, hash)
+    }
+
+    fn contains(&self, hash: Hash) -> Result<bool>
+    {
+        HttpVolume::contains(self, hash)
+    }
+
+    fn all(&self) -> Result<Hashes<'_>>
+    {
+        Ok(Box::new(HttpVolume::all(self)?.into_iter().map(Ok)))
+    }
+
+    fn insert_from_reader(&self, reader: &mut dyn Read) -> Result<Hash>
+    {
+        HttpVolume::insert_from_reader(self, &mut &mut *reader)
+    }
+}
+
+/// Parse a `Content-Range` header of the form
+/// `bytes <first>-<last>/<length>` into the range it denotes.
+fn parse_content_range(value: &str) -> Option<Range<u64>>
+{
+    let (range, _length) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (first, last) = range.split_once('-')?;
+    let first: u64 = first.parse().ok()?;
+    let last: u64 = last.parse().ok()?;
+    if first > last {
+        return None;
+    }
+    Some(first .. last.checked_add(1)?)
+}
+
+fn unexpected_status(status: u16) -> Error
+{
+    invalid_response(&format!("unexpected HTTP status {}", status))
+}
+
+fn invalid_response(message: &str) -> Error
+{
+    Error::new(InvalidData, format!("invalid response from server: {}",
+                                    message))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::thread;
+    use super::*;
+    use wallace_volume::MemoryVolume;
+
+    /// Serve a single request for the given volume.
+    fn serve_one(stream: TcpStream, volume: &MemoryVolume) -> Result<()>
+    {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let mut words = request_line.split_whitespace();
+        let method = words.next().unwrap_or("").to_owned();
+        let path = words.next().unwrap_or("").to_owned();
+
+        let mut range = None;
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line)?;
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("Range: bytes=") {
+                let (first, last) = value.split_once('-').unwrap();
+                range = Some((first.parse::<usize>().unwrap(),
+                              last.parse::<usize>().unwrap()));
+            }
+            if let Some(value) = line.strip_prefix("Content-Length: ") {
+                content_length = value.parse().unwrap();
+            }
+        }
+
+        let hash = path.strip_prefix("/objects/").and_then(|h| h.parse().ok());
+        let mut stream = stream;
+        let mut respond = |status: &str, body: &[u8]| {
+            write!(stream, "HTTP/1.1 {}\r\nContent-Length: {}\r\n\r\n",
+                   status, body.len())?;
+            stream.write_all(body)
+        };
+
+        match (method.as_str(), hash) {
+            ("GET", None) if path == "/objects/" => {
+                let list: String = volume.all().iter()
+                    .map(|hash| format!("{}\n", hash)).collect();
+                respond("200 OK", list.as_bytes())
+            },
+            ("GET", Some(hash)) => match volume.get(hash) {
+                None => respond("404 Not Found", b""),
+                Some((mut read, _)) => {
+                    let mut data = Vec::new();
+                    read.read_to_end(&mut data)?;
+                    match range {
+                        None => respond("200 OK", &data),
+                        Some((first, last)) => {
+                            let last = last.min(data.len() - 1);
+                            let body = &data[first ..= last];
+                            write!(stream, "HTTP/1.1 206 Partial Content\r\n\
+                                            Content-Range: bytes {}-{}/{}\r\n\
+                                            Content-Length: {}\r\n\r\n",
+                                   first, last, data.len(), body.len())?;
+                            stream.write_all(body)
+                        },
+                    }
+                },
+            },
+            ("HEAD", Some(hash)) if volume.contains(hash) => {
+                write!(stream, "HTTP/1.1 200 OK\r\n\r\n")
+            },
+            ("HEAD", Some(_)) => {
+                write!(stream, "HTTP/1.1 404 Not Found\r\n\r\n")
+            },
+            ("PUT", Some(hash)) => {
+                let mut data = vec![0; content_length];
+                reader.read_exact(&mut data)?;
+                if volume.insert_from_bytes(&data) == hash {
+                    respond("201 Created", b"")
+                } else {
+                    respond("400 Bad Request", b"")
+                }
+            },
+            _ => respond("400 Bad Request", b""),
+        }
+    }
+
+    #[test]
+    fn test_http_volume()
+    {
+        // Prepare the test.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let authority = listener.local_addr().unwrap().to_string();
+        let server_volume = Arc::new(MemoryVolume::new());
+        let server_volume2 = server_volume.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                serve_one(stream.unwrap(), &server_volume2).unwrap();
+            }
+        });
+        let volume = HttpVolume::new(authority);
+
+        // Insert the objects.
+        let hash1 = volume.insert_from_bytes(b"Hello, world!").unwrap();
+        let hash2 = volume.insert_from_reader(&mut &b"Bye"[..]).unwrap();
+        let hash3 = Hash::compute_from_bytes(b"absent");
+
+        // Get the objects.
+        let (mut read, size) = volume.get(hash1).unwrap().unwrap();
+        let mut data = Vec::new();
+        read.read_to_end(&mut data).unwrap();
+        let mut range = volume.get_range(hash1, 7 .. 12).unwrap().unwrap();
+        let mut range_data = Vec::new();
+        range.read_to_end(&mut range_data).unwrap();
+
+        // Check the results.
+        assert_eq!(data, b"Hello, world!");
+        assert_eq!(size, 13);
+        assert_eq!(range_data, b"world");
+        assert!(server_volume.contains(hash2));
+        assert!(volume.contains(hash1).unwrap());
+        assert!(!volume.contains(hash3).unwrap());
+        assert!(volume.get(hash3).unwrap().is_none());
+        let mut all = volume.all().unwrap();
+        all.sort();
+        let mut expected = vec![hash1, hash2];
+        expected.sort();
+        assert_eq!(all, expected);
+    }
+
+    /// Answer each request with the next of the given responses,
+    /// or with nothing at all once they run out.
+    fn serve_raw(responses: Vec<&'static [u8]>) -> HttpVolume
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let authority = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            let mut responses = responses.into_iter();
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                match responses.next() {
+                    Some(response) => stream.write_all(response).unwrap(),
+                    None => thread::sleep(Duration::from_secs(10)),
+                }
+            }
+        });
+        HttpVolume::new(authority)
+    }
+
+    #[test]
+    fn test_http_volume_invalid_response()
+    {
+        // Prepare the test.
+        let hash = Hash::compute_from_bytes(b"Hello");
+        let mut volume = serve_raw(vec![
+            b"HTTP/1.1 200 OK\r\n\r\nHello",
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+              5\r\nHello\r\n0\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nHallo",
+            b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nHel",
+        ]);
+        volume.set_timeout(Duration::from_millis(100));
+
+        // Get the object from the misbehaving server.
+        let read = || -> Result<Vec<u8>> {
+            let (mut body, _) = volume.get(hash)?.unwrap();
+            let mut data = Vec::new();
+            body.read_to_end(&mut data)?;
+            Ok(data)
+        };
+        let missing_length = read().unwrap_err();
+        let chunked = read().unwrap_err();
+        let corrupt = read().unwrap_err();
+        let truncated = read().unwrap_err();
+        let timed_out = read().unwrap_err();
+
+        // Check the results.
+        assert_eq!(missing_length.kind(), InvalidData);
+        assert_eq!(chunked.kind(), InvalidData);
+        assert_eq!(corrupt.kind(), InvalidData);
+        assert_eq!(truncated.kind(), InvalidData);
+        assert!(matches!(timed_out.kind(), std::io::ErrorKind::WouldBlock |
+                                           std::io::ErrorKind::TimedOut));
+    }
+
+    #[test]
+    fn test_http_volume_invalid_content_range()
+    {
+        // Prepare the test.
+        let hash = Hash::compute_from_bytes(b"Hello, world!");
+        let volume = serve_raw(vec![
+            b"HTTP/1.1 206 Partial Content\r\n\
+              Content-Range: bytes 7-11/13\r\nContent-Length: 5\r\n\r\n\
+              world",
+            b"HTTP/1.1 206 Partial Content\r\n\
+              Content-Range: bytes 7-12/13\r\nContent-Length: 6\r\n\r\n\
+              world!",
+            b"HTTP/1.1 206 Partial Content\r\n\
+              Content-Range: bytes 0-4/13\r\nContent-Length: 5\r\n\r\n\
+              Hello",
+            b"HTTP/1.1 206 Partial Content\r\n\
+              Content-Length: 5\r\n\r\nHello",
+        ]);
+
+        // Get a range of the object from the misbehaving server.
+        let read = || -> Result<Vec<u8>> {
+            let mut body = volume.get_range(hash, 7 .. 12)?.unwrap();
+            let mut data = Vec::new();
+            body.read_to_end(&mut data)?;
+            Ok(data)
+        };
+        let matching = read().unwrap();
+        let too_long = read().unwrap_err();
+        let other_range = read().unwrap_err();
+        let missing_range = read().unwrap_err();
+
+        // Check the results.
+        assert_eq!(matching, b"world");
+        assert_eq!(too_long.kind(), InvalidData);
+        assert_eq!(other_range.kind(), InvalidData);
+        assert_eq!(missing_range.kind(), InvalidData);
+    }
+}